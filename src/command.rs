@@ -22,6 +22,7 @@ use std::error::Error;
 use std::fmt;
 use std::collections::HashMap;
 
+use crate::config::LimitsConfig;
 use crate::utils::*;
 
 #[derive(Clone, Copy, Debug)]
@@ -90,6 +91,9 @@ impl<'a> Message<'a> {
         let (source, rest) = if rest.starts_with(':') {
             if let Some(source_end) = rest[1..].find(' ') {
                 let source = &rest[1..source_end + 1];
+                if !validate_source(source) {
+                    return Err(MessageError::WrongSource);
+                }
                 let rest = &rest[source_end + 2..];
                 (Some(source), rest)
             } else {
@@ -161,7 +165,9 @@ impl<'a> Message<'a> {
         // Add parameters
         for (i, param) in self.params.iter().enumerate() {
             result.push(' ');
-            if i == self.params.len() - 1 && param.contains(' ') {
+            if i == self.params.len() - 1
+                && (param.is_empty() || param.contains(' ') || param.starts_with(':'))
+            {
                 result.push(':');
             }
             result.push_str(param);
@@ -214,21 +220,28 @@ pub(crate) enum CommandId {
     KILLId = CommandName { name: "KILL" },
     _REHASHId = CommandName { name: "REHASH" },
     _RESTARTId = CommandName { name: "RESTART" },
-    _AWAYId = CommandName { name: "AWAY" },
+    AWAYId = CommandName { name: "AWAY" },
     USERHOSTId = CommandName { name: "USERHOST" },
     WALLOPSId = CommandName { name: "WALLOPS" },
     ISONId = CommandName { name: "ISON" },
     _DIEId = CommandName { name: "DIE" },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     NICKSERVId = CommandName { name: "NICKSERV" },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     NSId = CommandName { name: "NS" },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     CHANSERVId = CommandName { name: "CHANSERV" },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     CSId = CommandName { name: "CS" },
     SETNAMEId = CommandName { name: "SETNAME" },
     MONITORId = CommandName { name: "MONITOR" },
+    MEMOSERVId = CommandName { name: "MEMOSERV" },
+    MSId = CommandName { name: "MS" },
+    HOSTSERVId = CommandName { name: "HOSTSERV" },
+    HSId = CommandName { name: "HS" },
+    OPERSERVId = CommandName { name: "OPERSERV" },
+    OSId = CommandName { name: "OS" },
+    BOTSERVId = CommandName { name: "BOTSERV" },
+    BSId = CommandName { name: "BS" },
+    KNOCKId = CommandName { name: "KNOCK" },
+    ACCEPTId = CommandName { name: "ACCEPT" },
+    _SILENCEId = CommandName { name: "SILENCE" },
 }
 
 use CommandId::*;
@@ -424,22 +437,18 @@ pub(crate) enum Command<'a> {
     DIE {
         message: Option<&'a str>,
     },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     NICKSERV {
         subcommand: &'a str,
         params: Vec<&'a str>,
     },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     NS {
         subcommand: &'a str,
         params: Vec<&'a str>,
     },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     CHANSERV {
         subcommand: &'a str,
         params: Vec<&'a str>,
     },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     CS {
         subcommand: &'a str,
         params: Vec<&'a str>,
@@ -448,10 +457,61 @@ pub(crate) enum Command<'a> {
         realname: &'a str,
     },
     MONITOR { subcommand: &'a str, targets: Vec<&'a str> },
+    MEMOSERV {
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    },
+    MS {
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    },
+    HOSTSERV {
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    },
+    HS {
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    },
+    OPERSERV {
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    },
+    OS {
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    },
+    BOTSERV {
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    },
+    BS {
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    },
+    KNOCK {
+        channel: &'a str,
+        message: Option<&'a str>,
+    },
+    ACCEPT {
+        nicknames: Vec<&'a str>,
+    },
+    SILENCE {
+        mask: Option<&'a str>,
+    },
 }
 
 use Command::*;
 
+// Los límites de [limits] se cuentan en bytes, como en el 005
+fn check_length(param: &str, limit: usize, e: CommandError) -> Result<(), CommandError> {
+    if param.len() > limit {
+        Err(e)
+    } else {
+        Ok(())
+    }
+}
+
 impl<'a> Command<'a> {
     pub(crate) fn index(&self) -> usize {
         match self {
@@ -494,16 +554,23 @@ impl<'a> Command<'a> {
             WALLOPS { .. } => 36,
             ISON { .. } => 37,
             DIE { .. } => 38,
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             NICKSERV { .. } => 39,
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             NS { .. } => 40,
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             CHANSERV { .. } => 41,
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             CS { .. } => 42,
             SETNAME { .. } => 43,
             MONITOR { .. } => 44,
+            MEMOSERV { .. } => 45,
+            MS { .. } => 46,
+            HOSTSERV { .. } => 47,
+            HS { .. } => 48,
+            OPERSERV { .. } => 49,
+            OS { .. } => 50,
+            BOTSERV { .. } => 51,
+            BS { .. } => 52,
+            KNOCK { .. } => 53,
+            ACCEPT { .. } => 54,
+            SILENCE { .. } => 55,
         }
     }
 
@@ -926,7 +993,6 @@ impl<'a> Command<'a> {
                     Ok(DIE { message: None })
                 }
             }
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             "NICKSERV" => {
                 if !message.params.is_empty() {
                     Ok(NICKSERV {
@@ -937,7 +1003,6 @@ impl<'a> Command<'a> {
                     Err(NeedMoreParams(NICKSERVId))
                 }
             },
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             "NS" => {
                 if !message.params.is_empty() {
                     Ok(NS {
@@ -948,7 +1013,6 @@ impl<'a> Command<'a> {
                     Err(NeedMoreParams(NSId))
                 }
             },
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             "CHANSERV" => {
                 if !message.params.is_empty() {
                     Ok(CHANSERV {
@@ -959,7 +1023,6 @@ impl<'a> Command<'a> {
                     Err(NeedMoreParams(CHANSERVId))
                 }
             },
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             "CS" => {
                 if !message.params.is_empty() {
                     Ok(CS {
@@ -989,16 +1052,118 @@ impl<'a> Command<'a> {
                     Err(NeedMoreParams(MONITORId))
                 }
             },
+            "MEMOSERV" => {
+                if !message.params.is_empty() {
+                    Ok(MEMOSERV {
+                        subcommand: message.params[0],
+                        params: message.params[1..].to_vec(),
+                    })
+                } else {
+                    Err(NeedMoreParams(MEMOSERVId))
+                }
+            },
+            "MS" => {
+                if !message.params.is_empty() {
+                    Ok(MS {
+                        subcommand: message.params[0],
+                        params: message.params[1..].to_vec(),
+                    })
+                } else {
+                    Err(NeedMoreParams(MSId))
+                }
+            },
+            "HOSTSERV" => {
+                if !message.params.is_empty() {
+                    Ok(HOSTSERV {
+                        subcommand: message.params[0],
+                        params: message.params[1..].to_vec(),
+                    })
+                } else {
+                    Err(NeedMoreParams(HOSTSERVId))
+                }
+            },
+            "HS" => {
+                if !message.params.is_empty() {
+                    Ok(HS {
+                        subcommand: message.params[0],
+                        params: message.params[1..].to_vec(),
+                    })
+                } else {
+                    Err(NeedMoreParams(HSId))
+                }
+            },
+            "OPERSERV" => {
+                if !message.params.is_empty() {
+                    Ok(OPERSERV {
+                        subcommand: message.params[0],
+                        params: message.params[1..].to_vec(),
+                    })
+                } else {
+                    Err(NeedMoreParams(OPERSERVId))
+                }
+            },
+            "OS" => {
+                if !message.params.is_empty() {
+                    Ok(OS {
+                        subcommand: message.params[0],
+                        params: message.params[1..].to_vec(),
+                    })
+                } else {
+                    Err(NeedMoreParams(OSId))
+                }
+            },
+            "BOTSERV" => {
+                if !message.params.is_empty() {
+                    Ok(BOTSERV {
+                        subcommand: message.params[0],
+                        params: message.params[1..].to_vec(),
+                    })
+                } else {
+                    Err(NeedMoreParams(BOTSERVId))
+                }
+            },
+            "BS" => {
+                if !message.params.is_empty() {
+                    Ok(BS {
+                        subcommand: message.params[0],
+                        params: message.params[1..].to_vec(),
+                    })
+                } else {
+                    Err(NeedMoreParams(BSId))
+                }
+            },
+            "KNOCK" => {
+                if !message.params.is_empty() {
+                    Ok(KNOCK {
+                        channel: message.params[0],
+                        message: message.params.get(1).copied(),
+                    })
+                } else {
+                    Err(NeedMoreParams(KNOCKId))
+                }
+            }
+            "ACCEPT" => {
+                if !message.params.is_empty() {
+                    Ok(ACCEPT {
+                        nicknames: message.params[0].split(',').collect::<Vec<_>>(),
+                    })
+                } else {
+                    Err(NeedMoreParams(ACCEPTId))
+                }
+            }
+            "SILENCE" => Ok(SILENCE {
+                mask: message.params.first().copied(),
+            }),
             s => Err(UnknownCommand(s.to_string())),
         }
     }
 
     // get Command from message
-    pub(crate) fn from_message(message: &Message<'a>) -> Result<Self, CommandError> {
+    pub(crate) fn from_message(message: &Message<'a>, limits: &LimitsConfig) -> Result<Self, CommandError> {
         match Self::parse_from_message(message) {
             Ok(x) => {
                 // and validate command parameters
-                match x.validate() {
+                match x.validate(limits) {
                     Ok(()) => Ok(x),
                     Err(e) => Err(e),
                 }
@@ -1007,7 +1172,7 @@ impl<'a> Command<'a> {
         }
     }
 
-    fn validate(&self) -> Result<(), CommandError> {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), CommandError> {
         match self {
             CAP { version, .. } => {
                 if let Some(v) = version {
@@ -1022,32 +1187,38 @@ impl<'a> Command<'a> {
             }
             NICK { nickname } => {
                 validate_username(nickname).map_err(|_| WrongParameter(NICKId, 0))?;
-                Ok(())
+                check_length(nickname, limits.nick_len, WrongParameter(NICKId, 0))
             }
-            USER { username, .. } => {
-                validate_username(username).map_err(|_| WrongParameter(USERId, 0))
+            USER { username, realname, .. } => {
+                validate_username(username).map_err(|_| WrongParameter(USERId, 0))?;
+                check_length(username, limits.user_len, WrongParameter(USERId, 0))?;
+                check_length(realname, limits.realname_len, WrongParameter(USERId, 3))
             }
             OPER { name, .. } => validate_username(name).map_err(|_| WrongParameter(OPERId, 0)),
-            JOIN { channels, .. } => channels
-                .iter()
-                .try_for_each(|ch| validate_channel(ch))
-                .map_err(|_| WrongParameter(JOINId, 0)),
+            JOIN { channels, .. } => channels.iter().try_for_each(|ch| {
+                validate_channel(ch).map_err(|_| WrongParameter(JOINId, 0))?;
+                check_length(ch, limits.channel_len, WrongParameter(JOINId, 0))
+            }),
             PART { channels, .. } => channels
                 .iter()
                 .try_for_each(|ch| validate_channel(ch))
                 .map_err(|_| WrongParameter(PARTId, 0)),
-            TOPIC { channel, .. } => {
-                validate_channel(channel).map_err(|_| WrongParameter(TOPICId, 0))
+            TOPIC { channel, topic } => {
+                validate_channel(channel).map_err(|_| WrongParameter(TOPICId, 0))?;
+                if let Some(topic) = topic {
+                    check_length(topic, limits.topic_len, WrongParameter(TOPICId, 1))?;
+                }
+                Ok(())
             }
             NAMES { channels } => channels
                 .iter()
                 .try_for_each(|ch| validate_channel(ch))
                 .map_err(|_| WrongParameter(NAMESId, 0)),
             LIST { channels, server } => {
-                channels
-                    .iter()
-                    .try_for_each(|ch| validate_channel(ch))
-                    .map_err(|_| WrongParameter(LISTId, 0))?;
+                // canales o filtros ELIST
+                if !channels.iter().all(|ch| parse_list_filter(ch).is_some()) {
+                    return Err(WrongParameter(LISTId, 0));
+                }
                 if let Some(srv) = server {
                     validate_server(srv, WrongParameter(LISTId, 1))?;
                 }
@@ -1057,12 +1228,16 @@ impl<'a> Command<'a> {
                 validate_username(nickname).map_err(|_| WrongParameter(INVITEId, 0))?;
                 validate_channel(channel).map_err(|_| WrongParameter(INVITEId, 1))
             }
-            KICK { channel, users, .. } => {
+            KICK { channel, users, comment } => {
                 validate_channel(channel).map_err(|_| WrongParameter(KICKId, 0))?;
                 users
                     .iter()
                     .try_for_each(|u| validate_username(u))
-                    .map_err(|_| WrongParameter(KICKId, 1))
+                    .map_err(|_| WrongParameter(KICKId, 1))?;
+                if let Some(comment) = comment {
+                    check_length(comment, limits.kick_len, WrongParameter(KICKId, 2))?;
+                }
+                Ok(())
             }
             MOTD { target } => {
                 if let Some(t) = target {
@@ -1124,6 +1299,9 @@ impl<'a> Command<'a> {
                 }
             }
             PRIVMSG { targets, .. } => {
+                if targets.len() > limits.max_targets {
+                    return Err(WrongParameter(PRIVMSGId, 0));
+                }
                 targets.iter().try_for_each(|n| {
                     validate_username(n)
                         // in PRIVMSG we can use prefixed channels
@@ -1132,6 +1310,9 @@ impl<'a> Command<'a> {
                 })
             }
             NOTICE { targets, .. } => {
+                if targets.len() > limits.max_targets {
+                    return Err(WrongParameter(NOTICEId, 0));
+                }
                 targets.iter().try_for_each(|n| {
                     validate_username(n)
                         // in NOTICE we can use prefixed channels
@@ -1142,7 +1323,9 @@ impl<'a> Command<'a> {
             //WHO{ mask } => { Ok(()) }
             WHOIS { target, nickmasks } => {
                 let next_param_idx = if let Some(t) = target {
-                    validate_username(t).map_err(|_| WrongParameter(WHOISId, 0))?;
+                    // el destino puede ser un servidor o un nick
+                    validate_server(t, WrongParameter(WHOISId, 0))
+                        .or_else(|_| validate_username(t).map_err(|_| WrongParameter(WHOISId, 0)))?;
                     1
                 } else {
                     0
@@ -1164,40 +1347,42 @@ impl<'a> Command<'a> {
             KILL { nickname, .. } => {
                 validate_username(nickname).map_err(|_| WrongParameter(KILLId, 0))
             }
+            AWAY { text: Some(text) } => check_length(text, limits.away_len, WrongParameter(AWAYId, 0)),
             USERHOST { nicknames } => nicknames.iter().enumerate().try_for_each(|(i, n)| {
                 validate_username(n).map_err(|_| WrongParameter(USERHOSTId, i))
             }),
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             NICKSERV { subcommand, .. } => {
                 match subcommand.to_lowercase().as_str() {
                     "register" | "drop" | "email" | "url" | "noaccess" | "noop" | 
-                    "showmail" | "password" | "vhost" | "identify" | "help" | "info" => Ok(()),
+                    "showmail" | "password" | "vhost" | "identify" | "help" | "info" | "forbid" => Ok(()),
                     _ => Err(UnknownSubcommand(NICKSERVId, subcommand.to_string()))
                 }
             }
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             NS { subcommand, .. } => {
                 match subcommand.to_lowercase().as_str() {
                     "register" | "drop" | "email" | "url" | "noaccess" | "noop" | 
-                    "showmail" | "password" | "vhost" | "identify" | "help" | "info" => Ok(()),
+                    "showmail" | "password" | "vhost" | "identify" | "help" | "info" | "forbid" => Ok(()),
                     _ => Err(UnknownSubcommand(NSId, subcommand.to_string()))
                 }
             }
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             CHANSERV { subcommand, .. } => {
                 match subcommand.to_lowercase().as_str() {
-                    "register" | "drop" | "topic" | "vop" | "hop" | "aop" | "sop" | "transfer" | "mlock" | "info" => Ok(()),
+                    "register" | "drop" | "topic" | "vop" | "hop" | "aop" | "sop" | "transfer" | "mlock" | "info" | "akick" | "flags" | "set" |
+                    "suspend" | "unsuspend" | "forbid" => Ok(()),
                     _ => Err(UnknownSubcommand(CHANSERVId, subcommand.to_string()))
                 }
             }
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             CS { subcommand, .. } => {
                 match subcommand.to_lowercase().as_str() {
-                    "register" | "drop" | "topic" | "vop" | "hop" | "aop" | "sop" | "transfer" | "mlock" | "info" => Ok(()),
+                    "register" | "drop" | "topic" | "vop" | "hop" | "aop" | "sop" | "transfer" | "mlock" | "info" | "akick" | "flags" | "set" |
+                    "suspend" | "unsuspend" | "forbid" => Ok(()),
                     _ => Err(UnknownSubcommand(CSId, subcommand.to_string()))
                 }
             }
-            SETNAME { realname } => validate_username(realname).map_err(|_| WrongParameter(SETNAMEId, 0)),
+            SETNAME { realname } => {
+                validate_username(realname).map_err(|_| WrongParameter(SETNAMEId, 0))?;
+                check_length(realname, limits.realname_len, WrongParameter(SETNAMEId, 0))
+            }
             MONITOR { subcommand, .. } => {
                 if *subcommand == "drop" || *subcommand == "register" {
                     Ok(())
@@ -1205,6 +1390,60 @@ impl<'a> Command<'a> {
                     Err(UnknownSubcommand(MONITORId, subcommand.to_string()))
                 }
             },
+            MEMOSERV { subcommand, .. } => {
+                match subcommand.to_lowercase().as_str() {
+                    "send" | "list" | "read" | "del" | "forward" | "ignore" | "limit" | "help" => Ok(()),
+                    _ => Err(UnknownSubcommand(MEMOSERVId, subcommand.to_string()))
+                }
+            }
+            MS { subcommand, .. } => {
+                match subcommand.to_lowercase().as_str() {
+                    "send" | "list" | "read" | "del" | "forward" | "ignore" | "limit" | "help" => Ok(()),
+                    _ => Err(UnknownSubcommand(MSId, subcommand.to_string()))
+                }
+            }
+            HOSTSERV { subcommand, .. } => {
+                match subcommand.to_lowercase().as_str() {
+                    "request" | "activate" | "reject" | "waiting" | "on" | "off" | "group" | "help" => Ok(()),
+                    _ => Err(UnknownSubcommand(HOSTSERVId, subcommand.to_string()))
+                }
+            }
+            HS { subcommand, .. } => {
+                match subcommand.to_lowercase().as_str() {
+                    "request" | "activate" | "reject" | "waiting" | "on" | "off" | "group" | "help" => Ok(()),
+                    _ => Err(UnknownSubcommand(HSId, subcommand.to_string()))
+                }
+            }
+            OPERSERV { subcommand, .. } => {
+                match subcommand.to_lowercase().as_str() {
+                    "akill" | "global" | "exception" | "mode" | "jupe" | "stats" | "log" | "help" => Ok(()),
+                    _ => Err(UnknownSubcommand(OPERSERVId, subcommand.to_string()))
+                }
+            }
+            OS { subcommand, .. } => {
+                match subcommand.to_lowercase().as_str() {
+                    "akill" | "global" | "exception" | "mode" | "jupe" | "stats" | "log" | "help" => Ok(()),
+                    _ => Err(UnknownSubcommand(OSId, subcommand.to_string()))
+                }
+            }
+            BOTSERV { subcommand, .. } => {
+                match subcommand.to_lowercase().as_str() {
+                    "bot" | "botlist" | "assign" | "unassign" | "info" | "set" | "kick" | "badwords" | "help" => Ok(()),
+                    _ => Err(UnknownSubcommand(BOTSERVId, subcommand.to_string()))
+                }
+            }
+            BS { subcommand, .. } => {
+                match subcommand.to_lowercase().as_str() {
+                    "bot" | "botlist" | "assign" | "unassign" | "info" | "set" | "kick" | "badwords" | "help" => Ok(()),
+                    _ => Err(UnknownSubcommand(BSId, subcommand.to_string()))
+                }
+            }
+            KNOCK { channel, .. } => validate_channel(channel).map_err(|_| WrongParameter(KNOCKId, 0)),
+            ACCEPT { nicknames } => nicknames
+                .iter()
+                .filter(|nick| **nick != "*")
+                .try_for_each(|nick| validate_username(nick.strip_prefix('-').unwrap_or(nick)))
+                .map_err(|_| WrongParameter(ACCEPTId, 0)),
             _ => Ok(()),
         }
     }
@@ -1218,6 +1457,7 @@ mod test {
     fn test_message_from_shared_str() {
         assert_eq!(
             Ok(Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "QUIT",
                 params: vec![]
@@ -1226,6 +1466,7 @@ mod test {
         );
         assert_eq!(
             Ok(Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "QUIT",
                 params: vec![]
@@ -1234,6 +1475,7 @@ mod test {
         );
         assert_eq!(
             Ok(Message {
+                tags: HashMap::new(),
                 source: Some("source"),
                 command: "QUIT",
                 params: vec![]
@@ -1242,6 +1484,7 @@ mod test {
         );
         assert_eq!(
             Ok(Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "USER",
                 params: vec!["guest", "0", "*", "Ronnie Reagan"]
@@ -1250,6 +1493,7 @@ mod test {
         );
         assert_eq!(
             Ok(Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "USER",
                 params: vec!["guest", "0", "*", "Benny"]
@@ -1258,6 +1502,7 @@ mod test {
         );
         assert_eq!(
             Ok(Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["bobby", ":-). Hello guy!"]
@@ -1266,6 +1511,7 @@ mod test {
         );
         assert_eq!(
             Ok(Message {
+                tags: HashMap::new(),
                 source: Some("mati!mat@gg.com"),
                 command: "QUIT",
                 params: vec![]
@@ -1280,6 +1526,7 @@ mod test {
 
     #[test]
     fn test_command_from_message() {
+        let limits = LimitsConfig::default();
         assert_eq!(
             Ok(CAP {
                 subcommand: CapCommand::LS,
@@ -1287,10 +1534,11 @@ mod test {
                 version: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["LS"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1300,10 +1548,11 @@ mod test {
                 version: Some(302)
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["LS", "302"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1313,28 +1562,31 @@ mod test {
                 version: Some(303)
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["LS", "303"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'CAP'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["LS", "301"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'CAP'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["LS", "xxx"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1344,10 +1596,11 @@ mod test {
                 version: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["LIST"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1357,10 +1610,11 @@ mod test {
                 caps: Some(vec!["multi-prefix", "tls"])
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["REQ", "multi-prefix tls"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1370,10 +1624,11 @@ mod test {
                 version: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["END"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1383,103 +1638,114 @@ mod test {
                 caps: Some(vec!["multi-prefix", "tlsx"])
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["REQ", "multi-prefix tlsx"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Unknown subcommand 'LSS' in command 'CAP'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec!["LSS"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'CAP' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "CAP",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(AUTHENTICATE { data: None }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "AUTHENTICATE",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(PASS { password: "secret" }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PASS",
                 params: vec!["secret"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'PASS' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PASS",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(NICK { nickname: "lucky" }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NICK",
                 params: vec!["lucky"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'NICK'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NICK",
                 params: vec!["luc.ky"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'NICK'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NICK",
                 params: vec!["luc,ky"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'NICK'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NICK",
                 params: vec!["luc:ky"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'NICK' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NICK",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1491,47 +1757,52 @@ mod test {
                 realname: "Chris Wood"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "USER",
                 params: vec!["chris", "0", "*", "Chris Wood"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'USER'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "USER",
                 params: vec!["chr:is", "0", "*", "Chris Wood"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'USER' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "USER",
                 params: vec!["chris", "0", "*"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(PING { token: "xxxaaa" }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PING",
                 params: vec!["xxxaaa"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'PING' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PING",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1541,38 +1812,42 @@ mod test {
                 password: "mythebestday"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "OPER",
                 params: vec!["guru", "mythebestday"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'OPER'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "OPER",
                 params: vec!["gu:ru", "mythebestday"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'OPER' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "OPER",
                 params: vec!["guru"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(QUIT {}),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "QUIT",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1583,10 +1858,11 @@ mod test {
                 account: None,
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "JOIN",
                 params: vec!["#cats,&fruits,#software"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1596,28 +1872,31 @@ mod test {
                 account: None,
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "JOIN",
                 params: vec!["#cats,&fruits,#software", "mycat,apple,wesnoth"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'JOIN'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "JOIN",
                 params: vec!["#cats,&fru:its,#software"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'JOIN'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "JOIN",
                 params: vec!["#cats,fruits,#software"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1627,37 +1906,41 @@ mod test {
                 account: None,
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "JOIN",
                 params: vec!["#cats,&fru.its,#software"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Parameter 1 doesn't match for command 'JOIN'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "JOIN",
                 params: vec!["#cats,&fruits,#software,#countries", "mycat,apple,wesnoth"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Parameter 1 doesn't match for command 'JOIN'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "JOIN",
                 params: vec!["#cats,&fruits,#software", "mycat,apple,wesnoth,zizi"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'JOIN' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "JOIN",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1667,10 +1950,11 @@ mod test {
                 reason: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PART",
                 params: vec!["#dogs,&juices,#hardware"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1679,28 +1963,31 @@ mod test {
                 reason: Some("I don't like these channels")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PART",
                 params: vec!["#dogs,&juices,#hardware", "I don't like these channels"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'PART'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PART",
                 params: vec!["#dogs,&juices,#har:dware", "I don't like these channels"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'PART' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PART",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1710,10 +1997,11 @@ mod test {
                 topic: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "TOPIC",
                 params: vec!["#gizmo"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1722,28 +2010,31 @@ mod test {
                 topic: Some("Some creatures")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "TOPIC",
                 params: vec!["#gizmo", "Some creatures"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'TOPIC'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "TOPIC",
                 params: vec!["#giz:mo"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'TOPIC' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "TOPIC",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1752,28 +2043,31 @@ mod test {
                 channels: vec!["#dogs", "&juices", "#hardware"]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NAMES",
                 params: vec!["#dogs,&juices,#hardware"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Ok(NAMES { channels: vec![] }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NAMES",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'NAMES'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NAMES",
                 params: vec!["#dogs,&juices,#hard:ware"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1783,10 +2077,11 @@ mod test {
                 server: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LIST",
                 params: vec!["#dogs,&juices,#hardware"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1795,10 +2090,11 @@ mod test {
                 server: Some("funny.checkbox.org")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LIST",
                 params: vec!["#dogs,&juices,#hardware", "funny.checkbox.org"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1807,28 +2103,54 @@ mod test {
                 server: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LIST",
                 params: vec![]
-            })
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(LIST {
+                channels: vec![">3", "C<60", "T>5", "#*dog*", "!#cat*"],
+                server: None
+            }),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "LIST",
+                params: vec![">3,C<60,T>5,#*dog*,!#cat*"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 0 in command 'LIST'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "LIST",
+                params: vec![">3,X<5"]
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'LIST'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LIST",
                 params: vec!["#dogs,&juices,#har:dware", "funny.checkbox.org"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'LIST'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LIST",
                 params: vec!["#dogs,&juices,#hardware", "fnny"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1838,37 +2160,143 @@ mod test {
                 channel: "#plants"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "INVITE",
                 params: vec!["greg", "#plants"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'INVITE'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "INVITE",
                 params: vec!["gr:eg", "#plants"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'INVITE'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "INVITE",
                 params: vec!["greg", "_plants"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'INVITE' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "INVITE",
                 params: vec!["greg"]
-            })
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+
+        assert_eq!(
+            Ok(KNOCK {
+                channel: "#plants",
+                message: Some("let me in")
+            }),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "KNOCK",
+                params: vec!["#plants", "let me in"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(KNOCK {
+                channel: "#plants",
+                message: None
+            }),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "KNOCK",
+                params: vec!["#plants"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 0 in command 'KNOCK'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "KNOCK",
+                params: vec!["_plants"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Command 'KNOCK' needs more parameters".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "KNOCK",
+                params: vec![]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+
+        assert_eq!(
+            Ok(ACCEPT {
+                nicknames: vec!["greg", "-bob", "*"]
+            }),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "ACCEPT",
+                params: vec!["greg,-bob,*"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 0 in command 'ACCEPT'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "ACCEPT",
+                params: vec!["greg,-b:ob"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Command 'ACCEPT' needs more parameters".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "ACCEPT",
+                params: vec![]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(SILENCE {
+                mask: Some("+*!*@spam.org")
+            }),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "SILENCE",
+                params: vec!["+*!*@spam.org"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(SILENCE { mask: None }),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "SILENCE",
+                params: vec![]
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -1879,10 +2307,11 @@ mod test {
                 comment: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KICK",
                 params: vec!["#toolkits", "mickey"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1892,10 +2321,11 @@ mod test {
                 comment: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KICK",
                 params: vec!["#toolkits", "mickey,lola"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1905,47 +2335,52 @@ mod test {
                 comment: Some("Mickey is not polite")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KICK",
                 params: vec!["#toolkits", "mickey", "Mickey is not polite"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'KICK'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KICK",
                 params: vec!["@toolkits", "mickey"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'KICK'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KICK",
                 params: vec!["#toolkits", "mic:key"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'KICK' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KICK",
                 params: vec!["#toolkits"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(MOTD { target: None }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MOTD",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1953,10 +2388,11 @@ mod test {
                 target: Some("bubu.com")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MOTD",
                 params: vec!["bubu.com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1964,29 +2400,32 @@ mod test {
                 target: Some("*com")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MOTD",
                 params: vec!["*com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'MOTD'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MOTD",
                 params: vec!["bubucom"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(VERSION { target: None }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "VERSION",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -1994,10 +2433,11 @@ mod test {
                 target: Some("bubu.com")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "VERSION",
                 params: vec!["bubu.com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2005,29 +2445,32 @@ mod test {
                 target: Some("*com")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "VERSION",
                 params: vec!["*com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'VERSION'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "VERSION",
                 params: vec!["bubucom"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(ADMIN { target: None }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "ADMIN",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2035,10 +2478,11 @@ mod test {
                 target: Some("bubu.com")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "ADMIN",
                 params: vec!["bubu.com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2046,39 +2490,43 @@ mod test {
                 target: Some("*com")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "ADMIN",
                 params: vec!["*com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'ADMIN'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "ADMIN",
                 params: vec!["bubucom"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(LUSERS {}),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LUSERS",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(TIME { server: None }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "TIME",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2086,28 +2534,31 @@ mod test {
                 server: Some("bubu.com")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "TIME",
                 params: vec!["bubu.com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'TIME'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "TIME",
                 params: vec!["*com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'TIME'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "TIME",
                 params: vec!["bubucom"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -2117,10 +2568,11 @@ mod test {
                 server: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "STATS",
                 params: vec!["c"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2129,10 +2581,11 @@ mod test {
                 server: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "STATS",
                 params: vec!["l"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2141,10 +2594,11 @@ mod test {
                 server: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "STATS",
                 params: vec!["o"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2153,37 +2607,41 @@ mod test {
                 server: Some("chat.fruits.com")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "STATS",
                 params: vec!["o", "chat.fruits.com"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'STATS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "STATS",
                 params: vec!["z"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'STATS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "STATS",
                 params: vec!["o", "chatfruitscom"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'STATS' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "STATS",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -2193,10 +2651,11 @@ mod test {
                 server_mask: Some("*.gigo.net")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LINKS",
                 params: vec!["*.gigo.net"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2205,37 +2664,41 @@ mod test {
                 server_mask: Some("*.gigo.net")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LINKS",
                 params: vec!["first.proxy.net", "*.gigo.net"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'LINKS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LINKS",
                 params: vec!["gigonet"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'LINKS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LINKS",
                 params: vec!["first.proxy.net", "gigonet"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'LINKS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LINKS",
                 params: vec!["firstproxynet", "*.gigo.net"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2244,10 +2707,11 @@ mod test {
                 server_mask: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "LINKS",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -2256,10 +2720,11 @@ mod test {
                 subject: Some("PING Message")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "HELP",
                 params: vec!["PING Message"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2267,29 +2732,32 @@ mod test {
                 subject: Some("PONG Message")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "HELP",
                 params: vec!["PONG Message"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Ok(HELP { subject: None }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "HELP",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(INFO {}),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "INFO",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -2299,10 +2767,11 @@ mod test {
                 modes: vec![]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["andy"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2311,10 +2780,11 @@ mod test {
                 modes: vec![]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#engines"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2323,10 +2793,11 @@ mod test {
                 modes: vec![("+owWz", vec![])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["andy", "+owWz"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2335,10 +2806,11 @@ mod test {
                 modes: vec![("+oOr-iw", vec![])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["andy", "+oOr-iw"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2347,28 +2819,31 @@ mod test {
                 modes: vec![("+oOr", vec![]), ("-iw", vec![])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["andy", "+oOr", "-iw"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Unknown umode flag in parameter 1".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["teddy", "+otOr"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Unknown umode flag in parameter 1".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["teddy", "+otOr", "bbb"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2377,10 +2852,11 @@ mod test {
                 modes: vec![("+ntsm", vec![])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+ntsm"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2389,10 +2865,11 @@ mod test {
                 modes: vec![("+b", vec!["*@192.168.1.7"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+b", "*@192.168.1.7"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2401,10 +2878,11 @@ mod test {
                 modes: vec![("-b", vec!["*@192.168.1.7"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-b", "*@192.168.1.7"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2417,6 +2895,7 @@ mod test {
                 ]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec![
@@ -2428,7 +2907,7 @@ mod test {
                     "+I",
                     "*@192.168.1.9"
                 ]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2441,6 +2920,7 @@ mod test {
                 ]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec![
@@ -2452,7 +2932,7 @@ mod test {
                     "-I",
                     "*@192.168.1.9"
                 ]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2465,6 +2945,7 @@ mod test {
                 ]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec![
@@ -2476,7 +2957,7 @@ mod test {
                     "-tI",
                     "*@192.168.1.9"
                 ]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2485,10 +2966,11 @@ mod test {
                 modes: vec![("+b", vec![])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+b"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2497,10 +2979,11 @@ mod test {
                 modes: vec![("+l", vec!["123"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+l", "123"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2509,10 +2992,11 @@ mod test {
                 modes: vec![("-l", vec![])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-l"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2521,10 +3005,11 @@ mod test {
                 modes: vec![("+k", vec!["secret"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+k", "secret"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2533,10 +3018,11 @@ mod test {
                 modes: vec![("-k", vec![])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-k"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2545,10 +3031,11 @@ mod test {
                 modes: vec![("-bl+i", vec!["*@192.168.0.1"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-bl+i", "*@192.168.0.1"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2557,10 +3044,11 @@ mod test {
                 modes: vec![("+o", vec!["barry"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+o", "barry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2569,10 +3057,11 @@ mod test {
                 modes: vec![("-o", vec!["barry"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-o", "barry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2581,10 +3070,11 @@ mod test {
                 modes: vec![("+h", vec!["barry"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+h", "barry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2593,10 +3083,11 @@ mod test {
                 modes: vec![("-h", vec!["barry"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-h", "barry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2605,10 +3096,11 @@ mod test {
                 modes: vec![("+v", vec!["barry"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+v", "barry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2617,10 +3109,11 @@ mod test {
                 modes: vec![("-v", vec!["barry"])]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-v", "barry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2629,82 +3122,91 @@ mod test {
                 modes: vec![]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["andy"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis l  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+l"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis k  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+k"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis o  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+o"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis o  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-o"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis h  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+h"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis h  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-h"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis v  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+v"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis v  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-v"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2714,10 +3216,11 @@ mod test {
                     .to_string()
             ),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "+l", "xxx"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2725,10 +3228,11 @@ mod test {
                 Unexpected argument"
                 .to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-l", "145"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2736,28 +3240,31 @@ mod test {
                 Unexpected argument"
                 .to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-k", "145"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Invalid mode parameter: #chasis o  No argument".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec!["#chasis", "-bl+oi", "*@192.168.0.1"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'MODE' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "MODE",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -2767,10 +3274,11 @@ mod test {
                 text: "Hello, guys"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["bobby,andy", "Hello, guys"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2779,10 +3287,11 @@ mod test {
                 text: "Hello, guys"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["#graphics,&musics", "Hello, guys"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2791,10 +3300,11 @@ mod test {
                 text: "Hello, cruel world!"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["#graphics,&musics,jimmy", "Hello, cruel world!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2803,10 +3313,11 @@ mod test {
                 text: "Hello, cruel world!"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["%#graphics,~&musics,jimmy", "Hello, cruel world!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2815,10 +3326,11 @@ mod test {
                 text: "Hello, cruel world!"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["@#graphics,&&musics,jimmy", "Hello, cruel world!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2827,10 +3339,11 @@ mod test {
                 text: "Hello, cruel world!"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["@+#graphics,&&musics,jimmy", "Hello, cruel world!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2839,28 +3352,31 @@ mod test {
                 text: "Hello, cruel world!"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["+#graphics,+&musics,jimmy", "Hello, cruel world!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'PRIVMSG'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["#graphics,&musics,ji.mmy", "Hello, cruel world!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'PRIVMSG' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "PRIVMSG",
                 params: vec!["#graphics,&musics,jimmy"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -2870,10 +3386,11 @@ mod test {
                 text: "Hello, guys"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NOTICE",
                 params: vec!["bobby,andy", "Hello, guys"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2882,10 +3399,11 @@ mod test {
                 text: "Hello, guys"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NOTICE",
                 params: vec!["#graphics,&musics", "Hello, guys"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2894,47 +3412,52 @@ mod test {
                 text: "Hello, cruel world!"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NOTICE",
                 params: vec!["#graphics,&musics,jimmy", "Hello, cruel world!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'NOTICE'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NOTICE",
                 params: vec!["#graphics,&musics,ji.mmy", "Hello, cruel world!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'NOTICE' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "NOTICE",
                 params: vec!["#graphics,&musics,jimmy"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(WHO { mask: "bla*bla" }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHO",
                 params: vec!["bla*bla"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'WHO' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHO",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -2944,10 +3467,11 @@ mod test {
                 nickmasks: vec!["alice", "eliz", "garry"]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOIS",
                 params: vec!["alice,eliz,garry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -2956,46 +3480,51 @@ mod test {
                 nickmasks: vec!["alice", "eliz", "garry"]
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOIS",
                 params: vec!["coco.net", "alice,eliz,garry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'WHOIS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOIS",
                 params: vec!["alice,el:iz,garry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'WHOIS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOIS",
-                params: vec!["coconet", "alice,eliz,garry"]
-            })
+                params: vec!["coco:net", "alice,eliz,garry"]
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'WHOIS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOIS",
                 params: vec!["coco.net", "alice,eliz,ga:rry"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'WHOIS' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOIS",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -3006,10 +3535,11 @@ mod test {
                 server: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOWAS",
                 params: vec!["mat"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -3019,10 +3549,11 @@ mod test {
                 server: None
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOWAS",
                 params: vec!["mat", "10"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
@@ -3032,46 +3563,51 @@ mod test {
                 server: Some("some.where.net")
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOWAS",
                 params: vec!["mat", "10", "some.where.net"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'WHOWAS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOWAS",
                 params: vec!["mat:"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 1 in command 'WHOWAS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOWAS",
                 params: vec!["mat", "XX"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 2 in command 'WHOWAS'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOWAS",
                 params: vec!["mat", "10", "somewherenet"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'WHOWAS' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "WHOWAS",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
@@ -3081,48 +3617,158 @@ mod test {
                 comment: "Killed!"
             }),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KILL",
                 params: vec!["bobby", "Killed!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Wrong parameter 0 in command 'KILL'".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KILL",
                 params: vec!["bob:by", "Killed!"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
         assert_eq!(
             Err("Command 'KILL' needs more parameters".to_string()),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "KILL",
                 params: vec!["bobby"]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(REHASH {}),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "REHASH",
                 params: vec![]
-            })
+            }, &limits)
             .map_err(|e| e.to_string())
         );
 
         assert_eq!(
             Ok(RESTART {}),
             Command::from_message(&Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "RESTART",
                 params: vec![]
-            })
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn test_command_from_message_limits() {
+        let limits = LimitsConfig {
+            nick_len: 5,
+            user_len: 5,
+            realname_len: 10,
+            topic_len: 10,
+            kick_len: 10,
+            away_len: 10,
+            channel_len: 6,
+            max_targets: 2,
+            ..LimitsConfig::default()
+        };
+        assert_eq!(
+            Ok(NICK { nickname: "greg" }),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "NICK",
+                params: vec!["greg"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 0 in command 'NICK'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "NICK",
+                params: vec!["gregory"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 0 in command 'USER'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "USER",
+                params: vec!["gregory", "0", "*", "Greg"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 3 in command 'USER'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "USER",
+                params: vec!["greg", "0", "*", "Gregory Somebody"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 0 in command 'JOIN'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "JOIN",
+                params: vec!["#dogs,#plants"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 1 in command 'TOPIC'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "TOPIC",
+                params: vec!["#dogs", "About dogs and cats"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 2 in command 'KICK'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "KICK",
+                params: vec!["#dogs", "greg", "Go away and never come back"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 0 in command 'AWAY'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "AWAY",
+                params: vec!["Gone for a long walk"]
+            }, &limits)
+            .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Err("Wrong parameter 0 in command 'PRIVMSG'".to_string()),
+            Command::from_message(&Message {
+                tags: HashMap::new(),
+                source: None,
+                command: "PRIVMSG",
+                params: vec!["greg,bob,#dogs", "Hello"]
+            }, &limits)
             .map_err(|e| e.to_string())
         );
     }
@@ -3132,6 +3778,7 @@ mod test {
         assert_eq!(
             ":buru USER guest 0 * :Ronnie Reagan".to_string(),
             Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "USER",
                 params: vec!["guest", "0", "*", "Ronnie Reagan"]
@@ -3141,6 +3788,7 @@ mod test {
         assert_eq!(
             ":buru USER guest 0 * :".to_string(),
             Message {
+                tags: HashMap::new(),
                 source: None,
                 command: "USER",
                 params: vec!["guest", "0", "*", ""]
@@ -3150,6 +3798,7 @@ mod test {
         assert_eq!(
             ":sonny INVITE mati1 #xxx".to_string(),
             Message {
+                tags: HashMap::new(),
                 source: Some("xxxx"),
                 command: "INVITE",
                 params: vec!["mati1", "#xxx"]
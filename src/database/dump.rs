@@ -0,0 +1,822 @@
+use crate::database::migrations::ServiceDatabases;
+use crate::database::{is_bot_kicker, is_channel_list, is_channel_setting, DbResult, ACCESS_FLAGS, CHANNEL_SETTINGS};
+use crate::utils::{casefold, validate_channel, validate_password_hash, validate_username};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, SystemTime};
+
+// Copia de los datos de servicios en JSON (--export-services / --import-services).
+// Las fechas son timestamps UNIX en segundos y las listas que falten se toman como vacías,
+// así que otro paquete de servicios puede generar sólo las partes que tenga.
+pub const DUMP_FORMAT: &str = "zeusircd2-services";
+pub const DUMP_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ServicesDump {
+    pub format: String,
+    pub version: u32,
+    #[serde(default)]
+    pub nicks: Vec<NickDump>,
+    #[serde(default)]
+    pub forbidden_nicks: Vec<ForbidDump>,
+    #[serde(default)]
+    pub vhost_requests: Vec<VhostRequestDump>,
+    #[serde(default)]
+    pub channels: Vec<ChannelDump>,
+    #[serde(default)]
+    pub forbidden_channels: Vec<ForbidDump>,
+    #[serde(default)]
+    pub memo_accounts: Vec<MemoAccountDump>,
+    #[serde(default)]
+    pub akills: Vec<AkillDump>,
+    #[serde(default)]
+    pub session_exceptions: Vec<SessionExceptionDump>,
+    #[serde(default)]
+    pub bots: Vec<BotDump>,
+    #[serde(default)]
+    pub bot_channels: Vec<BotChannelDump>,
+}
+
+// Nick registrado. `password` es el hash argon2 que genera --gen-password-hash.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NickDump {
+    pub nick: String,
+    pub password: String,
+    pub user: String,
+    pub registration_time: u64,
+    pub email: Option<String>,
+    pub url: Option<String>,
+    pub vhost: Option<String>,
+    pub last_vhost: Option<u64>,
+    #[serde(default)]
+    pub noaccess: bool,
+    #[serde(default)]
+    pub noop: bool,
+    #[serde(default)]
+    pub showmail: bool,
+}
+
+// Nick o canal prohibido con FORBID
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForbidDump {
+    pub name: String,
+    pub reason: String,
+    pub set_by: String,
+    pub set_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VhostRequestDump {
+    pub nick: String,
+    pub vhost: String,
+    pub request_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChannelDump {
+    pub name: String,
+    pub founder: String,
+    pub creation_time: u64,
+    pub topic: Option<String>,
+    pub topic_setter: Option<String>,
+    pub topic_time: Option<u64>,
+    pub modes: Option<String>,
+    // Opciones de CS SET; las que falten quedan con su valor por defecto
+    #[serde(default)]
+    pub settings: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub access: Vec<AccessDump>,
+    #[serde(default)]
+    pub akick: Vec<AkickDump>,
+    pub suspension: Option<SuspensionDump>,
+    // Listas b/e/I/Z, clave y límite guardados del canal
+    #[serde(default)]
+    pub lists: Vec<ListEntryDump>,
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccessDump {
+    pub nick: String,
+    pub flags: String,
+    pub added_by: String,
+    pub added_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AkickDump {
+    pub mask: String,
+    pub reason: Option<String>,
+    pub added_by: String,
+    pub added_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ListEntryDump {
+    pub list: char,
+    pub mask: String,
+    pub set_by: String,
+    pub set_time: u64,
+    pub expires: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SuspensionDump {
+    pub reason: String,
+    pub suspended_by: String,
+    pub suspended_time: u64,
+    pub expires: Option<u64>,
+}
+
+// Memos, límite e ignorados de una cuenta (nick o canal)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MemoAccountDump {
+    pub account: String,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub ignores: Vec<String>,
+    #[serde(default)]
+    pub memos: Vec<MemoDump>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MemoDump {
+    pub sender: String,
+    pub text: String,
+    pub sent_time: u64,
+    #[serde(default)]
+    pub read: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AkillDump {
+    pub mask: String,
+    pub reason: String,
+    pub set_by: String,
+    pub set_time: u64,
+    pub expires: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SessionExceptionDump {
+    pub ip: String,
+    pub limit: usize,
+    pub reason: String,
+    pub set_by: String,
+    pub set_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BotDump {
+    pub nick: String,
+    pub user: String,
+    pub host: String,
+    pub realname: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BotChannelDump {
+    pub channel: String,
+    pub bot: String,
+    pub greet: Option<String>,
+    #[serde(default)]
+    pub kickers: Vec<String>,
+    #[serde(default)]
+    pub badwords: Vec<String>,
+}
+
+// Resultado de una importación: entradas importadas y motivos de las omitidas
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: Vec<String>,
+}
+
+fn to_timestamp(time: SystemTime) -> DbResult<u64> {
+    Ok(time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs())
+}
+
+fn from_timestamp(timestamp: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp)
+}
+
+impl ServicesDump {
+    // Lee todos los datos de servicios a través de los traits de las bases de datos
+    pub async fn export(dbs: &ServiceDatabases) -> DbResult<ServicesDump> {
+        let mut nicks = Vec::new();
+        for nick in dbs.nick_db.get_nicks().await? {
+            let Some(info) = dbs.nick_db.get_nick_info(&nick).await? else {
+                continue;
+            };
+            let password = dbs.nick_db.get_nick_password(&nick).await?.unwrap_or_default();
+            nicks.push(NickDump {
+                nick,
+                password,
+                user: info.user,
+                registration_time: to_timestamp(info.registration_time)?,
+                email: info.email,
+                url: info.url,
+                vhost: info.vhost,
+                last_vhost: info.last_vhost.map(to_timestamp).transpose()?,
+                noaccess: info.noaccess,
+                noop: info.noop,
+                showmail: info.showmail,
+            });
+        }
+
+        let mut forbidden_nicks = Vec::new();
+        for entry in dbs.nick_db.get_forbidden_nicks().await? {
+            forbidden_nicks.push(ForbidDump {
+                name: entry.name,
+                reason: entry.reason,
+                set_by: entry.set_by,
+                set_time: to_timestamp(entry.set_time)?,
+            });
+        }
+
+        let mut vhost_requests = Vec::new();
+        for request in dbs.nick_db.get_vhost_requests().await? {
+            vhost_requests.push(VhostRequestDump {
+                nick: request.nick,
+                vhost: request.vhost,
+                request_time: to_timestamp(request.request_time)?,
+            });
+        }
+
+        let mut channels = Vec::new();
+        for name in dbs.chan_db.get_channels().await? {
+            let Some(info) = dbs.chan_db.get_channel_info(&name).await? else {
+                continue;
+            };
+            let mut settings = BTreeMap::new();
+            for (setting, _) in CHANNEL_SETTINGS {
+                settings.insert(setting.to_string(), dbs.chan_db.get_channel_setting(&name, setting).await?);
+            }
+            let mut access = Vec::new();
+            for entry in dbs.chan_db.get_channel_access_list(&name, None).await? {
+                access.push(AccessDump {
+                    nick: entry.nick,
+                    flags: entry.flags,
+                    added_by: entry.added_by,
+                    added_time: to_timestamp(entry.added_time)?,
+                });
+            }
+            let mut akick = Vec::new();
+            for (mask, reason, added_by, added_time) in dbs.chan_db.get_channel_akick_list(&name).await? {
+                akick.push(AkickDump { mask, reason, added_by, added_time: to_timestamp(added_time)? });
+            }
+            let suspension = match dbs.chan_db.get_channel_suspension(&name).await? {
+                Some(suspension) => Some(SuspensionDump {
+                    reason: suspension.reason,
+                    suspended_by: suspension.suspended_by,
+                    suspended_time: to_timestamp(suspension.suspended_time)?,
+                    expires: suspension.expires.map(to_timestamp).transpose()?,
+                }),
+                None => None,
+            };
+            let mut lists = Vec::new();
+            for entry in dbs.chan_db.get_channel_list_entries(&name).await? {
+                lists.push(ListEntryDump {
+                    list: entry.list,
+                    mask: entry.mask,
+                    set_by: entry.set_by,
+                    set_time: to_timestamp(entry.set_time)?,
+                    expires: entry.expires.map(to_timestamp).transpose()?,
+                });
+            }
+            let (key, limit) = dbs.chan_db.get_channel_key_limit(&name).await?;
+            channels.push(ChannelDump {
+                name,
+                founder: info.founder,
+                creation_time: to_timestamp(info.creation_time)?,
+                topic: info.topic,
+                topic_setter: info.topic_setter,
+                topic_time: info.topic_time.map(to_timestamp).transpose()?,
+                modes: info.modes,
+                settings,
+                access,
+                akick,
+                suspension,
+                lists,
+                key,
+                limit,
+            });
+        }
+
+        let mut forbidden_channels = Vec::new();
+        for entry in dbs.chan_db.get_forbidden_channels().await? {
+            forbidden_channels.push(ForbidDump {
+                name: entry.name,
+                reason: entry.reason,
+                set_by: entry.set_by,
+                set_time: to_timestamp(entry.set_time)?,
+            });
+        }
+
+        let mut memo_accounts = Vec::new();
+        for account in dbs.memo_db.get_memo_accounts().await? {
+            let mut memos = Vec::new();
+            for (_, sender, text, sent_time, read) in dbs.memo_db.get_memos(&account).await? {
+                memos.push(MemoDump { sender, text, sent_time: to_timestamp(sent_time)?, read });
+            }
+            memo_accounts.push(MemoAccountDump {
+                limit: dbs.memo_db.get_memo_limit(&account).await?,
+                ignores: dbs.memo_db.get_memo_ignores(&account).await?,
+                account,
+                memos,
+            });
+        }
+
+        let mut akills = Vec::new();
+        for akill in dbs.oper_db.get_akills().await? {
+            akills.push(AkillDump {
+                mask: akill.mask,
+                reason: akill.reason,
+                set_by: akill.set_by,
+                set_time: to_timestamp(akill.set_time)?,
+                expires: akill.expires.map(to_timestamp).transpose()?,
+            });
+        }
+
+        let mut session_exceptions = Vec::new();
+        for (ip, limit, reason, set_by, set_time) in dbs.oper_db.get_session_exceptions().await? {
+            session_exceptions.push(SessionExceptionDump { ip, limit, reason, set_by, set_time: to_timestamp(set_time)? });
+        }
+
+        let bots = dbs
+            .bot_db
+            .get_bots()
+            .await?
+            .into_iter()
+            .map(|(nick, user, host, realname)| BotDump { nick, user, host, realname })
+            .collect();
+
+        let mut bot_channels = Vec::new();
+        for (channel, bot, greet, kickers) in dbs.bot_db.get_bot_channels().await? {
+            let badwords = dbs.bot_db.get_badwords(&channel).await?;
+            bot_channels.push(BotChannelDump { channel, bot, greet, kickers, badwords });
+        }
+
+        Ok(ServicesDump {
+            format: DUMP_FORMAT.to_string(),
+            version: DUMP_VERSION,
+            nicks,
+            forbidden_nicks,
+            vhost_requests,
+            channels,
+            forbidden_channels,
+            memo_accounts,
+            akills,
+            session_exceptions,
+            bots,
+            bot_channels,
+        })
+    }
+
+    // Comprueba el contenido sin tocar la base de datos. Devuelve los errores encontrados.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.format != DUMP_FORMAT {
+            errors.push(format!("Unknown format '{}', expected '{DUMP_FORMAT}'", self.format));
+        }
+        if self.version == 0 || self.version > DUMP_VERSION {
+            errors.push(format!("Unsupported version {}", self.version));
+        }
+
+        let mut seen = HashSet::new();
+        for nick in &self.nicks {
+            if nick.nick.is_empty() || validate_username(&nick.nick).is_err() {
+                errors.push(format!("Invalid nick '{}'", nick.nick));
+            }
+            if !seen.insert(casefold(&nick.nick)) {
+                errors.push(format!("Duplicate nick '{}'", nick.nick));
+            }
+            if validate_password_hash(&nick.password).is_err() {
+                errors.push(format!("Nick '{}' has no valid password hash", nick.nick));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for channel in &self.channels {
+            if validate_channel(&channel.name).is_err() {
+                errors.push(format!("Invalid channel name '{}'", channel.name));
+            }
+            if !seen.insert(casefold(&channel.name)) {
+                errors.push(format!("Duplicate channel '{}'", channel.name));
+            }
+            for setting in channel.settings.keys() {
+                if !is_channel_setting(setting) {
+                    errors.push(format!("Unknown setting '{setting}' in {}", channel.name));
+                }
+            }
+            for entry in &channel.access {
+                if entry.flags.is_empty() || !entry.flags.chars().all(|c| ACCESS_FLAGS.contains(c)) {
+                    errors.push(format!(
+                        "Invalid access flags '{}' for {} in {}",
+                        entry.flags, entry.nick, channel.name
+                    ));
+                }
+            }
+            for entry in &channel.lists {
+                if !is_channel_list(entry.list) {
+                    errors.push(format!("Unknown list '{}' for {} in {}", entry.list, entry.mask, channel.name));
+                }
+            }
+        }
+
+        for forbid in &self.forbidden_channels {
+            if validate_channel(&forbid.name).is_err() {
+                errors.push(format!("Invalid forbidden channel name '{}'", forbid.name));
+            }
+        }
+
+        for bot_channel in &self.bot_channels {
+            if validate_channel(&bot_channel.channel).is_err() {
+                errors.push(format!("Invalid bot channel name '{}'", bot_channel.channel));
+            }
+            for kicker in &bot_channel.kickers {
+                if !is_bot_kicker(kicker) {
+                    errors.push(format!("Unknown kicker '{kicker}' in {}", bot_channel.channel));
+                }
+            }
+        }
+        errors
+    }
+
+    // Carga los datos en las bases de datos. Las entradas que ya existen (o que se repiten
+    // en el volcado) se omiten, igual que los memos de cuentas que ya tienen memos. Con
+    // dry_run sólo se comprueba qué se importaría, con las mismas comprobaciones.
+    pub async fn import(&self, dbs: &mut ServiceDatabases, dry_run: bool) -> DbResult<ImportReport> {
+        let mut report = ImportReport::default();
+
+        for nick in &self.nicks {
+            if dbs.nick_db.get_nick_info(&nick.nick).await?.is_some() {
+                report.skipped.push(format!("Nick {} already exists", nick.nick));
+                continue;
+            }
+            if !dry_run {
+                dbs.nick_db
+                    .add_nick(&nick.nick, &nick.password, &nick.user, from_timestamp(nick.registration_time))
+                    .await?;
+                dbs.nick_db
+                    .update_nick_info(
+                        &nick.nick,
+                        None,
+                        nick.email.as_deref(),
+                        nick.url.as_deref(),
+                        nick.vhost.as_deref(),
+                        nick.last_vhost.map(from_timestamp),
+                        Some(nick.noaccess),
+                        Some(nick.noop),
+                        Some(nick.showmail),
+                    )
+                    .await?;
+            }
+            report.imported += 1;
+        }
+
+        let mut forbidden_nicks: HashSet<String> =
+            dbs.nick_db.get_forbidden_nicks().await?.into_iter().map(|forbid| casefold(&forbid.name)).collect();
+        for forbid in &self.forbidden_nicks {
+            if !forbidden_nicks.insert(casefold(&forbid.name)) {
+                report.skipped.push(format!("Forbidden nick {} already exists", forbid.name));
+                continue;
+            }
+            if !dry_run {
+                dbs.nick_db
+                    .add_forbidden_nick(&forbid.name, &forbid.reason, &forbid.set_by, from_timestamp(forbid.set_time))
+                    .await?;
+            }
+            report.imported += 1;
+        }
+
+        let mut vhost_requests: HashSet<String> =
+            dbs.nick_db.get_vhost_requests().await?.into_iter().map(|request| casefold(&request.nick)).collect();
+        for request in &self.vhost_requests {
+            if !vhost_requests.insert(casefold(&request.nick)) {
+                report.skipped.push(format!("Vhost request of {} already exists", request.nick));
+                continue;
+            }
+            if !dry_run {
+                dbs.nick_db
+                    .add_vhost_request(&request.nick, &request.vhost, from_timestamp(request.request_time))
+                    .await?;
+            }
+            report.imported += 1;
+        }
+
+        for channel in &self.channels {
+            if dbs.chan_db.get_channel_info(&channel.name).await?.is_some() {
+                report.skipped.push(format!("Channel {} already exists", channel.name));
+                continue;
+            }
+            if !dry_run {
+                let name = channel.name.as_str();
+                dbs.chan_db.add_channel(name, &channel.founder, from_timestamp(channel.creation_time)).await?;
+                dbs.chan_db
+                    .update_channel_info(
+                        name,
+                        channel.topic.as_deref(),
+                        channel.topic_setter.as_deref(),
+                        channel.topic_time.map(from_timestamp),
+                        channel.modes.as_deref(),
+                    )
+                    .await?;
+                for (setting, enabled) in &channel.settings {
+                    dbs.chan_db.set_channel_setting(name, setting, *enabled).await?;
+                }
+                for entry in &channel.access {
+                    dbs.chan_db
+                        .add_channel_access(name, &entry.nick, &entry.flags, &entry.added_by, from_timestamp(entry.added_time))
+                        .await?;
+                }
+                for entry in &channel.akick {
+                    dbs.chan_db
+                        .add_channel_akick(
+                            name,
+                            &entry.mask,
+                            entry.reason.as_deref(),
+                            &entry.added_by,
+                            from_timestamp(entry.added_time),
+                        )
+                        .await?;
+                }
+                if let Some(suspension) = &channel.suspension {
+                    dbs.chan_db
+                        .suspend_channel(
+                            name,
+                            &suspension.reason,
+                            &suspension.suspended_by,
+                            from_timestamp(suspension.suspended_time),
+                            suspension.expires.map(from_timestamp),
+                        )
+                        .await?;
+                }
+                for entry in &channel.lists {
+                    dbs.chan_db
+                        .add_channel_list_entry(
+                            name,
+                            entry.list,
+                            &entry.mask,
+                            &entry.set_by,
+                            from_timestamp(entry.set_time),
+                            entry.expires.map(from_timestamp),
+                        )
+                        .await?;
+                }
+                if channel.key.is_some() || channel.limit.is_some() {
+                    dbs.chan_db.set_channel_key_limit(name, channel.key.as_deref(), channel.limit).await?;
+                }
+            }
+            report.imported += 1;
+        }
+
+        let mut forbidden_channels: HashSet<String> =
+            dbs.chan_db.get_forbidden_channels().await?.into_iter().map(|forbid| casefold(&forbid.name)).collect();
+        for forbid in &self.forbidden_channels {
+            if !forbidden_channels.insert(casefold(&forbid.name)) {
+                report.skipped.push(format!("Forbidden channel {} already exists", forbid.name));
+                continue;
+            }
+            if !dry_run {
+                dbs.chan_db
+                    .add_forbidden_channel(&forbid.name, &forbid.reason, &forbid.set_by, from_timestamp(forbid.set_time))
+                    .await?;
+            }
+            report.imported += 1;
+        }
+
+        for account in &self.memo_accounts {
+            let has_memos = !dbs.memo_db.get_memos(&account.account).await?.is_empty();
+            if has_memos && !account.memos.is_empty() {
+                report.skipped.push(format!("Memos of {} already exist", account.account));
+            }
+            let mut ignores: HashSet<String> =
+                dbs.memo_db.get_memo_ignores(&account.account).await?.iter().map(|ignored| casefold(ignored)).collect();
+            let mut new_ignores = Vec::new();
+            for ignored in &account.ignores {
+                if ignores.insert(casefold(ignored)) {
+                    new_ignores.push(ignored);
+                } else {
+                    report.skipped.push(format!("Memo ignore of {ignored} for {} already exists", account.account));
+                }
+            }
+            if !dry_run {
+                if account.limit.is_some() {
+                    dbs.memo_db.set_memo_limit(&account.account, account.limit).await?;
+                }
+                for ignored in new_ignores {
+                    dbs.memo_db.add_memo_ignore(&account.account, ignored).await?;
+                }
+                if !has_memos && !account.memos.is_empty() {
+                    for memo in &account.memos {
+                        dbs.memo_db
+                            .add_memo(&account.account, &memo.sender, &memo.text, from_timestamp(memo.sent_time))
+                            .await?;
+                    }
+                    // los memos se devuelven en el mismo orden en que se han añadido
+                    let stored = dbs.memo_db.get_memos(&account.account).await?;
+                    for ((id, ..), memo) in stored.iter().zip(&account.memos) {
+                        if memo.read {
+                            dbs.memo_db.mark_memo_read(*id).await?;
+                        }
+                    }
+                }
+            }
+            report.imported += 1;
+        }
+
+        // las máscaras de AKILL se guardan en minúsculas
+        let mut akills: HashSet<String> =
+            dbs.oper_db.get_akills().await?.into_iter().map(|akill| akill.mask.to_lowercase()).collect();
+        for akill in &self.akills {
+            if !akills.insert(akill.mask.to_lowercase()) {
+                report.skipped.push(format!("AKILL {} already exists", akill.mask));
+                continue;
+            }
+            if !dry_run {
+                dbs.oper_db
+                    .add_akill(
+                        &akill.mask,
+                        &akill.reason,
+                        &akill.set_by,
+                        from_timestamp(akill.set_time),
+                        akill.expires.map(from_timestamp),
+                    )
+                    .await?;
+            }
+            report.imported += 1;
+        }
+
+        let mut session_exceptions: HashSet<String> =
+            dbs.oper_db.get_session_exceptions().await?.into_iter().map(|(ip, ..)| ip).collect();
+        for exception in &self.session_exceptions {
+            if !session_exceptions.insert(exception.ip.clone()) {
+                report.skipped.push(format!("Session exception for {} already exists", exception.ip));
+                continue;
+            }
+            if !dry_run {
+                dbs.oper_db
+                    .add_session_exception(
+                        &exception.ip,
+                        exception.limit,
+                        &exception.reason,
+                        &exception.set_by,
+                        from_timestamp(exception.set_time),
+                    )
+                    .await?;
+            }
+            report.imported += 1;
+        }
+
+        let mut bots: HashSet<String> =
+            dbs.bot_db.get_bots().await?.into_iter().map(|(nick, ..)| casefold(&nick)).collect();
+        for bot in &self.bots {
+            if !bots.insert(casefold(&bot.nick)) {
+                report.skipped.push(format!("Bot {} already exists", bot.nick));
+                continue;
+            }
+            if !dry_run {
+                dbs.bot_db.add_bot(&bot.nick, &bot.user, &bot.host, &bot.realname, SystemTime::now()).await?;
+            }
+            report.imported += 1;
+        }
+
+        for bot_channel in &self.bot_channels {
+            if !bots.contains(&casefold(&bot_channel.bot)) {
+                report.skipped.push(format!("Bot {} of {} does not exist", bot_channel.bot, bot_channel.channel));
+                continue;
+            }
+            if !dry_run {
+                let channel = bot_channel.channel.as_str();
+                dbs.bot_db.assign_bot(channel, &bot_channel.bot).await?;
+                dbs.bot_db.set_bot_greet(channel, bot_channel.greet.as_deref()).await?;
+                for kicker in &bot_channel.kickers {
+                    dbs.bot_db.set_bot_kicker(channel, kicker, true).await?;
+                }
+                for word in &bot_channel.badwords {
+                    dbs.bot_db.add_badword(channel, word).await?;
+                }
+            }
+            report.imported += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_services_dump_validate() {
+        let json = r##"{
+            "format": "zeusircd2-services",
+            "version": 1,
+            "nicks": [
+                {"nick": "mati", "password": "bad", "user": "mati@host", "registration_time": 1700000000},
+                {"nick": "Mati", "password": "bad", "user": "mati@host", "registration_time": 1700000000}
+            ],
+            "channels": [
+                {"name": "#zeus", "founder": "mati", "creation_time": 1700000000,
+                 "settings": {"keeptopic": false, "colors": true},
+                 "access": [{"nick": "bob", "flags": "vx", "added_by": "mati", "added_time": 1700000000}]}
+            ],
+            "bot_channels": [{"channel": "zeus", "bot": "Zeus", "kickers": ["caps", "flood"]}]
+        }"##;
+        let dump: ServicesDump = serde_json::from_str(json).unwrap();
+        assert!(dump.memo_accounts.is_empty());
+        assert!(!dump.nicks[0].showmail);
+        assert_eq!(
+            vec![
+                "Nick 'mati' has no valid password hash".to_string(),
+                "Duplicate nick 'Mati'".to_string(),
+                "Nick 'Mati' has no valid password hash".to_string(),
+                "Unknown setting 'colors' in #zeus".to_string(),
+                "Invalid access flags 'vx' for bob in #zeus".to_string(),
+                "Invalid bot channel name 'zeus'".to_string(),
+                "Unknown kicker 'flood' in zeus".to_string(),
+            ],
+            dump.validate()
+        );
+
+        let dump = ServicesDump {
+            format: DUMP_FORMAT.to_string(),
+            version: DUMP_VERSION,
+            nicks: vec![],
+            forbidden_nicks: vec![],
+            vhost_requests: vec![],
+            channels: vec![],
+            forbidden_channels: vec![],
+            memo_accounts: vec![],
+            akills: vec![],
+            session_exceptions: vec![],
+            bots: vec![],
+            bot_channels: vec![],
+        };
+        assert!(dump.validate().is_empty());
+        let json = serde_json::to_string(&dump).unwrap();
+        assert_eq!(dump, serde_json::from_str(&json).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_services_dump_import_skips_existing() {
+        let json = r##"{
+            "format": "zeusircd2-services",
+            "version": 1,
+            "forbidden_nicks": [
+                {"name": "badnick", "reason": "Spam", "set_by": "oper", "set_time": 1700000000},
+                {"name": "BadNick", "reason": "Spam", "set_by": "oper", "set_time": 1700000000}
+            ],
+            "vhost_requests": [{"nick": "mati", "vhost": "mati.vhost", "request_time": 1700000000}],
+            "forbidden_channels": [{"name": "#evil", "reason": "Spam", "set_by": "oper", "set_time": 1700000000}],
+            "memo_accounts": [{"account": "mati", "limit": null, "ignores": ["bob", "Bob"]}],
+            "akills": [{"mask": "*@Spam.host", "reason": "Spam", "set_by": "oper", "set_time": 1700000000, "expires": null}],
+            "session_exceptions": [{"ip": "10.0.0.1", "limit": 10, "reason": "Office", "set_by": "oper", "set_time": 1700000000}]
+        }"##;
+        let dump: ServicesDump = serde_json::from_str(json).unwrap();
+        assert!(dump.validate().is_empty());
+        let db_config = crate::config::DatabaseConfig {
+            database: "memory".to_string(),
+            url: String::new(),
+            forbidden_vhosts: vec![],
+            snapshot_interval: None,
+        };
+        let mut dbs = ServiceDatabases::open(&db_config).await.unwrap();
+
+        // las repeticiones dentro del volcado se omiten también en dry-run
+        let first = ImportReport {
+            imported: 6,
+            skipped: vec![
+                "Forbidden nick BadNick already exists".to_string(),
+                "Memo ignore of Bob for mati already exists".to_string(),
+            ],
+        };
+        assert_eq!(first, dump.import(&mut dbs, true).await.unwrap());
+        assert!(dbs.nick_db.get_forbidden_nicks().await.unwrap().is_empty());
+        assert_eq!(first, dump.import(&mut dbs, false).await.unwrap());
+
+        // la segunda importación no falla y omite todo lo que ya existe
+        let second = ImportReport {
+            imported: 1,
+            skipped: vec![
+                "Forbidden nick badnick already exists".to_string(),
+                "Forbidden nick BadNick already exists".to_string(),
+                "Vhost request of mati already exists".to_string(),
+                "Forbidden channel #evil already exists".to_string(),
+                "Memo ignore of bob for mati already exists".to_string(),
+                "Memo ignore of Bob for mati already exists".to_string(),
+                "AKILL *@Spam.host already exists".to_string(),
+                "Session exception for 10.0.0.1 already exists".to_string(),
+            ],
+        };
+        assert_eq!(second, dump.import(&mut dbs, true).await.unwrap());
+        assert_eq!(second, dump.import(&mut dbs, false).await.unwrap());
+        assert_eq!(1, dbs.nick_db.get_forbidden_nicks().await.unwrap().len());
+        assert_eq!(vec!["bob".to_string()], dbs.memo_db.get_memo_ignores("mati").await.unwrap());
+    }
+}
@@ -0,0 +1,1180 @@
+use crate::database::dump::ServicesDump;
+use crate::database::migrations::ServiceDatabases;
+use crate::database::{NickDatabase, ChannelDatabase, MemoDatabase, OperDatabase, BotDatabase, SchemaDatabase};
+use crate::database::{AccessEntry, AkillEntry, ChannelListEntry, ChannelRecord, ChannelSuspension, DbResult, ForbiddenEntry, NickRecord, VhostRequest};
+use crate::utils::casefold;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tracing::*;
+
+// Segundos entre dos copias del almacén si no se configura snapshot_interval
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 300;
+
+struct NickRow {
+    password: String,
+    record: NickRecord,
+}
+
+struct ChannelRow {
+    record: ChannelRecord,
+    settings: BTreeMap<String, bool>,
+    key: Option<String>,
+    limit: Option<usize>,
+}
+
+struct MemoRow {
+    recipient: String,
+    sender: String,
+    text: String,
+    sent_time: SystemTime,
+    read: bool,
+}
+
+struct BotChannelRow {
+    bot: String,
+    greet: Option<String>,
+    kickers: HashSet<String>,
+}
+
+// Datos de todos los servicios, con las mismas claves que las tablas de SQLite
+#[derive(Default)]
+pub struct MemoryStore {
+    nicks: BTreeMap<String, NickRow>,
+    forbidden_nicks: BTreeMap<String, ForbiddenEntry>,
+    vhost_requests: BTreeMap<String, VhostRequest>,
+    channels: BTreeMap<String, ChannelRow>,
+    channel_access: BTreeMap<(String, String), AccessEntry>,
+    channel_lists: BTreeMap<(String, char, String), ChannelListEntry>,
+    channel_akicks: BTreeMap<(String, String), (Option<String>, String, SystemTime)>,
+    channel_suspensions: BTreeMap<String, ChannelSuspension>,
+    forbidden_channels: BTreeMap<String, ForbiddenEntry>,
+    memos: BTreeMap<i64, MemoRow>,
+    last_memo_id: i64,
+    memo_limits: BTreeMap<String, usize>,
+    memo_ignores: BTreeSet<(String, String)>,
+    akills: BTreeMap<String, AkillEntry>,
+    session_exceptions: BTreeMap<String, (usize, String, String, SystemTime)>,
+    bots: BTreeMap<String, (String, String, String, SystemTime)>,
+    bot_channels: BTreeMap<String, BotChannelRow>,
+    bot_badwords: BTreeSet<(String, String)>,
+    schema_versions: BTreeMap<u32, (String, SystemTime)>,
+}
+
+pub type SharedMemoryStore = Arc<Mutex<MemoryStore>>;
+
+impl MemoryStore {
+    pub fn shared() -> SharedMemoryStore {
+        Arc::new(Mutex::new(MemoryStore::default()))
+    }
+}
+
+// Ningún método espera con el almacén bloqueado, así que un Mutex normal basta
+fn lock(store: &SharedMemoryStore) -> MutexGuard<'_, MemoryStore> {
+    store.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Como las demás bases de datos, las fechas se guardan en segundos UNIX
+fn stored_time(time: SystemTime) -> DbResult<SystemTime> {
+    let secs = time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn stored_expires(expires: Option<SystemTime>) -> DbResult<Option<SystemTime>> {
+    expires.map(stored_time).transpose()
+}
+
+// Bases de datos de todos los servicios sobre el mismo almacén
+pub fn memory_databases(store: &SharedMemoryStore) -> ServiceDatabases {
+    ServiceDatabases {
+        nick_db: Box::new(MemoryNickDatabase::new(store.clone())),
+        chan_db: Box::new(MemoryChannelDatabase::new(store.clone())),
+        memo_db: Box::new(MemoryMemoDatabase::new(store.clone())),
+        oper_db: Box::new(MemoryOperDatabase::new(store.clone())),
+        bot_db: Box::new(MemoryBotDatabase::new(store.clone())),
+        schema_db: Box::new(MemorySchemaDatabase::new(store.clone())),
+        snapshot: None,
+    }
+}
+
+// Copia del almacén en un fichero JSON con el formato de --export-services
+#[derive(Clone)]
+pub struct MemorySnapshot {
+    store: SharedMemoryStore,
+    path: PathBuf,
+}
+
+impl MemorySnapshot {
+    pub fn new(store: SharedMemoryStore, path: &str) -> Self {
+        MemorySnapshot { store, path: PathBuf::from(path) }
+    }
+
+    // Carga la última copia, si existe, y devuelve el número de entradas cargadas
+    pub async fn load(&self) -> Result<usize, String> {
+        if !self.path.exists() {
+            return Ok(0);
+        }
+        let path = self.path.display();
+        let content = tokio::fs::read_to_string(&self.path).await.map_err(|e| format!("{path}: {e}"))?;
+        let dump: ServicesDump = serde_json::from_str(&content).map_err(|e| format!("{path}: {e}"))?;
+        let errors = dump.validate();
+        if !errors.is_empty() {
+            return Err(format!("{path}: {}", errors.join(", ")));
+        }
+        let report = dump
+            .import(&mut memory_databases(&self.store), false)
+            .await
+            .map_err(|e| format!("{path}: {e}"))?;
+        Ok(report.imported)
+    }
+
+    // Escribe primero un fichero temporal para no dejar una copia a medias
+    pub async fn save(&self) -> Result<(), String> {
+        let dump = ServicesDump::export(&memory_databases(&self.store)).await.map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(&dump).map_err(|e| e.to_string())?;
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        tokio::fs::write(&tmp_path, json).await.map_err(|e| e.to_string())?;
+        tokio::fs::rename(&tmp_path, &self.path).await.map_err(|e| e.to_string())
+    }
+
+    // Guarda una copia cada `interval` mientras el servidor esté en marcha
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // el primer tick es inmediato y acabamos de cargar la copia
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.save().await {
+                    error!("Can't save database snapshot to {}: {e}", self.path.display());
+                }
+            }
+        })
+    }
+}
+
+pub struct MemoryNickDatabase {
+    store: SharedMemoryStore,
+}
+
+impl MemoryNickDatabase {
+    pub fn new(store: SharedMemoryStore) -> Self {
+        MemoryNickDatabase { store }
+    }
+}
+
+#[async_trait]
+impl NickDatabase for MemoryNickDatabase {
+    async fn connect(&mut self, _db_config: &str) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_nick(
+        &mut self,
+        nick: &str,
+        password: &str,
+        user: &str,
+        registration_time: SystemTime,
+    ) -> DbResult<()> {
+        let registration_time = stored_time(registration_time)?;
+        let mut store = lock(&self.store);
+        if store.nicks.contains_key(&casefold(nick)) {
+            return Err(format!("Nick {nick} already exists").into());
+        }
+        let record = NickRecord {
+            user: user.to_string(),
+            registration_time,
+            email: None,
+            url: None,
+            vhost: None,
+            last_vhost: None,
+            noaccess: false,
+            noop: false,
+            showmail: false,
+        };
+        store.nicks.insert(casefold(nick), NickRow { password: password.to_string(), record });
+        Ok(())
+    }
+
+    async fn get_nick_info(&self, nick: &str) -> DbResult<Option<NickRecord>> {
+        Ok(lock(&self.store).nicks.get(&casefold(nick)).map(|row| row.record.clone()))
+    }
+
+    async fn get_nick_password(&self, nick: &str) -> DbResult<Option<String>> {
+        Ok(lock(&self.store).nicks.get(&casefold(nick)).map(|row| row.password.clone()))
+    }
+
+    async fn update_nick_password(&mut self, nick: &str, password: &str) -> DbResult<()> {
+        if let Some(row) = lock(&self.store).nicks.get_mut(&casefold(nick)) {
+            row.password = password.to_string();
+        }
+        Ok(())
+    }
+
+    async fn update_nick_info(
+        &mut self,
+        nick: &str,
+        user: Option<&str>,
+        email: Option<&str>,
+        url: Option<&str>,
+        vhost: Option<&str>,
+        last_vhost: Option<SystemTime>,
+        noaccess: Option<bool>,
+        noop: Option<bool>,
+        showmail: Option<bool>,
+    ) -> DbResult<()> {
+        let last_vhost = stored_expires(last_vhost)?;
+        if let Some(row) = lock(&self.store).nicks.get_mut(&casefold(nick)) {
+            let record = &mut row.record;
+            if let Some(u) = user {
+                record.user = u.to_string();
+            }
+            if let Some(e) = email {
+                record.email = Some(e.to_string());
+            }
+            if let Some(u) = url {
+                record.url = Some(u.to_string());
+            }
+            if let Some(v) = vhost {
+                record.vhost = Some(v.to_string());
+            }
+            if last_vhost.is_some() {
+                record.last_vhost = last_vhost;
+            }
+            if let Some(na) = noaccess {
+                record.noaccess = na;
+            }
+            if let Some(n) = noop {
+                record.noop = n;
+            }
+            if let Some(sm) = showmail {
+                record.showmail = sm;
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_nick(&mut self, nick: &str) -> DbResult<()> {
+        lock(&self.store).nicks.remove(&casefold(nick));
+        Ok(())
+    }
+
+    async fn get_nicks(&self) -> DbResult<Vec<String>> {
+        Ok(lock(&self.store).nicks.keys().cloned().collect())
+    }
+
+    async fn create_forbid_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_forbidden_nick(
+        &mut self,
+        nick: &str,
+        reason: &str,
+        set_by: &str,
+        set_time: SystemTime,
+    ) -> DbResult<()> {
+        let entry = ForbiddenEntry {
+            name: casefold(nick),
+            reason: reason.to_string(),
+            set_by: set_by.to_string(),
+            set_time: stored_time(set_time)?,
+        };
+        lock(&self.store).forbidden_nicks.insert(entry.name.clone(), entry);
+        Ok(())
+    }
+
+    async fn get_forbidden_nick(&self, nick: &str) -> DbResult<Option<ForbiddenEntry>> {
+        Ok(lock(&self.store).forbidden_nicks.get(&casefold(nick)).cloned())
+    }
+
+    async fn delete_forbidden_nick(&mut self, nick: &str) -> DbResult<bool> {
+        Ok(lock(&self.store).forbidden_nicks.remove(&casefold(nick)).is_some())
+    }
+
+    async fn get_forbidden_nicks(&self) -> DbResult<Vec<ForbiddenEntry>> {
+        Ok(lock(&self.store).forbidden_nicks.values().cloned().collect())
+    }
+
+    async fn create_vhost_request_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_vhost_request(
+        &mut self,
+        nick: &str,
+        vhost: &str,
+        request_time: SystemTime,
+    ) -> DbResult<()> {
+        let request = VhostRequest {
+            nick: casefold(nick),
+            vhost: vhost.to_string(),
+            request_time: stored_time(request_time)?,
+        };
+        lock(&self.store).vhost_requests.insert(request.nick.clone(), request);
+        Ok(())
+    }
+
+    async fn get_vhost_request(&self, nick: &str) -> DbResult<Option<VhostRequest>> {
+        Ok(lock(&self.store).vhost_requests.get(&casefold(nick)).cloned())
+    }
+
+    async fn get_vhost_requests(&self) -> DbResult<Vec<VhostRequest>> {
+        let mut results: Vec<_> = lock(&self.store).vhost_requests.values().cloned().collect();
+        results.sort_by_key(|request| request.request_time);
+        Ok(results)
+    }
+
+    async fn delete_vhost_request(&mut self, nick: &str) -> DbResult<bool> {
+        Ok(lock(&self.store).vhost_requests.remove(&casefold(nick)).is_some())
+    }
+
+    // las claves se guardan ya en forma canónica
+    async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+}
+
+pub struct MemoryChannelDatabase {
+    store: SharedMemoryStore,
+}
+
+impl MemoryChannelDatabase {
+    pub fn new(store: SharedMemoryStore) -> Self {
+        MemoryChannelDatabase { store }
+    }
+}
+
+#[async_trait]
+impl ChannelDatabase for MemoryChannelDatabase {
+    async fn connect(&mut self, _db_config: &str) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_channel(
+        &mut self,
+        channel_name: &str,
+        creator_nick: &str,
+        creation_time: SystemTime,
+    ) -> DbResult<()> {
+        let creation_time = stored_time(creation_time)?;
+        let mut store = lock(&self.store);
+        if store.channels.contains_key(&casefold(channel_name)) {
+            return Err(format!("Channel {channel_name} already exists").into());
+        }
+        let record = ChannelRecord {
+            founder: creator_nick.to_string(),
+            creation_time,
+            topic: None,
+            modes: Some("+r".to_string()),
+            topic_setter: None,
+            topic_time: None,
+        };
+        let settings = crate::database::CHANNEL_SETTINGS
+            .iter()
+            .map(|(setting, default)| (setting.to_string(), *default))
+            .collect();
+        store.channels.insert(casefold(channel_name), ChannelRow { record, settings, key: None, limit: None });
+        Ok(())
+    }
+
+    async fn get_channel_info(&self, channel_name: &str) -> DbResult<Option<ChannelRecord>> {
+        Ok(lock(&self.store).channels.get(&casefold(channel_name)).map(|row| row.record.clone()))
+    }
+
+    async fn update_channel_info(
+        &mut self,
+        channel_name: &str,
+        topic: Option<&str>,
+        topic_setter: Option<&str>,
+        topic_time: Option<SystemTime>,
+        modes: Option<&str>,
+    ) -> DbResult<()> {
+        let topic_time = stored_expires(topic_time)?;
+        if let Some(row) = lock(&self.store).channels.get_mut(&casefold(channel_name)) {
+            let record = &mut row.record;
+            if let Some(t) = topic {
+                record.topic = Some(t.to_string());
+            }
+            if let Some(ts) = topic_setter {
+                record.topic_setter = Some(ts.to_string());
+            }
+            if topic_time.is_some() {
+                record.topic_time = topic_time;
+            }
+            if let Some(m) = modes {
+                record.modes = Some(m.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_channel_owner(&mut self, channel_name: &str, new_owner: &str) -> DbResult<()> {
+        if let Some(row) = lock(&self.store).channels.get_mut(&casefold(channel_name)) {
+            row.record.founder = new_owner.to_string();
+        }
+        Ok(())
+    }
+
+    async fn delete_channel(&mut self, channel_name: &str) -> DbResult<()> {
+        let channel_name = casefold(channel_name);
+        let mut store = lock(&self.store);
+        store.channels.remove(&channel_name);
+        store.channel_lists.retain(|(channel, _, _), _| *channel != channel_name);
+        Ok(())
+    }
+
+    async fn get_channels(&self) -> DbResult<Vec<String>> {
+        Ok(lock(&self.store).channels.keys().cloned().collect())
+    }
+
+    async fn create_access_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_channel_access(
+        &mut self,
+        channel_name: &str,
+        nick: &str,
+        flags: &str,
+        added_by: &str,
+        added_time: SystemTime,
+    ) -> DbResult<()> {
+        let entry = AccessEntry {
+            nick: casefold(nick),
+            flags: flags.to_string(),
+            added_by: added_by.to_string(),
+            added_time: stored_time(added_time)?,
+        };
+        lock(&self.store)
+            .channel_access
+            .insert((casefold(channel_name), casefold(nick)), entry);
+        Ok(())
+    }
+
+    async fn get_channel_access(&self, channel_name: &str, nick: &str) -> DbResult<Option<AccessEntry>> {
+        Ok(lock(&self.store)
+            .channel_access
+            .get(&(casefold(channel_name), casefold(nick)))
+            .cloned())
+    }
+
+    async fn get_channel_access_list(&self, channel_name: &str, flags: Option<&str>) -> DbResult<Vec<AccessEntry>> {
+        let channel_name = casefold(channel_name);
+        let mut results: Vec<AccessEntry> = lock(&self.store)
+            .channel_access
+            .iter()
+            .filter(|((channel, _), entry)| *channel == channel_name && flags.is_none_or(|f| entry.flags == f))
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        results.sort_by(|a, b| a.flags.cmp(&b.flags).then(a.added_time.cmp(&b.added_time)));
+        Ok(results)
+    }
+
+    async fn update_channel_access(
+        &mut self,
+        channel_name: &str,
+        nick: &str,
+        flags: &str,
+        updated_by: &str,
+        updated_time: SystemTime,
+    ) -> DbResult<()> {
+        let updated_time = stored_time(updated_time)?;
+        if let Some(entry) = lock(&self.store)
+            .channel_access
+            .get_mut(&(casefold(channel_name), casefold(nick)))
+        {
+            entry.flags = flags.to_string();
+            entry.added_by = updated_by.to_string();
+            entry.added_time = updated_time;
+        }
+        Ok(())
+    }
+
+    async fn delete_channel_access(&mut self, channel_name: &str, nick: &str) -> DbResult<()> {
+        lock(&self.store)
+            .channel_access
+            .remove(&(casefold(channel_name), casefold(nick)));
+        Ok(())
+    }
+
+    async fn create_akick_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_channel_akick(
+        &mut self,
+        channel_name: &str,
+        mask: &str,
+        reason: Option<&str>,
+        added_by: &str,
+        added_time: SystemTime,
+    ) -> DbResult<()> {
+        let added_time = stored_time(added_time)?;
+        lock(&self.store).channel_akicks.insert(
+            (casefold(channel_name), mask.to_string()),
+            (reason.map(|r| r.to_string()), added_by.to_string(), added_time),
+        );
+        Ok(())
+    }
+
+    async fn get_channel_akick_list(
+        &self,
+        channel_name: &str,
+    ) -> DbResult<Vec<(String, Option<String>, String, SystemTime)>> {
+        let channel_name = casefold(channel_name);
+        let mut results: Vec<_> = lock(&self.store)
+            .channel_akicks
+            .iter()
+            .filter(|((channel, _), _)| *channel == channel_name)
+            .map(|((_, mask), (reason, added_by, added_time))| (mask.clone(), reason.clone(), added_by.clone(), *added_time))
+            .collect();
+        results.sort_by_key(|(_, _, _, added_time)| *added_time);
+        Ok(results)
+    }
+
+    async fn delete_channel_akick(&mut self, channel_name: &str, mask: &str) -> DbResult<bool> {
+        Ok(lock(&self.store)
+            .channel_akicks
+            .remove(&(casefold(channel_name), mask.to_string()))
+            .is_some())
+    }
+
+    async fn create_suspend_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn suspend_channel(
+        &mut self,
+        channel_name: &str,
+        reason: &str,
+        suspended_by: &str,
+        suspended_time: SystemTime,
+        expires: Option<SystemTime>,
+    ) -> DbResult<()> {
+        let suspension = ChannelSuspension {
+            reason: reason.to_string(),
+            suspended_by: suspended_by.to_string(),
+            suspended_time: stored_time(suspended_time)?,
+            expires: stored_expires(expires)?,
+        };
+        lock(&self.store)
+            .channel_suspensions
+            .insert(casefold(channel_name), suspension);
+        Ok(())
+    }
+
+    async fn get_channel_suspension(&self, channel_name: &str) -> DbResult<Option<ChannelSuspension>> {
+        Ok(lock(&self.store).channel_suspensions.get(&casefold(channel_name)).cloned())
+    }
+
+    async fn unsuspend_channel(&mut self, channel_name: &str) -> DbResult<bool> {
+        Ok(lock(&self.store).channel_suspensions.remove(&casefold(channel_name)).is_some())
+    }
+
+    async fn create_forbid_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_forbidden_channel(
+        &mut self,
+        channel_name: &str,
+        reason: &str,
+        set_by: &str,
+        set_time: SystemTime,
+    ) -> DbResult<()> {
+        let entry = ForbiddenEntry {
+            name: casefold(channel_name),
+            reason: reason.to_string(),
+            set_by: set_by.to_string(),
+            set_time: stored_time(set_time)?,
+        };
+        lock(&self.store).forbidden_channels.insert(entry.name.clone(), entry);
+        Ok(())
+    }
+
+    async fn get_forbidden_channel(&self, channel_name: &str) -> DbResult<Option<ForbiddenEntry>> {
+        Ok(lock(&self.store).forbidden_channels.get(&casefold(channel_name)).cloned())
+    }
+
+    async fn delete_forbidden_channel(&mut self, channel_name: &str) -> DbResult<bool> {
+        Ok(lock(&self.store).forbidden_channels.remove(&casefold(channel_name)).is_some())
+    }
+
+    async fn get_forbidden_channels(&self) -> DbResult<Vec<ForbiddenEntry>> {
+        Ok(lock(&self.store).forbidden_channels.values().cloned().collect())
+    }
+
+    async fn get_channel_setting(&self, channel_name: &str, setting: &str) -> DbResult<bool> {
+        if !crate::database::is_channel_setting(setting) {
+            return Err(format!("Unknown channel setting: {setting}").into());
+        }
+        Ok(lock(&self.store)
+            .channels
+            .get(&casefold(channel_name))
+            .map(|row| row.settings.get(setting).copied().unwrap_or(crate::database::channel_setting_default(setting)))
+            .unwrap_or(false))
+    }
+
+    async fn set_channel_setting(&mut self, channel_name: &str, setting: &str, enabled: bool) -> DbResult<()> {
+        if !crate::database::is_channel_setting(setting) {
+            return Err(format!("Unknown channel setting: {setting}").into());
+        }
+        if let Some(row) = lock(&self.store).channels.get_mut(&casefold(channel_name)) {
+            row.settings.insert(setting.to_string(), enabled);
+        }
+        Ok(())
+    }
+
+    async fn get_channels_with_setting(&self, setting: &str) -> DbResult<Vec<String>> {
+        if !crate::database::is_channel_setting(setting) {
+            return Err(format!("Unknown channel setting: {setting}").into());
+        }
+        Ok(lock(&self.store)
+            .channels
+            .iter()
+            .filter(|(_, row)| row.settings.get(setting).copied().unwrap_or(crate::database::channel_setting_default(setting)))
+            .map(|(channel, _)| channel.clone())
+            .collect())
+    }
+
+    // El almacén se crea siempre con el esquema actual, no hay nada que migrar
+    async fn migrate_topic_fields(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn migrate_access_flags(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn migrate_channel_settings(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn create_list_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_channel_list_entry(
+        &mut self,
+        channel_name: &str,
+        list: char,
+        mask: &str,
+        set_by: &str,
+        set_time: SystemTime,
+        expires: Option<SystemTime>,
+    ) -> DbResult<()> {
+        if !crate::database::is_channel_list(list) {
+            return Err(format!("Unknown channel list: {list}").into());
+        }
+        let entry = ChannelListEntry {
+            list,
+            mask: mask.to_string(),
+            set_by: set_by.to_string(),
+            set_time: stored_time(set_time)?,
+            expires: stored_expires(expires)?,
+        };
+        lock(&self.store)
+            .channel_lists
+            .insert((casefold(channel_name), list, mask.to_string()), entry);
+        Ok(())
+    }
+
+    async fn get_channel_list_entries(&self, channel_name: &str) -> DbResult<Vec<ChannelListEntry>> {
+        let channel_name = casefold(channel_name);
+        let mut results: Vec<ChannelListEntry> = lock(&self.store)
+            .channel_lists
+            .iter()
+            .filter(|((channel, _, _), _)| *channel == channel_name)
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        results.sort_by(|a, b| a.set_time.cmp(&b.set_time).then_with(|| a.mask.cmp(&b.mask)));
+        Ok(results)
+    }
+
+    async fn delete_channel_list_entry(&mut self, channel_name: &str, list: char, mask: &str) -> DbResult<bool> {
+        Ok(lock(&self.store)
+            .channel_lists
+            .remove(&(casefold(channel_name), list, mask.to_string()))
+            .is_some())
+    }
+
+    async fn get_channel_key_limit(&self, channel_name: &str) -> DbResult<(Option<String>, Option<usize>)> {
+        Ok(lock(&self.store)
+            .channels
+            .get(&casefold(channel_name))
+            .map(|row| (row.key.clone(), row.limit))
+            .unwrap_or((None, None)))
+    }
+
+    async fn set_channel_key_limit(&mut self, channel_name: &str, key: Option<&str>, limit: Option<usize>) -> DbResult<()> {
+        if let Some(row) = lock(&self.store).channels.get_mut(&casefold(channel_name)) {
+            row.key = key.map(|k| k.to_string());
+            row.limit = limit;
+        }
+        Ok(())
+    }
+
+    async fn migrate_channel_key_limit(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+}
+
+pub struct MemoryMemoDatabase {
+    store: SharedMemoryStore,
+}
+
+impl MemoryMemoDatabase {
+    pub fn new(store: SharedMemoryStore) -> Self {
+        MemoryMemoDatabase { store }
+    }
+}
+
+#[async_trait]
+impl MemoDatabase for MemoryMemoDatabase {
+    async fn connect(&mut self, _db_config: &str) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_memo(
+        &mut self,
+        recipient: &str,
+        sender: &str,
+        text: &str,
+        sent_time: SystemTime,
+    ) -> DbResult<()> {
+        let sent_time = stored_time(sent_time)?;
+        let mut store = lock(&self.store);
+        store.last_memo_id += 1;
+        let id = store.last_memo_id;
+        store.memos.insert(
+            id,
+            MemoRow {
+                recipient: casefold(recipient),
+                sender: sender.to_string(),
+                text: text.to_string(),
+                sent_time,
+                read: false,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_memos(&self, recipient: &str) -> DbResult<Vec<(i64, String, String, SystemTime, bool)>> {
+        let recipient = casefold(recipient);
+        Ok(lock(&self.store)
+            .memos
+            .iter()
+            .filter(|(_, memo)| memo.recipient == recipient)
+            .map(|(id, memo)| (*id, memo.sender.clone(), memo.text.clone(), memo.sent_time, memo.read))
+            .collect())
+    }
+
+    async fn count_unread_memos(&self, recipient: &str) -> DbResult<usize> {
+        let recipient = casefold(recipient);
+        Ok(lock(&self.store)
+            .memos
+            .values()
+            .filter(|memo| memo.recipient == recipient && !memo.read)
+            .count())
+    }
+
+    async fn mark_memo_read(&mut self, id: i64) -> DbResult<()> {
+        if let Some(memo) = lock(&self.store).memos.get_mut(&id) {
+            memo.read = true;
+        }
+        Ok(())
+    }
+
+    async fn delete_memo(&mut self, id: i64) -> DbResult<bool> {
+        Ok(lock(&self.store).memos.remove(&id).is_some())
+    }
+
+    async fn get_memo_accounts(&self) -> DbResult<Vec<String>> {
+        let store = lock(&self.store);
+        let accounts: BTreeSet<String> = store
+            .memos
+            .values()
+            .map(|memo| memo.recipient.clone())
+            .chain(store.memo_limits.keys().cloned())
+            .chain(store.memo_ignores.iter().map(|(nick, _)| nick.clone()))
+            .collect();
+        Ok(accounts.into_iter().collect())
+    }
+
+    async fn get_memo_limit(&self, nick: &str) -> DbResult<Option<usize>> {
+        Ok(lock(&self.store).memo_limits.get(&casefold(nick)).copied())
+    }
+
+    async fn set_memo_limit(&mut self, nick: &str, limit: Option<usize>) -> DbResult<()> {
+        let mut store = lock(&self.store);
+        if let Some(l) = limit {
+            store.memo_limits.insert(casefold(nick), l);
+        } else {
+            store.memo_limits.remove(&casefold(nick));
+        }
+        Ok(())
+    }
+
+    async fn add_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<()> {
+        lock(&self.store)
+            .memo_ignores
+            .insert((casefold(nick), casefold(ignored)));
+        Ok(())
+    }
+
+    async fn get_memo_ignores(&self, nick: &str) -> DbResult<Vec<String>> {
+        let nick = casefold(nick);
+        Ok(lock(&self.store)
+            .memo_ignores
+            .iter()
+            .filter(|(owner, _)| *owner == nick)
+            .map(|(_, ignored)| ignored.clone())
+            .collect())
+    }
+
+    async fn delete_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<bool> {
+        Ok(lock(&self.store)
+            .memo_ignores
+            .remove(&(casefold(nick), casefold(ignored))))
+    }
+
+    async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+}
+
+pub struct MemoryOperDatabase {
+    store: SharedMemoryStore,
+}
+
+impl MemoryOperDatabase {
+    pub fn new(store: SharedMemoryStore) -> Self {
+        MemoryOperDatabase { store }
+    }
+}
+
+#[async_trait]
+impl OperDatabase for MemoryOperDatabase {
+    async fn connect(&mut self, _db_config: &str) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_akill(
+        &mut self,
+        mask: &str,
+        reason: &str,
+        set_by: &str,
+        set_time: SystemTime,
+        expires: Option<SystemTime>,
+    ) -> DbResult<()> {
+        let akill = AkillEntry {
+            mask: mask.to_lowercase(),
+            reason: reason.to_string(),
+            set_by: set_by.to_string(),
+            set_time: stored_time(set_time)?,
+            expires: stored_expires(expires)?,
+        };
+        lock(&self.store).akills.insert(akill.mask.clone(), akill);
+        Ok(())
+    }
+
+    async fn get_akills(&self) -> DbResult<Vec<AkillEntry>> {
+        let mut results: Vec<_> = lock(&self.store).akills.values().cloned().collect();
+        results.sort_by_key(|akill| akill.set_time);
+        Ok(results)
+    }
+
+    async fn delete_akill(&mut self, mask: &str) -> DbResult<bool> {
+        Ok(lock(&self.store).akills.remove(&mask.to_lowercase()).is_some())
+    }
+
+    async fn add_session_exception(
+        &mut self,
+        ip: &str,
+        limit: usize,
+        reason: &str,
+        set_by: &str,
+        set_time: SystemTime,
+    ) -> DbResult<()> {
+        let set_time = stored_time(set_time)?;
+        lock(&self.store)
+            .session_exceptions
+            .insert(ip.to_string(), (limit, reason.to_string(), set_by.to_string(), set_time));
+        Ok(())
+    }
+
+    async fn get_session_exception(&self, ip: &str) -> DbResult<Option<usize>> {
+        Ok(lock(&self.store).session_exceptions.get(ip).map(|(limit, _, _, _)| *limit))
+    }
+
+    async fn get_session_exceptions(&self) -> DbResult<Vec<(String, usize, String, String, SystemTime)>> {
+        let mut results: Vec<_> = lock(&self.store)
+            .session_exceptions
+            .iter()
+            .map(|(ip, (limit, reason, set_by, set_time))| (ip.clone(), *limit, reason.clone(), set_by.clone(), *set_time))
+            .collect();
+        results.sort_by_key(|(_, _, _, _, set_time)| *set_time);
+        Ok(results)
+    }
+
+    async fn delete_session_exception(&mut self, ip: &str) -> DbResult<bool> {
+        Ok(lock(&self.store).session_exceptions.remove(ip).is_some())
+    }
+}
+
+pub struct MemoryBotDatabase {
+    store: SharedMemoryStore,
+}
+
+impl MemoryBotDatabase {
+    pub fn new(store: SharedMemoryStore) -> Self {
+        MemoryBotDatabase { store }
+    }
+}
+
+#[async_trait]
+impl BotDatabase for MemoryBotDatabase {
+    async fn connect(&mut self, _db_config: &str) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn add_bot(&mut self, nick: &str, user: &str, host: &str, realname: &str, created_time: SystemTime) -> DbResult<()> {
+        let created_time = stored_time(created_time)?;
+        let mut store = lock(&self.store);
+        if store.bots.contains_key(nick) {
+            return Err(format!("Bot {nick} already exists").into());
+        }
+        store.bots.insert(
+            nick.to_string(),
+            (user.to_string(), host.to_string(), realname.to_string(), created_time),
+        );
+        Ok(())
+    }
+
+    async fn get_bots(&self) -> DbResult<Vec<(String, String, String, String)>> {
+        Ok(lock(&self.store)
+            .bots
+            .iter()
+            .map(|(nick, (user, host, realname, _))| (nick.clone(), user.clone(), host.clone(), realname.clone()))
+            .collect())
+    }
+
+    async fn delete_bot(&mut self, nick: &str) -> DbResult<bool> {
+        let mut store = lock(&self.store);
+        let count = store.bots.len();
+        store.bots.retain(|bot, _| casefold(bot) != casefold(nick));
+        store.bot_channels.retain(|_, row| casefold(&row.bot) != casefold(nick));
+        Ok(store.bots.len() < count)
+    }
+
+    async fn assign_bot(&mut self, channel_name: &str, bot: &str) -> DbResult<()> {
+        // Al cambiar de bot se conservan el saludo y los kickers del canal
+        lock(&self.store)
+            .bot_channels
+            .entry(casefold(channel_name))
+            .and_modify(|row| row.bot = bot.to_string())
+            .or_insert_with(|| BotChannelRow { bot: bot.to_string(), greet: None, kickers: HashSet::new() });
+        Ok(())
+    }
+
+    async fn unassign_bot(&mut self, channel_name: &str) -> DbResult<bool> {
+        Ok(lock(&self.store).bot_channels.remove(&casefold(channel_name)).is_some())
+    }
+
+    async fn get_bot_channels(&self) -> DbResult<Vec<(String, String, Option<String>, Vec<String>)>> {
+        Ok(lock(&self.store)
+            .bot_channels
+            .iter()
+            .map(|(channel, row)| {
+                let kickers = crate::database::BOT_KICKERS
+                    .iter()
+                    .filter(|kicker| row.kickers.contains(**kicker))
+                    .map(|kicker| kicker.to_string())
+                    .collect();
+                (channel.clone(), row.bot.clone(), row.greet.clone(), kickers)
+            })
+            .collect())
+    }
+
+    async fn set_bot_greet(&mut self, channel_name: &str, greet: Option<&str>) -> DbResult<()> {
+        if let Some(row) = lock(&self.store).bot_channels.get_mut(&casefold(channel_name)) {
+            row.greet = greet.map(|g| g.to_string());
+        }
+        Ok(())
+    }
+
+    async fn set_bot_kicker(&mut self, channel_name: &str, kicker: &str, enabled: bool) -> DbResult<()> {
+        if !crate::database::is_bot_kicker(kicker) {
+            return Err(format!("Unknown kicker: {kicker}").into());
+        }
+        if let Some(row) = lock(&self.store).bot_channels.get_mut(&casefold(channel_name)) {
+            if enabled {
+                row.kickers.insert(kicker.to_string());
+            } else {
+                row.kickers.remove(kicker);
+            }
+        }
+        Ok(())
+    }
+
+    async fn add_badword(&mut self, channel_name: &str, word: &str) -> DbResult<()> {
+        lock(&self.store)
+            .bot_badwords
+            .insert((casefold(channel_name), word.to_lowercase()));
+        Ok(())
+    }
+
+    async fn get_badwords(&self, channel_name: &str) -> DbResult<Vec<String>> {
+        let channel_name = casefold(channel_name);
+        Ok(lock(&self.store)
+            .bot_badwords
+            .iter()
+            .filter(|(channel, _)| *channel == channel_name)
+            .map(|(_, word)| word.clone())
+            .collect())
+    }
+
+    async fn delete_badword(&mut self, channel_name: &str, word: &str) -> DbResult<bool> {
+        Ok(lock(&self.store)
+            .bot_badwords
+            .remove(&(casefold(channel_name), word.to_lowercase())))
+    }
+
+    async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+}
+
+pub struct MemorySchemaDatabase {
+    store: SharedMemoryStore,
+}
+
+impl MemorySchemaDatabase {
+    pub fn new(store: SharedMemoryStore) -> Self {
+        MemorySchemaDatabase { store }
+    }
+}
+
+#[async_trait]
+impl SchemaDatabase for MemorySchemaDatabase {
+    async fn connect(&mut self, _db_config: &str) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    async fn get_schema_version(&self) -> DbResult<u32> {
+        Ok(lock(&self.store).schema_versions.keys().next_back().copied().unwrap_or(0))
+    }
+
+    async fn add_schema_version(&mut self, version: u32, description: &str, applied_time: SystemTime) -> DbResult<()> {
+        let applied_time = stored_time(applied_time)?;
+        let mut store = lock(&self.store);
+        if store.schema_versions.contains_key(&version) {
+            return Err(format!("Schema version {version} already applied").into());
+        }
+        store.schema_versions.insert(version, (description.to_string(), applied_time));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_channel_database() {
+        let store = MemoryStore::shared();
+        let mut db = MemoryChannelDatabase::new(store.clone());
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        db.add_channel("#zeus", "mati", time).await.unwrap();
+        assert!(db.add_channel("#zeus", "bob", time).await.is_err());
+        assert!(db.get_channel_setting("#zeus", "keeptopic").await.unwrap());
+        db.set_channel_setting("#zeus", "guard", true).await.unwrap();
+        assert_eq!(vec!["#zeus".to_string()], db.get_channels_with_setting("guard").await.unwrap());
+        assert!(db.set_channel_setting("#zeus", "nosuch", true).await.is_err());
+
+        db.add_channel_access("#zeus", "bob", "v", "mati", time + Duration::from_secs(5)).await.unwrap();
+        db.add_channel_access("#zeus", "ala", "v", "mati", time).await.unwrap();
+        db.add_channel_access("#zeus", "eve", "aotsirf", "mati", time).await.unwrap();
+        let list = db.get_channel_access_list("#zeus", Some("v")).await.unwrap();
+        assert_eq!(vec!["ala", "bob"], list.iter().map(|e| e.nick.as_str()).collect::<Vec<_>>());
+        assert_eq!(3, db.get_channel_access_list("#zeus", None).await.unwrap().len());
+
+        db.add_channel_list_entry("#zeus", 'b', "*!*@bad.host", "mati", time + Duration::from_secs(5), None).await.unwrap();
+        db.add_channel_list_entry("#zeus", 'I', "*!*@good.host", "mati", time, Some(time + Duration::from_secs(60))).await.unwrap();
+        assert!(db.add_channel_list_entry("#zeus", 'q', "*!*@x", "mati", time, None).await.is_err());
+        let lists = db.get_channel_list_entries("#zeus").await.unwrap();
+        assert_eq!(vec!['I', 'b'], lists.iter().map(|e| e.list).collect::<Vec<_>>());
+        assert!(db.delete_channel_list_entry("#zeus", 'I', "*!*@good.host").await.unwrap());
+        assert!(!db.delete_channel_list_entry("#zeus", 'e', "*!*@good.host").await.unwrap());
+        db.set_channel_key_limit("#zeus", Some("secret"), Some(10)).await.unwrap();
+        assert_eq!((Some("secret".to_string()), Some(10)), db.get_channel_key_limit("#zeus").await.unwrap());
+
+        // los demás servicios ven el mismo almacén
+        let mut memo_db = MemoryMemoDatabase::new(store);
+        memo_db.add_memo("#Zeus", "mati", "Hi", time).await.unwrap();
+        let memos = memo_db.get_memos("#zeus").await.unwrap();
+        assert_eq!(1, memos.len());
+        memo_db.mark_memo_read(memos[0].0).await.unwrap();
+        assert_eq!(0, memo_db.count_unread_memos("#zeus").await.unwrap());
+        assert_eq!(vec!["#zeus".to_string()], memo_db.get_memo_accounts().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_memory_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json").to_string_lossy().to_string();
+
+        let store = MemoryStore::shared();
+        let mut dbs = memory_databases(&store);
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        dbs.chan_db.add_channel("#zeus", "mati", time).await.unwrap();
+        dbs.chan_db.update_channel_info("#zeus", Some("Welcome"), Some("mati"), Some(time), None).await.unwrap();
+        dbs.bot_db.add_bot("Zeus", "zeus", "bots.zeus", "Zeus Bot", time).await.unwrap();
+        dbs.bot_db.assign_bot("#zeus", "Zeus").await.unwrap();
+        dbs.bot_db.set_bot_kicker("#zeus", "caps", true).await.unwrap();
+        let snapshot = MemorySnapshot::new(store.clone(), &path);
+        snapshot.save().await.unwrap();
+
+        let loaded = MemorySnapshot::new(MemoryStore::shared(), &path);
+        assert!(loaded.load().await.unwrap() > 0);
+        let loaded_dbs = memory_databases(&loaded.store);
+        assert_eq!(
+            ServicesDump::export(&dbs).await.unwrap(),
+            ServicesDump::export(&loaded_dbs).await.unwrap()
+        );
+        dir.close().unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+use crate::config::DatabaseConfig;
+#[cfg(feature = "mysql")]
+use crate::database::mysql::mysql_impl::*;
+#[cfg(feature = "postgres")]
+use crate::database::postgres::postgres_impl::*;
+#[cfg(feature = "sqlite")]
+use crate::database::sqlite::*;
+use crate::database::memory::{memory_databases, MemorySnapshot, MemoryStore};
+use crate::database::{
+    BotDatabase, ChannelDatabase, DbResult, MemoDatabase, NickDatabase, OperDatabase, SchemaDatabase,
+};
+use std::time::SystemTime;
+
+// Migraciones en orden de versión. Cada una llama a métodos de los traits de las bases
+// de datos, así que cada backend la aplica con su propio SQL. Todas deben poder aplicarse
+// sobre bases de datos creadas antes de que existiera la tabla schema_version.
+pub const MIGRATIONS: [(u32, &str); 6] = [
+    (1, "Service tables"),
+    (2, "Topic setter and time of registered channels"),
+    (3, "ChanServ access flags instead of access levels"),
+    (4, "ChanServ SET options"),
+    (5, "Ban lists, key and limit of registered channels"),
+    (6, "Nicks and channels stored with the configured casemapping"),
+];
+
+// Bases de datos de todos los servicios, conectadas al mismo backend
+pub struct ServiceDatabases {
+    pub nick_db: Box<dyn NickDatabase>,
+    pub chan_db: Box<dyn ChannelDatabase>,
+    pub memo_db: Box<dyn MemoDatabase>,
+    pub oper_db: Box<dyn OperDatabase>,
+    pub bot_db: Box<dyn BotDatabase>,
+    pub schema_db: Box<dyn SchemaDatabase>,
+    // Copia en fichero del backend "memory", si se ha configurado
+    pub snapshot: Option<MemorySnapshot>,
+}
+
+impl ServiceDatabases {
+    // Abre y conecta las bases de datos del backend configurado, sin tocar el esquema
+    pub async fn open(db_config: &DatabaseConfig) -> Result<ServiceDatabases, String> {
+        let mut dbs = match db_config.database.as_str() {
+            #[cfg(feature = "sqlite")]
+            "sqlite" => ServiceDatabases {
+                nick_db: Box::new(SQLiteNickDatabase::new()),
+                chan_db: Box::new(SQLiteChannelDatabase::new()),
+                memo_db: Box::new(SQLiteMemoDatabase::new()),
+                oper_db: Box::new(SQLiteOperDatabase::new()),
+                bot_db: Box::new(SQLiteBotDatabase::new()),
+                schema_db: Box::new(SQLiteSchemaDatabase::new()),
+                snapshot: None,
+            },
+            #[cfg(feature = "mysql")]
+            "mysql" => ServiceDatabases {
+                nick_db: Box::new(MysqlNickDatabase::new()),
+                chan_db: Box::new(MysqlChannelDatabase::new()),
+                memo_db: Box::new(MysqlMemoDatabase::new()),
+                oper_db: Box::new(MysqlOperDatabase::new()),
+                bot_db: Box::new(MysqlBotDatabase::new()),
+                schema_db: Box::new(MysqlSchemaDatabase::new()),
+                snapshot: None,
+            },
+            #[cfg(feature = "postgres")]
+            "postgres" => ServiceDatabases {
+                nick_db: Box::new(PostgresNickDatabase::new()),
+                chan_db: Box::new(PostgresChannelDatabase::new()),
+                memo_db: Box::new(PostgresMemoDatabase::new()),
+                oper_db: Box::new(PostgresOperDatabase::new()),
+                bot_db: Box::new(PostgresBotDatabase::new()),
+                schema_db: Box::new(PostgresSchemaDatabase::new()),
+                snapshot: None,
+            },
+            // `url` es el fichero de la copia; vacío, los datos se pierden al parar
+            "memory" => {
+                let store = MemoryStore::shared();
+                let mut dbs = memory_databases(&store);
+                // el almacén nace con el esquema actual
+                dbs.migrate().await.map_err(|e| e.to_string())?;
+                if !db_config.url.is_empty() {
+                    let snapshot = MemorySnapshot::new(store, &db_config.url);
+                    snapshot.load().await?;
+                    dbs.snapshot = Some(snapshot);
+                }
+                dbs
+            }
+            _ => return Err("Unsupported database type".to_string()),
+        };
+
+        dbs.nick_db.connect(&db_config.url).await.map_err(|e| e.to_string())?;
+        dbs.chan_db.connect(&db_config.url).await.map_err(|e| e.to_string())?;
+        dbs.memo_db.connect(&db_config.url).await.map_err(|e| e.to_string())?;
+        dbs.oper_db.connect(&db_config.url).await.map_err(|e| e.to_string())?;
+        dbs.bot_db.connect(&db_config.url).await.map_err(|e| e.to_string())?;
+        dbs.schema_db.connect(&db_config.url).await.map_err(|e| e.to_string())?;
+        dbs.schema_db.create_table().await.map_err(|e| e.to_string())?;
+        Ok(dbs)
+    }
+
+    // Migraciones que faltan por aplicar: (versión, descripción)
+    pub async fn pending_migrations(&self) -> DbResult<Vec<(u32, &'static str)>> {
+        let version = self.schema_db.get_schema_version().await?;
+        Ok(pending_after(version))
+    }
+
+    // Aplica en orden las migraciones pendientes y devuelve las aplicadas
+    pub async fn migrate(&mut self) -> DbResult<Vec<(u32, &'static str)>> {
+        let pending = self.pending_migrations().await?;
+        for (version, description) in &pending {
+            self.apply_migration(*version).await?;
+            self.schema_db.add_schema_version(*version, description, SystemTime::now()).await?;
+        }
+        Ok(pending)
+    }
+
+    async fn apply_migration(&mut self, version: u32) -> DbResult<()> {
+        match version {
+            1 => {
+                self.nick_db.create_table().await?;
+                self.nick_db.create_forbid_table().await?;
+                self.nick_db.create_vhost_request_table().await?;
+                self.chan_db.create_table().await?;
+                self.chan_db.create_access_table().await?;
+                self.chan_db.create_akick_table().await?;
+                self.chan_db.create_suspend_table().await?;
+                self.chan_db.create_forbid_table().await?;
+                self.memo_db.create_table().await?;
+                self.oper_db.create_table().await?;
+                self.bot_db.create_table().await
+            }
+            2 => self.chan_db.migrate_topic_fields().await,
+            3 => self.chan_db.migrate_access_flags().await,
+            4 => self.chan_db.migrate_channel_settings().await,
+            5 => {
+                self.chan_db.create_list_table().await?;
+                self.chan_db.migrate_channel_key_limit().await
+            }
+            6 => {
+                self.nick_db.migrate_casefold_names().await?;
+                self.chan_db.migrate_casefold_names().await?;
+                self.memo_db.migrate_casefold_names().await?;
+                self.bot_db.migrate_casefold_names().await
+            }
+            _ => Err(format!("Unknown schema version {version}").into()),
+        }
+    }
+}
+
+fn pending_after(version: u32) -> Vec<(u32, &'static str)> {
+    MIGRATIONS.iter().filter(|(v, _)| *v > version).copied().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_migrations_order() {
+        for (i, (version, _)) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(i as u32 + 1, *version);
+        }
+        assert_eq!(MIGRATIONS.len(), pending_after(0).len());
+        assert_eq!(vec![(4, "ChanServ SET options")], pending_after(3).into_iter().take(1).collect::<Vec<_>>());
+        assert_eq!(vec![(5, "Ban lists, key and limit of registered channels")], pending_after(4).into_iter().take(1).collect::<Vec<_>>());
+        assert_eq!(vec![(6, "Nicks and channels stored with the configured casemapping")], pending_after(5));
+        assert!(pending_after(MIGRATIONS.len() as u32).is_empty());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_migrate_casefold_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("casefold.db");
+        let url = format!("sqlite://{}", path.to_string_lossy());
+        let db_config = DatabaseConfig {
+            database: "sqlite".to_string(),
+            url: url.clone(),
+            forbidden_vhosts: vec![],
+            snapshot_interval: None,
+        };
+        let mut dbs = ServiceDatabases::open(&db_config).await.unwrap();
+        for (version, description) in pending_after(0).into_iter().filter(|(v, _)| *v < 6) {
+            dbs.apply_migration(version).await.unwrap();
+            dbs.schema_db.add_schema_version(version, description, SystemTime::now()).await.unwrap();
+        }
+        // filas guardadas antes del casemapping, tal como se escribieron
+        let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+        for query in [
+            "INSERT INTO nicks (nick, password, user, registration_time) VALUES ('Zeus', 'x', 'zeus@host', 0)",
+            "INSERT INTO nicks (nick, password, user, registration_time) VALUES ('MATI', 'x', 'mati@host', 0)",
+            "INSERT INTO nicks (nick, password, user, registration_time) VALUES ('mati', 'y', 'mati@host', 0)",
+            "INSERT INTO channels (channel_name, creator_nick, creation_time) VALUES ('#Zeus', 'Zeus', 0)",
+            "INSERT INTO channel_access (channel_name, nick, level, added_by, added_time) VALUES ('#Zeus', 'Bob', 'v', 'Zeus', 0)",
+        ] {
+            sqlx::query(query).execute(&pool).await.unwrap();
+        }
+
+        assert_eq!(vec![(6, "Nicks and channels stored with the configured casemapping")], dbs.migrate().await.unwrap());
+        assert_eq!(vec!["mati".to_string(), "zeus".to_string()], dbs.nick_db.get_nicks().await.unwrap());
+        // si ya existía el nombre canónico se queda esa fila
+        assert_eq!(Some("y".to_string()), dbs.nick_db.get_nick_password("Mati").await.unwrap());
+        assert_eq!(vec!["#zeus".to_string()], dbs.chan_db.get_channels().await.unwrap());
+        assert!(dbs.chan_db.get_channel_access("#ZEUS", "BOB").await.unwrap().is_some());
+        pool.close().await;
+        dir.close().unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_migrate_channel_settings_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.db");
+        let url = format!("sqlite://{}", path.to_string_lossy());
+        let db_config = DatabaseConfig {
+            database: "sqlite".to_string(),
+            url: url.clone(),
+            forbidden_vhosts: vec![],
+            snapshot_interval: None,
+        };
+        let mut dbs = ServiceDatabases::open(&db_config).await.unwrap();
+        for (version, description) in pending_after(0).into_iter().filter(|(v, _)| *v < 4) {
+            dbs.apply_migration(version).await.unwrap();
+            dbs.schema_db.add_schema_version(version, description, SystemTime::now()).await.unwrap();
+        }
+        // canal registrado antes de que existieran las opciones de CS SET
+        let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+        sqlx::query("INSERT INTO channels (channel_name, creator_nick, creation_time) VALUES ('#zeus', 'zeus', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        dbs.migrate().await.unwrap();
+        for (setting, default) in crate::database::CHANNEL_SETTINGS {
+            assert_eq!(default, dbs.chan_db.get_channel_setting("#zeus", setting).await.unwrap());
+        }
+        pool.close().await;
+        dir.close().unwrap();
+    }
+}
@@ -2,40 +2,576 @@
 pub mod sqlite;
 #[cfg(feature = "mysql")]
 pub mod mysql;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod memory;
+pub mod migrations;
+pub mod dump;
 
 use std::error::Error;
-use std::time::SystemTime;
+use std::fmt;
+use std::time::{SystemTime, SystemTimeError};
+
+// Error de las bases de datos de servicios
+#[derive(Debug)]
+pub enum DatabaseError {
+    // SQLite, MySQL y PostgreSQL (sqlx)
+    #[cfg(any(feature = "sqlite", feature = "mysql", feature = "postgres"))]
+    Sqlx(sqlx::Error),
+    // Fecha que no se puede guardar como timestamp UNIX
+    Time(SystemTimeError),
+    // Valor no válido para la consulta (opción o kicker desconocido, etc.)
+    Invalid(String),
+}
+
+pub type DbResult<T> = Result<T, DatabaseError>;
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(any(feature = "sqlite", feature = "mysql", feature = "postgres"))]
+            DatabaseError::Sqlx(e) => write!(f, "SQL error: {e}"),
+            DatabaseError::Time(e) => write!(f, "Invalid time: {e}"),
+            DatabaseError::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for DatabaseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(any(feature = "sqlite", feature = "mysql", feature = "postgres"))]
+            DatabaseError::Sqlx(e) => Some(e),
+            DatabaseError::Time(e) => Some(e),
+            DatabaseError::Invalid(_) => None,
+        }
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "mysql", feature = "postgres"))]
+impl From<sqlx::Error> for DatabaseError {
+    fn from(e: sqlx::Error) -> Self {
+        DatabaseError::Sqlx(e)
+    }
+}
+
+impl From<SystemTimeError> for DatabaseError {
+    fn from(e: SystemTimeError) -> Self {
+        DatabaseError::Time(e)
+    }
+}
+
+impl From<String> for DatabaseError {
+    fn from(e: String) -> Self {
+        DatabaseError::Invalid(e)
+    }
+}
+
+impl From<&str> for DatabaseError {
+    fn from(e: &str) -> Self {
+        DatabaseError::Invalid(e.to_string())
+    }
+}
+
+// Nick registrado en NickServ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NickRecord {
+    pub user: String,
+    pub registration_time: SystemTime,
+    pub email: Option<String>,
+    pub url: Option<String>,
+    pub vhost: Option<String>,
+    pub last_vhost: Option<SystemTime>,
+    pub noaccess: bool,
+    pub noop: bool,
+    pub showmail: bool,
+}
+
+// Canal registrado en ChanServ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelRecord {
+    pub founder: String,
+    pub creation_time: SystemTime,
+    pub topic: Option<String>,
+    pub modes: Option<String>,
+    pub topic_setter: Option<String>,
+    pub topic_time: Option<SystemTime>,
+}
+
+// Entrada de la lista de acceso de un canal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessEntry {
+    pub nick: String,
+    pub flags: String,
+    pub added_by: String,
+    pub added_time: SystemTime,
+}
+
+// Entrada guardada de una lista de canal: ban (b), excepción (e) o invitación (I) o silencio (Z)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelListEntry {
+    pub list: char,
+    pub mask: String,
+    pub set_by: String,
+    pub set_time: SystemTime,
+    pub expires: Option<SystemTime>,
+}
+
+// Nick o canal prohibido con NS FORBID o CS FORBID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForbiddenEntry {
+    pub name: String,
+    pub reason: String,
+    pub set_by: String,
+    pub set_time: SystemTime,
+}
+
+// Petición de vhost pendiente de HostServ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VhostRequest {
+    pub nick: String,
+    pub vhost: String,
+    pub request_time: SystemTime,
+}
+
+// Suspensión de un canal con CS SUSPEND
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelSuspension {
+    pub reason: String,
+    pub suspended_by: String,
+    pub suspended_time: SystemTime,
+    pub expires: Option<SystemTime>,
+}
+
+// AKILL de red sobre una máscara user@host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AkillEntry {
+    pub mask: String,
+    pub reason: String,
+    pub set_by: String,
+    pub set_time: SystemTime,
+    pub expires: Option<SystemTime>,
+}
 
 #[async_trait::async_trait]
 pub trait NickDatabase: Send + Sync {
-    async fn connect(&mut self, db_config: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn create_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn add_nick(&mut self, nick: &str, password: &str, user: &str, registration_time: SystemTime) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn get_nick_info(&self, nick: &str) -> Result<Option<(String, SystemTime, Option<String>, Option<String>, Option<String>, Option<SystemTime>, bool, bool, bool)>, Box<dyn Error + Send + Sync>>;
-    async fn get_nick_password(&self, nick: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>>;
-    async fn update_nick_password(&mut self, nick: &str, password: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn update_nick_info(&mut self, nick: &str, user: Option<&str>, email: Option<&str>, url: Option<&str>, vhost: Option<&str>, last_vhost: Option<SystemTime>, noaccess: Option<bool>, noop: Option<bool>, showmail: Option<bool>) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn delete_nick(&mut self, nick: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn connect(&mut self, db_config: &str) -> DbResult<()>;
+    async fn create_table(&mut self) -> DbResult<()>;
+    async fn add_nick(&mut self, nick: &str, password: &str, user: &str, registration_time: SystemTime) -> DbResult<()>;
+    async fn get_nick_info(&self, nick: &str) -> DbResult<Option<NickRecord>>;
+    async fn get_nick_password(&self, nick: &str) -> DbResult<Option<String>>;
+    async fn update_nick_password(&mut self, nick: &str, password: &str) -> DbResult<()>;
+    async fn update_nick_info(&mut self, nick: &str, user: Option<&str>, email: Option<&str>, url: Option<&str>, vhost: Option<&str>, last_vhost: Option<SystemTime>, noaccess: Option<bool>, noop: Option<bool>, showmail: Option<bool>) -> DbResult<()>;
+    async fn delete_nick(&mut self, nick: &str) -> DbResult<()>;
+    // Todos los nicks registrados, por orden alfabético
+    async fn get_nicks(&self) -> DbResult<Vec<String>>;
+
+    // Nicks prohibidos con NS FORBID
+    async fn create_forbid_table(&mut self) -> DbResult<()>;
+    async fn add_forbidden_nick(&mut self, nick: &str, reason: &str, set_by: &str, set_time: SystemTime) -> DbResult<()>;
+    async fn get_forbidden_nick(&self, nick: &str) -> DbResult<Option<ForbiddenEntry>>;
+    async fn delete_forbidden_nick(&mut self, nick: &str) -> DbResult<bool>;
+    // Todos los nicks prohibidos
+    async fn get_forbidden_nicks(&self) -> DbResult<Vec<ForbiddenEntry>>;
+
+    // Peticiones de vhost pendientes de HostServ
+    async fn create_vhost_request_table(&mut self) -> DbResult<()>;
+    async fn add_vhost_request(&mut self, nick: &str, vhost: &str, request_time: SystemTime) -> DbResult<()>;
+    async fn get_vhost_request(&self, nick: &str) -> DbResult<Option<VhostRequest>>;
+    async fn get_vhost_requests(&self) -> DbResult<Vec<VhostRequest>>;
+    async fn delete_vhost_request(&mut self, nick: &str) -> DbResult<bool>;
+
+    // Pasa los nicks guardados a su forma canónica (ver casefold)
+    async fn migrate_casefold_names(&mut self) -> DbResult<()>;
 }
 
 #[async_trait::async_trait]
 pub trait ChannelDatabase: Send + Sync {
-    async fn connect(&mut self, db_config: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn create_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn add_channel(&mut self, channel_name: &str, creator_nick: &str, creation_time: SystemTime) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn get_channel_info(&self, channel_name: &str) -> Result<Option<(String, SystemTime, Option<String>, Option<String>, Option<String>, Option<SystemTime>)>, Box<dyn Error + Send + Sync>>;
-    async fn update_channel_info(&mut self, channel_name: &str, topic: Option<&str>, topic_setter: Option<&str>, topic_time: Option<SystemTime>, modes: Option<&str>) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn update_channel_owner(&mut self, channel_name: &str, new_owner: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn delete_channel(&mut self, channel_name: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn connect(&mut self, db_config: &str) -> DbResult<()>;
+    async fn create_table(&mut self) -> DbResult<()>;
+    async fn add_channel(&mut self, channel_name: &str, creator_nick: &str, creation_time: SystemTime) -> DbResult<()>;
+    async fn get_channel_info(&self, channel_name: &str) -> DbResult<Option<ChannelRecord>>;
+    async fn update_channel_info(&mut self, channel_name: &str, topic: Option<&str>, topic_setter: Option<&str>, topic_time: Option<SystemTime>, modes: Option<&str>) -> DbResult<()>;
+    async fn update_channel_owner(&mut self, channel_name: &str, new_owner: &str) -> DbResult<()>;
+    async fn delete_channel(&mut self, channel_name: &str) -> DbResult<()>;
+    // Todos los canales registrados, por orden alfabético
+    async fn get_channels(&self) -> DbResult<Vec<String>>;
     
     // Funciones para manejo de acceso de canales
-    async fn create_access_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn add_channel_access(&mut self, channel_name: &str, nick: &str, level: &str, added_by: &str, added_time: SystemTime) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn get_channel_access(&self, channel_name: &str, nick: &str) -> Result<Option<(String, String, SystemTime)>, Box<dyn Error + Send + Sync>>;
-    async fn get_channel_access_list(&self, channel_name: &str, level: Option<&str>) -> Result<Vec<(String, String, String, SystemTime)>, Box<dyn Error + Send + Sync>>;
-    async fn update_channel_access(&mut self, channel_name: &str, nick: &str, level: &str, updated_by: &str, updated_time: SystemTime) -> Result<(), Box<dyn Error + Send + Sync>>;
-    async fn delete_channel_access(&mut self, channel_name: &str, nick: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn create_access_table(&mut self) -> DbResult<()>;
+    // La columna `level` guarda los flags de acceso normalizados (ver ACCESS_FLAGS)
+    async fn add_channel_access(&mut self, channel_name: &str, nick: &str, flags: &str, added_by: &str, added_time: SystemTime) -> DbResult<()>;
+    async fn get_channel_access(&self, channel_name: &str, nick: &str) -> DbResult<Option<AccessEntry>>;
+    async fn get_channel_access_list(&self, channel_name: &str, flags: Option<&str>) -> DbResult<Vec<AccessEntry>>;
+    async fn update_channel_access(&mut self, channel_name: &str, nick: &str, flags: &str, updated_by: &str, updated_time: SystemTime) -> DbResult<()>;
+    async fn delete_channel_access(&mut self, channel_name: &str, nick: &str) -> DbResult<()>;
+
+    // Funciones para la lista de auto-kick (AKICK) de canales
+    async fn create_akick_table(&mut self) -> DbResult<()>;
+    async fn add_channel_akick(&mut self, channel_name: &str, mask: &str, reason: Option<&str>, added_by: &str, added_time: SystemTime) -> DbResult<()>;
+    async fn get_channel_akick_list(&self, channel_name: &str) -> DbResult<Vec<(String, Option<String>, String, SystemTime)>>;
+    async fn delete_channel_akick(&mut self, channel_name: &str, mask: &str) -> DbResult<bool>;
     
-    // Migration function
-    async fn migrate_topic_fields(&mut self) -> Result<(), Box<dyn Error + Send + Sync>>;
+    // Suspensión de canales (CS SUSPEND)
+    async fn create_suspend_table(&mut self) -> DbResult<()>;
+    async fn suspend_channel(&mut self, channel_name: &str, reason: &str, suspended_by: &str, suspended_time: SystemTime, expires: Option<SystemTime>) -> DbResult<()>;
+    async fn get_channel_suspension(&self, channel_name: &str) -> DbResult<Option<ChannelSuspension>>;
+    async fn unsuspend_channel(&mut self, channel_name: &str) -> DbResult<bool>;
+
+    // Canales prohibidos con CS FORBID
+    async fn create_forbid_table(&mut self) -> DbResult<()>;
+    async fn add_forbidden_channel(&mut self, channel_name: &str, reason: &str, set_by: &str, set_time: SystemTime) -> DbResult<()>;
+    async fn get_forbidden_channel(&self, channel_name: &str) -> DbResult<Option<ForbiddenEntry>>;
+    async fn delete_forbidden_channel(&mut self, channel_name: &str) -> DbResult<bool>;
+    // Todos los canales prohibidos
+    async fn get_forbidden_channels(&self) -> DbResult<Vec<ForbiddenEntry>>;
+
+    // Opciones de canal de ChanServ (CS SET), ver CHANNEL_SETTINGS
+    async fn get_channel_setting(&self, channel_name: &str, setting: &str) -> DbResult<bool>;
+    async fn set_channel_setting(&mut self, channel_name: &str, setting: &str, enabled: bool) -> DbResult<()>;
+    async fn get_channels_with_setting(&self, setting: &str) -> DbResult<Vec<String>>;
+
+    // Migration functions
+    async fn migrate_topic_fields(&mut self) -> DbResult<()>;
+    // Convierte los niveles antiguos vop/hop/aop/sop a sus plantillas de flags
+    async fn migrate_access_flags(&mut self) -> DbResult<()>;
+    // Añade las columnas de CHANNEL_SETTINGS a la tabla channels
+    async fn migrate_channel_settings(&mut self) -> DbResult<()>;
+
+    // Listas b/e/I/Z de los canales registrados, ver CHANNEL_LISTS
+    async fn create_list_table(&mut self) -> DbResult<()>;
+    async fn add_channel_list_entry(&mut self, channel_name: &str, list: char, mask: &str, set_by: &str, set_time: SystemTime, expires: Option<SystemTime>) -> DbResult<()>;
+    async fn get_channel_list_entries(&self, channel_name: &str) -> DbResult<Vec<ChannelListEntry>>;
+    async fn delete_channel_list_entry(&mut self, channel_name: &str, list: char, mask: &str) -> DbResult<bool>;
+
+    // Clave (+k) y límite (+l) actuales de un canal registrado
+    async fn get_channel_key_limit(&self, channel_name: &str) -> DbResult<(Option<String>, Option<usize>)>;
+    async fn set_channel_key_limit(&mut self, channel_name: &str, key: Option<&str>, limit: Option<usize>) -> DbResult<()>;
+    // Añade las columnas mode_key y mode_limit a la tabla channels
+    async fn migrate_channel_key_limit(&mut self) -> DbResult<()>;
+    // Pasa los canales y los nicks de las listas de acceso a su forma canónica
+    async fn migrate_casefold_names(&mut self) -> DbResult<()>;
+}
+
+#[async_trait::async_trait]
+pub trait MemoDatabase: Send + Sync {
+    async fn connect(&mut self, db_config: &str) -> DbResult<()>;
+    // Crea las tablas de memos, de límites por cuenta y de ignorados
+    async fn create_table(&mut self) -> DbResult<()>;
+    async fn add_memo(&mut self, recipient: &str, sender: &str, text: &str, sent_time: SystemTime) -> DbResult<()>;
+    // Memos de una cuenta por orden de llegada: (id, remitente, texto, fecha, leído)
+    async fn get_memos(&self, recipient: &str) -> DbResult<Vec<(i64, String, String, SystemTime, bool)>>;
+    async fn count_unread_memos(&self, recipient: &str) -> DbResult<usize>;
+    async fn mark_memo_read(&mut self, id: i64) -> DbResult<()>;
+    async fn delete_memo(&mut self, id: i64) -> DbResult<bool>;
+    // Cuentas (nicks o canales) con memos, límite propio o ignorados
+    async fn get_memo_accounts(&self) -> DbResult<Vec<String>>;
+
+    // Límite de memos de una cuenta (None usa DEFAULT_MEMO_LIMIT)
+    async fn get_memo_limit(&self, nick: &str) -> DbResult<Option<usize>>;
+    async fn set_memo_limit(&mut self, nick: &str, limit: Option<usize>) -> DbResult<()>;
+
+    // Lista de nicks cuyos memos se ignoran (MS IGNORE)
+    async fn add_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<()>;
+    async fn get_memo_ignores(&self, nick: &str) -> DbResult<Vec<String>>;
+    async fn delete_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<bool>;
+
+    // Pasa las cuentas de memos, límites e ignorados a su forma canónica
+    async fn migrate_casefold_names(&mut self) -> DbResult<()>;
+}
+
+#[async_trait::async_trait]
+pub trait OperDatabase: Send + Sync {
+    async fn connect(&mut self, db_config: &str) -> DbResult<()>;
+    // Crea las tablas de AKILL y de excepciones de sesión
+    async fn create_table(&mut self) -> DbResult<()>;
+
+    // AKILL de red sobre máscaras user@host
+    async fn add_akill(&mut self, mask: &str, reason: &str, set_by: &str, set_time: SystemTime, expires: Option<SystemTime>) -> DbResult<()>;
+    async fn get_akills(&self) -> DbResult<Vec<AkillEntry>>;
+    async fn delete_akill(&mut self, mask: &str) -> DbResult<bool>;
+
+    // Excepciones al límite de conexiones por IP: (ip, límite, motivo, puesta por, fecha)
+    async fn add_session_exception(&mut self, ip: &str, limit: usize, reason: &str, set_by: &str, set_time: SystemTime) -> DbResult<()>;
+    async fn get_session_exception(&self, ip: &str) -> DbResult<Option<usize>>;
+    async fn get_session_exceptions(&self) -> DbResult<Vec<(String, usize, String, String, SystemTime)>>;
+    async fn delete_session_exception(&mut self, ip: &str) -> DbResult<bool>;
+}
+
+#[async_trait::async_trait]
+pub trait BotDatabase: Send + Sync {
+    async fn connect(&mut self, db_config: &str) -> DbResult<()>;
+    // Crea las tablas de bots, canales con bot asignado y palabras prohibidas
+    async fn create_table(&mut self) -> DbResult<()>;
+
+    // Bots definidos por los IRCops: (nick, user, host, realname)
+    async fn add_bot(&mut self, nick: &str, user: &str, host: &str, realname: &str, created_time: SystemTime) -> DbResult<()>;
+    async fn get_bots(&self) -> DbResult<Vec<(String, String, String, String)>>;
+    // Borra el bot y lo quita de los canales a los que estaba asignado
+    async fn delete_bot(&mut self, nick: &str) -> DbResult<bool>;
+
+    // Asignación de bots a canales: (canal, bot, mensaje de bienvenida, kickers activos)
+    async fn assign_bot(&mut self, channel_name: &str, bot: &str) -> DbResult<()>;
+    async fn unassign_bot(&mut self, channel_name: &str) -> DbResult<bool>;
+    async fn get_bot_channels(&self) -> DbResult<Vec<(String, String, Option<String>, Vec<String>)>>;
+    async fn set_bot_greet(&mut self, channel_name: &str, greet: Option<&str>) -> DbResult<()>;
+    async fn set_bot_kicker(&mut self, channel_name: &str, kicker: &str, enabled: bool) -> DbResult<()>;
+
+    // Palabras prohibidas para el kicker BADWORDS
+    async fn add_badword(&mut self, channel_name: &str, word: &str) -> DbResult<()>;
+    async fn get_badwords(&self, channel_name: &str) -> DbResult<Vec<String>>;
+    async fn delete_badword(&mut self, channel_name: &str, word: &str) -> DbResult<bool>;
+
+    // Pasa los canales con bot asignado a su forma canónica
+    async fn migrate_casefold_names(&mut self) -> DbResult<()>;
+}
+
+// Versión del esquema de las bases de datos de servicios (ver migrations.rs)
+#[async_trait::async_trait]
+pub trait SchemaDatabase: Send + Sync {
+    async fn connect(&mut self, db_config: &str) -> DbResult<()>;
+    // Crea la tabla schema_version, con una fila por migración aplicada
+    async fn create_table(&mut self) -> DbResult<()>;
+    // Última versión aplicada, 0 si la base de datos no tiene ninguna
+    async fn get_schema_version(&self) -> DbResult<u32>;
+    async fn add_schema_version(&mut self, version: u32, description: &str, applied_time: SystemTime) -> DbResult<()>;
+}
+
+// Kickers de BotServ. Cada uno es una columna kick_<nombre> de la tabla bot_channels.
+pub const BOT_KICKERS: [&str; 4] = ["caps", "colors", "repeat", "badwords"];
+
+pub fn is_bot_kicker(kicker: &str) -> bool {
+    BOT_KICKERS.contains(&kicker)
+}
+
+// Número máximo de memos que puede guardar una cuenta si no tiene otro límite
+pub const DEFAULT_MEMO_LIMIT: usize = 20;
+
+// Opciones de CS SET con su valor por defecto. Cada una es una columna de la tabla channels.
+pub const CHANNEL_SETTINGS: [(&str, bool); 6] = [
+    ("keeptopic", true),
+    ("topiclock", false),
+    ("secureops", false),
+    ("restricted", false),
+    ("private", false),
+    ("guard", false),
+];
+
+// Listas de máscaras de los canales que se guardan en la base de datos
+pub const CHANNEL_LISTS: &str = "beIZ";
+
+pub fn is_channel_list(list: char) -> bool {
+    CHANNEL_LISTS.contains(list)
+}
+
+pub fn is_channel_setting(setting: &str) -> bool {
+    CHANNEL_SETTINGS.iter().any(|(name, _)| *name == setting)
+}
+
+// Valor de una opción de CS SET en los canales que no la tienen guardada
+pub fn channel_setting_default(setting: &str) -> bool {
+    CHANNEL_SETTINGS.iter().find(|(name, _)| *name == setting).map(|(_, default)| *default).unwrap_or(false)
+}
+
+// Flags de acceso de ChanServ, en el orden en que se guardan:
+//   v - auto-voice        h - auto-halfop       o - auto-op
+//   a - auto-protect      t - cambiar el topic  s - SET/MLOCK
+//   i - entrar con +i     r - AKICK             f - modificar accesos
+pub const ACCESS_FLAGS: &str = "vhoatsirf";
+
+// Plantillas que corresponden a los niveles antiguos de acceso
+pub const ACCESS_TEMPLATES: [(&str, &str); 4] = [
+    ("vop", "v"),
+    ("hop", "h"),
+    ("aop", "otsir"),
+    ("sop", "aotsirf"),
+];
+
+pub fn access_template(name: &str) -> Option<&'static str> {
+    ACCESS_TEMPLATES
+        .iter()
+        .find(|(template, _)| template.eq_ignore_ascii_case(name))
+        .map(|(_, flags)| *flags)
+}
+
+// Nombre de la plantilla que coincide exactamente con los flags, si existe
+pub fn access_template_name(flags: &str) -> Option<&'static str> {
+    let flags = normalize_access_flags(flags);
+    ACCESS_TEMPLATES
+        .iter()
+        .find(|(_, template_flags)| *template_flags == flags)
+        .map(|(template, _)| *template)
+}
+
+// Nivel VOP/HOP/AOP/SOP de unos flags: la plantilla más alta cuyos flags tiene todos.
+// Así las entradas con flags personalizados también aparecen en las listas de niveles.
+pub fn access_level(flags: &str) -> Option<&'static str> {
+    ACCESS_TEMPLATES
+        .iter()
+        .rev()
+        .find(|(_, template_flags)| template_flags.chars().all(|flag| flags.contains(flag)))
+        .map(|(template, _)| *template)
+}
+
+pub fn has_access_flag(flags: &str, flag: char) -> bool {
+    flags.contains(flag)
+}
+
+// Ordena los flags según ACCESS_FLAGS y elimina duplicados y flags desconocidos
+pub fn normalize_access_flags(flags: &str) -> String {
+    ACCESS_FLAGS.chars().filter(|c| flags.contains(*c)).collect()
+}
+
+// Aplica una modificación de flags ("+vo-t") o una plantilla ("AOP") a los flags actuales.
+// Devuelve el flag desconocido en caso de error.
+pub fn apply_access_flags(current: &str, change: &str) -> Result<String, char> {
+    if let Some(flags) = access_template(change) {
+        return Ok(flags.to_string());
+    }
+    let mut flags = normalize_access_flags(current);
+    let mut adding = true;
+    for c in change.chars() {
+        match c {
+            '+' => adding = true,
+            '-' => adding = false,
+            '*' => {
+                flags = if adding { ACCESS_FLAGS.to_string() } else { String::new() };
+            }
+            c if ACCESS_FLAGS.contains(c) => {
+                if adding {
+                    if !flags.contains(c) {
+                        flags.push(c);
+                    }
+                } else {
+                    flags.retain(|f| f != c);
+                }
+            }
+            c => return Err(c),
+        }
+    }
+    Ok(normalize_access_flags(&flags))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_access_templates() {
+        assert_eq!(Some("v"), access_template("VOP"));
+        assert_eq!(Some("aotsirf"), access_template("sop"));
+        assert_eq!(None, access_template("xop"));
+        assert_eq!(Some("aop"), access_template_name("rsito"));
+        assert_eq!(None, access_template_name("vo"));
+        assert_eq!(Some("vop"), access_level("vo"));
+        assert_eq!(Some("aop"), access_level("votsir"));
+        assert_eq!(Some("sop"), access_level("vhoatsirf"));
+        assert_eq!(None, access_level("ot"));
+    }
+
+    #[test]
+    fn test_apply_access_flags() {
+        assert_eq!(Ok("vot".to_string()), apply_access_flags("", "+tov"));
+        assert_eq!(Ok("v".to_string()), apply_access_flags("vot", "-ot"));
+        assert_eq!(Ok("vhoatsirf".to_string()), apply_access_flags("v", "+*"));
+        assert_eq!(Ok(String::new()), apply_access_flags("vot", "-*"));
+        assert_eq!(Ok("otsir".to_string()), apply_access_flags("v", "aop"));
+        assert_eq!(Ok("vt".to_string()), apply_access_flags("v", "+t-h"));
+        assert_eq!(Err('x'), apply_access_flags("v", "+x"));
+    }
+
+    // Base de datos para las pruebas: la de DATABASE_URL (PostgreSQL o MySQL) si está
+    // definida, SQLite en memoria si está compilado o el almacén en memoria
+    async fn test_nick_database() -> Option<Box<dyn NickDatabase>> {
+        let url = std::env::var("DATABASE_URL").unwrap_or_default();
+        #[cfg(feature = "postgres")]
+        if url.starts_with("postgres") {
+            let mut db: Box<dyn NickDatabase> = Box::new(postgres::postgres_impl::PostgresNickDatabase::new());
+            db.connect(&url).await.ok()?;
+            return Some(db);
+        }
+        #[cfg(feature = "mysql")]
+        if url.starts_with("mysql") {
+            let mut db: Box<dyn NickDatabase> = Box::new(mysql::mysql_impl::MysqlNickDatabase::new());
+            db.connect(&url).await.ok()?;
+            return Some(db);
+        }
+        #[cfg(feature = "sqlite")]
+        {
+            let mut db: Box<dyn NickDatabase> = Box::new(sqlite::SQLiteNickDatabase::new());
+            db.connect(":memory:").await.ok()?;
+            return Some(db);
+        }
+        #[allow(unreachable_code)]
+        Some(Box::new(memory::MemoryNickDatabase::new(memory::MemoryStore::shared())))
+    }
+
+    #[tokio::test]
+    async fn test_nick_record_roundtrip() {
+        let Some(mut db) = test_nick_database().await else {
+            return;
+        };
+        let nick = "zeus_test_nick";
+        db.create_table().await.unwrap();
+        db.delete_nick(nick).await.unwrap();
+
+        let registration_time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        db.add_nick(nick, "hash", "user@host", registration_time).await.unwrap();
+        db.update_nick_info(nick, None, Some("zeus@example.org"), None, Some("zeus.vhost"), None, None, Some(true), None)
+            .await
+            .unwrap();
+        let record = db.get_nick_info(nick).await.unwrap().unwrap();
+        assert_eq!("user@host", record.user);
+        assert_eq!(registration_time, record.registration_time);
+        assert_eq!(Some("zeus@example.org".to_string()), record.email);
+        assert_eq!(Some("zeus.vhost".to_string()), record.vhost);
+        assert!(!record.noaccess && record.noop && !record.showmail);
+
+        // los nicks se buscan sin distinguir mayúsculas
+        let record = db.get_nick_info("Zeus_Test_NICK").await.unwrap().unwrap();
+        assert_eq!("user@host", record.user);
+        assert!(db.add_nick("ZEUS_TEST_NICK", "hash", "user@host", registration_time).await.is_err());
+
+        db.delete_nick("Zeus_Test_Nick").await.unwrap();
+        assert!(db.get_nick_info(nick).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_and_vhost_records() {
+        let Some(mut db) = test_nick_database().await else {
+            return;
+        };
+        let set_time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        db.create_forbid_table().await.unwrap();
+        db.create_vhost_request_table().await.unwrap();
+        db.delete_forbidden_nick("zeus_forbidden").await.unwrap();
+        db.delete_vhost_request("zeus_vhost").await.unwrap();
+
+        db.add_forbidden_nick("Zeus_Forbidden", "reserved", "fanny", set_time).await.unwrap();
+        let entry = ForbiddenEntry {
+            name: "zeus_forbidden".to_string(),
+            reason: "reserved".to_string(),
+            set_by: "fanny".to_string(),
+            set_time,
+        };
+        assert_eq!(Some(entry.clone()), db.get_forbidden_nick("ZEUS_FORBIDDEN").await.unwrap());
+        assert!(db.get_forbidden_nicks().await.unwrap().contains(&entry));
+
+        db.add_vhost_request("Zeus_Vhost", "zeus.vhost", set_time).await.unwrap();
+        let request = VhostRequest { nick: "zeus_vhost".to_string(), vhost: "zeus.vhost".to_string(), request_time: set_time };
+        assert_eq!(Some(request.clone()), db.get_vhost_request("zeus_vhost").await.unwrap());
+        assert!(db.get_vhost_requests().await.unwrap().contains(&request));
+
+        assert!(db.delete_forbidden_nick("zeus_forbidden").await.unwrap());
+        assert!(db.delete_vhost_request("zeus_vhost").await.unwrap());
+    }
+
+    #[test]
+    fn test_database_error() {
+        let err = DatabaseError::from(format!("Unknown kicker: {}", "flood"));
+        assert_eq!("Unknown kicker: flood", err.to_string());
+        assert!(err.source().is_none());
+        let err: Box<dyn Error + Send + Sync> = Box::new(DatabaseError::from("Failed to get UNIX timestamp"));
+        assert_eq!("Failed to get UNIX timestamp", err.to_string());
+    }
 }
\ No newline at end of file
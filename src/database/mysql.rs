@@ -1,11 +1,39 @@
 #[cfg(feature = "mysql")]
 pub mod mysql_impl {
-    use crate::database::{NickDatabase, ChannelDatabase};
-    use std::error::Error;
+    use crate::database::{NickDatabase, ChannelDatabase, MemoDatabase, OperDatabase, BotDatabase, SchemaDatabase};
+    use crate::database::{AccessEntry, AkillEntry, ChannelListEntry, ChannelRecord, ChannelSuspension, DbResult, ForbiddenEntry, NickRecord, VhostRequest};
+    use crate::utils::casefold;
     use std::time::{Duration, SystemTime};
     use async_trait::async_trait;
     use sqlx::mysql::MySqlPoolOptions;
-    use sqlx::MySqlPool;
+    use sqlx::{MySqlConnection, MySqlPool};
+
+    // Pasa los nombres de una columna a su forma canónica. Si el nombre ya existía en forma
+    // canónica se conserva esa fila y se borra la otra. Se usa una sola conexión para poder
+    // desactivar la comprobación de claves externas al renombrar canales.
+    async fn refold_column(conn: &mut MySqlConnection, table: &str, column: &str) -> DbResult<()> {
+        let names: Vec<String> = sqlx::query_scalar(&format!("SELECT DISTINCT {column} FROM {table}"))
+            .fetch_all(&mut *conn)
+            .await?;
+        for name in names {
+            let folded = casefold(&name);
+            if folded == name {
+                continue;
+            }
+            let updated = sqlx::query(&format!("UPDATE {table} SET {column} = ? WHERE {column} = ?"))
+                .bind(&folded)
+                .bind(&name)
+                .execute(&mut *conn)
+                .await;
+            if updated.is_err() {
+                sqlx::query(&format!("DELETE FROM {table} WHERE {column} = ?"))
+                    .bind(&name)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
 
     pub struct MysqlNickDatabase {
         pool: Option<MySqlPool>,
@@ -19,7 +47,7 @@ pub mod mysql_impl {
 
     #[async_trait]
     impl NickDatabase for MysqlNickDatabase {
-        async fn connect(&mut self, db_config: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
             let pool = MySqlPoolOptions::new()
                 .max_connections(5)
                 .connect(db_config)
@@ -28,7 +56,7 @@ pub mod mysql_impl {
             Ok(())
         }
 
-        async fn create_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn create_table(&mut self) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 sqlx::query(
                     "CREATE TABLE IF NOT EXISTS nicks (
@@ -57,13 +85,13 @@ pub mod mysql_impl {
             password: &str,
             user: &str,
             registration_time: SystemTime,
-        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 let timestamp = registration_time
                     .duration_since(SystemTime::UNIX_EPOCH)?
                     .as_secs();
                 sqlx::query("INSERT INTO nicks (nick, password, user, registration_time) VALUES (?, ?, ?, ?)")
-                    .bind(nick)
+                    .bind(casefold(nick))
                     .bind(password)
                     .bind(user)
                     .bind(timestamp as i64)
@@ -76,11 +104,11 @@ pub mod mysql_impl {
         async fn get_nick_info(
             &self,
             nick: &str,
-        ) -> Result<Option<(String, SystemTime, Option<String>, Option<String>, Option<String>, Option<SystemTime>, bool, bool, bool)>, Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<Option<NickRecord>> {
             if let Some(pool) = &self.pool {
                 let row: Option<(String, i64, Option<String>, Option<String>, Option<String>, Option<i64>, bool, bool, bool)> =
                     sqlx::query_as("SELECT user, registration_time, email, url, vhost, last_vhost, noaccess, noop, showmail FROM nicks WHERE nick = ?")
-                        .bind(nick)
+                        .bind(casefold(nick))
                         .fetch_optional(pool)
                         .await?;
 
@@ -90,17 +118,17 @@ pub mod mysql_impl {
                     let last_vhost = last_vhost_timestamp.map(|ts| 
                         SystemTime::UNIX_EPOCH + Duration::from_secs(ts as u64)
                     );
-                    return Ok(Some((user, registration_time, email, url, vhost, last_vhost, noaccess, noop, showmail)));
+                    return Ok(Some(NickRecord { user, registration_time, email, url, vhost, last_vhost, noaccess, noop, showmail }));
                 }
             }
             Ok(None)
         }
 
-        async fn get_nick_password(&self, nick: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+        async fn get_nick_password(&self, nick: &str) -> DbResult<Option<String>> {
             if let Some(pool) = &self.pool {
                 let row: Option<(String,)> =
                     sqlx::query_as("SELECT password FROM nicks WHERE nick = ?")
-                        .bind(nick)
+                        .bind(casefold(nick))
                         .fetch_optional(pool)
                         .await?;
                 return Ok(row.map(|(password,)| password));
@@ -108,11 +136,11 @@ pub mod mysql_impl {
             Ok(None)
         }
 
-        async fn update_nick_password(&mut self, nick: &str, password: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn update_nick_password(&mut self, nick: &str, password: &str) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 sqlx::query("UPDATE nicks SET password = ? WHERE nick = ?")
                     .bind(password)
-                    .bind(nick)
+                    .bind(casefold(nick))
                     .execute(pool)
                     .await?;
             }
@@ -130,7 +158,7 @@ pub mod mysql_impl {
             noaccess: Option<bool>,
             noop: Option<bool>,
             showmail: Option<bool>,
-        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 let mut set_clauses = Vec::new();
                 if user.is_some() {
@@ -189,18 +217,205 @@ pub mod mysql_impl {
                     if let Some(sm) = showmail {
                         query = query.bind(sm);
                     }
-                    query.bind(nick).execute(pool).await?;
+                    query.bind(casefold(nick)).execute(pool).await?;
                 }
             }
             Ok(())
         }
 
-        async fn delete_nick(&mut self, nick: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn delete_nick(&mut self, nick: &str) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 sqlx::query("DELETE FROM nicks WHERE nick = ?")
-                    .bind(nick)
+                    .bind(casefold(nick))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_nicks(&self) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let names: Vec<String> = sqlx::query_scalar("SELECT nick FROM nicks ORDER BY nick")
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(names);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn create_forbid_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS forbidden_nicks (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        reason TEXT NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_forbidden_nick(
+            &mut self,
+            nick: &str,
+            reason: &str,
+            set_by: &str,
+            set_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query(
+                    "INSERT INTO forbidden_nicks (nick, reason, set_by, set_time) VALUES (?, ?, ?, ?)
+                     ON DUPLICATE KEY UPDATE reason = VALUES(reason), set_by = VALUES(set_by), set_time = VALUES(set_time)",
+                )
+                .bind(casefold(nick))
+                .bind(reason)
+                .bind(set_by)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_forbidden_nick(&self, nick: &str) -> DbResult<Option<ForbiddenEntry>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, reason, set_by, set_time FROM forbidden_nicks WHERE nick = ?",
+                )
+                .bind(casefold(nick))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((name, reason, set_by, timestamp)) = row {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    return Ok(Some(ForbiddenEntry { name, reason, set_by, set_time }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn delete_forbidden_nick(&mut self, nick: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM forbidden_nicks WHERE nick = ?")
+                    .bind(casefold(nick))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_forbidden_nicks(&self) -> DbResult<Vec<ForbiddenEntry>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, reason, set_by, set_time FROM forbidden_nicks ORDER BY nick",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (name, reason, set_by, timestamp) in rows {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push(ForbiddenEntry { name, reason, set_by, set_time });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn create_vhost_request_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS vhost_requests (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        vhost VARCHAR(255) NOT NULL,
+                        request_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_vhost_request(
+            &mut self,
+            nick: &str,
+            vhost: &str,
+            request_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = request_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query(
+                    "INSERT INTO vhost_requests (nick, vhost, request_time) VALUES (?, ?, ?)
+                     ON DUPLICATE KEY UPDATE vhost = VALUES(vhost), request_time = VALUES(request_time)",
+                )
+                .bind(casefold(nick))
+                .bind(vhost)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_vhost_request(&self, nick: &str) -> DbResult<Option<VhostRequest>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, vhost, request_time FROM vhost_requests WHERE nick = ?",
+                )
+                .bind(casefold(nick))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((nick, vhost, timestamp)) = row {
+                    let request_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    return Ok(Some(VhostRequest { nick, vhost, request_time }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn get_vhost_requests(&self) -> DbResult<Vec<VhostRequest>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, vhost, request_time FROM vhost_requests ORDER BY request_time",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (nick, vhost, timestamp) in rows {
+                    let request_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push(VhostRequest { nick, vhost, request_time });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_vhost_request(&mut self, nick: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM vhost_requests WHERE nick = ?")
+                    .bind(casefold(nick))
                     .execute(pool)
                     .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let mut conn = pool.acquire().await?;
+                refold_column(&mut conn, "nicks", "nick").await?;
+                refold_column(&mut conn, "forbidden_nicks", "nick").await?;
+                refold_column(&mut conn, "vhost_requests", "nick").await?;
             }
             Ok(())
         }
@@ -218,7 +433,7 @@ pub mod mysql_impl {
 
     #[async_trait]
     impl ChannelDatabase for MysqlChannelDatabase {
-        async fn connect(&mut self, db_config: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
             let pool = MySqlPoolOptions::new()
                 .max_connections(5)
                 .connect(db_config)
@@ -227,7 +442,7 @@ pub mod mysql_impl {
             Ok(())
         }
 
-        async fn create_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn create_table(&mut self) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 sqlx::query(
                     "CREATE TABLE IF NOT EXISTS channels (
@@ -237,7 +452,13 @@ pub mod mysql_impl {
                         topic TEXT,
                         topic_setter VARCHAR(255),
                         topic_time BIGINT,
-                        modes TEXT
+                        modes TEXT,
+                        keeptopic BOOLEAN NOT NULL DEFAULT TRUE,
+                        topiclock BOOLEAN NOT NULL DEFAULT FALSE,
+                        secureops BOOLEAN NOT NULL DEFAULT FALSE,
+                        restricted BOOLEAN NOT NULL DEFAULT FALSE,
+                        private BOOLEAN NOT NULL DEFAULT FALSE,
+                        guard BOOLEAN NOT NULL DEFAULT FALSE
                     )",
                 )
                 .execute(pool)
@@ -251,7 +472,7 @@ pub mod mysql_impl {
             channel_name: &str,
             creator_nick: &str,
             creation_time: SystemTime,
-        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 let timestamp = creation_time
                     .duration_since(SystemTime::UNIX_EPOCH)?
@@ -259,7 +480,7 @@ pub mod mysql_impl {
                 sqlx::query(
                     "INSERT INTO channels (channel_name, creator_nick, creation_time, modes) VALUES (?, ?, ?, '+r')",
                 )
-                .bind(channel_name)
+                .bind(casefold(channel_name))
                 .bind(creator_nick)
                 .bind(timestamp as i64)
                 .execute(pool)
@@ -271,12 +492,12 @@ pub mod mysql_impl {
         async fn get_channel_info(
             &self,
             channel_name: &str,
-        ) -> Result<Option<(String, SystemTime, Option<String>, Option<String>, Option<String>, Option<SystemTime>)>, Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<Option<ChannelRecord>> {
             if let Some(pool) = &self.pool {
                 let row: Option<(String, i64, Option<String>, Option<String>, Option<String>, Option<i64>)> = sqlx::query_as(
                     "SELECT creator_nick, creation_time, topic, modes, topic_setter, topic_time FROM channels WHERE channel_name = ?",
                 )
-                .bind(channel_name)
+                .bind(casefold(channel_name))
                 .fetch_optional(pool)
                 .await?;
 
@@ -286,7 +507,7 @@ pub mod mysql_impl {
                     let topic_time = topic_time_secs.map(|secs| 
                         SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64)
                     );
-                    return Ok(Some((creator, creation_time, topic, modes, topic_setter, topic_time)));
+                    return Ok(Some(ChannelRecord { founder: creator, creation_time, topic, modes, topic_setter, topic_time }));
                 }
             }
             Ok(None)
@@ -299,7 +520,7 @@ pub mod mysql_impl {
             topic_setter: Option<&str>,
             topic_time: Option<SystemTime>,
             modes: Option<&str>,
-        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 let mut set_clauses = Vec::new();
                 if topic.is_some() {
@@ -334,7 +555,7 @@ pub mod mysql_impl {
                     if let Some(m) = modes {
                         query = query.bind(m);
                     }
-                    query.bind(channel_name).execute(pool).await?;
+                    query.bind(casefold(channel_name)).execute(pool).await?;
                 }
             }
             Ok(())
@@ -344,34 +565,48 @@ pub mod mysql_impl {
             &mut self,
             channel_name: &str,
             new_owner: &str,
-        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 sqlx::query("UPDATE channels SET creator_nick = ? WHERE channel_name = ?")
                     .bind(new_owner)
-                    .bind(channel_name)
+                    .bind(casefold(channel_name))
                     .execute(pool)
                     .await?;
             }
             Ok(())
         }
 
-        async fn delete_channel(&mut self, channel_name: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn delete_channel(&mut self, channel_name: &str) -> DbResult<()> {
             if let Some(pool) = &self.pool {
+                sqlx::query("DELETE FROM channel_lists WHERE channel_name = ?")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
                 sqlx::query("DELETE FROM channels WHERE channel_name = ?")
-                    .bind(channel_name)
+                    .bind(casefold(channel_name))
                     .execute(pool)
                     .await?;
             }
             Ok(())
         }
 
-        async fn create_access_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn get_channels(&self) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let names: Vec<String> = sqlx::query_scalar("SELECT channel_name FROM channels ORDER BY channel_name")
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(names);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn create_access_table(&mut self) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 sqlx::query(
                     "CREATE TABLE IF NOT EXISTS channel_access (
                         channel_name VARCHAR(255) NOT NULL,
                         nick VARCHAR(255) NOT NULL,
-                        level VARCHAR(10) NOT NULL,
+                        level VARCHAR(32) NOT NULL,
                         added_by VARCHAR(255) NOT NULL,
                         added_time BIGINT NOT NULL,
                         PRIMARY KEY (channel_name, nick),
@@ -391,7 +626,7 @@ pub mod mysql_impl {
             level: &str,
             added_by: &str,
             added_time: SystemTime,
-        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 let timestamp = added_time
                     .duration_since(SystemTime::UNIX_EPOCH)?
@@ -399,8 +634,8 @@ pub mod mysql_impl {
                 sqlx::query(
                     "INSERT INTO channel_access (channel_name, nick, level, added_by, added_time) VALUES (?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE level = VALUES(level), added_by = VALUES(added_by), added_time = VALUES(added_time)",
                 )
-                .bind(channel_name)
-                .bind(nick)
+                .bind(casefold(channel_name))
+                .bind(casefold(nick))
                 .bind(level)
                 .bind(added_by)
                 .bind(timestamp as i64)
@@ -414,19 +649,19 @@ pub mod mysql_impl {
             &self,
             channel_name: &str,
             nick: &str,
-        ) -> Result<Option<(String, String, SystemTime)>, Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<Option<AccessEntry>> {
             if let Some(pool) = &self.pool {
-                let row: Option<(String, String, i64)> = sqlx::query_as(
-                    "SELECT level, added_by, added_time FROM channel_access WHERE channel_name = ? AND nick = ?",
+                let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = ? AND nick = ?",
                 )
-                .bind(channel_name)
-                .bind(nick)
+                .bind(casefold(channel_name))
+                .bind(casefold(nick))
                 .fetch_optional(pool)
                 .await?;
 
-                if let Some((level, added_by, timestamp)) = row {
+                if let Some((nick, level, added_by, timestamp)) = row {
                     let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
-                    return Ok(Some((level, added_by, added_time)));
+                    return Ok(Some(AccessEntry { nick, flags: level, added_by, added_time }));
                 }
             }
             Ok(None)
@@ -436,7 +671,7 @@ pub mod mysql_impl {
             &self,
             channel_name: &str,
             level: Option<&str>,
-        ) -> Result<Vec<(String, String, String, SystemTime)>, Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<Vec<AccessEntry>> {
             if let Some(pool) = &self.pool {
                 let query = if let Some(_l) = level {
                     "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = ? AND level = ? ORDER BY added_time"
@@ -445,7 +680,7 @@ pub mod mysql_impl {
                 };
                 
                 let mut query_builder = sqlx::query_as::<_, (String, String, String, i64)>(query);
-                query_builder = query_builder.bind(channel_name);
+                query_builder = query_builder.bind(casefold(channel_name));
                 if let Some(l) = level {
                     query_builder = query_builder.bind(l);
                 }
@@ -454,7 +689,7 @@ pub mod mysql_impl {
                 let mut results = Vec::new();
                 for (nick, level, added_by, timestamp) in rows {
                     let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
-                    results.push((nick, level, added_by, added_time));
+                    results.push(AccessEntry { nick, flags: level, added_by, added_time });
                 }
                 return Ok(results);
             }
@@ -468,7 +703,7 @@ pub mod mysql_impl {
             level: &str,
             updated_by: &str,
             updated_time: SystemTime,
-        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        ) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 let timestamp = updated_time
                     .duration_since(SystemTime::UNIX_EPOCH)?
@@ -479,26 +714,105 @@ pub mod mysql_impl {
                 .bind(level)
                 .bind(updated_by)
                 .bind(timestamp as i64)
-                .bind(channel_name)
-                .bind(nick)
+                .bind(casefold(channel_name))
+                .bind(casefold(nick))
                 .execute(pool)
                 .await?;
             }
             Ok(())
         }
 
-        async fn delete_channel_access(&mut self, channel_name: &str, nick: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn delete_channel_access(&mut self, channel_name: &str, nick: &str) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 sqlx::query("DELETE FROM channel_access WHERE channel_name = ? AND nick = ?")
-                    .bind(channel_name)
-                    .bind(nick)
+                    .bind(casefold(channel_name))
+                    .bind(casefold(nick))
                     .execute(pool)
                     .await?;
             }
             Ok(())
         }
 
-        async fn migrate_topic_fields(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        async fn create_akick_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS channel_akick (
+                        channel_name VARCHAR(255) NOT NULL,
+                        mask VARCHAR(255) NOT NULL,
+                        reason TEXT,
+                        added_by VARCHAR(255) NOT NULL,
+                        added_time BIGINT NOT NULL,
+                        PRIMARY KEY (channel_name, mask),
+                        FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_channel_akick(
+            &mut self,
+            channel_name: &str,
+            mask: &str,
+            reason: Option<&str>,
+            added_by: &str,
+            added_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = added_time
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs();
+                sqlx::query(
+                    "INSERT INTO channel_akick (channel_name, mask, reason, added_by, added_time) VALUES (?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE reason = VALUES(reason), added_by = VALUES(added_by), added_time = VALUES(added_time)",
+                )
+                .bind(casefold(channel_name))
+                .bind(mask)
+                .bind(reason)
+                .bind(added_by)
+                .bind(timestamp as i64)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channel_akick_list(
+            &self,
+            channel_name: &str,
+        ) -> DbResult<Vec<(String, Option<String>, String, SystemTime)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, Option<String>, String, i64)> = sqlx::query_as(
+                    "SELECT mask, reason, added_by, added_time FROM channel_akick WHERE channel_name = ? ORDER BY added_time",
+                )
+                .bind(casefold(channel_name))
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (mask, reason, added_by, timestamp) in rows {
+                    let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push((mask, reason, added_by, added_time));
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_channel_akick(&mut self, channel_name: &str, mask: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM channel_akick WHERE channel_name = ? AND mask = ?")
+                    .bind(casefold(channel_name))
+                    .bind(mask)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn migrate_topic_fields(&mut self) -> DbResult<()> {
             if let Some(pool) = &self.pool {
                 // Check if topic_setter column exists
                 let check_query = "SELECT COUNT(*) FROM information_schema.columns 
@@ -529,5 +843,1075 @@ pub mod mysql_impl {
             
             Ok(())
         }
+
+        async fn create_suspend_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS channel_suspensions (
+                        channel_name VARCHAR(255) PRIMARY KEY,
+                        reason TEXT NOT NULL,
+                        suspended_by VARCHAR(255) NOT NULL,
+                        suspended_time BIGINT NOT NULL,
+                        expires_time BIGINT,
+                        FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn suspend_channel(
+            &mut self,
+            channel_name: &str,
+            reason: &str,
+            suspended_by: &str,
+            suspended_time: SystemTime,
+            expires: Option<SystemTime>,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = suspended_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                let expires_timestamp = match expires {
+                    Some(expires) => Some(expires.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                    None => None,
+                };
+                sqlx::query(
+                    "INSERT INTO channel_suspensions (channel_name, reason, suspended_by, suspended_time, expires_time) VALUES (?, ?, ?, ?, ?)
+                     ON DUPLICATE KEY UPDATE reason = VALUES(reason), suspended_by = VALUES(suspended_by), suspended_time = VALUES(suspended_time), expires_time = VALUES(expires_time)",
+                )
+                .bind(casefold(channel_name))
+                .bind(reason)
+                .bind(suspended_by)
+                .bind(timestamp)
+                .bind(expires_timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channel_suspension(&self, channel_name: &str) -> DbResult<Option<ChannelSuspension>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, i64, Option<i64>)> = sqlx::query_as(
+                    "SELECT reason, suspended_by, suspended_time, expires_time FROM channel_suspensions WHERE channel_name = ?",
+                )
+                .bind(casefold(channel_name))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((reason, suspended_by, timestamp, expires_timestamp)) = row {
+                    let suspended_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    let expires = expires_timestamp.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64));
+                    return Ok(Some(ChannelSuspension { reason, suspended_by, suspended_time, expires }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn unsuspend_channel(&mut self, channel_name: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM channel_suspensions WHERE channel_name = ?")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+        async fn create_forbid_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS forbidden_channels (
+                        channel_name VARCHAR(255) PRIMARY KEY,
+                        reason TEXT NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_forbidden_channel(
+            &mut self,
+            channel_name: &str,
+            reason: &str,
+            set_by: &str,
+            set_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query(
+                    "INSERT INTO forbidden_channels (channel_name, reason, set_by, set_time) VALUES (?, ?, ?, ?)
+                     ON DUPLICATE KEY UPDATE reason = VALUES(reason), set_by = VALUES(set_by), set_time = VALUES(set_time)",
+                )
+                .bind(casefold(channel_name))
+                .bind(reason)
+                .bind(set_by)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_forbidden_channel(&self, channel_name: &str) -> DbResult<Option<ForbiddenEntry>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT channel_name, reason, set_by, set_time FROM forbidden_channels WHERE channel_name = ?",
+                )
+                .bind(casefold(channel_name))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((name, reason, set_by, timestamp)) = row {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    return Ok(Some(ForbiddenEntry { name, reason, set_by, set_time }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn delete_forbidden_channel(&mut self, channel_name: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM forbidden_channels WHERE channel_name = ?")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_forbidden_channels(&self) -> DbResult<Vec<ForbiddenEntry>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT channel_name, reason, set_by, set_time FROM forbidden_channels ORDER BY channel_name",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (name, reason, set_by, timestamp) in rows {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push(ForbiddenEntry { name, reason, set_by, set_time });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn get_channel_setting(&self, channel_name: &str, setting: &str) -> DbResult<bool> {
+            if !crate::database::is_channel_setting(setting) {
+                return Err(format!("Unknown channel setting: {setting}").into());
+            }
+            if let Some(pool) = &self.pool {
+                // el nombre de la columna ya se ha validado con CHANNEL_SETTINGS
+                let value: Option<bool> = sqlx::query_scalar(&format!("SELECT {setting} FROM channels WHERE channel_name = ?"))
+                    .bind(casefold(channel_name))
+                    .fetch_optional(pool)
+                    .await?;
+                Ok(value.unwrap_or(false))
+            } else {
+                Ok(false)
+            }
+        }
+
+        async fn set_channel_setting(&mut self, channel_name: &str, setting: &str, enabled: bool) -> DbResult<()> {
+            if !crate::database::is_channel_setting(setting) {
+                return Err(format!("Unknown channel setting: {setting}").into());
+            }
+            if let Some(pool) = &self.pool {
+                sqlx::query(&format!("UPDATE channels SET {setting} = ? WHERE channel_name = ?"))
+                    .bind(enabled)
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channels_with_setting(&self, setting: &str) -> DbResult<Vec<String>> {
+            if !crate::database::is_channel_setting(setting) {
+                return Err(format!("Unknown channel setting: {setting}").into());
+            }
+            if let Some(pool) = &self.pool {
+                let channels: Vec<String> = sqlx::query_scalar(&format!("SELECT channel_name FROM channels WHERE {setting} = TRUE"))
+                    .fetch_all(pool)
+                    .await?;
+                Ok(channels)
+            } else {
+                Ok(Vec::new())
+            }
+        }
+
+        async fn migrate_access_flags(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                // VARCHAR(10) no basta para todos los flags
+                sqlx::query("ALTER TABLE channel_access MODIFY level VARCHAR(32) NOT NULL")
+                    .execute(pool)
+                    .await?;
+
+                for (template, flags) in crate::database::ACCESS_TEMPLATES {
+                    sqlx::query("UPDATE channel_access SET level = ? WHERE level = ?")
+                        .bind(flags)
+                        .bind(template)
+                        .execute(pool)
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+
+        async fn migrate_channel_settings(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                for (setting, default) in crate::database::CHANNEL_SETTINGS {
+                    let count: i64 = sqlx::query_scalar(
+                        "SELECT COUNT(*) FROM information_schema.columns
+                         WHERE table_name = 'channels' AND column_name = ?",
+                    )
+                    .bind(setting)
+                    .fetch_one(pool)
+                    .await?;
+
+                    if count == 0 {
+                        sqlx::query(&format!(
+                            "ALTER TABLE channels ADD COLUMN {setting} BOOLEAN NOT NULL DEFAULT {}",
+                            if default { "TRUE" } else { "FALSE" }
+                        ))
+                        .execute(pool)
+                        .await?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        async fn create_list_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS channel_lists (
+                        channel_name VARCHAR(255) NOT NULL,
+                        list_mode VARCHAR(1) NOT NULL,
+                        mask VARCHAR(255) NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL,
+                        expires_time BIGINT,
+                        PRIMARY KEY (channel_name, list_mode, mask),
+                        FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_channel_list_entry(
+            &mut self,
+            channel_name: &str,
+            list: char,
+            mask: &str,
+            set_by: &str,
+            set_time: SystemTime,
+            expires: Option<SystemTime>,
+        ) -> DbResult<()> {
+            if !crate::database::is_channel_list(list) {
+                return Err(format!("Unknown channel list: {list}").into());
+            }
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                let expires_timestamp = match expires {
+                    Some(e) => Some(e.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                    None => None,
+                };
+                sqlx::query(
+                    "INSERT INTO channel_lists (channel_name, list_mode, mask, set_by, set_time, expires_time) VALUES (?, ?, ?, ?, ?, ?)
+                     ON DUPLICATE KEY UPDATE set_by = VALUES(set_by), set_time = VALUES(set_time), expires_time = VALUES(expires_time)",
+                )
+                .bind(casefold(channel_name))
+                .bind(list.to_string())
+                .bind(mask)
+                .bind(set_by)
+                .bind(timestamp)
+                .bind(expires_timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channel_list_entries(&self, channel_name: &str) -> DbResult<Vec<ChannelListEntry>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, i64, Option<i64>)> = sqlx::query_as(
+                    "SELECT list_mode, mask, set_by, set_time, expires_time FROM channel_lists WHERE channel_name = ? ORDER BY set_time, mask",
+                )
+                .bind(casefold(channel_name))
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (list_mode, mask, set_by, timestamp, expires_timestamp) in rows {
+                    let Some(list) = list_mode.chars().next() else {
+                        continue;
+                    };
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    let expires = expires_timestamp.map(|t| SystemTime::UNIX_EPOCH + Duration::from_secs(t as u64));
+                    results.push(ChannelListEntry { list, mask, set_by, set_time, expires });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_channel_list_entry(&mut self, channel_name: &str, list: char, mask: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM channel_lists WHERE channel_name = ? AND list_mode = ? AND mask = ?")
+                    .bind(casefold(channel_name))
+                    .bind(list.to_string())
+                    .bind(mask)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_channel_key_limit(&self, channel_name: &str) -> DbResult<(Option<String>, Option<usize>)> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(Option<String>, Option<i64>)> =
+                    sqlx::query_as("SELECT mode_key, mode_limit FROM channels WHERE channel_name = ?")
+                        .bind(casefold(channel_name))
+                        .fetch_optional(pool)
+                        .await?;
+                if let Some((key, limit)) = row {
+                    return Ok((key, limit.map(|l| l as usize)));
+                }
+            }
+            Ok((None, None))
+        }
+
+        async fn set_channel_key_limit(&mut self, channel_name: &str, key: Option<&str>, limit: Option<usize>) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("UPDATE channels SET mode_key = ?, mode_limit = ? WHERE channel_name = ?")
+                    .bind(key)
+                    .bind(limit.map(|l| l as i64))
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn migrate_channel_key_limit(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                for (column, column_type) in [("mode_key", "VARCHAR(255)"), ("mode_limit", "BIGINT")] {
+                    let count: i64 = sqlx::query_scalar(
+                        "SELECT COUNT(*) FROM information_schema.columns
+                         WHERE table_name = 'channels' AND column_name = ?",
+                    )
+                    .bind(column)
+                    .fetch_one(pool)
+                    .await?;
+
+                    if count == 0 {
+                        sqlx::query(&format!("ALTER TABLE channels ADD COLUMN {column} {column_type}"))
+                            .execute(pool)
+                            .await?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let mut conn = pool.acquire().await?;
+                sqlx::query("SET FOREIGN_KEY_CHECKS = 0").execute(&mut *conn).await?;
+                refold_column(&mut conn, "channels", "channel_name").await?;
+                refold_column(&mut conn, "channel_access", "channel_name").await?;
+                refold_column(&mut conn, "channel_access", "nick").await?;
+                refold_column(&mut conn, "channel_akick", "channel_name").await?;
+                refold_column(&mut conn, "channel_suspensions", "channel_name").await?;
+                refold_column(&mut conn, "forbidden_channels", "channel_name").await?;
+                refold_column(&mut conn, "channel_lists", "channel_name").await?;
+                sqlx::query("SET FOREIGN_KEY_CHECKS = 1").execute(&mut *conn).await?;
+            }
+            Ok(())
+        }
+    }
+
+    pub struct MysqlMemoDatabase {
+        pool: Option<MySqlPool>,
+    }
+
+    impl MysqlMemoDatabase {
+        pub fn new() -> Self {
+            MysqlMemoDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl MemoDatabase for MysqlMemoDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = MySqlPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS memos (
+                        id BIGINT AUTO_INCREMENT PRIMARY KEY,
+                        recipient VARCHAR(255) NOT NULL,
+                        sender VARCHAR(255) NOT NULL,
+                        text TEXT NOT NULL,
+                        sent_time BIGINT NOT NULL,
+                        is_read BOOLEAN NOT NULL DEFAULT FALSE,
+                        INDEX (recipient)
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS memo_limits (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        memo_limit INT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS memo_ignores (
+                        nick VARCHAR(255) NOT NULL,
+                        ignored VARCHAR(255) NOT NULL,
+                        PRIMARY KEY (nick, ignored)
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_memo(
+            &mut self,
+            recipient: &str,
+            sender: &str,
+            text: &str,
+            sent_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = sent_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query("INSERT INTO memos (recipient, sender, text, sent_time) VALUES (?, ?, ?, ?)")
+                    .bind(casefold(recipient))
+                    .bind(sender)
+                    .bind(text)
+                    .bind(timestamp)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_memos(&self, recipient: &str) -> DbResult<Vec<(i64, String, String, SystemTime, bool)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(i64, String, String, i64, bool)> = sqlx::query_as(
+                    "SELECT id, sender, text, sent_time, is_read FROM memos WHERE recipient = ? ORDER BY id",
+                )
+                .bind(casefold(recipient))
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (id, sender, text, timestamp, is_read) in rows {
+                    let sent_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push((id, sender, text, sent_time, is_read));
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn count_unread_memos(&self, recipient: &str) -> DbResult<usize> {
+            if let Some(pool) = &self.pool {
+                let (count,): (i64,) = sqlx::query_as(
+                    "SELECT COUNT(*) FROM memos WHERE recipient = ? AND is_read = FALSE",
+                )
+                .bind(casefold(recipient))
+                .fetch_one(pool)
+                .await?;
+                return Ok(count as usize);
+            }
+            Ok(0)
+        }
+
+        async fn mark_memo_read(&mut self, id: i64) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("UPDATE memos SET is_read = TRUE WHERE id = ?")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn delete_memo(&mut self, id: i64) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM memos WHERE id = ?")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_memo_accounts(&self) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let names: Vec<String> = sqlx::query_scalar("SELECT recipient FROM memos UNION SELECT nick FROM memo_limits UNION SELECT nick FROM memo_ignores ORDER BY 1")
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(names);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn get_memo_limit(&self, nick: &str) -> DbResult<Option<usize>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(i32,)> = sqlx::query_as("SELECT memo_limit FROM memo_limits WHERE nick = ?")
+                    .bind(casefold(nick))
+                    .fetch_optional(pool)
+                    .await?;
+                return Ok(row.map(|(limit,)| limit as usize));
+            }
+            Ok(None)
+        }
+
+        async fn set_memo_limit(&mut self, nick: &str, limit: Option<usize>) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                if let Some(l) = limit {
+                    sqlx::query(
+                        "INSERT INTO memo_limits (nick, memo_limit) VALUES (?, ?)
+                         ON DUPLICATE KEY UPDATE memo_limit = VALUES(memo_limit)",
+                    )
+                    .bind(casefold(nick))
+                    .bind(l as i32)
+                    .execute(pool)
+                    .await?;
+                } else {
+                    sqlx::query("DELETE FROM memo_limits WHERE nick = ?")
+                        .bind(casefold(nick))
+                        .execute(pool)
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+
+        async fn add_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("INSERT IGNORE INTO memo_ignores (nick, ignored) VALUES (?, ?)")
+                    .bind(casefold(nick))
+                    .bind(casefold(ignored))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_memo_ignores(&self, nick: &str) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String,)> = sqlx::query_as("SELECT ignored FROM memo_ignores WHERE nick = ? ORDER BY ignored")
+                    .bind(casefold(nick))
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(rows.into_iter().map(|(ignored,)| ignored).collect());
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM memo_ignores WHERE nick = ? AND ignored = ?")
+                    .bind(casefold(nick))
+                    .bind(casefold(ignored))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let mut conn = pool.acquire().await?;
+                refold_column(&mut conn, "memos", "recipient").await?;
+                refold_column(&mut conn, "memo_limits", "nick").await?;
+                refold_column(&mut conn, "memo_ignores", "nick").await?;
+                refold_column(&mut conn, "memo_ignores", "ignored").await?;
+            }
+            Ok(())
+        }
+    }
+
+    pub struct MysqlOperDatabase {
+        pool: Option<MySqlPool>,
+    }
+
+    impl MysqlOperDatabase {
+        pub fn new() -> Self {
+            MysqlOperDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl OperDatabase for MysqlOperDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = MySqlPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS akills (
+                        mask VARCHAR(255) PRIMARY KEY,
+                        reason TEXT NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL,
+                        expires_time BIGINT
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS session_exceptions (
+                        ip VARCHAR(64) PRIMARY KEY,
+                        session_limit INT NOT NULL,
+                        reason TEXT NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_akill(
+            &mut self,
+            mask: &str,
+            reason: &str,
+            set_by: &str,
+            set_time: SystemTime,
+            expires: Option<SystemTime>,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                let expires_timestamp = match expires {
+                    Some(expires) => Some(expires.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                    None => None,
+                };
+                sqlx::query(
+                    "INSERT INTO akills (mask, reason, set_by, set_time, expires_time) VALUES (?, ?, ?, ?, ?)
+                     ON DUPLICATE KEY UPDATE reason = VALUES(reason), set_by = VALUES(set_by), set_time = VALUES(set_time), expires_time = VALUES(expires_time)",
+                )
+                .bind(mask.to_lowercase())
+                .bind(reason)
+                .bind(set_by)
+                .bind(timestamp)
+                .bind(expires_timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_akills(&self) -> DbResult<Vec<AkillEntry>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, i64, Option<i64>)> = sqlx::query_as(
+                    "SELECT mask, reason, set_by, set_time, expires_time FROM akills ORDER BY set_time",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (mask, reason, set_by, timestamp, expires_timestamp) in rows {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    let expires = expires_timestamp.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64));
+                    results.push(AkillEntry { mask, reason, set_by, set_time, expires });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_akill(&mut self, mask: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM akills WHERE mask = ?")
+                    .bind(mask.to_lowercase())
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn add_session_exception(
+            &mut self,
+            ip: &str,
+            limit: usize,
+            reason: &str,
+            set_by: &str,
+            set_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query(
+                    "INSERT INTO session_exceptions (ip, session_limit, reason, set_by, set_time) VALUES (?, ?, ?, ?, ?)
+                     ON DUPLICATE KEY UPDATE session_limit = VALUES(session_limit), reason = VALUES(reason), set_by = VALUES(set_by), set_time = VALUES(set_time)",
+                )
+                .bind(ip)
+                .bind(limit as i32)
+                .bind(reason)
+                .bind(set_by)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_session_exception(&self, ip: &str) -> DbResult<Option<usize>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(i32,)> = sqlx::query_as("SELECT session_limit FROM session_exceptions WHERE ip = ?")
+                    .bind(ip)
+                    .fetch_optional(pool)
+                    .await?;
+                return Ok(row.map(|(limit,)| limit as usize));
+            }
+            Ok(None)
+        }
+
+        async fn get_session_exceptions(&self) -> DbResult<Vec<(String, usize, String, String, SystemTime)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, i32, String, String, i64)> = sqlx::query_as(
+                    "SELECT ip, session_limit, reason, set_by, set_time FROM session_exceptions ORDER BY set_time",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (ip, limit, reason, set_by, timestamp) in rows {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push((ip, limit as usize, reason, set_by, set_time));
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_session_exception(&mut self, ip: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM session_exceptions WHERE ip = ?")
+                    .bind(ip)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+    }
+
+    pub struct MysqlBotDatabase {
+        pool: Option<MySqlPool>,
+    }
+
+    impl MysqlBotDatabase {
+        pub fn new() -> Self {
+            MysqlBotDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl BotDatabase for MysqlBotDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = MySqlPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS bots (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        user VARCHAR(255) NOT NULL,
+                        host VARCHAR(255) NOT NULL,
+                        realname TEXT NOT NULL,
+                        created_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS bot_channels (
+                        channel_name VARCHAR(255) PRIMARY KEY,
+                        bot VARCHAR(255) NOT NULL,
+                        greet TEXT,
+                        kick_caps BOOLEAN NOT NULL DEFAULT FALSE,
+                        kick_colors BOOLEAN NOT NULL DEFAULT FALSE,
+                        kick_repeat BOOLEAN NOT NULL DEFAULT FALSE,
+                        kick_badwords BOOLEAN NOT NULL DEFAULT FALSE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS bot_badwords (
+                        channel_name VARCHAR(255) NOT NULL,
+                        word VARCHAR(255) NOT NULL,
+                        PRIMARY KEY (channel_name, word)
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_bot(&mut self, nick: &str, user: &str, host: &str, realname: &str, created_time: SystemTime) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = created_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query("INSERT INTO bots (nick, user, host, realname, created_time) VALUES (?, ?, ?, ?, ?)")
+                    .bind(nick)
+                    .bind(user)
+                    .bind(host)
+                    .bind(realname)
+                    .bind(timestamp)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_bots(&self) -> DbResult<Vec<(String, String, String, String)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, String)> = sqlx::query_as("SELECT nick, user, host, realname FROM bots ORDER BY nick")
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(rows);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_bot(&mut self, nick: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM bots WHERE nick = ?")
+                    .bind(nick)
+                    .execute(pool)
+                    .await?;
+                sqlx::query("DELETE FROM bot_channels WHERE bot = ?")
+                    .bind(nick)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn assign_bot(&mut self, channel_name: &str, bot: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                // Al cambiar de bot se conservan el saludo y los kickers del canal
+                sqlx::query(
+                    "INSERT INTO bot_channels (channel_name, bot) VALUES (?, ?)
+                     ON DUPLICATE KEY UPDATE bot = VALUES(bot)",
+                )
+                .bind(casefold(channel_name))
+                .bind(bot)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn unassign_bot(&mut self, channel_name: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM bot_channels WHERE channel_name = ?")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_bot_channels(&self) -> DbResult<Vec<(String, String, Option<String>, Vec<String>)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, Option<String>, bool, bool, bool, bool)> = sqlx::query_as(
+                    "SELECT channel_name, bot, greet, kick_caps, kick_colors, kick_repeat, kick_badwords FROM bot_channels ORDER BY channel_name",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (channel_name, bot, greet, caps, colors, repeat, badwords) in rows {
+                    let kickers = crate::database::BOT_KICKERS
+                        .iter()
+                        .zip([caps, colors, repeat, badwords])
+                        .filter(|(_, enabled)| *enabled)
+                        .map(|(kicker, _)| kicker.to_string())
+                        .collect();
+                    results.push((channel_name, bot, greet, kickers));
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn set_bot_greet(&mut self, channel_name: &str, greet: Option<&str>) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("UPDATE bot_channels SET greet = ? WHERE channel_name = ?")
+                    .bind(greet)
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn set_bot_kicker(&mut self, channel_name: &str, kicker: &str, enabled: bool) -> DbResult<()> {
+            if !crate::database::is_bot_kicker(kicker) {
+                return Err(format!("Unknown kicker: {kicker}").into());
+            }
+            if let Some(pool) = &self.pool {
+                let query = format!("UPDATE bot_channels SET kick_{kicker} = ? WHERE channel_name = ?");
+                sqlx::query(&query)
+                    .bind(enabled)
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_badword(&mut self, channel_name: &str, word: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("INSERT IGNORE INTO bot_badwords (channel_name, word) VALUES (?, ?)")
+                    .bind(casefold(channel_name))
+                    .bind(word.to_lowercase())
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_badwords(&self, channel_name: &str) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String,)> = sqlx::query_as("SELECT word FROM bot_badwords WHERE channel_name = ? ORDER BY word")
+                    .bind(casefold(channel_name))
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(rows.into_iter().map(|(word,)| word).collect());
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_badword(&mut self, channel_name: &str, word: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM bot_badwords WHERE channel_name = ? AND word = ?")
+                    .bind(casefold(channel_name))
+                    .bind(word.to_lowercase())
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let mut conn = pool.acquire().await?;
+                refold_column(&mut conn, "bot_channels", "channel_name").await?;
+                refold_column(&mut conn, "bot_badwords", "channel_name").await?;
+            }
+            Ok(())
+        }
+    }
+
+    pub struct MysqlSchemaDatabase {
+        pool: Option<MySqlPool>,
+    }
+
+    impl MysqlSchemaDatabase {
+        pub fn new() -> Self {
+            MysqlSchemaDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl SchemaDatabase for MysqlSchemaDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = MySqlPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS schema_version (
+                        version INTEGER PRIMARY KEY,
+                        description VARCHAR(255) NOT NULL,
+                        applied_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_schema_version(&self) -> DbResult<u32> {
+            if let Some(pool) = &self.pool {
+                let version: Option<i32> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+                    .fetch_one(pool)
+                    .await?;
+                return Ok(version.unwrap_or(0) as u32);
+            }
+            Ok(0)
+        }
+
+        async fn add_schema_version(&mut self, version: u32, description: &str, applied_time: SystemTime) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = applied_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query("INSERT INTO schema_version (version, description, applied_time) VALUES (?, ?, ?)")
+                    .bind(version as i32)
+                    .bind(description)
+                    .bind(timestamp)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
     }
-}
\ No newline at end of file
+}
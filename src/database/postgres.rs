@@ -0,0 +1,1924 @@
+#[cfg(feature = "postgres")]
+pub mod postgres_impl {
+    use crate::database::{NickDatabase, ChannelDatabase, MemoDatabase, OperDatabase, BotDatabase, SchemaDatabase};
+    use crate::database::{AccessEntry, AkillEntry, ChannelListEntry, ChannelRecord, ChannelSuspension, DbResult, ForbiddenEntry, NickRecord, VhostRequest};
+    use crate::utils::casefold;
+    use std::time::{Duration, SystemTime};
+    use async_trait::async_trait;
+    use sqlx::postgres::PgPoolOptions;
+    use sqlx::PgPool;
+
+    // Pasa los nombres de una columna a su forma canónica. Si el nombre ya existía en forma
+    // canónica se conserva esa fila y se borra la otra.
+    async fn refold_column(pool: &PgPool, table: &str, column: &str) -> DbResult<()> {
+        let names: Vec<String> = sqlx::query_scalar(&format!("SELECT DISTINCT {column} FROM {table}"))
+            .fetch_all(pool)
+            .await?;
+        for name in names {
+            let folded = casefold(&name);
+            if folded == name {
+                continue;
+            }
+            let updated = sqlx::query(&format!("UPDATE {table} SET {column} = $1 WHERE {column} = $2"))
+                .bind(&folded)
+                .bind(&name)
+                .execute(pool)
+                .await;
+            if updated.is_err() {
+                sqlx::query(&format!("DELETE FROM {table} WHERE {column} = $1"))
+                    .bind(&name)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub struct PostgresNickDatabase {
+        pool: Option<PgPool>,
+    }
+
+    impl PostgresNickDatabase {
+        pub fn new() -> Self {
+            PostgresNickDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl NickDatabase for PostgresNickDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = PgPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS nicks (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        password VARCHAR(255) NOT NULL,
+                        \"user\" VARCHAR(255) NOT NULL,
+                        registration_time BIGINT NOT NULL,
+                        email VARCHAR(255),
+                        url VARCHAR(255),
+                        vhost VARCHAR(255),
+                        last_vhost BIGINT,
+                        noaccess BOOLEAN NOT NULL DEFAULT FALSE,
+                        noop BOOLEAN NOT NULL DEFAULT FALSE,
+                        showmail BOOLEAN NOT NULL DEFAULT FALSE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_nick(
+            &mut self,
+            nick: &str,
+            password: &str,
+            user: &str,
+            registration_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = registration_time
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs();
+                sqlx::query("INSERT INTO nicks (nick, password, \"user\", registration_time) VALUES ($1, $2, $3, $4)")
+                    .bind(casefold(nick))
+                    .bind(password)
+                    .bind(user)
+                    .bind(timestamp as i64)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_nick_info(
+            &self,
+            nick: &str,
+        ) -> DbResult<Option<NickRecord>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, i64, Option<String>, Option<String>, Option<String>, Option<i64>, bool, bool, bool)> =
+                    sqlx::query_as("SELECT \"user\", registration_time, email, url, vhost, last_vhost, noaccess, noop, showmail FROM nicks WHERE nick = $1")
+                        .bind(casefold(nick))
+                        .fetch_optional(pool)
+                        .await?;
+
+                if let Some((user, timestamp, email, url, vhost, last_vhost_timestamp, noaccess, noop, showmail)) = row {
+                    let registration_time =
+                        SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    let last_vhost = last_vhost_timestamp.map(|ts| 
+                        SystemTime::UNIX_EPOCH + Duration::from_secs(ts as u64)
+                    );
+                    return Ok(Some(NickRecord { user, registration_time, email, url, vhost, last_vhost, noaccess, noop, showmail }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn get_nick_password(&self, nick: &str) -> DbResult<Option<String>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String,)> =
+                    sqlx::query_as("SELECT password FROM nicks WHERE nick = $1")
+                        .bind(casefold(nick))
+                        .fetch_optional(pool)
+                        .await?;
+                return Ok(row.map(|(password,)| password));
+            }
+            Ok(None)
+        }
+
+        async fn update_nick_password(&mut self, nick: &str, password: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("UPDATE nicks SET password = $1 WHERE nick = $2")
+                    .bind(password)
+                    .bind(casefold(nick))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn update_nick_info(
+            &mut self,
+            nick: &str,
+            user: Option<&str>,
+            email: Option<&str>,
+            url: Option<&str>,
+            vhost: Option<&str>,
+            last_vhost: Option<SystemTime>,
+            noaccess: Option<bool>,
+            noop: Option<bool>,
+            showmail: Option<bool>,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let mut set_clauses = Vec::new();
+                if user.is_some() {
+                    set_clauses.push(format!("\"user\" = ${}", set_clauses.len() + 1));
+                }
+                if email.is_some() {
+                    set_clauses.push(format!("email = ${}", set_clauses.len() + 1));
+                }
+                if url.is_some() {
+                    set_clauses.push(format!("url = ${}", set_clauses.len() + 1));
+                }
+                if vhost.is_some() {
+                    set_clauses.push(format!("vhost = ${}", set_clauses.len() + 1));
+                }
+                if last_vhost.is_some() {
+                    set_clauses.push(format!("last_vhost = ${}", set_clauses.len() + 1));
+                }
+                if noaccess.is_some() {
+                    set_clauses.push(format!("noaccess = ${}", set_clauses.len() + 1));
+                }
+                if noop.is_some() {
+                    set_clauses.push(format!("noop = ${}", set_clauses.len() + 1));
+                }
+                if showmail.is_some() {
+                    set_clauses.push(format!("showmail = ${}", set_clauses.len() + 1));
+                }
+
+                if !set_clauses.is_empty() {
+                    let query_str = format!(
+                        "UPDATE nicks SET {} WHERE nick = ${}",
+                        set_clauses.join(", "),
+                        set_clauses.len() + 1
+                    );
+                    let mut query = sqlx::query(&query_str);
+                    if let Some(u) = user {
+                        query = query.bind(u);
+                    }
+                    if let Some(e) = email {
+                        query = query.bind(e);
+                    }
+                    if let Some(u) = url {
+                        query = query.bind(u);
+                    }
+                    if let Some(v) = vhost {
+                        query = query.bind(v);
+                    }
+                    if let Some(lv) = last_vhost {
+                        let timestamp = lv.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+                        query = query.bind(timestamp as i64);
+                    }
+                    if let Some(na) = noaccess {
+                        query = query.bind(na);
+                    }
+                    if let Some(n) = noop {
+                        query = query.bind(n);
+                    }
+                    if let Some(sm) = showmail {
+                        query = query.bind(sm);
+                    }
+                    query.bind(casefold(nick)).execute(pool).await?;
+                }
+            }
+            Ok(())
+        }
+
+        async fn delete_nick(&mut self, nick: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("DELETE FROM nicks WHERE nick = $1")
+                    .bind(casefold(nick))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_nicks(&self) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let names: Vec<String> = sqlx::query_scalar("SELECT nick FROM nicks ORDER BY nick")
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(names);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn create_forbid_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS forbidden_nicks (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        reason TEXT NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_forbidden_nick(
+            &mut self,
+            nick: &str,
+            reason: &str,
+            set_by: &str,
+            set_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query(
+                    "INSERT INTO forbidden_nicks (nick, reason, set_by, set_time) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT (nick) DO UPDATE SET reason = EXCLUDED.reason, set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time",
+                )
+                .bind(casefold(nick))
+                .bind(reason)
+                .bind(set_by)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_forbidden_nick(&self, nick: &str) -> DbResult<Option<ForbiddenEntry>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, reason, set_by, set_time FROM forbidden_nicks WHERE nick = $1",
+                )
+                .bind(casefold(nick))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((name, reason, set_by, timestamp)) = row {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    return Ok(Some(ForbiddenEntry { name, reason, set_by, set_time }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn delete_forbidden_nick(&mut self, nick: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM forbidden_nicks WHERE nick = $1")
+                    .bind(casefold(nick))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_forbidden_nicks(&self) -> DbResult<Vec<ForbiddenEntry>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, reason, set_by, set_time FROM forbidden_nicks ORDER BY nick",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (name, reason, set_by, timestamp) in rows {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push(ForbiddenEntry { name, reason, set_by, set_time });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn create_vhost_request_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS vhost_requests (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        vhost VARCHAR(255) NOT NULL,
+                        request_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_vhost_request(
+            &mut self,
+            nick: &str,
+            vhost: &str,
+            request_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = request_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query(
+                    "INSERT INTO vhost_requests (nick, vhost, request_time) VALUES ($1, $2, $3)
+                     ON CONFLICT (nick) DO UPDATE SET vhost = EXCLUDED.vhost, request_time = EXCLUDED.request_time",
+                )
+                .bind(casefold(nick))
+                .bind(vhost)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_vhost_request(&self, nick: &str) -> DbResult<Option<VhostRequest>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, vhost, request_time FROM vhost_requests WHERE nick = $1",
+                )
+                .bind(casefold(nick))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((nick, vhost, timestamp)) = row {
+                    let request_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    return Ok(Some(VhostRequest { nick, vhost, request_time }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn get_vhost_requests(&self) -> DbResult<Vec<VhostRequest>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, vhost, request_time FROM vhost_requests ORDER BY request_time",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (nick, vhost, timestamp) in rows {
+                    let request_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push(VhostRequest { nick, vhost, request_time });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_vhost_request(&mut self, nick: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM vhost_requests WHERE nick = $1")
+                    .bind(casefold(nick))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                refold_column(pool, "nicks", "nick").await?;
+                refold_column(pool, "forbidden_nicks", "nick").await?;
+                refold_column(pool, "vhost_requests", "nick").await?;
+            }
+            Ok(())
+        }
+    }
+
+    pub struct PostgresChannelDatabase {
+        pool: Option<PgPool>,
+    }
+
+    impl PostgresChannelDatabase {
+        pub fn new() -> Self {
+            PostgresChannelDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl ChannelDatabase for PostgresChannelDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = PgPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS channels (
+                        channel_name VARCHAR(255) PRIMARY KEY,
+                        creator_nick VARCHAR(255) NOT NULL,
+                        creation_time BIGINT NOT NULL,
+                        topic TEXT,
+                        topic_setter VARCHAR(255),
+                        topic_time BIGINT,
+                        modes TEXT,
+                        keeptopic BOOLEAN NOT NULL DEFAULT TRUE,
+                        topiclock BOOLEAN NOT NULL DEFAULT FALSE,
+                        secureops BOOLEAN NOT NULL DEFAULT FALSE,
+                        restricted BOOLEAN NOT NULL DEFAULT FALSE,
+                        private BOOLEAN NOT NULL DEFAULT FALSE,
+                        guard BOOLEAN NOT NULL DEFAULT FALSE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_channel(
+            &mut self,
+            channel_name: &str,
+            creator_nick: &str,
+            creation_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = creation_time
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs();
+                sqlx::query(
+                    "INSERT INTO channels (channel_name, creator_nick, creation_time, modes) VALUES ($1, $2, $3, '+r')",
+                )
+                .bind(casefold(channel_name))
+                .bind(creator_nick)
+                .bind(timestamp as i64)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channel_info(
+            &self,
+            channel_name: &str,
+        ) -> DbResult<Option<ChannelRecord>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, i64, Option<String>, Option<String>, Option<String>, Option<i64>)> = sqlx::query_as(
+                    "SELECT creator_nick, creation_time, topic, modes, topic_setter, topic_time FROM channels WHERE channel_name = $1",
+                )
+                .bind(casefold(channel_name))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((creator, timestamp, topic, modes, topic_setter, topic_time_secs)) = row {
+                    let creation_time =
+                        SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    let topic_time = topic_time_secs.map(|secs| 
+                        SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64)
+                    );
+                    return Ok(Some(ChannelRecord { founder: creator, creation_time, topic, modes, topic_setter, topic_time }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn update_channel_info(
+            &mut self,
+            channel_name: &str,
+            topic: Option<&str>,
+            topic_setter: Option<&str>,
+            topic_time: Option<SystemTime>,
+            modes: Option<&str>,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let mut set_clauses = Vec::new();
+                if topic.is_some() {
+                    set_clauses.push(format!("topic = ${}", set_clauses.len() + 1));
+                }
+                if topic_setter.is_some() {
+                    set_clauses.push(format!("topic_setter = ${}", set_clauses.len() + 1));
+                }
+                if topic_time.is_some() {
+                    set_clauses.push(format!("topic_time = ${}", set_clauses.len() + 1));
+                }
+                if modes.is_some() {
+                    set_clauses.push(format!("modes = ${}", set_clauses.len() + 1));
+                }
+
+                if !set_clauses.is_empty() {
+                    let query_str = format!(
+                        "UPDATE channels SET {} WHERE channel_name = ${}",
+                        set_clauses.join(", "),
+                        set_clauses.len() + 1
+                    );
+                    let mut query = sqlx::query(&query_str);
+                    if let Some(t) = topic {
+                        query = query.bind(t);
+                    }
+                    if let Some(ts) = topic_setter {
+                        query = query.bind(ts);
+                    }
+                    if let Some(tt) = topic_time {
+                        let timestamp = tt.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+                        query = query.bind(timestamp as i64);
+                    }
+                    if let Some(m) = modes {
+                        query = query.bind(m);
+                    }
+                    query.bind(casefold(channel_name)).execute(pool).await?;
+                }
+            }
+            Ok(())
+        }
+
+        async fn update_channel_owner(
+            &mut self,
+            channel_name: &str,
+            new_owner: &str,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("UPDATE channels SET creator_nick = $1 WHERE channel_name = $2")
+                    .bind(new_owner)
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn delete_channel(&mut self, channel_name: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("DELETE FROM channel_lists WHERE channel_name = $1")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+                sqlx::query("DELETE FROM channels WHERE channel_name = $1")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channels(&self) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let names: Vec<String> = sqlx::query_scalar("SELECT channel_name FROM channels ORDER BY channel_name")
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(names);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn create_access_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS channel_access (
+                        channel_name VARCHAR(255) NOT NULL,
+                        nick VARCHAR(255) NOT NULL,
+                        level VARCHAR(32) NOT NULL,
+                        added_by VARCHAR(255) NOT NULL,
+                        added_time BIGINT NOT NULL,
+                        PRIMARY KEY (channel_name, nick),
+                        FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_channel_access(
+            &mut self,
+            channel_name: &str,
+            nick: &str,
+            level: &str,
+            added_by: &str,
+            added_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = added_time
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs();
+                sqlx::query(
+                    "INSERT INTO channel_access (channel_name, nick, level, added_by, added_time) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (channel_name, nick) DO UPDATE SET level = EXCLUDED.level, added_by = EXCLUDED.added_by, added_time = EXCLUDED.added_time",
+                )
+                .bind(casefold(channel_name))
+                .bind(casefold(nick))
+                .bind(level)
+                .bind(added_by)
+                .bind(timestamp as i64)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channel_access(
+            &self,
+            channel_name: &str,
+            nick: &str,
+        ) -> DbResult<Option<AccessEntry>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = $1 AND nick = $2",
+                )
+                .bind(casefold(channel_name))
+                .bind(casefold(nick))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((nick, level, added_by, timestamp)) = row {
+                    let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    return Ok(Some(AccessEntry { nick, flags: level, added_by, added_time }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn get_channel_access_list(
+            &self,
+            channel_name: &str,
+            level: Option<&str>,
+        ) -> DbResult<Vec<AccessEntry>> {
+            if let Some(pool) = &self.pool {
+                let query = if let Some(_l) = level {
+                    "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = $1 AND level = $2 ORDER BY added_time"
+                } else {
+                    "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = $1 ORDER BY level, added_time"
+                };
+                
+                let mut query_builder = sqlx::query_as::<_, (String, String, String, i64)>(query);
+                query_builder = query_builder.bind(casefold(channel_name));
+                if let Some(l) = level {
+                    query_builder = query_builder.bind(l);
+                }
+                
+                let rows = query_builder.fetch_all(pool).await?;
+                let mut results = Vec::new();
+                for (nick, level, added_by, timestamp) in rows {
+                    let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push(AccessEntry { nick, flags: level, added_by, added_time });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn update_channel_access(
+            &mut self,
+            channel_name: &str,
+            nick: &str,
+            level: &str,
+            updated_by: &str,
+            updated_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = updated_time
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs();
+                sqlx::query(
+                    "UPDATE channel_access SET level = $1, added_by = $2, added_time = $3 WHERE channel_name = $4 AND nick = $5",
+                )
+                .bind(level)
+                .bind(updated_by)
+                .bind(timestamp as i64)
+                .bind(casefold(channel_name))
+                .bind(casefold(nick))
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn delete_channel_access(&mut self, channel_name: &str, nick: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("DELETE FROM channel_access WHERE channel_name = $1 AND nick = $2")
+                    .bind(casefold(channel_name))
+                    .bind(casefold(nick))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn create_akick_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS channel_akick (
+                        channel_name VARCHAR(255) NOT NULL,
+                        mask VARCHAR(255) NOT NULL,
+                        reason TEXT,
+                        added_by VARCHAR(255) NOT NULL,
+                        added_time BIGINT NOT NULL,
+                        PRIMARY KEY (channel_name, mask),
+                        FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_channel_akick(
+            &mut self,
+            channel_name: &str,
+            mask: &str,
+            reason: Option<&str>,
+            added_by: &str,
+            added_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = added_time
+                    .duration_since(SystemTime::UNIX_EPOCH)?
+                    .as_secs();
+                sqlx::query(
+                    "INSERT INTO channel_akick (channel_name, mask, reason, added_by, added_time) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (channel_name, mask) DO UPDATE SET reason = EXCLUDED.reason, added_by = EXCLUDED.added_by, added_time = EXCLUDED.added_time",
+                )
+                .bind(casefold(channel_name))
+                .bind(mask)
+                .bind(reason)
+                .bind(added_by)
+                .bind(timestamp as i64)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channel_akick_list(
+            &self,
+            channel_name: &str,
+        ) -> DbResult<Vec<(String, Option<String>, String, SystemTime)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, Option<String>, String, i64)> = sqlx::query_as(
+                    "SELECT mask, reason, added_by, added_time FROM channel_akick WHERE channel_name = $1 ORDER BY added_time",
+                )
+                .bind(casefold(channel_name))
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (mask, reason, added_by, timestamp) in rows {
+                    let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push((mask, reason, added_by, added_time));
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_channel_akick(&mut self, channel_name: &str, mask: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM channel_akick WHERE channel_name = $1 AND mask = $2")
+                    .bind(casefold(channel_name))
+                    .bind(mask)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn migrate_topic_fields(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                // Check if topic_setter column exists
+                let check_query = "SELECT COUNT(*) FROM information_schema.columns 
+                                  WHERE table_name = 'channels' AND column_name = 'topic_setter'";
+                let count: i64 = sqlx::query_scalar(check_query)
+                    .fetch_one(pool)
+                    .await?;
+                
+                if count == 0 {
+                    sqlx::query("ALTER TABLE channels ADD COLUMN topic_setter VARCHAR(255)")
+                        .execute(pool)
+                        .await?;
+                }
+                
+                // Check if topic_time column exists
+                let check_query = "SELECT COUNT(*) FROM information_schema.columns 
+                                  WHERE table_name = 'channels' AND column_name = 'topic_time'";
+                let count: i64 = sqlx::query_scalar(check_query)
+                    .fetch_one(pool)
+                    .await?;
+                
+                if count == 0 {
+                    sqlx::query("ALTER TABLE channels ADD COLUMN topic_time BIGINT")
+                        .execute(pool)
+                        .await?;
+                }
+            }
+            
+            Ok(())
+        }
+
+        async fn create_suspend_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS channel_suspensions (
+                        channel_name VARCHAR(255) PRIMARY KEY,
+                        reason TEXT NOT NULL,
+                        suspended_by VARCHAR(255) NOT NULL,
+                        suspended_time BIGINT NOT NULL,
+                        expires_time BIGINT,
+                        FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn suspend_channel(
+            &mut self,
+            channel_name: &str,
+            reason: &str,
+            suspended_by: &str,
+            suspended_time: SystemTime,
+            expires: Option<SystemTime>,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = suspended_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                let expires_timestamp = match expires {
+                    Some(expires) => Some(expires.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                    None => None,
+                };
+                sqlx::query(
+                    "INSERT INTO channel_suspensions (channel_name, reason, suspended_by, suspended_time, expires_time) VALUES ($1, $2, $3, $4, $5)
+                     ON CONFLICT (channel_name) DO UPDATE SET reason = EXCLUDED.reason, suspended_by = EXCLUDED.suspended_by, suspended_time = EXCLUDED.suspended_time, expires_time = EXCLUDED.expires_time",
+                )
+                .bind(casefold(channel_name))
+                .bind(reason)
+                .bind(suspended_by)
+                .bind(timestamp)
+                .bind(expires_timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channel_suspension(&self, channel_name: &str) -> DbResult<Option<ChannelSuspension>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, i64, Option<i64>)> = sqlx::query_as(
+                    "SELECT reason, suspended_by, suspended_time, expires_time FROM channel_suspensions WHERE channel_name = $1",
+                )
+                .bind(casefold(channel_name))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((reason, suspended_by, timestamp, expires_timestamp)) = row {
+                    let suspended_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    let expires = expires_timestamp.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64));
+                    return Ok(Some(ChannelSuspension { reason, suspended_by, suspended_time, expires }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn unsuspend_channel(&mut self, channel_name: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM channel_suspensions WHERE channel_name = $1")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+        async fn create_forbid_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS forbidden_channels (
+                        channel_name VARCHAR(255) PRIMARY KEY,
+                        reason TEXT NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_forbidden_channel(
+            &mut self,
+            channel_name: &str,
+            reason: &str,
+            set_by: &str,
+            set_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query(
+                    "INSERT INTO forbidden_channels (channel_name, reason, set_by, set_time) VALUES ($1, $2, $3, $4)
+                     ON CONFLICT (channel_name) DO UPDATE SET reason = EXCLUDED.reason, set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time",
+                )
+                .bind(casefold(channel_name))
+                .bind(reason)
+                .bind(set_by)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_forbidden_channel(&self, channel_name: &str) -> DbResult<Option<ForbiddenEntry>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT channel_name, reason, set_by, set_time FROM forbidden_channels WHERE channel_name = $1",
+                )
+                .bind(casefold(channel_name))
+                .fetch_optional(pool)
+                .await?;
+
+                if let Some((name, reason, set_by, timestamp)) = row {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    return Ok(Some(ForbiddenEntry { name, reason, set_by, set_time }));
+                }
+            }
+            Ok(None)
+        }
+
+        async fn delete_forbidden_channel(&mut self, channel_name: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM forbidden_channels WHERE channel_name = $1")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_forbidden_channels(&self) -> DbResult<Vec<ForbiddenEntry>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, i64)> = sqlx::query_as(
+                    "SELECT channel_name, reason, set_by, set_time FROM forbidden_channels ORDER BY channel_name",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (name, reason, set_by, timestamp) in rows {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push(ForbiddenEntry { name, reason, set_by, set_time });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn get_channel_setting(&self, channel_name: &str, setting: &str) -> DbResult<bool> {
+            if !crate::database::is_channel_setting(setting) {
+                return Err(format!("Unknown channel setting: {setting}").into());
+            }
+            if let Some(pool) = &self.pool {
+                // el nombre de la columna ya se ha validado con CHANNEL_SETTINGS
+                let value: Option<bool> = sqlx::query_scalar(&format!("SELECT {setting} FROM channels WHERE channel_name = $1"))
+                    .bind(casefold(channel_name))
+                    .fetch_optional(pool)
+                    .await?;
+                Ok(value.unwrap_or(false))
+            } else {
+                Ok(false)
+            }
+        }
+
+        async fn set_channel_setting(&mut self, channel_name: &str, setting: &str, enabled: bool) -> DbResult<()> {
+            if !crate::database::is_channel_setting(setting) {
+                return Err(format!("Unknown channel setting: {setting}").into());
+            }
+            if let Some(pool) = &self.pool {
+                sqlx::query(&format!("UPDATE channels SET {setting} = $1 WHERE channel_name = $2"))
+                    .bind(enabled)
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channels_with_setting(&self, setting: &str) -> DbResult<Vec<String>> {
+            if !crate::database::is_channel_setting(setting) {
+                return Err(format!("Unknown channel setting: {setting}").into());
+            }
+            if let Some(pool) = &self.pool {
+                let channels: Vec<String> = sqlx::query_scalar(&format!("SELECT channel_name FROM channels WHERE {setting} = TRUE"))
+                    .fetch_all(pool)
+                    .await?;
+                Ok(channels)
+            } else {
+                Ok(Vec::new())
+            }
+        }
+
+        async fn migrate_access_flags(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                // VARCHAR(10) no basta para todos los flags
+                sqlx::query("ALTER TABLE channel_access ALTER COLUMN level TYPE VARCHAR(32)")
+                    .execute(pool)
+                    .await?;
+
+                for (template, flags) in crate::database::ACCESS_TEMPLATES {
+                    sqlx::query("UPDATE channel_access SET level = $1 WHERE level = $2")
+                        .bind(flags)
+                        .bind(template)
+                        .execute(pool)
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+
+        async fn migrate_channel_settings(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                for (setting, default) in crate::database::CHANNEL_SETTINGS {
+                    let count: i64 = sqlx::query_scalar(
+                        "SELECT COUNT(*) FROM information_schema.columns
+                         WHERE table_name = 'channels' AND column_name = $1",
+                    )
+                    .bind(setting)
+                    .fetch_one(pool)
+                    .await?;
+
+                    if count == 0 {
+                        sqlx::query(&format!(
+                            "ALTER TABLE channels ADD COLUMN {setting} BOOLEAN NOT NULL DEFAULT {}",
+                            if default { "TRUE" } else { "FALSE" }
+                        ))
+                        .execute(pool)
+                        .await?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        async fn create_list_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS channel_lists (
+                        channel_name VARCHAR(255) NOT NULL,
+                        list_mode VARCHAR(1) NOT NULL,
+                        mask VARCHAR(255) NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL,
+                        expires_time BIGINT,
+                        PRIMARY KEY (channel_name, list_mode, mask),
+                        FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_channel_list_entry(
+            &mut self,
+            channel_name: &str,
+            list: char,
+            mask: &str,
+            set_by: &str,
+            set_time: SystemTime,
+            expires: Option<SystemTime>,
+        ) -> DbResult<()> {
+            if !crate::database::is_channel_list(list) {
+                return Err(format!("Unknown channel list: {list}").into());
+            }
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                let expires_timestamp = match expires {
+                    Some(e) => Some(e.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                    None => None,
+                };
+                sqlx::query(
+                    "INSERT INTO channel_lists (channel_name, list_mode, mask, set_by, set_time, expires_time) VALUES ($1, $2, $3, $4, $5, $6)
+                     ON CONFLICT (channel_name, list_mode, mask) DO UPDATE SET set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time, expires_time = EXCLUDED.expires_time",
+                )
+                .bind(casefold(channel_name))
+                .bind(list.to_string())
+                .bind(mask)
+                .bind(set_by)
+                .bind(timestamp)
+                .bind(expires_timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_channel_list_entries(&self, channel_name: &str) -> DbResult<Vec<ChannelListEntry>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, i64, Option<i64>)> = sqlx::query_as(
+                    "SELECT list_mode, mask, set_by, set_time, expires_time FROM channel_lists WHERE channel_name = $1 ORDER BY set_time, mask",
+                )
+                .bind(casefold(channel_name))
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (list_mode, mask, set_by, timestamp, expires_timestamp) in rows {
+                    let Some(list) = list_mode.chars().next() else {
+                        continue;
+                    };
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    let expires = expires_timestamp.map(|t| SystemTime::UNIX_EPOCH + Duration::from_secs(t as u64));
+                    results.push(ChannelListEntry { list, mask, set_by, set_time, expires });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_channel_list_entry(&mut self, channel_name: &str, list: char, mask: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM channel_lists WHERE channel_name = $1 AND list_mode = $2 AND mask = $3")
+                    .bind(casefold(channel_name))
+                    .bind(list.to_string())
+                    .bind(mask)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_channel_key_limit(&self, channel_name: &str) -> DbResult<(Option<String>, Option<usize>)> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(Option<String>, Option<i64>)> =
+                    sqlx::query_as("SELECT mode_key, mode_limit FROM channels WHERE channel_name = $1")
+                        .bind(casefold(channel_name))
+                        .fetch_optional(pool)
+                        .await?;
+                if let Some((key, limit)) = row {
+                    return Ok((key, limit.map(|l| l as usize)));
+                }
+            }
+            Ok((None, None))
+        }
+
+        async fn set_channel_key_limit(&mut self, channel_name: &str, key: Option<&str>, limit: Option<usize>) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("UPDATE channels SET mode_key = $1, mode_limit = $2 WHERE channel_name = $3")
+                    .bind(key)
+                    .bind(limit.map(|l| l as i64))
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn migrate_channel_key_limit(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                for (column, column_type) in [("mode_key", "VARCHAR(255)"), ("mode_limit", "BIGINT")] {
+                    let count: i64 = sqlx::query_scalar(
+                        "SELECT COUNT(*) FROM information_schema.columns
+                         WHERE table_name = 'channels' AND column_name = $1",
+                    )
+                    .bind(column)
+                    .fetch_one(pool)
+                    .await?;
+
+                    if count == 0 {
+                        sqlx::query(&format!("ALTER TABLE channels ADD COLUMN {column} {column_type}"))
+                            .execute(pool)
+                            .await?;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                // las tablas que dependen de channels siguen al nuevo nombre del canal
+                for table in ["channel_access", "channel_akick", "channel_suspensions", "channel_lists"] {
+                    sqlx::query(&format!(
+                        "ALTER TABLE {table} DROP CONSTRAINT IF EXISTS {table}_channel_name_fkey,
+                         ADD CONSTRAINT {table}_channel_name_fkey FOREIGN KEY (channel_name)
+                         REFERENCES channels(channel_name) ON DELETE CASCADE ON UPDATE CASCADE"
+                    ))
+                    .execute(pool)
+                    .await?;
+                }
+                refold_column(pool, "channels", "channel_name").await?;
+                refold_column(pool, "channel_access", "channel_name").await?;
+                refold_column(pool, "channel_access", "nick").await?;
+                refold_column(pool, "channel_akick", "channel_name").await?;
+                refold_column(pool, "channel_suspensions", "channel_name").await?;
+                refold_column(pool, "forbidden_channels", "channel_name").await?;
+                refold_column(pool, "channel_lists", "channel_name").await?;
+            }
+            Ok(())
+        }
+    }
+
+    pub struct PostgresMemoDatabase {
+        pool: Option<PgPool>,
+    }
+
+    impl PostgresMemoDatabase {
+        pub fn new() -> Self {
+            PostgresMemoDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl MemoDatabase for PostgresMemoDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = PgPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS memos (
+                        id BIGSERIAL PRIMARY KEY,
+                        recipient VARCHAR(255) NOT NULL,
+                        sender VARCHAR(255) NOT NULL,
+                        text TEXT NOT NULL,
+                        sent_time BIGINT NOT NULL,
+                        is_read BOOLEAN NOT NULL DEFAULT FALSE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query("CREATE INDEX IF NOT EXISTS memos_recipient ON memos (recipient)")
+                    .execute(pool)
+                    .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS memo_limits (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        memo_limit INTEGER NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS memo_ignores (
+                        nick VARCHAR(255) NOT NULL,
+                        ignored VARCHAR(255) NOT NULL,
+                        PRIMARY KEY (nick, ignored)
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_memo(
+            &mut self,
+            recipient: &str,
+            sender: &str,
+            text: &str,
+            sent_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = sent_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query("INSERT INTO memos (recipient, sender, text, sent_time) VALUES ($1, $2, $3, $4)")
+                    .bind(casefold(recipient))
+                    .bind(sender)
+                    .bind(text)
+                    .bind(timestamp)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_memos(&self, recipient: &str) -> DbResult<Vec<(i64, String, String, SystemTime, bool)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(i64, String, String, i64, bool)> = sqlx::query_as(
+                    "SELECT id, sender, text, sent_time, is_read FROM memos WHERE recipient = $1 ORDER BY id",
+                )
+                .bind(casefold(recipient))
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (id, sender, text, timestamp, is_read) in rows {
+                    let sent_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push((id, sender, text, sent_time, is_read));
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn count_unread_memos(&self, recipient: &str) -> DbResult<usize> {
+            if let Some(pool) = &self.pool {
+                let (count,): (i64,) = sqlx::query_as(
+                    "SELECT COUNT(*) FROM memos WHERE recipient = $1 AND is_read = FALSE",
+                )
+                .bind(casefold(recipient))
+                .fetch_one(pool)
+                .await?;
+                return Ok(count as usize);
+            }
+            Ok(0)
+        }
+
+        async fn mark_memo_read(&mut self, id: i64) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("UPDATE memos SET is_read = TRUE WHERE id = $1")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn delete_memo(&mut self, id: i64) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM memos WHERE id = $1")
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_memo_accounts(&self) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let names: Vec<String> = sqlx::query_scalar("SELECT recipient FROM memos UNION SELECT nick FROM memo_limits UNION SELECT nick FROM memo_ignores ORDER BY 1")
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(names);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn get_memo_limit(&self, nick: &str) -> DbResult<Option<usize>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(i32,)> = sqlx::query_as("SELECT memo_limit FROM memo_limits WHERE nick = $1")
+                    .bind(casefold(nick))
+                    .fetch_optional(pool)
+                    .await?;
+                return Ok(row.map(|(limit,)| limit as usize));
+            }
+            Ok(None)
+        }
+
+        async fn set_memo_limit(&mut self, nick: &str, limit: Option<usize>) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                if let Some(l) = limit {
+                    sqlx::query(
+                        "INSERT INTO memo_limits (nick, memo_limit) VALUES ($1, $2)
+                         ON CONFLICT (nick) DO UPDATE SET memo_limit = EXCLUDED.memo_limit",
+                    )
+                    .bind(casefold(nick))
+                    .bind(l as i32)
+                    .execute(pool)
+                    .await?;
+                } else {
+                    sqlx::query("DELETE FROM memo_limits WHERE nick = $1")
+                        .bind(casefold(nick))
+                        .execute(pool)
+                        .await?;
+                }
+            }
+            Ok(())
+        }
+
+        async fn add_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("INSERT INTO memo_ignores (nick, ignored) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+                    .bind(casefold(nick))
+                    .bind(casefold(ignored))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_memo_ignores(&self, nick: &str) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String,)> = sqlx::query_as("SELECT ignored FROM memo_ignores WHERE nick = $1 ORDER BY ignored")
+                    .bind(casefold(nick))
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(rows.into_iter().map(|(ignored,)| ignored).collect());
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM memo_ignores WHERE nick = $1 AND ignored = $2")
+                    .bind(casefold(nick))
+                    .bind(casefold(ignored))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                refold_column(pool, "memos", "recipient").await?;
+                refold_column(pool, "memo_limits", "nick").await?;
+                refold_column(pool, "memo_ignores", "nick").await?;
+                refold_column(pool, "memo_ignores", "ignored").await?;
+            }
+            Ok(())
+        }
+    }
+
+    pub struct PostgresOperDatabase {
+        pool: Option<PgPool>,
+    }
+
+    impl PostgresOperDatabase {
+        pub fn new() -> Self {
+            PostgresOperDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl OperDatabase for PostgresOperDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = PgPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS akills (
+                        mask VARCHAR(255) PRIMARY KEY,
+                        reason TEXT NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL,
+                        expires_time BIGINT
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS session_exceptions (
+                        ip VARCHAR(64) PRIMARY KEY,
+                        session_limit INTEGER NOT NULL,
+                        reason TEXT NOT NULL,
+                        set_by VARCHAR(255) NOT NULL,
+                        set_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_akill(
+            &mut self,
+            mask: &str,
+            reason: &str,
+            set_by: &str,
+            set_time: SystemTime,
+            expires: Option<SystemTime>,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                let expires_timestamp = match expires {
+                    Some(expires) => Some(expires.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                    None => None,
+                };
+                sqlx::query(
+                    "INSERT INTO akills (mask, reason, set_by, set_time, expires_time) VALUES ($1, $2, $3, $4, $5)
+                     ON CONFLICT (mask) DO UPDATE SET reason = EXCLUDED.reason, set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time, expires_time = EXCLUDED.expires_time",
+                )
+                .bind(mask.to_lowercase())
+                .bind(reason)
+                .bind(set_by)
+                .bind(timestamp)
+                .bind(expires_timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_akills(&self) -> DbResult<Vec<AkillEntry>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, i64, Option<i64>)> = sqlx::query_as(
+                    "SELECT mask, reason, set_by, set_time, expires_time FROM akills ORDER BY set_time",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (mask, reason, set_by, timestamp, expires_timestamp) in rows {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    let expires = expires_timestamp.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64));
+                    results.push(AkillEntry { mask, reason, set_by, set_time, expires });
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_akill(&mut self, mask: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM akills WHERE mask = $1")
+                    .bind(mask.to_lowercase())
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn add_session_exception(
+            &mut self,
+            ip: &str,
+            limit: usize,
+            reason: &str,
+            set_by: &str,
+            set_time: SystemTime,
+        ) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query(
+                    "INSERT INTO session_exceptions (ip, session_limit, reason, set_by, set_time) VALUES ($1, $2, $3, $4, $5)
+                     ON CONFLICT (ip) DO UPDATE SET session_limit = EXCLUDED.session_limit, reason = EXCLUDED.reason, set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time",
+                )
+                .bind(ip)
+                .bind(limit as i32)
+                .bind(reason)
+                .bind(set_by)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_session_exception(&self, ip: &str) -> DbResult<Option<usize>> {
+            if let Some(pool) = &self.pool {
+                let row: Option<(i32,)> = sqlx::query_as("SELECT session_limit FROM session_exceptions WHERE ip = $1")
+                    .bind(ip)
+                    .fetch_optional(pool)
+                    .await?;
+                return Ok(row.map(|(limit,)| limit as usize));
+            }
+            Ok(None)
+        }
+
+        async fn get_session_exceptions(&self) -> DbResult<Vec<(String, usize, String, String, SystemTime)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, i32, String, String, i64)> = sqlx::query_as(
+                    "SELECT ip, session_limit, reason, set_by, set_time FROM session_exceptions ORDER BY set_time",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (ip, limit, reason, set_by, timestamp) in rows {
+                    let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                    results.push((ip, limit as usize, reason, set_by, set_time));
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_session_exception(&mut self, ip: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM session_exceptions WHERE ip = $1")
+                    .bind(ip)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+    }
+
+    pub struct PostgresBotDatabase {
+        pool: Option<PgPool>,
+    }
+
+    impl PostgresBotDatabase {
+        pub fn new() -> Self {
+            PostgresBotDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl BotDatabase for PostgresBotDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = PgPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS bots (
+                        nick VARCHAR(255) PRIMARY KEY,
+                        \"user\" VARCHAR(255) NOT NULL,
+                        host VARCHAR(255) NOT NULL,
+                        realname TEXT NOT NULL,
+                        created_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS bot_channels (
+                        channel_name VARCHAR(255) PRIMARY KEY,
+                        bot VARCHAR(255) NOT NULL,
+                        greet TEXT,
+                        kick_caps BOOLEAN NOT NULL DEFAULT FALSE,
+                        kick_colors BOOLEAN NOT NULL DEFAULT FALSE,
+                        kick_repeat BOOLEAN NOT NULL DEFAULT FALSE,
+                        kick_badwords BOOLEAN NOT NULL DEFAULT FALSE
+                    )",
+                )
+                .execute(pool)
+                .await?;
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS bot_badwords (
+                        channel_name VARCHAR(255) NOT NULL,
+                        word VARCHAR(255) NOT NULL,
+                        PRIMARY KEY (channel_name, word)
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_bot(&mut self, nick: &str, user: &str, host: &str, realname: &str, created_time: SystemTime) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = created_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query("INSERT INTO bots (nick, \"user\", host, realname, created_time) VALUES ($1, $2, $3, $4, $5)")
+                    .bind(nick)
+                    .bind(user)
+                    .bind(host)
+                    .bind(realname)
+                    .bind(timestamp)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_bots(&self) -> DbResult<Vec<(String, String, String, String)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, String, String)> = sqlx::query_as("SELECT nick, \"user\", host, realname FROM bots ORDER BY nick")
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(rows);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_bot(&mut self, nick: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM bots WHERE LOWER(nick) = LOWER($1)")
+                    .bind(nick)
+                    .execute(pool)
+                    .await?;
+                sqlx::query("DELETE FROM bot_channels WHERE LOWER(bot) = LOWER($1)")
+                    .bind(nick)
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn assign_bot(&mut self, channel_name: &str, bot: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                // Al cambiar de bot se conservan el saludo y los kickers del canal
+                sqlx::query(
+                    "INSERT INTO bot_channels (channel_name, bot) VALUES ($1, $2)
+                     ON CONFLICT (channel_name) DO UPDATE SET bot = EXCLUDED.bot",
+                )
+                .bind(casefold(channel_name))
+                .bind(bot)
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn unassign_bot(&mut self, channel_name: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM bot_channels WHERE channel_name = $1")
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn get_bot_channels(&self) -> DbResult<Vec<(String, String, Option<String>, Vec<String>)>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String, String, Option<String>, bool, bool, bool, bool)> = sqlx::query_as(
+                    "SELECT channel_name, bot, greet, kick_caps, kick_colors, kick_repeat, kick_badwords FROM bot_channels ORDER BY channel_name",
+                )
+                .fetch_all(pool)
+                .await?;
+
+                let mut results = Vec::new();
+                for (channel_name, bot, greet, caps, colors, repeat, badwords) in rows {
+                    let kickers = crate::database::BOT_KICKERS
+                        .iter()
+                        .zip([caps, colors, repeat, badwords])
+                        .filter(|(_, enabled)| *enabled)
+                        .map(|(kicker, _)| kicker.to_string())
+                        .collect();
+                    results.push((channel_name, bot, greet, kickers));
+                }
+                return Ok(results);
+            }
+            Ok(Vec::new())
+        }
+
+        async fn set_bot_greet(&mut self, channel_name: &str, greet: Option<&str>) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("UPDATE bot_channels SET greet = $1 WHERE channel_name = $2")
+                    .bind(greet)
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn set_bot_kicker(&mut self, channel_name: &str, kicker: &str, enabled: bool) -> DbResult<()> {
+            if !crate::database::is_bot_kicker(kicker) {
+                return Err(format!("Unknown kicker: {kicker}").into());
+            }
+            if let Some(pool) = &self.pool {
+                let query = format!("UPDATE bot_channels SET kick_{kicker} = $1 WHERE channel_name = $2");
+                sqlx::query(&query)
+                    .bind(enabled)
+                    .bind(casefold(channel_name))
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn add_badword(&mut self, channel_name: &str, word: &str) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query("INSERT INTO bot_badwords (channel_name, word) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+                    .bind(casefold(channel_name))
+                    .bind(word.to_lowercase())
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_badwords(&self, channel_name: &str) -> DbResult<Vec<String>> {
+            if let Some(pool) = &self.pool {
+                let rows: Vec<(String,)> = sqlx::query_as("SELECT word FROM bot_badwords WHERE channel_name = $1 ORDER BY word")
+                    .bind(casefold(channel_name))
+                    .fetch_all(pool)
+                    .await?;
+                return Ok(rows.into_iter().map(|(word,)| word).collect());
+            }
+            Ok(Vec::new())
+        }
+
+        async fn delete_badword(&mut self, channel_name: &str, word: &str) -> DbResult<bool> {
+            if let Some(pool) = &self.pool {
+                let result = sqlx::query("DELETE FROM bot_badwords WHERE channel_name = $1 AND word = $2")
+                    .bind(casefold(channel_name))
+                    .bind(word.to_lowercase())
+                    .execute(pool)
+                    .await?;
+                return Ok(result.rows_affected() > 0);
+            }
+            Ok(false)
+        }
+
+        async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                refold_column(pool, "bot_channels", "channel_name").await?;
+                refold_column(pool, "bot_badwords", "channel_name").await?;
+            }
+            Ok(())
+        }
+    }
+
+    pub struct PostgresSchemaDatabase {
+        pool: Option<PgPool>,
+    }
+
+    impl PostgresSchemaDatabase {
+        pub fn new() -> Self {
+            PostgresSchemaDatabase { pool: None }
+        }
+    }
+
+    #[async_trait]
+    impl SchemaDatabase for PostgresSchemaDatabase {
+        async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+            let pool = PgPoolOptions::new()
+                .max_connections(5)
+                .connect(db_config)
+                .await?;
+            self.pool = Some(pool);
+            Ok(())
+        }
+
+        async fn create_table(&mut self) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS schema_version (
+                        version INTEGER PRIMARY KEY,
+                        description VARCHAR(255) NOT NULL,
+                        applied_time BIGINT NOT NULL
+                    )",
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+
+        async fn get_schema_version(&self) -> DbResult<u32> {
+            if let Some(pool) = &self.pool {
+                let version: Option<i32> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+                    .fetch_one(pool)
+                    .await?;
+                return Ok(version.unwrap_or(0) as u32);
+            }
+            Ok(0)
+        }
+
+        async fn add_schema_version(&mut self, version: u32, description: &str, applied_time: SystemTime) -> DbResult<()> {
+            if let Some(pool) = &self.pool {
+                let timestamp = applied_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+                sqlx::query("INSERT INTO schema_version (version, description, applied_time) VALUES ($1, $2, $3)")
+                    .bind(version as i32)
+                    .bind(description)
+                    .bind(timestamp)
+                    .execute(pool)
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -1,35 +1,89 @@
-use crate::database::{ChannelDatabase, NickDatabase};
+use crate::database::{NickDatabase, ChannelDatabase, MemoDatabase, OperDatabase, BotDatabase, SchemaDatabase};
+use crate::database::{AccessEntry, AkillEntry, ChannelListEntry, ChannelRecord, ChannelSuspension, DbResult, ForbiddenEntry, NickRecord, VhostRequest};
+use crate::utils::casefold;
+use std::time::{Duration, SystemTime};
 use async_trait::async_trait;
-use sqlite::Connection;
-use std::error::Error;
-use std::sync::Mutex;
-use std::time::SystemTime;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous};
+use std::str::FromStr;
+
+// Sentencias preparadas que se guardan por conexión
+const STATEMENT_CACHE_CAPACITY: usize = 100;
+
+// Abre el pool de conexiones de la base de datos. sqlx ejecuta cada conexión SQLite en
+// su propio hilo, así que las consultas lentas no bloquean los hilos de Tokio. Con WAL
+// las lecturas no esperan a las escrituras de otras conexiones.
+async fn connect_pool(db_path: &str) -> DbResult<SqlitePool> {
+    let options = SqliteConnectOptions::from_str(db_path)?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        // como con el driver anterior, sin comprobar las claves externas
+        .foreign_keys(false)
+        .statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await?;
+    Ok(pool)
+}
+
+// Columnas de una tabla
+async fn table_columns(pool: &SqlitePool, table: &str) -> DbResult<Vec<String>> {
+    let columns = sqlx::query_scalar("SELECT name FROM pragma_table_info($1)")
+        .bind(table)
+        .fetch_all(pool)
+        .await?;
+    Ok(columns)
+}
+
+// Pasa los nombres de una columna a su forma canónica. Si el nombre ya existía en forma
+// canónica se conserva esa fila y se borra la otra.
+async fn refold_column(pool: &SqlitePool, table: &str, column: &str) -> DbResult<()> {
+    let names: Vec<String> = sqlx::query_scalar(&format!("SELECT DISTINCT {column} FROM {table}"))
+        .fetch_all(pool)
+        .await?;
+    for name in names {
+        let folded = casefold(&name);
+        if folded == name {
+            continue;
+        }
+        let updated = sqlx::query(&format!("UPDATE {table} SET {column} = $1 WHERE {column} = $2"))
+            .bind(&folded)
+            .bind(&name)
+            .execute(pool)
+            .await;
+        if updated.is_err() {
+            sqlx::query(&format!("DELETE FROM {table} WHERE {column} = $1"))
+                .bind(&name)
+                .execute(pool)
+                .await?;
+        }
+    }
+    Ok(())
+}
 
 pub struct SQLiteNickDatabase {
-    connection: Mutex<Connection>,
+    pool: Option<SqlitePool>,
 }
 
 impl SQLiteNickDatabase {
-    pub fn new(db_path: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
-        let conn = Connection::open(db_path)?;
-        Ok(SQLiteNickDatabase {
-            connection: Mutex::new(conn),
-        })
+    pub fn new() -> Self {
+        SQLiteNickDatabase { pool: None }
     }
 }
 
 #[async_trait]
 impl NickDatabase for SQLiteNickDatabase {
-    async fn connect(&mut self, db_config: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let conn = Connection::open(db_config)?;
-        self.connection = Mutex::new(conn);
+    async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+        let pool = connect_pool(db_config).await?;
+        self.pool = Some(pool);
         Ok(())
     }
 
-    async fn create_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        match db_guard.execute(
-            "CREATE TABLE IF NOT EXISTS nicks (
+    async fn create_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS nicks (
                     nick TEXT PRIMARY KEY,
                     password TEXT NOT NULL,
                     user TEXT NOT NULL,
@@ -42,10 +96,11 @@ impl NickDatabase for SQLiteNickDatabase {
                     noop INTEGER NOT NULL DEFAULT 0,
                     showmail INTEGER NOT NULL DEFAULT 0
                 )",
-        ) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(Box::new(e) as Box<dyn Error + Send + Sync>),
+            )
+            .execute(pool)
+            .await?;
         }
+        Ok(())
     }
 
     async fn add_nick(
@@ -54,87 +109,66 @@ impl NickDatabase for SQLiteNickDatabase {
         password: &str,
         user: &str,
         registration_time: SystemTime,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let timestamp = registration_time
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map_err(|_| "Failed to get UNIX timestamp")?
-            .as_secs();
-        let query =
-            "INSERT INTO nicks (nick, password, user, registration_time) VALUES (?, ?, ?, ?)";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((2, password)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((3, user)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((4, timestamp as i64)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = registration_time
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs();
+            sqlx::query("INSERT INTO nicks (nick, password, user, registration_time) VALUES ($1, $2, $3, $4)")
+                .bind(casefold(nick))
+                .bind(password)
+                .bind(user)
+                .bind(timestamp as i64)
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
     }
 
     async fn get_nick_info(
         &self,
         nick: &str,
-    ) -> Result<Option<(String, SystemTime, Option<String>, Option<String>, Option<String>, Option<SystemTime>, bool, bool, bool)>, Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query = "SELECT user, registration_time, email, url, vhost, last_vhost, noaccess, noop, showmail FROM nicks WHERE nick = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        match statement.next() {
-            Ok(sqlite::State::Row) => {
-                // Process the row
-                let user: String = statement.read(0).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let timestamp_secs: i64 =
-                    statement.read(1).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+    ) -> DbResult<Option<NickRecord>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(String, i64, Option<String>, Option<String>, Option<String>, Option<i64>, bool, bool, bool)> =
+                sqlx::query_as("SELECT user, registration_time, email, url, vhost, last_vhost, noaccess, noop, showmail FROM nicks WHERE nick = $1")
+                    .bind(casefold(nick))
+                    .fetch_optional(pool)
+                    .await?;
+
+            if let Some((user, timestamp, email, url, vhost, last_vhost_timestamp, noaccess, noop, showmail)) = row {
                 let registration_time =
-                    SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(timestamp_secs as u64);
-                let email: Option<String> = statement.read(2).ok();
-                let url: Option<String> = statement.read(3).ok();
-                let vhost: Option<String> = statement.read(4).ok();
-                let last_vhost_timestamp: Option<i64> = statement.read(5).ok();
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
                 let last_vhost = last_vhost_timestamp.map(|ts| 
-                    SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(ts as u64)
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(ts as u64)
                 );
-                let noaccess: i64 = statement.read(6).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let noop: i64 = statement.read(7).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let showmail: i64 = statement.read(8).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                Ok(Some((user, registration_time, email, url, vhost, last_vhost, noaccess != 0, noop != 0, showmail != 0)))
+                return Ok(Some(NickRecord { user, registration_time, email, url, vhost, last_vhost, noaccess, noop, showmail }));
             }
-            Ok(sqlite::State::Done) => Ok(None), // No rows found
-            Err(e) => Err(Box::new(e) as Box<dyn Error + Send + Sync>), // Error from statement.next()
         }
+        Ok(None)
     }
 
-    async fn get_nick_password(&self, nick: &str) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query = "SELECT password FROM nicks WHERE nick = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        match statement.next() {
-            Ok(sqlite::State::Row) => {
-                // Process the row
-                let password: String =
-                    statement.read(0).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                Ok(Some(password))
-            }
-            Ok(sqlite::State::Done) => Ok(None), // No rows found
-            Err(e) => Err(Box::new(e) as Box<dyn Error + Send + Sync>), // Error from statement.next()
+    async fn get_nick_password(&self, nick: &str) -> DbResult<Option<String>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(String,)> =
+                sqlx::query_as("SELECT password FROM nicks WHERE nick = $1")
+                    .bind(casefold(nick))
+                    .fetch_optional(pool)
+                    .await?;
+            return Ok(row.map(|(password,)| password));
         }
+        Ok(None)
     }
 
-    async fn update_nick_password(
-        &mut self,
-        nick: &str,
-        password: &str,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-
-        let query = "UPDATE nicks SET password = ? WHERE nick = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        statement.bind((1, password)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((2, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
+    async fn update_nick_password(&mut self, nick: &str, password: &str) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("UPDATE nicks SET password = $1 WHERE nick = $2")
+                .bind(password)
+                .bind(casefold(nick))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
     }
 
     async fn update_nick_info(
@@ -148,101 +182,310 @@ impl NickDatabase for SQLiteNickDatabase {
         noaccess: Option<bool>,
         noop: Option<bool>,
         showmail: Option<bool>,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let mut updates = Vec::new();
-        if let Some(u) = user {
-            updates.push(("user = ?", u.to_string()));
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let mut set_clauses = Vec::new();
+            if user.is_some() {
+                set_clauses.push(format!("user = ${}", set_clauses.len() + 1));
+            }
+            if email.is_some() {
+                set_clauses.push(format!("email = ${}", set_clauses.len() + 1));
+            }
+            if url.is_some() {
+                set_clauses.push(format!("url = ${}", set_clauses.len() + 1));
+            }
+            if vhost.is_some() {
+                set_clauses.push(format!("vhost = ${}", set_clauses.len() + 1));
+            }
+            if last_vhost.is_some() {
+                set_clauses.push(format!("last_vhost = ${}", set_clauses.len() + 1));
+            }
+            if noaccess.is_some() {
+                set_clauses.push(format!("noaccess = ${}", set_clauses.len() + 1));
+            }
+            if noop.is_some() {
+                set_clauses.push(format!("noop = ${}", set_clauses.len() + 1));
+            }
+            if showmail.is_some() {
+                set_clauses.push(format!("showmail = ${}", set_clauses.len() + 1));
+            }
+
+            if !set_clauses.is_empty() {
+                let query_str = format!(
+                    "UPDATE nicks SET {} WHERE nick = ${}",
+                    set_clauses.join(", "),
+                    set_clauses.len() + 1
+                );
+                let mut query = sqlx::query(&query_str);
+                if let Some(u) = user {
+                    query = query.bind(u);
+                }
+                if let Some(e) = email {
+                    query = query.bind(e);
+                }
+                if let Some(u) = url {
+                    query = query.bind(u);
+                }
+                if let Some(v) = vhost {
+                    query = query.bind(v);
+                }
+                if let Some(lv) = last_vhost {
+                    let timestamp = lv.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+                    query = query.bind(timestamp as i64);
+                }
+                if let Some(na) = noaccess {
+                    query = query.bind(na);
+                }
+                if let Some(n) = noop {
+                    query = query.bind(n);
+                }
+                if let Some(sm) = showmail {
+                    query = query.bind(sm);
+                }
+                query.bind(casefold(nick)).execute(pool).await?;
+            }
         }
-        if let Some(e) = email {
-            updates.push(("email = ?", e.to_string()));
+        Ok(())
+    }
+
+    async fn delete_nick(&mut self, nick: &str) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("DELETE FROM nicks WHERE nick = $1")
+                .bind(casefold(nick))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_nicks(&self) -> DbResult<Vec<String>> {
+        if let Some(pool) = &self.pool {
+            let names: Vec<String> = sqlx::query_scalar("SELECT nick FROM nicks ORDER BY nick")
+                .fetch_all(pool)
+                .await?;
+            return Ok(names);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn create_forbid_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS forbidden_nicks (
+                    nick TEXT PRIMARY KEY,
+                    reason TEXT NOT NULL,
+                    set_by TEXT NOT NULL,
+                    set_time INTEGER NOT NULL
+                )",
+            )
+            .execute(pool)
+            .await?;
         }
-        if let Some(u) = url {
-            updates.push(("url = ?", u.to_string()));
+        Ok(())
+    }
+
+    async fn add_forbidden_nick(
+        &mut self,
+        nick: &str,
+        reason: &str,
+        set_by: &str,
+        set_time: SystemTime,
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            sqlx::query(
+                "INSERT INTO forbidden_nicks (nick, reason, set_by, set_time) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (nick) DO UPDATE SET reason = EXCLUDED.reason, set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time",
+            )
+            .bind(casefold(nick))
+            .bind(reason)
+            .bind(set_by)
+            .bind(timestamp)
+            .execute(pool)
+            .await?;
         }
-        if let Some(v) = vhost {
-            updates.push(("vhost = ?", v.to_string()));
+        Ok(())
+    }
+
+    async fn get_forbidden_nick(&self, nick: &str) -> DbResult<Option<ForbiddenEntry>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                "SELECT nick, reason, set_by, set_time FROM forbidden_nicks WHERE nick = $1",
+            )
+            .bind(casefold(nick))
+            .fetch_optional(pool)
+            .await?;
+
+            if let Some((name, reason, set_by, timestamp)) = row {
+                let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                return Ok(Some(ForbiddenEntry { name, reason, set_by, set_time }));
+            }
         }
-        if let Some(lv) = last_vhost {
-            let timestamp = lv
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .map_err(|_| "Failed to get UNIX timestamp for last_vhost")?
-                .as_secs();
-            updates.push(("last_vhost = ?", timestamp.to_string()));
+        Ok(None)
+    }
+
+    async fn delete_forbidden_nick(&mut self, nick: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM forbidden_nicks WHERE nick = $1")
+                .bind(casefold(nick))
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
         }
-        if let Some(na) = noaccess {
-            updates.push(("noaccess = ?", if na { "1" } else { "0" }.to_string()));
+        Ok(false)
+    }
+
+    async fn get_forbidden_nicks(&self) -> DbResult<Vec<ForbiddenEntry>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, String, String, i64)> = sqlx::query_as(
+                "SELECT nick, reason, set_by, set_time FROM forbidden_nicks ORDER BY nick",
+            )
+            .fetch_all(pool)
+            .await?;
+
+            let mut results = Vec::new();
+            for (name, reason, set_by, timestamp) in rows {
+                let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                results.push(ForbiddenEntry { name, reason, set_by, set_time });
+            }
+            return Ok(results);
         }
-        if let Some(n) = noop {
-            updates.push(("noop = ?", if n { "1" } else { "0" }.to_string()));
+        Ok(Vec::new())
+    }
+
+    async fn create_vhost_request_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS vhost_requests (
+                    nick TEXT PRIMARY KEY,
+                    vhost TEXT NOT NULL,
+                    request_time INTEGER NOT NULL
+                )",
+            )
+            .execute(pool)
+            .await?;
         }
-        if let Some(sm) = showmail {
-            updates.push(("showmail = ?", if sm { "1" } else { "0" }.to_string()));
+        Ok(())
+    }
+
+    async fn add_vhost_request(
+        &mut self,
+        nick: &str,
+        vhost: &str,
+        request_time: SystemTime,
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = request_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            sqlx::query(
+                "INSERT INTO vhost_requests (nick, vhost, request_time) VALUES ($1, $2, $3)
+                 ON CONFLICT (nick) DO UPDATE SET vhost = EXCLUDED.vhost, request_time = EXCLUDED.request_time",
+            )
+            .bind(casefold(nick))
+            .bind(vhost)
+            .bind(timestamp)
+            .execute(pool)
+            .await?;
         }
+        Ok(())
+    }
 
-        if updates.is_empty() {
-            return Ok(());
+    async fn get_vhost_request(&self, nick: &str) -> DbResult<Option<VhostRequest>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(String, String, i64)> = sqlx::query_as(
+                "SELECT nick, vhost, request_time FROM vhost_requests WHERE nick = $1",
+            )
+            .bind(casefold(nick))
+            .fetch_optional(pool)
+            .await?;
+
+            if let Some((nick, vhost, timestamp)) = row {
+                let request_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                return Ok(Some(VhostRequest { nick, vhost, request_time }));
+            }
         }
+        Ok(None)
+    }
 
-        let set_clause = updates.iter().map(|(field, _)| *field).collect::<Vec<&str>>().join(", ");
-        let query = format!("UPDATE nicks SET {set_clause} WHERE nick = ?");
-        let mut statement = db_guard.prepare(&query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+    async fn get_vhost_requests(&self) -> DbResult<Vec<VhostRequest>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, String, i64)> = sqlx::query_as(
+                "SELECT nick, vhost, request_time FROM vhost_requests ORDER BY request_time",
+            )
+            .fetch_all(pool)
+            .await?;
 
-        let mut bind_index = 1;
-        for (_, value) in updates {
-            statement.bind((bind_index, value.as_str())).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            bind_index += 1;
+            let mut results = Vec::new();
+            for (nick, vhost, timestamp) in rows {
+                let request_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                results.push(VhostRequest { nick, vhost, request_time });
+            }
+            return Ok(results);
         }
-        statement.bind((bind_index, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
+        Ok(Vec::new())
     }
 
-    async fn delete_nick(&mut self, nick: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query = "DELETE FROM nicks WHERE nick = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
+    async fn delete_vhost_request(&mut self, nick: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM vhost_requests WHERE nick = $1")
+                .bind(casefold(nick))
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            refold_column(pool, "nicks", "nick").await?;
+            refold_column(pool, "forbidden_nicks", "nick").await?;
+            refold_column(pool, "vhost_requests", "nick").await?;
+        }
+        Ok(())
     }
 }
 
 pub struct SQLiteChannelDatabase {
-    connection: Mutex<Connection>,
+    pool: Option<SqlitePool>,
 }
 
 impl SQLiteChannelDatabase {
-    pub fn new(db_path: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
-        let conn = Connection::open(db_path)?;
-        Ok(SQLiteChannelDatabase {
-            connection: Mutex::new(conn),
-        })
+    pub fn new() -> Self {
+        SQLiteChannelDatabase { pool: None }
     }
 }
 
 #[async_trait]
 impl ChannelDatabase for SQLiteChannelDatabase {
-    async fn connect(&mut self, db_config: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let conn = Connection::open(db_config)?;
-        self.connection = Mutex::new(conn);
+    async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+        let pool = connect_pool(db_config).await?;
+        self.pool = Some(pool);
         Ok(())
     }
 
-    async fn create_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        match db_guard.execute(
-            "CREATE TABLE IF NOT EXISTS channels (
+    async fn create_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS channels (
                     channel_name TEXT PRIMARY KEY,
                     creator_nick TEXT NOT NULL,
                     creation_time INTEGER NOT NULL,
                     topic TEXT,
                     topic_setter TEXT,
                     topic_time INTEGER,
-                    modes TEXT
+                    modes TEXT,
+                    keeptopic INTEGER NOT NULL DEFAULT 1,
+                    topiclock INTEGER NOT NULL DEFAULT 0,
+                    secureops INTEGER NOT NULL DEFAULT 0,
+                    restricted INTEGER NOT NULL DEFAULT 0,
+                    private INTEGER NOT NULL DEFAULT 0,
+                    guard INTEGER NOT NULL DEFAULT 0
                 )",
-        ) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(Box::new(e) as Box<dyn Error + Send + Sync>),
+            )
+            .execute(pool)
+            .await?;
         }
+        Ok(())
     }
 
     async fn add_channel(
@@ -250,57 +493,45 @@ impl ChannelDatabase for SQLiteChannelDatabase {
         channel_name: &str,
         creator_nick: &str,
         creation_time: SystemTime,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let timestamp = creation_time
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map_err(|_| "Failed to get UNIX timestamp")?
-            .as_secs();
-        let query =
-            "INSERT INTO channels (channel_name, creator_nick, creation_time, modes) VALUES (?, ?, ?, '+r')";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((2, creator_nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((3, timestamp as i64)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = creation_time
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs();
+            sqlx::query(
+                "INSERT INTO channels (channel_name, creator_nick, creation_time, modes) VALUES ($1, $2, $3, '+r')",
+            )
+            .bind(casefold(channel_name))
+            .bind(creator_nick)
+            .bind(timestamp as i64)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
     }
 
     async fn get_channel_info(
         &self,
         channel_name: &str,
-    ) -> Result<Option<(String, SystemTime, Option<String>, Option<String>, Option<String>, Option<SystemTime>)>, Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query =
-            "SELECT creator_nick, creation_time, topic, modes, topic_setter, topic_time FROM channels WHERE channel_name = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        match statement.next() {
-            Ok(sqlite::State::Row) => {
-                let creator_nick: String =
-                    statement.read(0).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let timestamp_secs: i64 =
-                    statement.read(1).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+    ) -> DbResult<Option<ChannelRecord>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(String, i64, Option<String>, Option<String>, Option<String>, Option<i64>)> = sqlx::query_as(
+                "SELECT creator_nick, creation_time, topic, modes, topic_setter, topic_time FROM channels WHERE channel_name = $1",
+            )
+            .bind(casefold(channel_name))
+            .fetch_optional(pool)
+            .await?;
+
+            if let Some((creator, timestamp, topic, modes, topic_setter, topic_time_secs)) = row {
                 let creation_time =
-                    SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(timestamp_secs as u64);
-                let topic: Option<String> =
-                    statement.read(2).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let modes: Option<String> =
-                    statement.read(3).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let topic_setter: Option<String> =
-                    statement.read(4).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let topic_time_secs: Option<i64> =
-                    statement.read(5).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
                 let topic_time = topic_time_secs.map(|secs| 
-                    SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs as u64)
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64)
                 );
-                
-                Ok(Some((creator_nick, creation_time, topic, modes, topic_setter, topic_time)))
+                return Ok(Some(ChannelRecord { founder: creator, creation_time, topic, modes, topic_setter, topic_time }));
             }
-            Ok(sqlite::State::Done) => Ok(None),
-            Err(e) => Err(Box::new(e) as Box<dyn Error + Send + Sync>),
         }
+        Ok(None)
     }
 
     async fn update_channel_info(
@@ -310,81 +541,91 @@ impl ChannelDatabase for SQLiteChannelDatabase {
         topic_setter: Option<&str>,
         topic_time: Option<SystemTime>,
         modes: Option<&str>,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let mut updates = Vec::new();
-        if topic.is_some() {
-            updates.push("topic = ?");
-        }
-        if topic_setter.is_some() {
-            updates.push("topic_setter = ?");
-        }
-        if topic_time.is_some() {
-            updates.push("topic_time = ?");
-        }
-        if modes.is_some() {
-            updates.push("modes = ?");
-        }
-
-        if updates.is_empty() {
-            return Ok(());
-        }
-
-        let set_clause = updates.join(", ");
-        let query = format!("UPDATE channels SET {set_clause} WHERE channel_name = ?");
-        let mut statement = db_guard.prepare(&query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let mut set_clauses = Vec::new();
+            if topic.is_some() {
+                set_clauses.push(format!("topic = ${}", set_clauses.len() + 1));
+            }
+            if topic_setter.is_some() {
+                set_clauses.push(format!("topic_setter = ${}", set_clauses.len() + 1));
+            }
+            if topic_time.is_some() {
+                set_clauses.push(format!("topic_time = ${}", set_clauses.len() + 1));
+            }
+            if modes.is_some() {
+                set_clauses.push(format!("modes = ${}", set_clauses.len() + 1));
+            }
 
-        let mut bind_index = 1;
-        if let Some(t) = topic {
-            statement.bind((bind_index, t)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            bind_index += 1;
-        }
-        if let Some(ts) = topic_setter {
-            statement.bind((bind_index, ts)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            bind_index += 1;
-        }
-        if let Some(tt) = topic_time {
-            let timestamp = tt
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .map_err(|_| "Failed to get UNIX timestamp for topic_time")?
-                .as_secs();
-            statement.bind((bind_index, timestamp as i64)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            bind_index += 1;
-        }
-        if let Some(m) = modes {
-            statement.bind((bind_index, m)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            bind_index += 1;
+            if !set_clauses.is_empty() {
+                let query_str = format!(
+                    "UPDATE channels SET {} WHERE channel_name = ${}",
+                    set_clauses.join(", "),
+                    set_clauses.len() + 1
+                );
+                let mut query = sqlx::query(&query_str);
+                if let Some(t) = topic {
+                    query = query.bind(t);
+                }
+                if let Some(ts) = topic_setter {
+                    query = query.bind(ts);
+                }
+                if let Some(tt) = topic_time {
+                    let timestamp = tt.duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+                    query = query.bind(timestamp as i64);
+                }
+                if let Some(m) = modes {
+                    query = query.bind(m);
+                }
+                query.bind(casefold(channel_name)).execute(pool).await?;
+            }
         }
-        statement.bind((bind_index, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
+        Ok(())
     }
 
     async fn update_channel_owner(
         &mut self,
         channel_name: &str,
         new_owner: &str,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query = "UPDATE channels SET creator_nick = ? WHERE channel_name = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, new_owner)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((2, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
-    }
-
-    async fn delete_channel(&mut self, channel_name: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query = "DELETE FROM channels WHERE channel_name = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
-    }
-
-    async fn create_access_table(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        match db_guard.execute(
-            "CREATE TABLE IF NOT EXISTS channel_access (
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("UPDATE channels SET creator_nick = $1 WHERE channel_name = $2")
+                .bind(new_owner)
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_channel(&mut self, channel_name: &str) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("DELETE FROM channel_lists WHERE channel_name = $1")
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+            sqlx::query("DELETE FROM channels WHERE channel_name = $1")
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_channels(&self) -> DbResult<Vec<String>> {
+        if let Some(pool) = &self.pool {
+            let names: Vec<String> = sqlx::query_scalar("SELECT channel_name FROM channels ORDER BY channel_name")
+                .fetch_all(pool)
+                .await?;
+            return Ok(names);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn create_access_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS channel_access (
                     channel_name TEXT NOT NULL,
                     nick TEXT NOT NULL,
                     level TEXT NOT NULL,
@@ -393,10 +634,11 @@ impl ChannelDatabase for SQLiteChannelDatabase {
                     PRIMARY KEY (channel_name, nick),
                     FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
                 )",
-        ) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(Box::new(e) as Box<dyn Error + Send + Sync>),
+            )
+            .execute(pool)
+            .await?;
         }
+        Ok(())
     }
 
     async fn add_channel_access(
@@ -406,74 +648,74 @@ impl ChannelDatabase for SQLiteChannelDatabase {
         level: &str,
         added_by: &str,
         added_time: SystemTime,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let timestamp = added_time
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map_err(|_| "Failed to get UNIX timestamp")?
-            .as_secs();
-        let query =
-            "INSERT OR REPLACE INTO channel_access (channel_name, nick, level, added_by, added_time) VALUES (?, ?, ?, ?, ?)";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((2, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((3, level)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((4, added_by)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((5, timestamp as i64)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = added_time
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs();
+            sqlx::query(
+                "INSERT INTO channel_access (channel_name, nick, level, added_by, added_time) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (channel_name, nick) DO UPDATE SET level = EXCLUDED.level, added_by = EXCLUDED.added_by, added_time = EXCLUDED.added_time",
+            )
+            .bind(casefold(channel_name))
+            .bind(casefold(nick))
+            .bind(level)
+            .bind(added_by)
+            .bind(timestamp as i64)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
     }
 
     async fn get_channel_access(
         &self,
         channel_name: &str,
         nick: &str,
-    ) -> Result<Option<(String, String, SystemTime)>, Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query = "SELECT level, added_by, added_time FROM channel_access WHERE channel_name = ? AND nick = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((2, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+    ) -> DbResult<Option<AccessEntry>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = $1 AND nick = $2",
+            )
+            .bind(casefold(channel_name))
+            .bind(casefold(nick))
+            .fetch_optional(pool)
+            .await?;
 
-        match statement.next() {
-            Ok(sqlite::State::Row) => {
-                let level: String = statement.read(0).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let added_by: String = statement.read(1).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let timestamp_secs: i64 = statement.read(2).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                let added_time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(timestamp_secs as u64);
-                Ok(Some((level, added_by, added_time)))
+            if let Some((nick, level, added_by, timestamp)) = row {
+                let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                return Ok(Some(AccessEntry { nick, flags: level, added_by, added_time }));
             }
-            Ok(sqlite::State::Done) => Ok(None),
-            Err(e) => Err(Box::new(e) as Box<dyn Error + Send + Sync>),
         }
+        Ok(None)
     }
 
     async fn get_channel_access_list(
         &self,
         channel_name: &str,
         level: Option<&str>,
-    ) -> Result<Vec<(String, String, String, SystemTime)>, Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query = if let Some(_l) = level {
-            "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = ? AND level = ? ORDER BY added_time"
-        } else {
-            "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = ? ORDER BY level, added_time"
-        };
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        if let Some(l) = level {
-            statement.bind((2, l)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        }
-
-        let mut results = Vec::new();
-        while let Ok(sqlite::State::Row) = statement.next() {
-            let nick: String = statement.read(0).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            let level: String = statement.read(1).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            let added_by: String = statement.read(2).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            let timestamp_secs: i64 = statement.read(3).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            let added_time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(timestamp_secs as u64);
-            results.push((nick, level, added_by, added_time));
+    ) -> DbResult<Vec<AccessEntry>> {
+        if let Some(pool) = &self.pool {
+            let query = if let Some(_l) = level {
+                "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = $1 AND level = $2 ORDER BY added_time"
+            } else {
+                "SELECT nick, level, added_by, added_time FROM channel_access WHERE channel_name = $1 ORDER BY level, added_time"
+            };
+            
+            let mut query_builder = sqlx::query_as::<_, (String, String, String, i64)>(query);
+            query_builder = query_builder.bind(casefold(channel_name));
+            if let Some(l) = level {
+                query_builder = query_builder.bind(l);
+            }
+            
+            let rows = query_builder.fetch_all(pool).await?;
+            let mut results = Vec::new();
+            for (nick, level, added_by, timestamp) in rows {
+                let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                results.push(AccessEntry { nick, flags: level, added_by, added_time });
+            }
+            return Ok(results);
         }
-        Ok(results)
+        Ok(Vec::new())
     }
 
     async fn update_channel_access(
@@ -483,63 +725,1164 @@ impl ChannelDatabase for SQLiteChannelDatabase {
         level: &str,
         updated_by: &str,
         updated_time: SystemTime,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let timestamp = updated_time
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map_err(|_| "Failed to get UNIX timestamp")?
-            .as_secs();
-        let query = "UPDATE channel_access SET level = ?, added_by = ?, added_time = ? WHERE channel_name = ? AND nick = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, level)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((2, updated_by)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((3, timestamp as i64)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((4, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((5, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
-    }
-
-    async fn delete_channel_access(&mut self, channel_name: &str, nick: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        let query = "DELETE FROM channel_access WHERE channel_name = ? AND nick = ?";
-        let mut statement = db_guard.prepare(query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((1, channel_name)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.bind((2, nick)).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        statement.next().map(|_| ()).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)
-    }
-
-    async fn migrate_topic_fields(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let db_guard = self.connection.lock().unwrap();
-        
-        // Check if topic_setter column exists
-        let check_query = "PRAGMA table_info(channels)";
-        let mut statement = db_guard.prepare(check_query).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        
-        let mut has_topic_setter = false;
-        let mut has_topic_time = false;
-        
-        while let Ok(sqlite::State::Row) = statement.next() {
-            let column_name: String = statement.read(1).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-            match column_name.as_str() {
-                "topic_setter" => has_topic_setter = true,
-                "topic_time" => has_topic_time = true,
-                _ => {}
-            }
-        }
-        
-        drop(statement);
-        
-        // Add columns if they don't exist
-        if !has_topic_setter {
-            db_guard.execute("ALTER TABLE channels ADD COLUMN topic_setter TEXT")
-                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        }
-        
-        if !has_topic_time {
-            db_guard.execute("ALTER TABLE channels ADD COLUMN topic_time INTEGER")
-                .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        }
-        
-        Ok(())
-    }
-}
\ No newline at end of file
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = updated_time
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs();
+            sqlx::query(
+                "UPDATE channel_access SET level = $1, added_by = $2, added_time = $3 WHERE channel_name = $4 AND nick = $5",
+            )
+            .bind(level)
+            .bind(updated_by)
+            .bind(timestamp as i64)
+            .bind(casefold(channel_name))
+            .bind(casefold(nick))
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_channel_access(&mut self, channel_name: &str, nick: &str) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("DELETE FROM channel_access WHERE channel_name = $1 AND nick = $2")
+                .bind(casefold(channel_name))
+                .bind(casefold(nick))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn create_akick_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS channel_akick (
+                    channel_name TEXT NOT NULL,
+                    mask TEXT NOT NULL,
+                    reason TEXT,
+                    added_by TEXT NOT NULL,
+                    added_time INTEGER NOT NULL,
+                    PRIMARY KEY (channel_name, mask),
+                    FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                )",
+            )
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn add_channel_akick(
+        &mut self,
+        channel_name: &str,
+        mask: &str,
+        reason: Option<&str>,
+        added_by: &str,
+        added_time: SystemTime,
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = added_time
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs();
+            sqlx::query(
+                "INSERT INTO channel_akick (channel_name, mask, reason, added_by, added_time) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (channel_name, mask) DO UPDATE SET reason = EXCLUDED.reason, added_by = EXCLUDED.added_by, added_time = EXCLUDED.added_time",
+            )
+            .bind(casefold(channel_name))
+            .bind(mask)
+            .bind(reason)
+            .bind(added_by)
+            .bind(timestamp as i64)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_channel_akick_list(
+        &self,
+        channel_name: &str,
+    ) -> DbResult<Vec<(String, Option<String>, String, SystemTime)>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, Option<String>, String, i64)> = sqlx::query_as(
+                "SELECT mask, reason, added_by, added_time FROM channel_akick WHERE channel_name = $1 ORDER BY added_time",
+            )
+            .bind(casefold(channel_name))
+            .fetch_all(pool)
+            .await?;
+
+            let mut results = Vec::new();
+            for (mask, reason, added_by, timestamp) in rows {
+                let added_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                results.push((mask, reason, added_by, added_time));
+            }
+            return Ok(results);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn delete_channel_akick(&mut self, channel_name: &str, mask: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM channel_akick WHERE channel_name = $1 AND mask = $2")
+                .bind(casefold(channel_name))
+                .bind(mask)
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn migrate_topic_fields(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let columns = table_columns(pool, "channels").await?;
+            if !columns.iter().any(|c| c == "topic_setter") {
+                sqlx::query("ALTER TABLE channels ADD COLUMN topic_setter TEXT")
+                    .execute(pool)
+                    .await?;
+            }
+            if !columns.iter().any(|c| c == "topic_time") {
+                sqlx::query("ALTER TABLE channels ADD COLUMN topic_time INTEGER")
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_suspend_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS channel_suspensions (
+                    channel_name TEXT PRIMARY KEY,
+                    reason TEXT NOT NULL,
+                    suspended_by TEXT NOT NULL,
+                    suspended_time INTEGER NOT NULL,
+                    expires_time INTEGER,
+                    FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                )",
+            )
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn suspend_channel(
+        &mut self,
+        channel_name: &str,
+        reason: &str,
+        suspended_by: &str,
+        suspended_time: SystemTime,
+        expires: Option<SystemTime>,
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = suspended_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            let expires_timestamp = match expires {
+                Some(expires) => Some(expires.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                None => None,
+            };
+            sqlx::query(
+                "INSERT INTO channel_suspensions (channel_name, reason, suspended_by, suspended_time, expires_time) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (channel_name) DO UPDATE SET reason = EXCLUDED.reason, suspended_by = EXCLUDED.suspended_by, suspended_time = EXCLUDED.suspended_time, expires_time = EXCLUDED.expires_time",
+            )
+            .bind(casefold(channel_name))
+            .bind(reason)
+            .bind(suspended_by)
+            .bind(timestamp)
+            .bind(expires_timestamp)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_channel_suspension(&self, channel_name: &str) -> DbResult<Option<ChannelSuspension>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(String, String, i64, Option<i64>)> = sqlx::query_as(
+                "SELECT reason, suspended_by, suspended_time, expires_time FROM channel_suspensions WHERE channel_name = $1",
+            )
+            .bind(casefold(channel_name))
+            .fetch_optional(pool)
+            .await?;
+
+            if let Some((reason, suspended_by, timestamp, expires_timestamp)) = row {
+                let suspended_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                let expires = expires_timestamp.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64));
+                return Ok(Some(ChannelSuspension { reason, suspended_by, suspended_time, expires }));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn unsuspend_channel(&mut self, channel_name: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM channel_suspensions WHERE channel_name = $1")
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+    async fn create_forbid_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS forbidden_channels (
+                    channel_name TEXT PRIMARY KEY,
+                    reason TEXT NOT NULL,
+                    set_by TEXT NOT NULL,
+                    set_time INTEGER NOT NULL
+                )",
+            )
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn add_forbidden_channel(
+        &mut self,
+        channel_name: &str,
+        reason: &str,
+        set_by: &str,
+        set_time: SystemTime,
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            sqlx::query(
+                "INSERT INTO forbidden_channels (channel_name, reason, set_by, set_time) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (channel_name) DO UPDATE SET reason = EXCLUDED.reason, set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time",
+            )
+            .bind(casefold(channel_name))
+            .bind(reason)
+            .bind(set_by)
+            .bind(timestamp)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_forbidden_channel(&self, channel_name: &str) -> DbResult<Option<ForbiddenEntry>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                "SELECT channel_name, reason, set_by, set_time FROM forbidden_channels WHERE channel_name = $1",
+            )
+            .bind(casefold(channel_name))
+            .fetch_optional(pool)
+            .await?;
+
+            if let Some((name, reason, set_by, timestamp)) = row {
+                let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                return Ok(Some(ForbiddenEntry { name, reason, set_by, set_time }));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn delete_forbidden_channel(&mut self, channel_name: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM forbidden_channels WHERE channel_name = $1")
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn get_forbidden_channels(&self) -> DbResult<Vec<ForbiddenEntry>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, String, String, i64)> = sqlx::query_as(
+                "SELECT channel_name, reason, set_by, set_time FROM forbidden_channels ORDER BY channel_name",
+            )
+            .fetch_all(pool)
+            .await?;
+
+            let mut results = Vec::new();
+            for (name, reason, set_by, timestamp) in rows {
+                let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                results.push(ForbiddenEntry { name, reason, set_by, set_time });
+            }
+            return Ok(results);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn get_channel_setting(&self, channel_name: &str, setting: &str) -> DbResult<bool> {
+        if !crate::database::is_channel_setting(setting) {
+            return Err(format!("Unknown channel setting: {setting}").into());
+        }
+        if let Some(pool) = &self.pool {
+            // el nombre de la columna ya se ha validado con CHANNEL_SETTINGS
+            let value: Option<bool> = sqlx::query_scalar(&format!("SELECT {setting} FROM channels WHERE channel_name = $1"))
+                .bind(casefold(channel_name))
+                .fetch_optional(pool)
+                .await?;
+            Ok(value.unwrap_or(false))
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn set_channel_setting(&mut self, channel_name: &str, setting: &str, enabled: bool) -> DbResult<()> {
+        if !crate::database::is_channel_setting(setting) {
+            return Err(format!("Unknown channel setting: {setting}").into());
+        }
+        if let Some(pool) = &self.pool {
+            sqlx::query(&format!("UPDATE channels SET {setting} = $1 WHERE channel_name = $2"))
+                .bind(enabled)
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_channels_with_setting(&self, setting: &str) -> DbResult<Vec<String>> {
+        if !crate::database::is_channel_setting(setting) {
+            return Err(format!("Unknown channel setting: {setting}").into());
+        }
+        if let Some(pool) = &self.pool {
+            let channels: Vec<String> = sqlx::query_scalar(&format!("SELECT channel_name FROM channels WHERE {setting} != 0"))
+                .fetch_all(pool)
+                .await?;
+            Ok(channels)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn migrate_access_flags(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            for (template, flags) in crate::database::ACCESS_TEMPLATES {
+                sqlx::query("UPDATE channel_access SET level = $1 WHERE level = $2")
+                    .bind(flags)
+                    .bind(template)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn migrate_channel_settings(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let columns = table_columns(pool, "channels").await?;
+            for (setting, default) in crate::database::CHANNEL_SETTINGS {
+                if !columns.iter().any(|c| c == setting) {
+                    sqlx::query(&format!(
+                        "ALTER TABLE channels ADD COLUMN {setting} INTEGER NOT NULL DEFAULT {}",
+                        default as i64
+                    ))
+                    .execute(pool)
+                    .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_list_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS channel_lists (
+                    channel_name TEXT NOT NULL,
+                    list_mode TEXT NOT NULL,
+                    mask TEXT NOT NULL,
+                    set_by TEXT NOT NULL,
+                    set_time INTEGER NOT NULL,
+                    expires_time INTEGER,
+                    PRIMARY KEY (channel_name, list_mode, mask),
+                    FOREIGN KEY (channel_name) REFERENCES channels(channel_name) ON DELETE CASCADE
+                )",
+            )
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn add_channel_list_entry(
+        &mut self,
+        channel_name: &str,
+        list: char,
+        mask: &str,
+        set_by: &str,
+        set_time: SystemTime,
+        expires: Option<SystemTime>,
+    ) -> DbResult<()> {
+        if !crate::database::is_channel_list(list) {
+            return Err(format!("Unknown channel list: {list}").into());
+        }
+        if let Some(pool) = &self.pool {
+            let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            let expires_timestamp = match expires {
+                Some(e) => Some(e.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                None => None,
+            };
+            sqlx::query(
+                "INSERT INTO channel_lists (channel_name, list_mode, mask, set_by, set_time, expires_time) VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (channel_name, list_mode, mask) DO UPDATE SET set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time, expires_time = EXCLUDED.expires_time",
+            )
+            .bind(casefold(channel_name))
+            .bind(list.to_string())
+            .bind(mask)
+            .bind(set_by)
+            .bind(timestamp)
+            .bind(expires_timestamp)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_channel_list_entries(&self, channel_name: &str) -> DbResult<Vec<ChannelListEntry>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, String, String, i64, Option<i64>)> = sqlx::query_as(
+                "SELECT list_mode, mask, set_by, set_time, expires_time FROM channel_lists WHERE channel_name = $1 ORDER BY set_time, mask",
+            )
+            .bind(casefold(channel_name))
+            .fetch_all(pool)
+            .await?;
+
+            let mut results = Vec::new();
+            for (list_mode, mask, set_by, timestamp, expires_timestamp) in rows {
+                let Some(list) = list_mode.chars().next() else {
+                    continue;
+                };
+                let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                let expires = expires_timestamp.map(|t| SystemTime::UNIX_EPOCH + Duration::from_secs(t as u64));
+                results.push(ChannelListEntry { list, mask, set_by, set_time, expires });
+            }
+            return Ok(results);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn delete_channel_list_entry(&mut self, channel_name: &str, list: char, mask: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM channel_lists WHERE channel_name = $1 AND list_mode = $2 AND mask = $3")
+                .bind(casefold(channel_name))
+                .bind(list.to_string())
+                .bind(mask)
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn get_channel_key_limit(&self, channel_name: &str) -> DbResult<(Option<String>, Option<usize>)> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(Option<String>, Option<i64>)> =
+                sqlx::query_as("SELECT mode_key, mode_limit FROM channels WHERE channel_name = $1")
+                    .bind(casefold(channel_name))
+                    .fetch_optional(pool)
+                    .await?;
+            if let Some((key, limit)) = row {
+                return Ok((key, limit.map(|l| l as usize)));
+            }
+        }
+        Ok((None, None))
+    }
+
+    async fn set_channel_key_limit(&mut self, channel_name: &str, key: Option<&str>, limit: Option<usize>) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("UPDATE channels SET mode_key = $1, mode_limit = $2 WHERE channel_name = $3")
+                .bind(key)
+                .bind(limit.map(|l| l as i64))
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn migrate_channel_key_limit(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let columns = table_columns(pool, "channels").await?;
+            if !columns.iter().any(|c| c == "mode_key") {
+                sqlx::query("ALTER TABLE channels ADD COLUMN mode_key TEXT").execute(pool).await?;
+            }
+            if !columns.iter().any(|c| c == "mode_limit") {
+                sqlx::query("ALTER TABLE channels ADD COLUMN mode_limit INTEGER").execute(pool).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            refold_column(pool, "channels", "channel_name").await?;
+            refold_column(pool, "channel_access", "channel_name").await?;
+            refold_column(pool, "channel_access", "nick").await?;
+            refold_column(pool, "channel_akick", "channel_name").await?;
+            refold_column(pool, "channel_suspensions", "channel_name").await?;
+            refold_column(pool, "forbidden_channels", "channel_name").await?;
+            refold_column(pool, "channel_lists", "channel_name").await?;
+        }
+        Ok(())
+    }
+}
+
+pub struct SQLiteMemoDatabase {
+    pool: Option<SqlitePool>,
+}
+
+impl SQLiteMemoDatabase {
+    pub fn new() -> Self {
+        SQLiteMemoDatabase { pool: None }
+    }
+}
+
+#[async_trait]
+impl MemoDatabase for SQLiteMemoDatabase {
+    async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+        let pool = connect_pool(db_config).await?;
+        self.pool = Some(pool);
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS memos (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    recipient TEXT NOT NULL,
+                    sender TEXT NOT NULL,
+                    text TEXT NOT NULL,
+                    sent_time INTEGER NOT NULL,
+                    is_read INTEGER NOT NULL DEFAULT 0
+                )",
+            )
+            .execute(pool)
+            .await?;
+            sqlx::query("CREATE INDEX IF NOT EXISTS memos_recipient ON memos (recipient)")
+                .execute(pool)
+                .await?;
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS memo_limits (
+                    nick TEXT PRIMARY KEY,
+                    memo_limit INTEGER NOT NULL
+                )",
+            )
+            .execute(pool)
+            .await?;
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS memo_ignores (
+                    nick TEXT NOT NULL,
+                    ignored TEXT NOT NULL,
+                    PRIMARY KEY (nick, ignored)
+                )",
+            )
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn add_memo(
+        &mut self,
+        recipient: &str,
+        sender: &str,
+        text: &str,
+        sent_time: SystemTime,
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = sent_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            sqlx::query("INSERT INTO memos (recipient, sender, text, sent_time) VALUES ($1, $2, $3, $4)")
+                .bind(casefold(recipient))
+                .bind(sender)
+                .bind(text)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_memos(&self, recipient: &str) -> DbResult<Vec<(i64, String, String, SystemTime, bool)>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(i64, String, String, i64, bool)> = sqlx::query_as(
+                "SELECT id, sender, text, sent_time, is_read FROM memos WHERE recipient = $1 ORDER BY id",
+            )
+            .bind(casefold(recipient))
+            .fetch_all(pool)
+            .await?;
+
+            let mut results = Vec::new();
+            for (id, sender, text, timestamp, is_read) in rows {
+                let sent_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                results.push((id, sender, text, sent_time, is_read));
+            }
+            return Ok(results);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn count_unread_memos(&self, recipient: &str) -> DbResult<usize> {
+        if let Some(pool) = &self.pool {
+            let (count,): (i64,) = sqlx::query_as(
+                "SELECT COUNT(*) FROM memos WHERE recipient = $1 AND is_read = 0",
+            )
+            .bind(casefold(recipient))
+            .fetch_one(pool)
+            .await?;
+            return Ok(count as usize);
+        }
+        Ok(0)
+    }
+
+    async fn mark_memo_read(&mut self, id: i64) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("UPDATE memos SET is_read = 1 WHERE id = $1")
+                .bind(id)
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_memo(&mut self, id: i64) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM memos WHERE id = $1")
+                .bind(id)
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn get_memo_accounts(&self) -> DbResult<Vec<String>> {
+        if let Some(pool) = &self.pool {
+            let names: Vec<String> = sqlx::query_scalar("SELECT recipient FROM memos UNION SELECT nick FROM memo_limits UNION SELECT nick FROM memo_ignores ORDER BY 1")
+                .fetch_all(pool)
+                .await?;
+            return Ok(names);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn get_memo_limit(&self, nick: &str) -> DbResult<Option<usize>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(i32,)> = sqlx::query_as("SELECT memo_limit FROM memo_limits WHERE nick = $1")
+                .bind(casefold(nick))
+                .fetch_optional(pool)
+                .await?;
+            return Ok(row.map(|(limit,)| limit as usize));
+        }
+        Ok(None)
+    }
+
+    async fn set_memo_limit(&mut self, nick: &str, limit: Option<usize>) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            if let Some(l) = limit {
+                sqlx::query(
+                    "INSERT INTO memo_limits (nick, memo_limit) VALUES ($1, $2)
+                     ON CONFLICT (nick) DO UPDATE SET memo_limit = EXCLUDED.memo_limit",
+                )
+                .bind(casefold(nick))
+                .bind(l as i32)
+                .execute(pool)
+                .await?;
+            } else {
+                sqlx::query("DELETE FROM memo_limits WHERE nick = $1")
+                    .bind(casefold(nick))
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn add_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("INSERT INTO memo_ignores (nick, ignored) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+                .bind(casefold(nick))
+                .bind(casefold(ignored))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_memo_ignores(&self, nick: &str) -> DbResult<Vec<String>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String,)> = sqlx::query_as("SELECT ignored FROM memo_ignores WHERE nick = $1 ORDER BY ignored")
+                .bind(casefold(nick))
+                .fetch_all(pool)
+                .await?;
+            return Ok(rows.into_iter().map(|(ignored,)| ignored).collect());
+        }
+        Ok(Vec::new())
+    }
+
+    async fn delete_memo_ignore(&mut self, nick: &str, ignored: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM memo_ignores WHERE nick = $1 AND ignored = $2")
+                .bind(casefold(nick))
+                .bind(casefold(ignored))
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            refold_column(pool, "memos", "recipient").await?;
+            refold_column(pool, "memo_limits", "nick").await?;
+            refold_column(pool, "memo_ignores", "nick").await?;
+            refold_column(pool, "memo_ignores", "ignored").await?;
+        }
+        Ok(())
+    }
+}
+
+pub struct SQLiteOperDatabase {
+    pool: Option<SqlitePool>,
+}
+
+impl SQLiteOperDatabase {
+    pub fn new() -> Self {
+        SQLiteOperDatabase { pool: None }
+    }
+}
+
+#[async_trait]
+impl OperDatabase for SQLiteOperDatabase {
+    async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+        let pool = connect_pool(db_config).await?;
+        self.pool = Some(pool);
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS akills (
+                    mask TEXT PRIMARY KEY,
+                    reason TEXT NOT NULL,
+                    set_by TEXT NOT NULL,
+                    set_time INTEGER NOT NULL,
+                    expires_time INTEGER
+                )",
+            )
+            .execute(pool)
+            .await?;
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS session_exceptions (
+                    ip TEXT PRIMARY KEY,
+                    session_limit INTEGER NOT NULL,
+                    reason TEXT NOT NULL,
+                    set_by TEXT NOT NULL,
+                    set_time INTEGER NOT NULL
+                )",
+            )
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn add_akill(
+        &mut self,
+        mask: &str,
+        reason: &str,
+        set_by: &str,
+        set_time: SystemTime,
+        expires: Option<SystemTime>,
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            let expires_timestamp = match expires {
+                Some(expires) => Some(expires.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64),
+                None => None,
+            };
+            sqlx::query(
+                "INSERT INTO akills (mask, reason, set_by, set_time, expires_time) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (mask) DO UPDATE SET reason = EXCLUDED.reason, set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time, expires_time = EXCLUDED.expires_time",
+            )
+            .bind(mask.to_lowercase())
+            .bind(reason)
+            .bind(set_by)
+            .bind(timestamp)
+            .bind(expires_timestamp)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_akills(&self) -> DbResult<Vec<AkillEntry>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, String, String, i64, Option<i64>)> = sqlx::query_as(
+                "SELECT mask, reason, set_by, set_time, expires_time FROM akills ORDER BY set_time",
+            )
+            .fetch_all(pool)
+            .await?;
+
+            let mut results = Vec::new();
+            for (mask, reason, set_by, timestamp, expires_timestamp) in rows {
+                let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                let expires = expires_timestamp.map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64));
+                results.push(AkillEntry { mask, reason, set_by, set_time, expires });
+            }
+            return Ok(results);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn delete_akill(&mut self, mask: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM akills WHERE mask = $1")
+                .bind(mask.to_lowercase())
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn add_session_exception(
+        &mut self,
+        ip: &str,
+        limit: usize,
+        reason: &str,
+        set_by: &str,
+        set_time: SystemTime,
+    ) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = set_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            sqlx::query(
+                "INSERT INTO session_exceptions (ip, session_limit, reason, set_by, set_time) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (ip) DO UPDATE SET session_limit = EXCLUDED.session_limit, reason = EXCLUDED.reason, set_by = EXCLUDED.set_by, set_time = EXCLUDED.set_time",
+            )
+            .bind(ip)
+            .bind(limit as i32)
+            .bind(reason)
+            .bind(set_by)
+            .bind(timestamp)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_session_exception(&self, ip: &str) -> DbResult<Option<usize>> {
+        if let Some(pool) = &self.pool {
+            let row: Option<(i32,)> = sqlx::query_as("SELECT session_limit FROM session_exceptions WHERE ip = $1")
+                .bind(ip)
+                .fetch_optional(pool)
+                .await?;
+            return Ok(row.map(|(limit,)| limit as usize));
+        }
+        Ok(None)
+    }
+
+    async fn get_session_exceptions(&self) -> DbResult<Vec<(String, usize, String, String, SystemTime)>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, i32, String, String, i64)> = sqlx::query_as(
+                "SELECT ip, session_limit, reason, set_by, set_time FROM session_exceptions ORDER BY set_time",
+            )
+            .fetch_all(pool)
+            .await?;
+
+            let mut results = Vec::new();
+            for (ip, limit, reason, set_by, timestamp) in rows {
+                let set_time = SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp as u64);
+                results.push((ip, limit as usize, reason, set_by, set_time));
+            }
+            return Ok(results);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn delete_session_exception(&mut self, ip: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM session_exceptions WHERE ip = $1")
+                .bind(ip)
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+}
+
+pub struct SQLiteBotDatabase {
+    pool: Option<SqlitePool>,
+}
+
+impl SQLiteBotDatabase {
+    pub fn new() -> Self {
+        SQLiteBotDatabase { pool: None }
+    }
+}
+
+#[async_trait]
+impl BotDatabase for SQLiteBotDatabase {
+    async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+        let pool = connect_pool(db_config).await?;
+        self.pool = Some(pool);
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS bots (
+                    nick TEXT PRIMARY KEY,
+                    user TEXT NOT NULL,
+                    host TEXT NOT NULL,
+                    realname TEXT NOT NULL,
+                    created_time INTEGER NOT NULL
+                )",
+            )
+            .execute(pool)
+            .await?;
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS bot_channels (
+                    channel_name TEXT PRIMARY KEY,
+                    bot TEXT NOT NULL,
+                    greet TEXT,
+                    kick_caps INTEGER NOT NULL DEFAULT 0,
+                    kick_colors INTEGER NOT NULL DEFAULT 0,
+                    kick_repeat INTEGER NOT NULL DEFAULT 0,
+                    kick_badwords INTEGER NOT NULL DEFAULT 0
+                )",
+            )
+            .execute(pool)
+            .await?;
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS bot_badwords (
+                    channel_name TEXT NOT NULL,
+                    word TEXT NOT NULL,
+                    PRIMARY KEY (channel_name, word)
+                )",
+            )
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn add_bot(&mut self, nick: &str, user: &str, host: &str, realname: &str, created_time: SystemTime) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = created_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            sqlx::query("INSERT INTO bots (nick, user, host, realname, created_time) VALUES ($1, $2, $3, $4, $5)")
+                .bind(nick)
+                .bind(user)
+                .bind(host)
+                .bind(realname)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_bots(&self) -> DbResult<Vec<(String, String, String, String)>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, String, String, String)> = sqlx::query_as("SELECT nick, user, host, realname FROM bots ORDER BY nick")
+                .fetch_all(pool)
+                .await?;
+            return Ok(rows);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn delete_bot(&mut self, nick: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM bots WHERE LOWER(nick) = LOWER($1)")
+                .bind(nick)
+                .execute(pool)
+                .await?;
+            sqlx::query("DELETE FROM bot_channels WHERE LOWER(bot) = LOWER($1)")
+                .bind(nick)
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn assign_bot(&mut self, channel_name: &str, bot: &str) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            // Al cambiar de bot se conservan el saludo y los kickers del canal
+            sqlx::query(
+                "INSERT INTO bot_channels (channel_name, bot) VALUES ($1, $2)
+                 ON CONFLICT (channel_name) DO UPDATE SET bot = EXCLUDED.bot",
+            )
+            .bind(casefold(channel_name))
+            .bind(bot)
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn unassign_bot(&mut self, channel_name: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM bot_channels WHERE channel_name = $1")
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn get_bot_channels(&self) -> DbResult<Vec<(String, String, Option<String>, Vec<String>)>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String, String, Option<String>, bool, bool, bool, bool)> = sqlx::query_as(
+                "SELECT channel_name, bot, greet, kick_caps, kick_colors, kick_repeat, kick_badwords FROM bot_channels ORDER BY channel_name",
+            )
+            .fetch_all(pool)
+            .await?;
+
+            let mut results = Vec::new();
+            for (channel_name, bot, greet, caps, colors, repeat, badwords) in rows {
+                let kickers = crate::database::BOT_KICKERS
+                    .iter()
+                    .zip([caps, colors, repeat, badwords])
+                    .filter(|(_, enabled)| *enabled)
+                    .map(|(kicker, _)| kicker.to_string())
+                    .collect();
+                results.push((channel_name, bot, greet, kickers));
+            }
+            return Ok(results);
+        }
+        Ok(Vec::new())
+    }
+
+    async fn set_bot_greet(&mut self, channel_name: &str, greet: Option<&str>) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("UPDATE bot_channels SET greet = $1 WHERE channel_name = $2")
+                .bind(greet)
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn set_bot_kicker(&mut self, channel_name: &str, kicker: &str, enabled: bool) -> DbResult<()> {
+        if !crate::database::is_bot_kicker(kicker) {
+            return Err(format!("Unknown kicker: {kicker}").into());
+        }
+        if let Some(pool) = &self.pool {
+            let query = format!("UPDATE bot_channels SET kick_{kicker} = $1 WHERE channel_name = $2");
+            sqlx::query(&query)
+                .bind(enabled)
+                .bind(casefold(channel_name))
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn add_badword(&mut self, channel_name: &str, word: &str) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query("INSERT INTO bot_badwords (channel_name, word) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+                .bind(casefold(channel_name))
+                .bind(word.to_lowercase())
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_badwords(&self, channel_name: &str) -> DbResult<Vec<String>> {
+        if let Some(pool) = &self.pool {
+            let rows: Vec<(String,)> = sqlx::query_as("SELECT word FROM bot_badwords WHERE channel_name = $1 ORDER BY word")
+                .bind(casefold(channel_name))
+                .fetch_all(pool)
+                .await?;
+            return Ok(rows.into_iter().map(|(word,)| word).collect());
+        }
+        Ok(Vec::new())
+    }
+
+    async fn delete_badword(&mut self, channel_name: &str, word: &str) -> DbResult<bool> {
+        if let Some(pool) = &self.pool {
+            let result = sqlx::query("DELETE FROM bot_badwords WHERE channel_name = $1 AND word = $2")
+                .bind(casefold(channel_name))
+                .bind(word.to_lowercase())
+                .execute(pool)
+                .await?;
+            return Ok(result.rows_affected() > 0);
+        }
+        Ok(false)
+    }
+
+    async fn migrate_casefold_names(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            refold_column(pool, "bot_channels", "channel_name").await?;
+            refold_column(pool, "bot_badwords", "channel_name").await?;
+        }
+        Ok(())
+    }
+}
+
+pub struct SQLiteSchemaDatabase {
+    pool: Option<SqlitePool>,
+}
+
+impl SQLiteSchemaDatabase {
+    pub fn new() -> Self {
+        SQLiteSchemaDatabase { pool: None }
+    }
+}
+
+#[async_trait]
+impl SchemaDatabase for SQLiteSchemaDatabase {
+    async fn connect(&mut self, db_config: &str) -> DbResult<()> {
+        let pool = connect_pool(db_config).await?;
+        self.pool = Some(pool);
+        Ok(())
+    }
+
+    async fn create_table(&mut self) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS schema_version (
+                    version INTEGER PRIMARY KEY,
+                    description TEXT NOT NULL,
+                    applied_time INTEGER NOT NULL
+                )",
+            )
+            .execute(pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn get_schema_version(&self) -> DbResult<u32> {
+        if let Some(pool) = &self.pool {
+            let version: Option<i32> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+                .fetch_one(pool)
+                .await?;
+            return Ok(version.unwrap_or(0) as u32);
+        }
+        Ok(0)
+    }
+
+    async fn add_schema_version(&mut self, version: u32, description: &str, applied_time: SystemTime) -> DbResult<()> {
+        if let Some(pool) = &self.pool {
+            let timestamp = applied_time.duration_since(SystemTime::UNIX_EPOCH)?.as_secs() as i64;
+            sqlx::query("INSERT INTO schema_version (version, description, applied_time) VALUES ($1, $2, $3)")
+                .bind(version as i32)
+                .bind(description)
+                .bind(timestamp)
+                .execute(pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -23,7 +23,6 @@ mod help;
 mod reply;
 mod state;
 mod utils;
-#[cfg(any(feature = "mysql", feature = "sqlite"))]
 mod database;
 
 use clap::Parser;
@@ -71,6 +70,15 @@ async fn tokio_main(cli: Cli) -> Result<(), Box<dyn Error>> {
             prompt_password("Enter password:")?
         };
         println!("Password Hash: {}", argon2_hash_password(&password));
+    } else if cli.migrate_only || cli.check_db {
+        let migrate = cli.migrate_only;
+        let config = MainConfig::new(cli)?;
+        check_database(&config, migrate).await?;
+    } else if cli.export_services.is_some() || cli.import_services.is_some() {
+        let (export_file, import_file, dry_run) =
+            (cli.export_services.clone(), cli.import_services.clone(), cli.dry_run);
+        let config = MainConfig::new(cli)?;
+        transfer_services(&config, export_file, import_file, dry_run).await?;
     } else {
         let config = MainConfig::new(cli)?;
         initialize_logging(&config);
@@ -83,4 +91,129 @@ async fn tokio_main(cli: Cli) -> Result<(), Box<dyn Error>> {
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+// Comprueba o aplica las migraciones pendientes sin arrancar los listeners
+async fn check_database(config: &MainConfig, migrate: bool) -> Result<(), Box<dyn Error>> {
+    let Some(db_config) = &config.database else {
+        return Err("No database configured".into());
+    };
+    // la migración 6 guarda los nombres con el casemapping configurado
+    config.casemapping.set_current()?;
+    let mut dbs = database::migrations::ServiceDatabases::open(db_config).await?;
+    let version = dbs.schema_db.get_schema_version().await?;
+    println!("Database schema version: {version}");
+    if migrate {
+        for (version, description) in dbs.migrate().await? {
+            println!("Applied migration {version}: {description}");
+        }
+    } else {
+        let pending = dbs.pending_migrations().await?;
+        if pending.is_empty() {
+            println!("Database schema is up to date");
+        }
+        for (version, description) in pending {
+            println!("Pending migration {version}: {description}");
+        }
+    }
+    Ok(())
+}
+
+// Exporta o importa los datos de servicios en JSON sin arrancar los listeners
+async fn transfer_services(
+    config: &MainConfig,
+    export_file: Option<String>,
+    import_file: Option<String>,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    use database::dump::ServicesDump;
+
+    let Some(db_config) = &config.database else {
+        return Err("No database configured".into());
+    };
+    config.casemapping.set_current()?;
+    let mut dbs = database::migrations::ServiceDatabases::open(db_config).await?;
+    // sin las tablas al día no se puede saber qué existe ya
+    let mut schema_ready = dbs.pending_migrations().await?.is_empty();
+    if !schema_ready && export_file.is_some() {
+        return Err("Database schema is not up to date, run --migrate-only first".into());
+    }
+    if !schema_ready && !dry_run {
+        dbs.migrate().await?;
+        schema_ready = true;
+    }
+
+    if let Some(file) = export_file {
+        let dump = ServicesDump::export(&dbs).await?;
+        std::fs::write(&file, serde_json::to_string_pretty(&dump)?)?;
+        println!(
+            "Exported {} nicks and {} channels to {file}",
+            dump.nicks.len(),
+            dump.channels.len()
+        );
+    }
+
+    if let Some(file) = import_file {
+        let dump: ServicesDump = serde_json::from_str(&std::fs::read_to_string(&file)?)?;
+        let errors = dump.validate();
+        if !errors.is_empty() {
+            for error in &errors {
+                eprintln!("{file}: {error}");
+            }
+            return Err(format!("{} errors in {file}", errors.len()).into());
+        }
+        if !schema_ready {
+            println!("{file} is valid, existing entries not checked: database schema is not up to date");
+            return Ok(());
+        }
+        let report = dump.import(&mut dbs, dry_run).await?;
+        for skipped in &report.skipped {
+            println!("Skipped: {skipped}");
+        }
+        if dry_run {
+            println!("{file} is valid, {} entries would be imported", report.imported);
+        } else {
+            if let Some(snapshot) = &dbs.snapshot {
+                snapshot.save().await?;
+            }
+            println!("Imported {} entries from {file}", report.imported);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_database_migrate_casemapping() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("services.db").to_string_lossy());
+        let config = MainConfig {
+            casemapping: CaseMapping::Rfc1459,
+            database: Some(DatabaseConfig {
+                database: "sqlite".to_string(),
+                url: url.clone(),
+                forbidden_vhosts: vec![],
+                snapshot_interval: None,
+            }),
+            ..MainConfig::default()
+        };
+        check_database(&config, true).await.unwrap();
+
+        // nick guardado antes de la migración 6, tal como se escribió
+        let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+        sqlx::query("DELETE FROM schema_version WHERE version = 6").execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO nicks (nick, password, user, registration_time) VALUES ('Zeus[X]', 'x', 'zeus@host', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        check_database(&config, true).await.unwrap();
+        let nicks: Vec<String> = sqlx::query_scalar("SELECT nick FROM nicks").fetch_all(&pool).await.unwrap();
+        assert_eq!(vec!["zeus{x}".to_string()], nicks);
+        pool.close().await;
+        dir.close().unwrap();
+    }
+}
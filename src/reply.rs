@@ -129,7 +129,21 @@ pub(crate) enum Reply<'a> {
         clients_num: usize,
         max_clients_num: usize,
     },
+    RplSileList271 {
+        client: &'a str,
+        mask: &'a str,
+    },
+    RplEndOfSileList272 {
+        client: &'a str,
+    },
     //RplWhoIsCertFP276{ client: &'a str, nick: &'a str, fingerprint: &'a str },
+    RplAcceptList281 {
+        client: &'a str,
+        nick: &'a str,
+    },
+    RplEndOfAccept282 {
+        client: &'a str,
+    },
     RplAway301 {
         client: &'a str,
         nick: &'a str,
@@ -383,6 +397,10 @@ pub(crate) enum Reply<'a> {
         client: &'a str,
         command: &'a str,
     },
+    ErrErroneusNickname432 {
+        client: &'a str,
+        nick: &'a str,
+    },
     ErrNicknameInUse433 {
         client: &'a str,
         nick: &'a str,
@@ -404,6 +422,17 @@ pub(crate) enum Reply<'a> {
     ErrNotRegistered451 {
         client: &'a str,
     },
+    ErrAcceptFull456 {
+        client: &'a str,
+    },
+    ErrAcceptExist457 {
+        client: &'a str,
+        nick: &'a str,
+    },
+    ErrAcceptNot458 {
+        client: &'a str,
+        nick: &'a str,
+    },
     ErrNeedMoreParams461 {
         client: &'a str,
         command: &'a str,
@@ -415,6 +444,11 @@ pub(crate) enum Reply<'a> {
         client: &'a str,
     },
     //ErrYoureBannedCreep465{ client: &'a str },
+    ErrLinkChannel470 {
+        client: &'a str,
+        channel: &'a str,
+        target: &'a str,
+    },
     ErrChannelIsFull471 {
         client: &'a str,
         channel: &'a str,
@@ -433,14 +467,35 @@ pub(crate) enum Reply<'a> {
         client: &'a str,
         channel: &'a str,
     },
+    ErrSecureOnlyChan489 {
+        client: &'a str,
+        channel: &'a str,
+    },
     ErrBannedFromChan474 {
         client: &'a str,
         channel: &'a str,
     },
+    ErrNeedReggedNick477 {
+        client: &'a str,
+        channel: &'a str,
+    },
     ErrBadChannelKey475 {
         client: &'a str,
         channel: &'a str,
     },
+    ErrBanListFull478 {
+        client: &'a str,
+        channel: &'a str,
+        mode: char,
+    },
+    ErrThrottle480 {
+        client: &'a str,
+        channel: &'a str,
+    },
+    ErrCannotKnock480 {
+        client: &'a str,
+        channel: &'a str,
+    },
     ErrNoPrivileges481 {
         client: &'a str,
     },
@@ -454,6 +509,10 @@ pub(crate) enum Reply<'a> {
     ErrYourConnRestricted484 {
         client: &'a str,
     },
+    ErrNoNonReg486 {
+        client: &'a str,
+        nick: &'a str,
+    },
     ErrNoOperHost491 {
         client: &'a str,
     },
@@ -463,6 +522,10 @@ pub(crate) enum Reply<'a> {
     ErrUsersDontMatch502 {
         client: &'a str,
     },
+    ErrSileListFull511 {
+        client: &'a str,
+        mask: &'a str,
+    },
     ErrHelpNotFound524 {
         client: &'a str,
         subject: &'a str,
@@ -499,6 +562,53 @@ pub(crate) enum Reply<'a> {
         subject: &'a str,
         line: &'a str,
     },
+    RplKnock710 {
+        client: &'a str,
+        channel: &'a str,
+        source: &'a str,
+        message: Option<&'a str>,
+    },
+    RplKnockDlvr711 {
+        client: &'a str,
+        channel: &'a str,
+    },
+    ErrTooManyKnock712 {
+        client: &'a str,
+        channel: &'a str,
+        reason: &'a str,
+    },
+    ErrChanOpen713 {
+        client: &'a str,
+        channel: &'a str,
+    },
+    ErrKnockOnChan714 {
+        client: &'a str,
+        channel: &'a str,
+    },
+    ErrTargUmodeG716 {
+        client: &'a str,
+        nick: &'a str,
+    },
+    RplTargNotify717 {
+        client: &'a str,
+        nick: &'a str,
+    },
+    RplUmodeGMsg718 {
+        client: &'a str,
+        nick: &'a str,
+        user_host: &'a str,
+    },
+    RplQuietList728 {
+        client: &'a str,
+        channel: &'a str,
+        mask: &'a str,
+        who: &'a str,
+        set_ts: u64,
+    },
+    RplEndOfQuietList729 {
+        client: &'a str,
+        channel: &'a str,
+    },
     RplSaslSuccess903{ client: &'a str },
     ErrSaslFail904{ client: &'a str },
     ErrSaslTooLong905{ client: &'a str },
@@ -508,7 +618,6 @@ pub(crate) enum Reply<'a> {
     ErrCannotDoCommand972 {
         client: &'a str,
     },
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     ErrNickRegistered465 {
         client: &'a str,
     },
@@ -668,9 +777,21 @@ impl<'a> fmt::Display for Reply<'a> {
                     client, clients_num, max_clients_num, clients_num, max_clients_num
                 )
             }
+            RplSileList271 { client, mask } => {
+                write!(f, "271 {} {}", client, mask)
+            }
+            RplEndOfSileList272 { client } => {
+                write!(f, "272 {} :End of Silence List", client)
+            }
             //RplWhoIsCertFP276{ client, nick, fingerprint } => {
             //    write!(f, "276 {} {} :has client certificate fingerprint {}", client, nick,
             //        fingerprint) }
+            RplAcceptList281 { client, nick } => {
+                write!(f, "281 {} {}", client, nick)
+            }
+            RplEndOfAccept282 { client } => {
+                write!(f, "282 {} :End of /ACCEPT list", client)
+            }
             RplAway301 {
                 client,
                 nick,
@@ -850,7 +971,7 @@ impl<'a> fmt::Display for Reply<'a> {
                 nick,
                 setat,
             } => {
-                write!(f, "333 {} {} {} :{}", client, channel, nick, setat)
+                write!(f, "333 {} {} {} {}", client, channel, nick, setat)
             }
             RplInviting341 {
                 client,
@@ -1034,6 +1155,9 @@ impl<'a> fmt::Display for Reply<'a> {
             ErrUnknownCommand421 { client, command } => {
                 write!(f, "421 {} {} :Unknown command", client, command)
             }
+            ErrErroneusNickname432 { client, nick } => {
+                write!(f, "432 {} {} :Erroneus nickname", client, nick)
+            }
             ErrNicknameInUse433 { client, nick } => {
                 write!(f, "433 {} {} :Nickname is already in use", client, nick)
             }
@@ -1065,6 +1189,15 @@ impl<'a> fmt::Display for Reply<'a> {
             ErrNotRegistered451 { client } => {
                 write!(f, "451 {} :You have not registered", client)
             }
+            ErrAcceptFull456 { client } => {
+                write!(f, "456 {} :Accept list is full", client)
+            }
+            ErrAcceptExist457 { client, nick } => {
+                write!(f, "457 {} {} :is already on your accept list", client, nick)
+            }
+            ErrAcceptNot458 { client, nick } => {
+                write!(f, "458 {} {} :is not on your accept list", client, nick)
+            }
             ErrNeedMoreParams461 { client, command } => {
                 write!(f, "461 {} {} :Not enough parameters", client, command)
             }
@@ -1076,6 +1209,13 @@ impl<'a> fmt::Display for Reply<'a> {
             }
             //ErrYoureBannedCreep465{ client } => {
             //    write!(f, "465 {} :You are banned from this server.", client) }
+            ErrLinkChannel470 {
+                client,
+                channel,
+                target,
+            } => {
+                write!(f, "470 {} {} {} :Forwarding to another channel", client, channel, target)
+            }
             ErrChannelIsFull471 { client, channel } => {
                 write!(f, "471 {} {} :Cannot join channel (+l)", client, channel)
             }
@@ -1096,12 +1236,39 @@ impl<'a> fmt::Display for Reply<'a> {
             ErrCannotJoinIrcopsOnly { client, channel } => {
                 write!(f, "489 {} {} :Cannot join channel (+O)", client, channel)
             }
+            ErrSecureOnlyChan489 { client, channel } => {
+                write!(f, "489 {} {} :Cannot join channel; SSL users only (+z)", client, channel)
+            }
             ErrBannedFromChan474 { client, channel } => {
                 write!(f, "474 {} {} :Cannot join channel (+b)", client, channel)
             }
+            ErrNeedReggedNick477 { client, channel } => {
+                write!(
+                    f,
+                    "477 {} {} :Cannot join channel (+R) - you need to be identified with services",
+                    client, channel
+                )
+            }
             ErrBadChannelKey475 { client, channel } => {
                 write!(f, "475 {} {} :Cannot join channel (+k)", client, channel)
             }
+            ErrBanListFull478 {
+                client,
+                channel,
+                mode,
+            } => {
+                write!(f, "478 {} {} {} :Channel list is full", client, channel, mode)
+            }
+            ErrThrottle480 { client, channel } => {
+                write!(
+                    f,
+                    "480 {} {} :Cannot join channel (+j) - throttle exceeded, try again later",
+                    client, channel
+                )
+            }
+            ErrCannotKnock480 { client, channel } => {
+                write!(f, "480 {} :Cannot knock on {} (+K)", client, channel)
+            }
             ErrNoPrivileges481 { client } => {
                 write!(
                     f,
@@ -1118,6 +1285,13 @@ impl<'a> fmt::Display for Reply<'a> {
             ErrYourConnRestricted484 { client } => {
                 write!(f, "484 {} :Your connection is restricted!", client)
             }
+            ErrNoNonReg486 { client, nick } => {
+                write!(
+                    f,
+                    "486 {} {} :You must identify to a registered nick to private message this person",
+                    client, nick
+                )
+            }
             ErrNoOperHost491 { client } => {
                 write!(f, "491 {} :No O-lines for your host", client)
             }
@@ -1127,6 +1301,9 @@ impl<'a> fmt::Display for Reply<'a> {
             ErrUsersDontMatch502 { client } => {
                 write!(f, "502 {} :Cant change mode for other users", client)
             }
+            ErrSileListFull511 { client, mask } => {
+                write!(f, "511 {} {} :Your silence list is full", client, mask)
+            }
             ErrHelpNotFound524 { client, subject } => {
                 write!(
                     f,
@@ -1178,6 +1355,51 @@ impl<'a> fmt::Display for Reply<'a> {
             } => {
                 write!(f, "706 {} {} :{}", client, subject, line)
             }
+            RplKnock710 {
+                client,
+                channel,
+                source,
+                message,
+            } => {
+                if let Some(message) = message {
+                    write!(f, "710 {} {} {} :has asked for an invite: {}", client, channel, source, message)
+                } else {
+                    write!(f, "710 {} {} {} :has asked for an invite.", client, channel, source)
+                }
+            }
+            RplKnockDlvr711 { client, channel } => {
+                write!(f, "711 {} {} :Your KNOCK has been delivered.", client, channel)
+            }
+            ErrTooManyKnock712 { client, channel, reason } => {
+                write!(f, "712 {} {} :Too many KNOCKs ({}).", client, channel, reason)
+            }
+            ErrChanOpen713 { client, channel } => {
+                write!(f, "713 {} {} :Channel is open.", client, channel)
+            }
+            ErrKnockOnChan714 { client, channel } => {
+                write!(f, "714 {} {} :You are already on that channel.", client, channel)
+            }
+            ErrTargUmodeG716 { client, nick } => {
+                write!(f, "716 {} {} :is in +g mode (server-side ignore).", client, nick)
+            }
+            RplTargNotify717 { client, nick } => {
+                write!(f, "717 {} {} :has been informed that you messaged them.", client, nick)
+            }
+            RplUmodeGMsg718 { client, nick, user_host } => {
+                write!(f, "718 {} {} {} :is messaging you, and you have umode +g.", client, nick, user_host)
+            }
+            RplQuietList728 {
+                client,
+                channel,
+                mask,
+                who,
+                set_ts,
+            } => {
+                write!(f, "728 {} {} Z {} {} {}", client, channel, mask, who, set_ts)
+            }
+            RplEndOfQuietList729 { client, channel } => {
+                write!(f, "729 {} {} Z :End of channel quiet list", client, channel)
+            }
             RplSaslSuccess903{ client } => {
                 write!(f, "903 {} :SASL authentication successful", client)
             }
@@ -1199,7 +1421,6 @@ impl<'a> fmt::Display for Reply<'a> {
             ErrCannotDoCommand972 { client } => {
                 write!(f, "972 {} :Can not do command", client)
             }
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             ErrNickRegistered465 { client } => {
                 write!(f, "465 {} :Nick registrado. Usa /NS IDENTIFY <nickname> <password> o /PASS <password>", client)
             }
@@ -1443,9 +1664,37 @@ mod test {
                 }
             )
         );
+        assert_eq!(
+            "271 <client> <mask>",
+            format!(
+                "{}",
+                RplSileList271 {
+                    client: "<client>",
+                    mask: "<mask>"
+                }
+            )
+        );
+        assert_eq!(
+            "272 <client> :End of Silence List",
+            format!("{}", RplEndOfSileList272 { client: "<client>" })
+        );
         //assert_eq!("276 <client> <nick> :has client certificate fingerprint <fingerprint>",
         //    format!("{}", RplWhoIsCertFP276{ client: "<client>", nick: "<nick>",
         //        fingerprint: "<fingerprint>" }));
+        assert_eq!(
+            "281 <client> <nick>",
+            format!(
+                "{}",
+                RplAcceptList281 {
+                    client: "<client>",
+                    nick: "<nick>"
+                }
+            )
+        );
+        assert_eq!(
+            "282 <client> :End of /ACCEPT list",
+            format!("{}", RplEndOfAccept282 { client: "<client>" })
+        );
         assert_eq!(
             "301 <client> <nick> :<message>",
             format!(
@@ -2075,6 +2324,30 @@ mod test {
             "451 <client> :You have not registered",
             format!("{}", ErrNotRegistered451 { client: "<client>" })
         );
+        assert_eq!(
+            "456 <client> :Accept list is full",
+            format!("{}", ErrAcceptFull456 { client: "<client>" })
+        );
+        assert_eq!(
+            "457 <client> <nick> :is already on your accept list",
+            format!(
+                "{}",
+                ErrAcceptExist457 {
+                    client: "<client>",
+                    nick: "<nick>"
+                }
+            )
+        );
+        assert_eq!(
+            "458 <client> <nick> :is not on your accept list",
+            format!(
+                "{}",
+                ErrAcceptNot458 {
+                    client: "<client>",
+                    nick: "<nick>"
+                }
+            )
+        );
         assert_eq!(
             "461 <client> <command> :Not enough parameters",
             format!(
@@ -2146,6 +2419,68 @@ mod test {
                 }
             )
         );
+        assert_eq!(
+            "478 <client> <channel> b :Channel list is full",
+            format!(
+                "{}",
+                ErrBanListFull478 {
+                    client: "<client>",
+                    channel: "<channel>",
+                    mode: 'b'
+                }
+            )
+        );
+        assert_eq!(
+            "470 <client> <channel> <target> :Forwarding to another channel",
+            format!(
+                "{}",
+                ErrLinkChannel470 {
+                    client: "<client>",
+                    channel: "<channel>",
+                    target: "<target>"
+                }
+            )
+        );
+        assert_eq!(
+            "489 <client> <channel> :Cannot join channel; SSL users only (+z)",
+            format!(
+                "{}",
+                ErrSecureOnlyChan489 {
+                    client: "<client>",
+                    channel: "<channel>"
+                }
+            )
+        );
+        assert_eq!(
+            "477 <client> <channel> :Cannot join channel (+R) - you need to be identified with services",
+            format!(
+                "{}",
+                ErrNeedReggedNick477 {
+                    client: "<client>",
+                    channel: "<channel>"
+                }
+            )
+        );
+        assert_eq!(
+            "480 <client> <channel> :Cannot join channel (+j) - throttle exceeded, try again later",
+            format!(
+                "{}",
+                ErrThrottle480 {
+                    client: "<client>",
+                    channel: "<channel>"
+                }
+            )
+        );
+        assert_eq!(
+            "480 <client> :Cannot knock on <channel> (+K)",
+            format!(
+                "{}",
+                ErrCannotKnock480 {
+                    client: "<client>",
+                    channel: "<channel>"
+                }
+            )
+        );
         assert_eq!(
             "481 <client> :Permission Denied- You're not an IRC operator",
             format!("{}", ErrNoPrivileges481 { client: "<client>" })
@@ -2164,6 +2499,16 @@ mod test {
             "484 <client> :Your connection is restricted!",
             format!("{}", ErrYourConnRestricted484 { client: "<client>" })
         );
+        assert_eq!(
+            "486 <client> <nick> :You must identify to a registered nick to private message this person",
+            format!(
+                "{}",
+                ErrNoNonReg486 {
+                    client: "<client>",
+                    nick: "<nick>"
+                }
+            )
+        );
         assert_eq!(
             "491 <client> :No O-lines for your host",
             format!("{}", ErrNoOperHost491 { client: "<client>" })
@@ -2176,6 +2521,16 @@ mod test {
             "502 <client> :Cant change mode for other users",
             format!("{}", ErrUsersDontMatch502 { client: "<client>" })
         );
+        assert_eq!(
+            "511 <client> <mask> :Your silence list is full",
+            format!(
+                "{}",
+                ErrSileListFull511 {
+                    client: "<client>",
+                    mask: "<mask>"
+                }
+            )
+        );
         assert_eq!(
             "524 <client> <subject> :No help available on this topic",
             format!(
@@ -2256,6 +2611,125 @@ mod test {
                 }
             )
         );
+        assert_eq!(
+            "710 <client> <channel> <source> :has asked for an invite.",
+            format!(
+                "{}",
+                RplKnock710 {
+                    client: "<client>",
+                    channel: "<channel>",
+                    source: "<source>",
+                    message: None
+                }
+            )
+        );
+        assert_eq!(
+            "710 <client> <channel> <source> :has asked for an invite: <message>",
+            format!(
+                "{}",
+                RplKnock710 {
+                    client: "<client>",
+                    channel: "<channel>",
+                    source: "<source>",
+                    message: Some("<message>")
+                }
+            )
+        );
+        assert_eq!(
+            "711 <client> <channel> :Your KNOCK has been delivered.",
+            format!(
+                "{}",
+                RplKnockDlvr711 {
+                    client: "<client>",
+                    channel: "<channel>"
+                }
+            )
+        );
+        assert_eq!(
+            "712 <client> <channel> :Too many KNOCKs (channel).",
+            format!(
+                "{}",
+                ErrTooManyKnock712 {
+                    client: "<client>",
+                    channel: "<channel>",
+                    reason: "channel"
+                }
+            )
+        );
+        assert_eq!(
+            "713 <client> <channel> :Channel is open.",
+            format!(
+                "{}",
+                ErrChanOpen713 {
+                    client: "<client>",
+                    channel: "<channel>"
+                }
+            )
+        );
+        assert_eq!(
+            "714 <client> <channel> :You are already on that channel.",
+            format!(
+                "{}",
+                ErrKnockOnChan714 {
+                    client: "<client>",
+                    channel: "<channel>"
+                }
+            )
+        );
+        assert_eq!(
+            "716 <client> <nick> :is in +g mode (server-side ignore).",
+            format!(
+                "{}",
+                ErrTargUmodeG716 {
+                    client: "<client>",
+                    nick: "<nick>"
+                }
+            )
+        );
+        assert_eq!(
+            "717 <client> <nick> :has been informed that you messaged them.",
+            format!(
+                "{}",
+                RplTargNotify717 {
+                    client: "<client>",
+                    nick: "<nick>"
+                }
+            )
+        );
+        assert_eq!(
+            "718 <client> <nick> <user@host> :is messaging you, and you have umode +g.",
+            format!(
+                "{}",
+                RplUmodeGMsg718 {
+                    client: "<client>",
+                    nick: "<nick>",
+                    user_host: "<user@host>"
+                }
+            )
+        );
+        assert_eq!(
+            "728 <client> <channel> Z <mask> <who> 1234567",
+            format!(
+                "{}",
+                RplQuietList728 {
+                    client: "<client>",
+                    channel: "<channel>",
+                    mask: "<mask>",
+                    who: "<who>",
+                    set_ts: 1234567
+                }
+            )
+        );
+        assert_eq!(
+            "729 <client> <channel> Z :End of channel quiet list",
+            format!(
+                "{}",
+                RplEndOfQuietList729 {
+                    client: "<client>",
+                    channel: "<channel>"
+                }
+            )
+        );
         //assert_eq!("900 <client> <nick>!~<user>@<host> <account> \
         //    :You are now logged in as <username>",
         //    format!("{}", RplLoggedIn900{ client: "<client>", nick: "<nick>",
@@ -0,0 +1,819 @@
+// botserv.rs - BotServ commands
+//
+// simple-irc-server - simple IRC server
+// Copyright (C) 2022-2024  Mateusz Szpakowski
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+use super::hostserv::validate_vhost;
+use crate::database::{is_bot_kicker, BOT_KICKERS};
+use crate::utils::match_wildcard;
+use serde::ser::StdError;
+use std::ops::DerefMut;
+use std::time::SystemTime;
+use tokio::sync::mpsc::unbounded_channel;
+
+// El kicker CAPS solo actúa en mensajes con al menos estas letras...
+const CAPS_MIN_LETTERS: usize = 10;
+// ...y con este porcentaje de mayúsculas
+const CAPS_PERCENT: usize = 75;
+// Veces seguidas que se puede enviar el mismo mensaje antes de que actúe el kicker REPEAT
+const REPEAT_LIMIT: usize = 3;
+
+impl super::MainState {
+    pub(super) async fn process_botserv<'a>(
+        &self,
+        conn_state: &mut ConnState,
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = conn_state.user_state.client_name().to_string();
+        let nick = if let Some(nick) = &conn_state.user_state.nick {
+            nick.clone()
+        } else {
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :You don't have a nick.")).await?;
+            return Ok(());
+        };
+
+        let subcommand = subcommand.to_lowercase();
+        let is_oper = self.is_ircop(&nick).await;
+        if subcommand == "help" {
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :BotServ - Channel Bot Service")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Available commands:")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  BOTLIST - List available bots")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  ASSIGN <channel> <bot> - Assign a bot to your channel")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  UNASSIGN <channel> - Remove the bot from your channel")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  INFO <channel> - Show the bot settings of a channel")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  SET <channel> GREET <message|OFF> - Message the bot sends to joining users")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  KICK <channel> <CAPS|COLORS|REPEAT|BADWORDS> <ON|OFF> - Configure kickers")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  BADWORDS <channel> <ADD|DEL|LIST> [word] - Manage the bad words list")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Fantasy commands in the channel: !op [nick], !deop [nick], !kick <nick> [reason], !topic <text>")).await?;
+            if is_oper {
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  BOT ADD <nick> <user> <host> <realname> - Create a bot (IRCop)")).await?;
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  BOT DEL <nick> - Delete a bot (IRCop)")).await?;
+            }
+            return Ok(());
+        }
+
+        let db_arc = if let Some(db_arc) = &self.databases.bot_db {
+            db_arc
+        } else {
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Database is not configured.")).await?;
+            return Ok(());
+        };
+
+        match subcommand.as_str() {
+            "bot" if !is_oper => {
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Only IRCops can create or delete bots.")).await?;
+            }
+            "bot" => {
+                let action = params.first().map(|s| s.to_lowercase()).unwrap_or_default();
+                match action.as_str() {
+                    "add" => {
+                        if params.len() < 5 {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS BOT ADD <nick> <user> <host> <realname>")).await?;
+                            return Ok(());
+                        }
+                        let (bot, user, host) = (params[1], params[2], params[3]);
+                        let realname = params[4..].join(" ");
+                        if validate_username(bot).is_err() || validate_username(user).is_err() {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Invalid bot nick or user name.")).await?;
+                            return Ok(());
+                        }
+                        if let Err(reason) = validate_vhost(host, &[]) {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Invalid bot host '{host}': {reason}")).await?;
+                            return Ok(());
+                        }
+                        if self.state.read().await.users.contains_key(&crate::state::structs::to_irccase(bot)) {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :The nick {bot} is already in use.")).await?;
+                            return Ok(());
+                        }
+                        if let Some(nick_db_arc) = &self.databases.nick_db {
+                            if nick_db_arc.read().await.get_nick_info(bot).await?.is_some() {
+                                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :The nick {bot} is registered and cannot be used by a bot.")).await?;
+                                return Ok(());
+                            }
+                        }
+
+                        db_arc.write().await.add_bot(bot, user, host, &realname, SystemTime::now()).await?;
+                        self.create_bot_user(bot, user, host, &realname).await;
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Bot {bot} ({user}@{host}) has been created.")).await?;
+                    }
+                    "del" => {
+                        if params.len() < 2 {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS BOT DEL <nick>")).await?;
+                            return Ok(());
+                        }
+                        let bot = params[1];
+                        if !db_arc.write().await.delete_bot(bot).await? {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :There is no bot named {bot}.")).await?;
+                            return Ok(());
+                        }
+
+                        let mut statem = self.state.write().await;
+                        let state = statem.deref_mut();
+                        state.bot_channels.retain(|_, bot_channel| casefold(&bot_channel.bot) != casefold(bot));
+                        if let Some(bot_user) = state.users.get(&crate::state::structs::to_irccase(bot)) {
+                            let source = bot_user.source.clone();
+                            let channels: Vec<String> = bot_user.channels.iter().cloned().collect();
+                            for channel in &channels {
+                                broadcast_to_channel(state, channel, &source, &format!("PART {channel} :Bot deleted"), Some(bot));
+                            }
+                            state.remove_user(bot);
+                        }
+                        drop(statem);
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Bot {bot} has been deleted.")).await?;
+                    }
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS BOT <ADD|DEL> [args]")).await?;
+                    }
+                }
+            }
+            "botlist" => {
+                let bots = db_arc.read().await.get_bots().await?;
+                if bots.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :There are no bots available.")).await?;
+                    return Ok(());
+                }
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Available bots:")).await?;
+                for (bot, user, host, realname) in bots {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  {bot} ({user}@{host}) - {realname}")).await?;
+                }
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :End of bot list.")).await?;
+            }
+            "assign" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS ASSIGN <channel> <bot>")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                if !self.is_channel_registered(channel).await {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Channel '{channel}' is not registered.")).await?;
+                    return Ok(());
+                }
+                if !self.can_manage_bot(channel, &nick, None).await {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Only the channel founder can assign a bot to '{channel}'.")).await?;
+                    return Ok(());
+                }
+                let bots = db_arc.read().await.get_bots().await?;
+                let bot = if let Some((bot, _, _, _)) = bots.into_iter().find(|(bot, _, _, _)| casefold(bot) == casefold(params[1])) {
+                    bot
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :There is no bot named {}.", params[1])).await?;
+                    return Ok(());
+                };
+
+                db_arc.write().await.assign_bot(channel, &bot).await?;
+                let badwords = db_arc.read().await.get_badwords(channel).await?;
+
+                let mut statem = self.state.write().await;
+                let state = statem.deref_mut();
+                let key = crate::state::structs::to_irccase(channel);
+                let mut bot_channel = state.bot_channels.remove(&key).unwrap_or_default();
+                if !bot_channel.bot.is_empty() && casefold(&bot_channel.bot) != casefold(&bot) {
+                    self.bot_part_channel(state, &bot_channel.bot, channel, "Bot replaced");
+                }
+                bot_channel.bot = bot.clone();
+                bot_channel.badwords = badwords;
+                state.bot_channels.insert(key, bot_channel);
+                self.bot_join_channel(state, &bot, channel);
+                drop(statem);
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Bot {bot} has been assigned to {channel}.")).await?;
+            }
+            "unassign" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS UNASSIGN <channel>")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                if !self.can_manage_bot(channel, &nick, None).await {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Only the channel founder can unassign the bot of '{channel}'.")).await?;
+                    return Ok(());
+                }
+                if !db_arc.write().await.unassign_bot(channel).await? {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Channel '{channel}' has no bot assigned.")).await?;
+                    return Ok(());
+                }
+                self.botserv_unassign(channel).await;
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :The bot of {channel} has been unassigned.")).await?;
+            }
+            "info" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS INFO <channel>")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                let bot_channel = self.state.read().await.bot_channels.get(&crate::state::structs::to_irccase(channel)).cloned();
+                let bot_channel = if let Some(bot_channel) = bot_channel {
+                    bot_channel
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Channel '{channel}' has no bot assigned.")).await?;
+                    return Ok(());
+                };
+                let kickers = BOT_KICKERS
+                    .iter()
+                    .filter(|kicker| bot_channel.kickers.contains(**kicker))
+                    .map(|kicker| kicker.to_uppercase())
+                    .collect::<Vec<_>>();
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Bot information for {channel}:")).await?;
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  Bot: {}", bot_channel.bot)).await?;
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  Greet: {}", bot_channel.greet.as_deref().unwrap_or("(none)"))).await?;
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  Kickers: {}", if kickers.is_empty() { "(none)".to_string() } else { kickers.join(", ") })).await?;
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  Bad words: {}", bot_channel.badwords.len())).await?;
+            }
+            "set" => {
+                if params.len() < 3 || !params[1].eq_ignore_ascii_case("greet") {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS SET <channel> GREET <message|OFF>")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                if !self.check_bot_channel(conn_state, &client, channel, &nick).await? {
+                    return Ok(());
+                }
+                let greet = params[2..].join(" ");
+                let greet = if greet.eq_ignore_ascii_case("off") { None } else { Some(greet) };
+                db_arc.write().await.set_bot_greet(channel, greet.as_deref()).await?;
+                if let Some(bot_channel) = self.state.write().await.bot_channels.get_mut(&crate::state::structs::to_irccase(channel)) {
+                    bot_channel.greet = greet.clone();
+                }
+                if greet.is_some() {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Greet message for {channel} has been set.")).await?;
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Greet message for {channel} has been removed.")).await?;
+                }
+            }
+            "kick" => {
+                if params.len() < 3 {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS KICK <channel> <CAPS|COLORS|REPEAT|BADWORDS> <ON|OFF>")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                let kicker = params[1].to_lowercase();
+                let enabled = match params[2].to_lowercase().as_str() {
+                    "on" => true,
+                    "off" => false,
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS KICK <channel> <CAPS|COLORS|REPEAT|BADWORDS> <ON|OFF>")).await?;
+                        return Ok(());
+                    }
+                };
+                if !is_bot_kicker(&kicker) {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Unknown kicker '{}'. Kickers: CAPS, COLORS, REPEAT, BADWORDS", params[1])).await?;
+                    return Ok(());
+                }
+                if !self.check_bot_channel(conn_state, &client, channel, &nick).await? {
+                    return Ok(());
+                }
+                db_arc.write().await.set_bot_kicker(channel, &kicker, enabled).await?;
+                if let Some(bot_channel) = self.state.write().await.bot_channels.get_mut(&crate::state::structs::to_irccase(channel)) {
+                    if enabled {
+                        bot_channel.kickers.insert(kicker.clone());
+                    } else {
+                        bot_channel.kickers.remove(&kicker);
+                    }
+                }
+                let status = if enabled { "on" } else { "off" };
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :{} kicker for {channel} is now {status}.", kicker.to_uppercase())).await?;
+            }
+            "badwords" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS BADWORDS <channel> <ADD|DEL|LIST> [word]")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                if !self.check_bot_channel(conn_state, &client, channel, &nick).await? {
+                    return Ok(());
+                }
+                match params[1].to_lowercase().as_str() {
+                    "add" | "del" if params.len() < 3 => {
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS BADWORDS <channel> <ADD|DEL> <word>")).await?;
+                    }
+                    "add" => {
+                        let word = params[2].to_lowercase();
+                        db_arc.write().await.add_badword(channel, &word).await?;
+                        if let Some(bot_channel) = self.state.write().await.bot_channels.get_mut(&crate::state::structs::to_irccase(channel)) {
+                            if !bot_channel.badwords.contains(&word) {
+                                bot_channel.badwords.push(word.clone());
+                            }
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :'{word}' added to the bad words list of {channel}.")).await?;
+                    }
+                    "del" => {
+                        let word = params[2].to_lowercase();
+                        if db_arc.write().await.delete_badword(channel, &word).await? {
+                            if let Some(bot_channel) = self.state.write().await.bot_channels.get_mut(&crate::state::structs::to_irccase(channel)) {
+                                bot_channel.badwords.retain(|w| *w != word);
+                            }
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :'{word}' removed from the bad words list of {channel}.")).await?;
+                        } else {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :'{word}' is not in the bad words list of {channel}.")).await?;
+                        }
+                    }
+                    "list" => {
+                        let badwords = db_arc.read().await.get_badwords(channel).await?;
+                        if badwords.is_empty() {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :The bad words list of {channel} is empty.")).await?;
+                            return Ok(());
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Bad words of {channel}:")).await?;
+                        for word in badwords {
+                            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :  {word}")).await?;
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :End of bad words list.")).await?;
+                    }
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Usage: /BS BADWORDS <channel> <ADD|DEL|LIST> [word]")).await?;
+                    }
+                }
+            }
+            _ => {
+                self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Unknown command. Use /BS HELP for a list of commands.")).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Aplica los kickers y los comandos fantasy a un mensaje enviado a un canal con bot
+    pub(super) async fn botserv_channel_message(
+        &self,
+        conn_state: &mut ConnState,
+        channel: &str,
+        text: &str,
+        notice: bool,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let nick = if let Some(nick) = &conn_state.user_state.nick {
+            nick.clone()
+        } else {
+            return Ok(());
+        };
+        let is_oper = self.is_ircop(&nick).await;
+
+        let mut statem = self.state.write().await;
+        let state = statem.deref_mut();
+        let key = crate::state::structs::to_irccase(channel);
+        let chum = if let Some(chum) = state.channels.get(&key).and_then(|chanobj| chanobj.users.get(&crate::state::structs::to_irccase(&nick))) {
+            *chum
+        } else {
+            return Ok(());
+        };
+        let bot_channel = if let Some(bot_channel) = state.bot_channels.get_mut(&key) {
+            bot_channel
+        } else {
+            return Ok(());
+        };
+
+        let repeats = {
+            let last = bot_channel
+                .last_messages
+                .entry(crate::state::structs::to_irccase(&nick))
+                .or_insert_with(|| (String::new(), 0));
+            if last.0.eq_ignore_ascii_case(text) {
+                last.1 += 1;
+            } else {
+                *last = (text.to_string(), 1);
+            }
+            last.1
+        };
+
+        // Los operadores del canal y los IRCops no se ven afectados por los kickers
+        let exempt = is_oper || chum.founder || chum.protected || chum.operator;
+        let bot = bot_channel.bot.clone();
+        if !exempt {
+            if let Some(reason) = kicker_reason(text, &bot_channel.kickers, &bot_channel.badwords, repeats) {
+                bot_channel.last_messages.remove(&crate::state::structs::to_irccase(&nick));
+                self.bot_kick(state, &bot, channel, &nick, reason);
+                return Ok(());
+            }
+        }
+        drop(statem);
+
+        if !notice {
+            if let Some(command) = text.strip_prefix('!') {
+                self.botserv_fantasy(conn_state, &bot, channel, &nick, command).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Comandos fantasy (!op, !deop, !kick, !topic), con los mismos permisos que en ChanServ
+    async fn botserv_fantasy(
+        &self,
+        conn_state: &mut ConnState,
+        bot: &str,
+        channel: &str,
+        nick: &str,
+        command: &str,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or_default().to_lowercase();
+        let args: Vec<&str> = words.collect();
+        let flag = match name.as_str() {
+            "op" | "deop" => 'o',
+            "kick" => 'r',
+            "topic" => 't',
+            _ => return Ok(()),
+        };
+
+        let bot_source = if let Some(bot_user) = self.state.read().await.users.get(&crate::state::structs::to_irccase(bot)) {
+            bot_user.source.clone()
+        } else {
+            return Ok(());
+        };
+        if !self.can_manage_bot(channel, nick, Some(flag)).await {
+            self.feed_msg_source(&mut conn_state.stream, &bot_source, format!("NOTICE {nick} :Access denied.")).await?;
+            return Ok(());
+        }
+
+        match name.as_str() {
+            "op" | "deop" => {
+                let target = args.first().copied().unwrap_or(nick);
+                let op = name == "op";
+                if op && self.secureops_denies(channel, target).await {
+                    self.feed_msg_source(&mut conn_state.stream, &bot_source, format!("NOTICE {nick} :SECUREOPS is enabled on {channel} and {target} has no +o access.")).await?;
+                    return Ok(());
+                }
+                let mut statem = self.state.write().await;
+                let state = statem.deref_mut();
+                let chanobj = if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
+                    chanobj
+                } else {
+                    return Ok(());
+                };
+                let target = if let Some((target, _)) = chanobj.users.get_key_value(&crate::state::structs::to_irccase(target)) {
+                    target.to_string()
+                } else {
+                    drop(statem);
+                    self.feed_msg_source(&mut conn_state.stream, &bot_source, format!("NOTICE {nick} :{target} is not on {channel}.")).await?;
+                    return Ok(());
+                };
+                if op {
+                    chanobj.add_operator(&target);
+                } else {
+                    chanobj.remove_operator(&target);
+                }
+                let mode = if op { "+o" } else { "-o" };
+                broadcast_to_channel(state, channel, &bot_source, &format!("MODE {channel} {mode} {target}"), None);
+            }
+            "kick" => {
+                let target = if let Some(target) = args.first() {
+                    *target
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, &bot_source, format!("NOTICE {nick} :Usage: !kick <nick> [reason]")).await?;
+                    return Ok(());
+                };
+                let reason = if args.len() > 1 {
+                    args[1..].join(" ")
+                } else {
+                    format!("Requested by {nick}")
+                };
+                let mut statem = self.state.write().await;
+                let state = statem.deref_mut();
+                let target = state
+                    .channels
+                    .get(&crate::state::structs::to_irccase(channel))
+                    .and_then(|chanobj| chanobj.users.get_key_value(&crate::state::structs::to_irccase(target)))
+                    .map(|(target, _)| target.to_string());
+                match target {
+                    Some(target) if casefold(&target) != casefold(bot) => {
+                        self.bot_kick(state, bot, channel, &target, &reason);
+                    }
+                    _ => {
+                        drop(statem);
+                        self.feed_msg_source(&mut conn_state.stream, &bot_source, format!("NOTICE {nick} :Cannot kick that user from {channel}.")).await?;
+                    }
+                }
+            }
+            "topic" => {
+                let topic = args.join(" ");
+                {
+                    let mut statem = self.state.write().await;
+                    let state = statem.deref_mut();
+                    if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
+                        chanobj.topic = if topic.is_empty() {
+                            None
+                        } else {
+                            Some(ChannelTopic::new_with_nick(topic.clone(), nick.to_string()))
+                        };
+                    } else {
+                        return Ok(());
+                    }
+                    broadcast_to_channel(state, channel, &bot_source, &format!("TOPIC {channel} :{topic}"), None);
+                }
+                if !topic.is_empty() {
+                    if let Some(db_arc) = &self.databases.chan_db {
+                        let mut db = db_arc.write().await;
+                        if let Ok(Some(_)) = db.get_channel_info(channel).await {
+                            let _ = db.update_channel_info(channel, Some(&topic), Some(nick), Some(SystemTime::now()), None).await;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Crea el usuario del bot. No tiene conexión, así que lo que recibe se descarta.
+    pub(super) async fn create_bot_user(&self, bot: &str, user: &str, host: &str, realname: &str) {
+        let (sender, mut receiver) = unbounded_channel();
+        tokio::spawn(async move { while receiver.recv().await.is_some() {} });
+        let bot_user = User::new_bot(&self.config, bot, user, host, realname, sender);
+        self.state.write().await.users.insert(crate::state::structs::to_irccase(bot), bot_user);
+    }
+
+    // Quita el bot asignado a un canal (BS UNASSIGN o CS DROP)
+    pub(super) async fn botserv_unassign(&self, channel: &str) {
+        let mut statem = self.state.write().await;
+        let state = statem.deref_mut();
+        if let Some(bot_channel) = state.bot_channels.remove(&crate::state::structs::to_irccase(channel)) {
+            self.bot_part_channel(state, &bot_channel.bot, channel, "Bot unassigned");
+        }
+    }
+
+    // Crea al arrancar los bots guardados y los mete en sus canales
+    pub(super) async fn restore_bots(&self) {
+        let db_arc = if let Some(db_arc) = &self.databases.bot_db {
+            db_arc
+        } else {
+            return;
+        };
+        let bots = db_arc.read().await.get_bots().await.unwrap_or_default();
+        for (bot, user, host, realname) in &bots {
+            self.create_bot_user(bot, user, host, realname).await;
+        }
+        let bot_channels = db_arc.read().await.get_bot_channels().await.unwrap_or_default();
+        for (channel, bot, greet, kickers) in bot_channels {
+            let badwords = db_arc.read().await.get_badwords(&channel).await.unwrap_or_default();
+            let mut statem = self.state.write().await;
+            let state = statem.deref_mut();
+            state.bot_channels.insert(
+                crate::state::structs::to_irccase(&channel),
+                BotChannel {
+                    bot: bot.clone(),
+                    greet,
+                    kickers: kickers.into_iter().collect(),
+                    badwords,
+                    last_messages: HashMap::new(),
+                },
+            );
+            self.bot_join_channel(state, &bot, &channel);
+        }
+    }
+
+    // Mete al bot en el canal (creándolo si hace falta) y le da +o
+    pub(super) fn bot_join_channel(&self, state: &mut VolatileState, bot: &str, channel: &str) {
+        let source = if let Some(bot_user) = state.users.get(&crate::state::structs::to_irccase(bot)) {
+            bot_user.source.clone()
+        } else {
+            return;
+        };
+        let key = crate::state::structs::to_irccase(channel);
+        let chname = state.channels.get_key_value(&key).map(|(name, _)| name.to_string()).unwrap_or_else(|| channel.to_string());
+        let chanobj = state.channels.entry(key).or_insert_with(|| {
+            let mut chanobj = Channel::new_on_user_join(bot.to_string());
+            chanobj.modes.registered = true;
+            chanobj
+        });
+        let bot_key = crate::state::structs::to_irccase(bot);
+        if !chanobj.users.contains_key(&bot_key) {
+            chanobj.add_user(&bot.to_string());
+        }
+        chanobj.add_operator(bot);
+
+        broadcast_to_channel(state, &chname, &source, &format!("JOIN {chname}"), Some(bot));
+        broadcast_to_channel(state, &chname, "ChanServ", &format!("MODE {chname} +o {bot}"), Some(bot));
+        if let Some(bot_user) = state.users.get_mut(&bot_key) {
+            bot_user.channels.insert(chname);
+        }
+    }
+
+    pub(super) fn bot_part_channel(&self, state: &mut VolatileState, bot: &str, channel: &str, reason: &str) {
+        let source = if let Some(bot_user) = state.users.get(&crate::state::structs::to_irccase(bot)) {
+            bot_user.source.clone()
+        } else {
+            return;
+        };
+        let chname = if let Some((chname, _)) = state.channels.get_key_value(&crate::state::structs::to_irccase(channel)) {
+            chname.to_string()
+        } else {
+            return;
+        };
+        broadcast_to_channel(state, &chname, &source, &format!("PART {chname} :{reason}"), Some(bot));
+        state.remove_user_from_channel(&chname, bot);
+    }
+
+    // El bot expulsa a un usuario del canal
+    fn bot_kick(&self, state: &mut VolatileState, bot: &str, channel: &str, nick: &str, reason: &str) {
+        let source = if let Some(bot_user) = state.users.get(&crate::state::structs::to_irccase(bot)) {
+            bot_user.source.clone()
+        } else {
+            return;
+        };
+        let chname = if let Some((chname, _)) = state.channels.get_key_value(&crate::state::structs::to_irccase(channel)) {
+            chname.to_string()
+        } else {
+            return;
+        };
+        broadcast_to_channel(state, &chname, &source, &format!("KICK {chname} {nick} :{reason}"), None);
+        state.remove_user_from_channel(&chname, nick);
+    }
+
+    async fn is_channel_registered(&self, channel: &str) -> bool {
+        if let Some(db_arc) = &self.databases.chan_db {
+            return matches!(db_arc.read().await.get_channel_info(channel).await, Ok(Some(_)));
+        }
+        false
+    }
+
+    // El fundador identificado y los IRCops pueden gestionar el bot del canal.
+    // Con `flag` también se admite a quien tenga ese flag de acceso en ChanServ.
+    async fn can_manage_bot(&self, channel: &str, nick: &str, flag: Option<char>) -> bool {
+        if self.is_ircop(nick).await {
+            return true;
+        }
+        let identified = self
+            .state
+            .read()
+            .await
+            .users
+            .get(&crate::state::structs::to_irccase(nick))
+            .is_some_and(|user| user.modes.registered);
+        if !identified {
+            return false;
+        }
+        if let Some(db_arc) = &self.databases.chan_db {
+            if let Ok(Some(channel_info)) = db_arc.read().await.get_channel_info(channel).await {
+                if channel_info.founder == nick {
+                    return true;
+                }
+            }
+        }
+        match flag {
+            Some(flag) => self.has_chanserv_flag(channel, nick, flag).await,
+            None => false,
+        }
+    }
+
+    // Comprueba que el canal tenga bot y que el usuario pueda configurarlo (fundador o flag +s)
+    async fn check_bot_channel(
+        &self,
+        conn_state: &mut ConnState,
+        client: &str,
+        channel: &str,
+        nick: &str,
+    ) -> Result<bool, Box<dyn StdError + Send + Sync>> {
+        if !self.state.read().await.bot_channels.contains_key(&crate::state::structs::to_irccase(channel)) {
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :Channel '{channel}' has no bot assigned.")).await?;
+            return Ok(false);
+        }
+        if !self.can_manage_bot(channel, nick, Some('s')).await {
+            self.feed_msg_source(&mut conn_state.stream, "BotServ", format!("NOTICE {client} :You don't have permission to change the bot settings of '{channel}'.")).await?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+// Envía un mensaje a todos los usuarios de un canal, salvo a `except`
+pub(super) fn broadcast_to_channel(state: &VolatileState, channel: &str, source: &str, msg: &str, except: Option<&str>) {
+    if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
+        for member in chanobj.users.keys() {
+            if except.is_some_and(|except| casefold(member) == casefold(except)) {
+                continue;
+            }
+            if let Some(user) = state.users.get(member) {
+                let _ = user.send_msg_display(source, msg);
+            }
+        }
+    }
+}
+
+// Motivo de la expulsión si el mensaje incumple alguno de los kickers activos
+fn kicker_reason(text: &str, kickers: &HashSet<String>, badwords: &[String], repeats: usize) -> Option<&'static str> {
+    if kickers.contains("caps") && is_caps_abuse(text) {
+        return Some("Turn caps lock OFF!");
+    }
+    if kickers.contains("colors") && text.contains('\x03') {
+        return Some("Don't use colors on this channel!");
+    }
+    if kickers.contains("repeat") && repeats >= REPEAT_LIMIT {
+        return Some("Stop repeating yourself!");
+    }
+    if kickers.contains("badwords") && contains_badword(text, badwords) {
+        return Some("Don't use bad words on this channel!");
+    }
+    None
+}
+
+fn is_caps_abuse(text: &str) -> bool {
+    let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+    let upper = text.chars().filter(|c| c.is_uppercase()).count();
+    letters >= CAPS_MIN_LETTERS && upper * 100 >= letters * CAPS_PERCENT
+}
+
+// Las palabras prohibidas se comparan palabra a palabra y admiten comodines
+fn contains_badword(text: &str, badwords: &[String]) -> bool {
+    text.split(|c: char| !c.is_alphanumeric() && c != '*' && c != '?')
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .any(|word| badwords.iter().any(|badword| match_wildcard(badword, &word)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::*;
+    use super::*;
+
+    #[test]
+    fn test_kicker_reason() {
+        let all: HashSet<String> = BOT_KICKERS.iter().map(|k| k.to_string()).collect();
+        let badwords = vec!["darn".to_string(), "heck*".to_string()];
+        assert_eq!(None, kicker_reason("hello there, how are you?", &all, &badwords, 1));
+        assert!(kicker_reason("HELLO THERE EVERYONE", &all, &badwords, 1).is_some());
+        assert_eq!(None, kicker_reason("OK BYE", &all, &badwords, 1));
+        assert!(kicker_reason("\x034red text", &all, &badwords, 1).is_some());
+        assert!(kicker_reason("again", &all, &badwords, REPEAT_LIMIT).is_some());
+        assert!(kicker_reason("oh darn it", &all, &badwords, 1).is_some());
+        assert!(kicker_reason("what the HECKING", &all, &badwords, 1).is_some());
+        assert_eq!(None, kicker_reason("darned", &all, &badwords, 1));
+        assert_eq!(None, kicker_reason("HELLO THERE EVERYONE", &HashSet::new(), &badwords, 1));
+    }
+
+    #[tokio::test]
+    async fn test_botserv_assign_and_badwords() {
+        let mut config = memory_database_config();
+        config.operators = Some(vec![OperatorConfig {
+            name: "fanny".to_string(),
+            password: argon2_hash_password("Funny"),
+            mask: None,
+        }]);
+        let (main_state, handle, port) = run_test_server(config).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "fanny", "fanny", "Fanny BumBumBum").await;
+            line_stream.send("OPER fanny Funny".to_string()).await.unwrap();
+            line_stream.next().await.unwrap().unwrap();
+            line_stream.send("BS BOT ADD Botty botty bot.host The Channel Bot".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been created").await;
+
+            // el fundador tiene que estar identificado para asignar el bot
+            {
+                let mut line_stream2 = login_to_test_and_skip(port, "ben", "ben", "Ben Founder").await;
+                line_stream2.send("NS REGISTER password ben@example.org".to_string()).await.unwrap();
+                skip_until(&mut line_stream2, "has been registered").await;
+            }
+            let mut line_stream2 = login_to_test_and_skip(port, "benny", "ben", "Ben Founder").await;
+            line_stream2.send("NS IDENTIFY ben password".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "successfully identified").await;
+            line_stream2.send("JOIN #botchan".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, " 366 ").await;
+            line_stream2.send("CS REGISTER #botchan".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "has been registered").await;
+
+            line_stream2.send("BS ASSIGN #botchan Botty".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "Bot Botty has been assigned to #botchan.").await;
+            assert_eq!(
+                ":Botty!botty@bot.host JOIN #botchan".to_string(),
+                skip_until(&mut line_stream2, "JOIN #botchan").await
+            );
+            assert_eq!(
+                ":ChanServ MODE #botchan +o Botty".to_string(),
+                skip_until(&mut line_stream2, "MODE #botchan").await
+            );
+            assert!(main_state.state.read().await.channels[&to_irccase("#botchan")].users[&to_irccase("Botty")].operator);
+
+            line_stream2.send("BS KICK #botchan BADWORDS ON".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "BADWORDS kicker for #botchan is now").await;
+            line_stream2.send("BS BADWORDS #botchan ADD darn".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "added to the bad words list").await;
+
+            let mut line_stream3 = login_to_test_and_skip(port, "jim", "jim", "Jim Rude").await;
+            line_stream3.send("JOIN #botchan".to_string()).await.unwrap();
+            skip_until(&mut line_stream3, " 366 ").await;
+            line_stream3.send("PRIVMSG #botchan :hello everyone".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "PRIVMSG #botchan :hello everyone").await;
+            line_stream3.send("PRIVMSG #botchan :oh darn it".to_string()).await.unwrap();
+            assert_eq!(
+                ":Botty!botty@bot.host KICK #botchan jim :Don't use bad words on this channel!".to_string(),
+                skip_until(&mut line_stream3, "KICK").await
+            );
+            assert!(!main_state.state.read().await.channels[&to_irccase("#botchan")].users.contains_key(&to_irccase("jim")));
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+}
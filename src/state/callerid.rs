@@ -0,0 +1,238 @@
+// callerid.rs - caller-ID (+g) with ACCEPT, +R and SILENCE
+//
+// simple-irc-server - simple IRC server
+// Copyright (C) 2022-2024  Mateusz Szpakowski
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+use serde::ser::StdError;
+use std::ops::DerefMut;
+
+impl super::MainState {
+    pub(super) async fn process_accept<'a>(
+        &self,
+        conn_state: &mut ConnState,
+        nicknames: Vec<&'a str>,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = conn_state.user_state.client_name();
+        let mut statem = self.state.write().await;
+        let state = statem.deref_mut();
+        let user_nick = conn_state.user_state.nick.as_ref().unwrap();
+
+        for nick in nicknames {
+            if nick == "*" {
+                let user = state.users.get(&crate::state::structs::to_irccase(user_nick)).unwrap();
+                for accepted in &user.accept {
+                    self.feed_msg(&mut conn_state.stream, RplAcceptList281 { client, nick: accepted })
+                        .await?;
+                }
+                self.feed_msg(&mut conn_state.stream, RplEndOfAccept282 { client })
+                    .await?;
+            } else if let Some(nick) = nick.strip_prefix('-') {
+                let user = state.users.get_mut(&crate::state::structs::to_irccase(user_nick)).unwrap();
+                if !user.accept.remove(&crate::state::structs::to_irccase(nick)) {
+                    self.feed_msg(&mut conn_state.stream, ErrAcceptNot458 { client, nick })
+                        .await?;
+                }
+            } else if !state.users.contains_key(&crate::state::structs::to_irccase(nick)) {
+                self.feed_msg(&mut conn_state.stream, ErrNoSuchNick401 { client, nick })
+                    .await?;
+            } else {
+                let user = state.users.get_mut(&crate::state::structs::to_irccase(user_nick)).unwrap();
+                if user.accept.contains(&crate::state::structs::to_irccase(nick)) {
+                    self.feed_msg(&mut conn_state.stream, ErrAcceptExist457 { client, nick })
+                        .await?;
+                } else if user.accept.len() >= self.config.limits.max_accept {
+                    self.feed_msg(&mut conn_state.stream, ErrAcceptFull456 { client })
+                        .await?;
+                } else {
+                    user.accept.insert(crate::state::structs::to_irccase(nick));
+                    // si vuelve a quedar fuera de la lista se le avisará otra vez
+                    user.callerid_notified.remove(&crate::state::structs::to_irccase(nick));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) async fn process_silence<'a>(
+        &self,
+        conn_state: &mut ConnState,
+        mask: Option<&'a str>,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = conn_state.user_state.client_name();
+        let mut state = self.state.write().await;
+        let user_nick = conn_state.user_state.nick.as_ref().unwrap();
+        let user = state.users.get_mut(&crate::state::structs::to_irccase(user_nick)).unwrap();
+
+        let Some(mask) = mask else {
+            for mask in &user.silence {
+                self.feed_msg(&mut conn_state.stream, RplSileList271 { client, mask })
+                    .await?;
+            }
+            self.feed_msg(&mut conn_state.stream, RplEndOfSileList272 { client })
+                .await?;
+            return Ok(());
+        };
+
+        if let Some(mask) = mask.strip_prefix('-') {
+            let mask = normalize_sourcemask(mask);
+            if let Some(pos) = user.silence.iter().position(|m| m.eq_ignore_ascii_case(&mask)) {
+                user.silence.remove(pos);
+                self.feed_msg_source(
+                    &mut conn_state.stream,
+                    &conn_state.user_state.source,
+                    format!("SILENCE -{mask}"),
+                )
+                .await?;
+            }
+        } else {
+            let mask = normalize_sourcemask(mask.strip_prefix('+').unwrap_or(mask));
+            if user.silence.iter().any(|m| m.eq_ignore_ascii_case(&mask)) {
+                return Ok(());
+            }
+            if user.silence.len() >= self.config.limits.max_silence {
+                self.feed_msg(&mut conn_state.stream, ErrSileListFull511 { client, mask: &mask })
+                    .await?;
+            } else {
+                user.silence.push(mask.clone());
+                self.feed_msg_source(
+                    &mut conn_state.stream,
+                    &conn_state.user_state.source,
+                    format!("SILENCE +{mask}"),
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_command_privmsg_caller_id() {
+        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+
+        {
+            let mut line_stream =
+                login_to_test_and_skip(port, "alan", "alan", "Alan Bodarski").await;
+            let mut line_stream2 =
+                login_to_test_and_skip(port, "bowie", "bowie", "Bowie Catcher").await;
+
+            line_stream.send("MODE alan +g".to_string()).await.unwrap();
+            assert_eq!(
+                ":alan!~alan@127.0.0.1 MODE alan +g".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
+            line_stream2.send("PRIVMSG alan :Hello".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 716 bowie alan :is in +g mode (server-side ignore).".to_string(),
+                line_stream2.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 717 bowie alan :has been informed that you messaged them.".to_string(),
+                line_stream2.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 718 alan bowie bowie@127.0.0.1 :is messaging you, and you have umode +g."
+                    .to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            // sólo se avisa una vez
+            line_stream2.send("PRIVMSG alan :Hello?".to_string()).await.unwrap();
+
+            line_stream.send("ACCEPT bowie,-carl,*".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 458 alan carl :is not on your accept list".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 281 alan bowie".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 282 alan :End of /ACCEPT list".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            line_stream2.send("PRIVMSG alan :Hello!".to_string()).await.unwrap();
+            assert_eq!(
+                ":bowie!~bowie@127.0.0.1 PRIVMSG alan :Hello!".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
+            line_stream.send("MODE alan -g+R".to_string()).await.unwrap();
+            assert_eq!(
+                ":alan!~alan@127.0.0.1 MODE alan +R-g".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            line_stream2.send("PRIVMSG alan :Hello!!".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 486 bowie alan :You must identify to a registered nick to private \
+                    message this person"
+                    .to_string(),
+                line_stream2.next().await.unwrap().unwrap()
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_command_silence() {
+        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+
+        {
+            let mut line_stream =
+                login_to_test_and_skip(port, "alan", "alan", "Alan Bodarski").await;
+            let mut line_stream2 =
+                login_to_test_and_skip(port, "bowie", "bowie", "Bowie Catcher").await;
+
+            line_stream.send("SILENCE +bowie".to_string()).await.unwrap();
+            assert_eq!(
+                ":alan!~alan@127.0.0.1 SILENCE +bowie!*@*".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            line_stream.send("SILENCE".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 271 alan bowie!*@*".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 272 alan :End of Silence List".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
+            line_stream2.send("PRIVMSG alan :Hello".to_string()).await.unwrap();
+            line_stream.send("SILENCE -bowie".to_string()).await.unwrap();
+            assert_eq!(
+                ":alan!~alan@127.0.0.1 SILENCE -bowie!*@*".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            line_stream2.send("PRIVMSG alan :Hello again".to_string()).await.unwrap();
+            assert_eq!(
+                ":bowie!~bowie@127.0.0.1 PRIVMSG alan :Hello again".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+}
@@ -18,11 +18,18 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
 use super::*;
+use crate::database::{AccessEntry, NickRecord};
 use std::collections::HashMap;
 use std::error::Error;
 use std::ops::DerefMut;
 use std::time::{SystemTime, UNIX_EPOCH};
-use unicase::UniCase;
+
+// Segundos entre dos KNOCK del mismo usuario al mismo canal y entre dos KNOCK
+// cualesquiera al mismo canal
+const KNOCK_USER_DELAY: u64 = 300;
+const KNOCK_CHANNEL_DELAY: u64 = 60;
+// canales por cada parte de la respuesta a LIST
+const LIST_CHUNK_SIZE: usize = 100;
 
 impl super::MainState {
     pub(super) async fn process_join<'a>(
@@ -32,6 +39,26 @@ impl super::MainState {
         keys_opt: Option<Vec<&'a str>>,
         account_opt: Option<&'a str>,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        // los canales con +L que están llenos o con +i mandan al usuario a otro canal,
+        // sin encadenar redirecciones
+        let redirects = self.join_channels(conn_state, channels, keys_opt, account_opt, true).await?;
+        if !redirects.is_empty() {
+            let redirects: Vec<&str> = redirects.iter().map(String::as_str).collect();
+            self.join_channels(conn_state, redirects, None, account_opt, false).await?;
+        }
+        Ok(())
+    }
+
+    // Devuelve los canales a los que hay que redirigir al usuario si redirect está activo
+    async fn join_channels<'a>(
+        &self,
+        conn_state: &mut ConnState,
+        channels: Vec<&'a str>,
+        keys_opt: Option<Vec<&'a str>>,
+        account_opt: Option<&'a str>,
+        redirect: bool,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let mut redirects = vec![];
         let mut statem = self.state.write().await;
         let state = statem.deref_mut();
         
@@ -43,7 +70,7 @@ impl super::MainState {
         };
         
         // Verificar que el usuario exista en el estado
-        let user_joined = if let Some(user) = state.users.get(&crate::state::structs::to_unicase(&user_nick)) {
+        let user_joined = if let Some(user) = state.users.get(&crate::state::structs::to_irccase(&user_nick)) {
             user.channels.len()
         } else {
             return Err("Usuario no encontrado en el estado".into());
@@ -52,18 +79,107 @@ impl super::MainState {
         let mut join_count = user_joined;
 
         let mut joined_created = vec![];
+        // canales en los que el usuario coincide con una entrada AKICK: (canal, máscara de ban, motivo)
+        let mut akicked = vec![];
 
         {
             let client = conn_state.user_state.client_name();
-            let user = if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(user_nick.as_str())) {
+            let user = if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(user_nick.as_str())) {
                 user
             } else {
                 return Err("Usuario no encontrado en el estado".into());
             };
             for (i, chname_str) in channels.iter().enumerate() {
                 let chname = chname_str.to_string();
-                let (join, create) = if let Some(channel) = state.channels.get(&crate::state::structs::to_unicase(&chname)) {
+
+                // Los canales prohibidos (CS FORBID) o suspendidos (CS SUSPEND) solo admiten IRCops
+                if !user.modes.is_local_oper() {
+                    if let Some(reason) = self.channel_join_denied(&chname).await {
+                        self.feed_msg(
+                            &mut conn_state.stream,
+                            ErrBannedFromChan474 {
+                                client,
+                                channel: chname_str,
+                            },
+                        )
+                        .await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{reason}"))
+                            .await?;
+                        joined_created.push((false, false));
+                        continue;
+                    }
+                    if let Some(reason) = state.jupes.get(&crate::state::structs::to_irccase(&chname)) {
+                        self.feed_msg(
+                            &mut conn_state.stream,
+                            ErrBannedFromChan474 {
+                                client,
+                                channel: chname_str,
+                            },
+                        )
+                        .await?;
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Channel {chname} is juped: {reason}"))
+                            .await?;
+                        joined_created.push((false, false));
+                        continue;
+                    }
+                }
+
+                // Verificar la lista AKICK de ChanServ antes que cualquier otro modo
+                {
+                    let account = if user.modes.registered { Some(user_nick.as_str()) } else { None };
+                    if let Some((mask, reason)) = self.find_akick(&chname, &conn_state.user_state.source, account).await {
+                        self.feed_msg(
+                            &mut conn_state.stream,
+                            ErrBannedFromChan474 {
+                                client,
+                                channel: chname_str,
+                            },
+                        )
+                        .await?;
+                        let reason = reason.unwrap_or_else(|| super::chanserv::AKICK_DEFAULT_REASON.to_string());
+                        self.feed_msg_source(
+                            &mut conn_state.stream,
+                            "ChanServ",
+                            format!("NOTICE {client} :You are on the AKICK list of {chname}: {reason}"),
+                        )
+                        .await?;
+                        let ban_mask = super::chanserv::akick_ban_mask(&mask, &conn_state.user_state.source);
+                        akicked.push((chname, ban_mask, reason));
+                        joined_created.push((false, false));
+                        continue;
+                    }
+                }
+
+                // con RESTRICTED solo pueden entrar los usuarios con acceso en ChanServ
+                if !user.modes.is_local_oper()
+                    && self.chanserv_setting(&chname, "restricted").await
+                    && !self.has_chanserv_access(&chname, &user_nick).await
+                {
+                    self.feed_msg(
+                        &mut conn_state.stream,
+                        ErrBannedFromChan474 {
+                            client,
+                            channel: chname_str,
+                        },
+                    )
+                    .await?;
+                    self.feed_msg_source(
+                        &mut conn_state.stream,
+                        "ChanServ",
+                        format!("NOTICE {client} :{chname} is restricted to users on its access list."),
+                    )
+                    .await?;
+                    joined_created.push((false, false));
+                    continue;
+                }
+
+                // el flag +i de ChanServ permite entrar en canales con +i sin invitación
+                let chanserv_invite = self.has_chanserv_flag(&chname, &user_nick, 'i').await;
+
+                let (join, create) = if let Some(channel) = state.channels.get(&crate::state::structs::to_irccase(&chname)) {
                     // if already created
+                    let ban_target = user.ban_target(&conn_state.user_state.source);
+                    let inherited = inherited_ban(&state.channels, &ban_target);
                     let do_join = if let Some(key) = &channel.modes.key {
                         if let Some(ref keys) = keys_opt {
                             // check key
@@ -94,92 +210,83 @@ impl super::MainState {
                         }
                     } else {
                         // Verificar si el canal está registrado en ChanServ y tiene clave configurada
-                        #[cfg(any(feature = "sqlite", feature = "mysql"))]
-                        let do_join = {
-                            if let Some(db_arc) = &self.databases.chan_db {
-                                if let Ok(Some(channel_info)) = db_arc.read().await.get_channel_info(&chname).await {
-                                    // Verificar si hay modos almacenados con clave o modo +i
-                                    if let Some(modes_str) = &channel_info.3 {
-                                        // Primero, verificar modo +i (invite-only)
-                                        if modes_str.contains("i") {
-                                            // Si el usuario no está invitado ni tiene excepción, rechazar
-                                            let invitado = user.invited_to.contains(&chname)
-                                                || channel.modes.invite_exception.as_ref().map_or(false, |e| {
-                                                    e.iter().any(|e| match_wildcard(e, &conn_state.user_state.source))
-                                                });
-                                            if !invitado {
-                                                self.feed_msg(
-                                                    &mut conn_state.stream,
-                                                    ErrInviteOnlyChan473 {
-                                                        client,
-                                                        channel: chname_str,
-                                                    },
-                                                )
-                                                .await?;
-                                                return Ok(()); // No permitir join, saltar al siguiente canal
-                                            }
+                        if let Some(db_arc) = &self.databases.chan_db {
+                            if let Ok(Some(channel_info)) = db_arc.read().await.get_channel_info(&chname).await {
+                                // Verificar si hay modos almacenados con clave o modo +i
+                                if let Some(modes_str) = &channel_info.modes {
+                                    // Primero, verificar modo +i (invite-only)
+                                    if modes_str.contains("i") {
+                                        // Si el usuario no está invitado ni tiene excepción, rechazar
+                                        let invitado = user.invited_to.contains(&chname)
+                                            || chanserv_invite
+                                            || channel.modes.invite_excepted(&ban_target, &inherited);
+                                        if !invitado {
+                                            self.feed_msg(
+                                                &mut conn_state.stream,
+                                                ErrInviteOnlyChan473 {
+                                                    client,
+                                                    channel: chname_str,
+                                                },
+                                            )
+                                            .await?;
+                                            return Ok(redirects); // No permitir join, saltar al siguiente canal
                                         }
-                                        // Luego, verificar modo +k (clave)
-                                        if modes_str.contains("k") {
-                                            if let Some(ref keys) = keys_opt {
-                                                // Extraer la clave del string de modos
-                                                if let Some(key) = self.extract_key_from_modes(modes_str) {
-                                                    if key != keys[i] {
-                                                        self.feed_msg(
-                                                            &mut conn_state.stream,
-                                                            ErrBadChannelKey475 {
-                                                                client,
-                                                                channel: chname_str,
-                                                            },
-                                                        )
-                                                        .await?;
-                                                        return Ok(());
-                                                    } else {
-                                                        true
-                                                    }
+                                    }
+                                    // Luego, verificar modo +k (clave)
+                                    if modes_str.contains("k") {
+                                        if let Some(ref keys) = keys_opt {
+                                            // Extraer la clave del string de modos
+                                            if let Some(key) = self.extract_key_from_modes(modes_str) {
+                                                if key != keys[i] {
+                                                    self.feed_msg(
+                                                        &mut conn_state.stream,
+                                                        ErrBadChannelKey475 {
+                                                            client,
+                                                            channel: chname_str,
+                                                        },
+                                                    )
+                                                    .await?;
+                                                    return Ok(redirects);
                                                 } else {
-                                                    // No se pudo extraer la clave, permitir entrada
                                                     true
                                                 }
                                             } else {
-                                                // No se proporcionó clave para canal con +k
-                                                self.feed_msg(
-                                                    &mut conn_state.stream,
-                                                    ErrBadChannelKey475 {
-                                                        client,
-                                                        channel: chname_str,
-                                                    },
-                                                )
-                                                .await?;
-                                                false
+                                                // No se pudo extraer la clave, permitir entrada
+                                                true
                                             }
                                         } else {
-                                            // No tiene modo +k, permitir entrada
-                                            true
+                                            // No se proporcionó clave para canal con +k
+                                            self.feed_msg(
+                                                &mut conn_state.stream,
+                                                ErrBadChannelKey475 {
+                                                    client,
+                                                    channel: chname_str,
+                                                },
+                                            )
+                                            .await?;
+                                            false
                                         }
                                     } else {
-                                        // No hay modos almacenados, permitir entrada
+                                        // No tiene modo +k, permitir entrada
                                         true
                                     }
                                 } else {
-                                    // Canal no registrado, usar lógica normal
+                                    // No hay modos almacenados, permitir entrada
                                     true
                                 }
                             } else {
-                                // Base de datos no disponible, usar lógica normal
+                                // Canal no registrado, usar lógica normal
                                 true
                             }
-                        };
-                        
-                        #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-                        let do_join = true;
-                        
-                        do_join
+                        } else {
+                            // Base de datos no disponible, usar lógica normal
+                            true
+                        }
                     };
 
                     // check whether user is banned
                     let do_join = do_join && {
-                        if !channel.modes.banned(&conn_state.user_state.source) {
+                        if !channel.modes.banned_user(&ban_target, false, &inherited) {
                             true
                         } else {
                             self.feed_msg(
@@ -198,12 +305,22 @@ impl super::MainState {
                     let do_join = do_join && {
                         if !channel.modes.invite_only
                             || user.invited_to.contains(&chname)
-                            || channel.modes.invite_exception.as_ref().map_or(false, |e| {
-                                e.iter()
-                                    .any(|e| match_wildcard(e, &conn_state.user_state.source))
-                            })
+                            || chanserv_invite
+                            || channel.modes.invite_excepted(&ban_target, &inherited)
                         {
                             true
+                        } else if let Some(target) = channel.modes.redirect.as_ref().filter(|_| redirect) {
+                            self.feed_msg(
+                                &mut conn_state.stream,
+                                ErrLinkChannel470 {
+                                    client,
+                                    channel: chname_str,
+                                    target,
+                                },
+                            )
+                            .await?;
+                            redirects.push(target.clone());
+                            false
                         } else {
                             self.feed_msg(
                                 &mut conn_state.stream,
@@ -226,6 +343,18 @@ impl super::MainState {
                         };
                         if not_full {
                             true
+                        } else if let Some(target) = channel.modes.redirect.as_ref().filter(|_| redirect) {
+                            self.feed_msg(
+                                &mut conn_state.stream,
+                                ErrLinkChannel470 {
+                                    client,
+                                    channel: chname_str,
+                                    target,
+                                },
+                            )
+                            .await?;
+                            redirects.push(target.clone());
+                            false
                         } else {
                             self.feed_msg(
                                 &mut conn_state.stream,
@@ -238,8 +367,57 @@ impl super::MainState {
                             false
                         }
                     };
+                    // check whether user has secure connection if channel is +z
+                    let do_join = do_join && {
+                        if !channel.modes.secure_only || conn_state.is_secure() || user.modes.is_local_oper() {
+                            true
+                        } else {
+                            self.feed_msg(
+                                &mut conn_state.stream,
+                                ErrSecureOnlyChan489 {
+                                    client,
+                                    channel: chname_str,
+                                },
+                            )
+                            .await?;
+                            false
+                        }
+                    };
+                    // check whether user is identified if channel is +R
+                    let do_join = do_join && {
+                        if !channel.modes.registered_only || user.modes.registered || user.modes.is_local_oper() {
+                            true
+                        } else {
+                            self.feed_msg(
+                                &mut conn_state.stream,
+                                ErrNeedReggedNick477 {
+                                    client,
+                                    channel: chname_str,
+                                },
+                            )
+                            .await?;
+                            false
+                        }
+                    };
+                    // check whether channel join throttle (+j) is not exceeded
+                    let do_join = do_join && {
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                        if user.modes.is_local_oper() || !channel.join_throttled(now) {
+                            true
+                        } else {
+                            self.feed_msg(
+                                &mut conn_state.stream,
+                                ErrThrottle480 {
+                                    client,
+                                    channel: chname_str,
+                                },
+                            )
+                            .await?;
+                            false
+                        }
+                    };
                     // check whether user is not alrady joined
-                    let do_join = do_join && !channel.users.contains_key(&crate::state::structs::to_unicase(&user_nick));
+                    let do_join = do_join && !channel.users.contains_key(&crate::state::structs::to_irccase(&user_nick));
 
                     // Verificar modo +O (solo IRCops)
                     let do_join = if channel.modes.only_ircops {
@@ -266,15 +444,15 @@ impl super::MainState {
                     }
                 } else {
                     // if new channel
-                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                     {
                         let mut permitido = true;
+                        let mut clave = None;
                         if let Some(db_arc) = &self.databases.chan_db {
                             if let Ok(Some(channel_info)) = db_arc.read().await.get_channel_info(&chname).await {
-                                if let Some(modes_str) = &channel_info.3 {
+                                if let Some(modes_str) = &channel_info.modes {
                                     // Verificar +i (invite-only)
                                     if modes_str.contains("i") {
-                                        let invitado = user.invited_to.contains(&chname);
+                                        let invitado = user.invited_to.contains(&chname) || chanserv_invite;
                                         if !invitado {
                                             self.feed_msg(
                                                 &mut conn_state.stream,
@@ -286,31 +464,20 @@ impl super::MainState {
                                             permitido = false;
                                         }
                                     }
-                                    // Verificar +k (clave)
+                                    // +k (clave): se comprueba abajo junto con la clave guardada
                                     if modes_str.contains("k") {
-                                        if let Some(ref keys) = keys_opt {
-                                            if let Some(key) = self.extract_key_from_modes(modes_str) {
-                                                if key != keys[i] {
-                                                    self.feed_msg(
-                                                        &mut conn_state.stream,
-                                                        ErrBadChannelKey475 {
-                                                            client,
-                                                            channel: chname_str,
-                                                        },
-                                                    ).await?;
-                                                    permitido = false;
-                                                }
-                                            }
-                                        } else {
-                                            self.feed_msg(
-                                                &mut conn_state.stream,
-                                                ErrBadChannelKey475 {
-                                                    client,
-                                                    channel: chname_str,
-                                                },
-                                            ).await?;
-                                            permitido = false;
-                                        }
+                                        clave = self.extract_key_from_modes(modes_str);
+                                    }
+                                    // Verificar +R (solo usuarios identificados)
+                                    if modes_str.contains('R') && !user.modes.registered && !user.modes.is_local_oper() {
+                                        self.feed_msg(
+                                            &mut conn_state.stream,
+                                            ErrNeedReggedNick477 {
+                                                client,
+                                                channel: chname_str,
+                                            },
+                                        ).await?;
+                                        permitido = false;
                                     }
                                     // Verificar +O (solo IRCops)
                                     if modes_str.contains("O") && !self.is_ircop(&user_nick).await {
@@ -326,16 +493,47 @@ impl super::MainState {
                                 }
                             }
                         }
+                        // Bans y clave guardados del canal registrado. La clave guardada con MODE
+                        // sustituye a la de los modos de ChanServ y solo se comprueba una vez.
+                        if permitido {
+                            if let Some(saved) = self.saved_channel_modes(&chname).await {
+                                let ban_target = user.ban_target(&conn_state.user_state.source);
+                                if saved.banned(&ban_target, &inherited_ban(&state.channels, &ban_target)) {
+                                    self.feed_msg(
+                                        &mut conn_state.stream,
+                                        ErrBannedFromChan474 {
+                                            client,
+                                            channel: chname_str,
+                                        },
+                                    ).await?;
+                                    permitido = false;
+                                }
+                                if saved.key.is_some() {
+                                    clave = saved.key;
+                                }
+                            }
+                        }
+                        if permitido {
+                            if let Some(clave) = &clave {
+                                let key_ok = keys_opt.as_ref().is_some_and(|keys| keys.get(i).is_some_and(|k| k == clave));
+                                if !key_ok {
+                                    self.feed_msg(
+                                        &mut conn_state.stream,
+                                        ErrBadChannelKey475 {
+                                            client,
+                                            channel: chname_str,
+                                        },
+                                    ).await?;
+                                    permitido = false;
+                                }
+                            }
+                        }
                         if permitido {
                     (true, true)
                         } else {
                             (false, false)
                         }
                     }
-                    #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-                    {
-                        (true, true)
-                    }
                 };
 
                 // check whether user is not in max channels
@@ -375,48 +573,24 @@ impl super::MainState {
                         );
                         state
                             .channels
-                            .insert(crate::state::structs::to_unicase(&chname), Channel::new_on_user_join(user_nick.clone()));
+                            .insert(crate::state::structs::to_irccase(&chname), Channel::new_on_user_join(user_nick.clone()));
                         
                         // Verificar si el canal está registrado y aplicar modos de ChanServ (para canales recién creados)
-                        #[cfg(any(feature = "sqlite", feature = "mysql"))]
                         {
                             if let Some(db_arc) = &self.databases.chan_db {
                                 if let Ok(Some(channel_info)) = db_arc.read().await.get_channel_info(&chname).await {
                                     // El canal está registrado
-                                    let creator_nick = &channel_info.0; // El primer elemento es el creador
-                                    let chanobj = state.channels.get_mut(&crate::state::structs::to_unicase(&chname)).unwrap();
-                                    let user_chum = chanobj.users.get_mut(&crate::state::structs::to_unicase(&user_nick)).unwrap();
+                                    let creator_nick = &channel_info.founder;
+                                    let keeptopic = self.chanserv_setting(&chname, "keeptopic").await;
+                                    let guard = self.chanserv_setting(&chname, "guard").await;
+                                    let saved = self.saved_channel_modes(&chname).await;
+                                    let chanobj = state.channels.get_mut(&crate::state::structs::to_irccase(&chname)).unwrap();
+                                    let user_chum = chanobj.users.get_mut(&crate::state::structs::to_irccase(&user_nick)).unwrap();
                                     
                                     // Verificar acceso de ChanServ para asignar modos
-                                    if let Ok(Some((access_level, _, _))) = db_arc.read().await.get_channel_access(&chname, &user_nick).await {
-                                        // Aplicar el nivel de acceso según ChanServ
-                                        match access_level.as_str() {
-                                            "sop" => {
-                                                user_chum.protected = true;
-                                                let mut protecteds = chanobj.modes.protecteds.take().unwrap_or_default();
-                                                protecteds.insert(user_nick.clone());
-                                                chanobj.modes.protecteds = Some(protecteds);
-                                            }
-                                            "aop" => {
-                                                user_chum.operator = true;
-                                                let mut operators = chanobj.modes.operators.take().unwrap_or_default();
-                                                operators.insert(user_nick.clone());
-                                                chanobj.modes.operators = Some(operators);
-                                            }
-                                            "hop" => {
-                                                user_chum.half_oper = true;
-                                                let mut half_operators = chanobj.modes.half_operators.take().unwrap_or_default();
-                                                half_operators.insert(user_nick.clone());
-                                                chanobj.modes.half_operators = Some(half_operators);
-                                            }
-                                            "vop" => {
-                                                user_chum.voice = true;
-                                                let mut voices = chanobj.modes.voices.take().unwrap_or_default();
-                                                voices.insert(user_nick.clone());
-                                                chanobj.modes.voices = Some(voices);
-                                            }
-                                            _ => {}
-                                        }
+                                    if let Ok(Some(AccessEntry { flags: access_flags, .. })) = db_arc.read().await.get_channel_access(&chname, &user_nick).await {
+                                        // Aplicar los modos automáticos según los flags de ChanServ
+                                        Self::apply_access_automodes(chanobj, &user_nick, &access_flags);
                                     } else {
                                         // Si no tiene acceso específico, verificar si es el creador del canal
                                         if user_nick == *creator_nick {
@@ -427,12 +601,12 @@ impl super::MainState {
                                         }
                                     }
                                     
-                                    // Apply topic and modes stored in ChanServ if they exist
-                                    if let Some(topic) = &channel_info.2 {
-                                        if chanobj.topic.is_none() {
+                                    // Apply topic (with KEEPTOPIC) and modes stored in ChanServ if they exist
+                                    if let Some(topic) = &channel_info.topic {
+                                        if chanobj.topic.is_none() && keeptopic {
                                             // Use topic_setter and topic_time from database if available
-                                            let topic_setter: String = channel_info.4.clone().unwrap_or_else(|| creator_nick.clone());
-                                            let topic_time = channel_info.5.map(|time| {
+                                            let topic_setter: String = channel_info.topic_setter.clone().unwrap_or_else(|| creator_nick.clone());
+                                            let topic_time = channel_info.topic_time.map(|time| {
                                                 time.duration_since(SystemTime::UNIX_EPOCH)
                                                     .unwrap_or_default()
                                                     .as_secs()
@@ -452,14 +626,24 @@ impl super::MainState {
                                     }
                                     
                                     // Aplicar modos almacenados en ChanServ si existen
-                                    if let Some(modes_str) = &channel_info.3 {
+                                    if let Some(modes_str) = &channel_info.modes {
                                         // Parsear y aplicar los modos almacenados
                                         self.apply_stored_modes(&mut chanobj.modes, modes_str);
                                     }
+
+                                    // Listas b/e/I/Z, clave y límite guardados
+                                    if let Some(saved) = saved {
+                                        self.apply_saved_channel_modes(&chname, chanobj, saved);
+                                    }
+
+                                    // con GUARD el canal no se elimina al quedarse vacío
+                                    if guard {
+                                        chanobj.preconfigured = true;
+                                    }
                                 } else {
                                     // El canal NO está registrado - asignar +q al creador
-                                    let chanobj = state.channels.get_mut(&crate::state::structs::to_unicase(&chname)).unwrap();
-                                    let user_chum = chanobj.users.get_mut(&crate::state::structs::to_unicase(&user_nick)).unwrap();
+                                    let chanobj = state.channels.get_mut(&crate::state::structs::to_irccase(&chname)).unwrap();
+                                    let user_chum = chanobj.users.get_mut(&crate::state::structs::to_irccase(&user_nick)).unwrap();
                                     user_chum.founder = true;
                                     let mut founders = chanobj.modes.founders.take().unwrap_or_default();
                                     founders.insert(user_nick.clone());
@@ -471,33 +655,28 @@ impl super::MainState {
                             }
                         }
                         
-                        #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-                        {
-                            // Sin base de datos - no asignar modos automáticamente
-                            // para evitar asignar +q a canales que podrían estar registrados
-                        }
                             
                     } else {
                         state
                             .channels
-                            .get_mut(&crate::state::structs::to_unicase(&chname))
+                            .get_mut(&crate::state::structs::to_irccase(&chname))
                             .unwrap()
                             .add_user(&user_nick);
                         
                                                 // Verificar si el canal está registrado y aplicar modos de ChanServ
-                        #[cfg(any(feature = "sqlite", feature = "mysql"))]
                         {
-                            #[cfg(any(feature = "sqlite", feature = "mysql"))]
                             if let Some(db_arc) = &self.databases.chan_db {
                                 if let Ok(Some(channel_info)) = db_arc.read().await.get_channel_info(&chname).await {
                                     // El canal está registrado
-                                    let creator_nick = &channel_info.0; // El primer elemento es el creador
-                                    let chanobj = state.channels.get_mut(&crate::state::structs::to_unicase(&chname)).unwrap();
-                                    let user_chum = chanobj.users.get_mut(&crate::state::structs::to_unicase(&user_nick)).unwrap();
+                                    let creator_nick = &channel_info.founder;
+                                    let keeptopic = self.chanserv_setting(&chname, "keeptopic").await;
+                                    let guard = self.chanserv_setting(&chname, "guard").await;
+                                    let chanobj = state.channels.get_mut(&crate::state::structs::to_irccase(&chname)).unwrap();
+                                    let user_chum = chanobj.users.get_mut(&crate::state::structs::to_irccase(&user_nick)).unwrap();
                                     
                                     // Verificar si el usuario tiene la opción noop habilitada
                                     let has_noop = if let Some(nick_db_arc) = &self.databases.nick_db {
-                                        if let Ok(Some((_, _, _, _, _, _, _, noop, _))) = nick_db_arc.read().await.get_nick_info(&user_nick).await {
+                                        if let Ok(Some(NickRecord { noop, .. })) = nick_db_arc.read().await.get_nick_info(&user_nick).await {
                                             noop
                                         } else {
                                             false
@@ -509,35 +688,9 @@ impl super::MainState {
                                     // Solo asignar modos si no tiene noop habilitado
                                     if !has_noop {
                                         // Verificar acceso de ChanServ para asignar modos
-                                        if let Ok(Some((access_level, _, _))) = db_arc.read().await.get_channel_access(&chname, &user_nick).await {
-                                            // Aplicar el nivel de acceso según ChanServ
-                                            match access_level.as_str() {
-                                                "sop" => {
-                                                    user_chum.protected = true;
-                                                    let mut protecteds = chanobj.modes.protecteds.take().unwrap_or_default();
-                                                    protecteds.insert(user_nick.clone());
-                                                    chanobj.modes.protecteds = Some(protecteds);
-                                                }
-                                                "aop" => {
-                                                    user_chum.operator = true;
-                                                    let mut operators = chanobj.modes.operators.take().unwrap_or_default();
-                                                    operators.insert(user_nick.clone());
-                                                    chanobj.modes.operators = Some(operators);
-                                                }
-                                                "hop" => {
-                                                    user_chum.half_oper = true;
-                                                    let mut half_operators = chanobj.modes.half_operators.take().unwrap_or_default();
-                                                    half_operators.insert(user_nick.clone());
-                                                    chanobj.modes.half_operators = Some(half_operators);
-                                                }
-                                                "vop" => {
-                                                    user_chum.voice = true;
-                                                    let mut voices = chanobj.modes.voices.take().unwrap_or_default();
-                                                    voices.insert(user_nick.clone());
-                                                    chanobj.modes.voices = Some(voices);
-                                                }
-                                                _ => {}
-                                            }
+                                        if let Ok(Some(AccessEntry { flags: access_flags, .. })) = db_arc.read().await.get_channel_access(&chname, &user_nick).await {
+                                            // Aplicar los modos automáticos según los flags de ChanServ
+                                            Self::apply_access_automodes(chanobj, &user_nick, &access_flags);
                                         } else {
                                             // Si no tiene acceso específico, verificar si es el creador del canal
                                             if user_nick == *creator_nick {
@@ -549,12 +702,12 @@ impl super::MainState {
                                         }
                                     }
                                     
-                                    // Apply topic and modes stored in ChanServ if they exist
-                                    if let Some(topic) = &channel_info.2 {
-                                        if chanobj.topic.is_none() {
+                                    // Apply topic (with KEEPTOPIC) and modes stored in ChanServ if they exist
+                                    if let Some(topic) = &channel_info.topic {
+                                        if chanobj.topic.is_none() && keeptopic {
                                             // Use topic_setter and topic_time from database if available
-                                            let topic_setter: String = channel_info.4.clone().unwrap_or_else(|| creator_nick.clone());
-                                            let topic_time = channel_info.5.map(|time| {
+                                            let topic_setter: String = channel_info.topic_setter.clone().unwrap_or_else(|| creator_nick.clone());
+                                            let topic_time = channel_info.topic_time.map(|time| {
                                                 time.duration_since(SystemTime::UNIX_EPOCH)
                                                     .unwrap_or_default()
                                                     .as_secs()
@@ -574,14 +727,29 @@ impl super::MainState {
                                     }
                                     
                                     // Aplicar modos almacenados en ChanServ si existen
-                                    if let Some(modes_str) = &channel_info.3 {
+                                    if let Some(modes_str) = &channel_info.modes {
                                         // Parsear y aplicar los modos almacenados
                                         self.apply_stored_modes(&mut chanobj.modes, modes_str);
                                     }
+
+                                    // con GUARD el canal no se elimina al quedarse vacío
+                                    if guard {
+                                        chanobj.preconfigured = true;
+                                    }
                                 }
                             }
                         }
                     }
+
+                    // con SECUREOPS solo conservan el +o (también el del primero en entrar)
+                    // los usuarios con acceso de operador en ChanServ
+                    if self.secureops_denies(&chname, &user_nick).await {
+                        if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(&chname)) {
+                            if chanobj.users.get(&crate::state::structs::to_irccase(&user_nick)).is_some_and(|chum| chum.operator) {
+                                chanobj.remove_operator(&user_nick);
+                            }
+                        }
+                    }
                 }
             }
             // if something done - then change last activity
@@ -593,11 +761,16 @@ impl super::MainState {
             }
         }
 
+        // poner el ban de las entradas AKICK en los canales existentes
+        for (chname, ban_mask, reason) in &akicked {
+            self.apply_akick(state, chname, &user_nick, ban_mask, reason, false);
+        }
+
         // sending messages
         {
             for ((join, _), chname_str) in joined_created.iter().zip(channels.iter()) {
                 if *join {
-                    let chanobj = state.channels.get(&crate::state::structs::to_unicase(chname_str)).unwrap();
+                    let chanobj = state.channels.get(&crate::state::structs::to_irccase(chname_str)).unwrap();
                     let mut join_msg = "JOIN ".to_string() + chname_str;
                     
                     // Add account if extended-join is enabled and account is provided
@@ -611,7 +784,7 @@ impl super::MainState {
                     }
                     
                     let client = conn_state.user_state.client_name();
-                    let user = state.users.get(&crate::state::structs::to_unicase(&user_nick)).unwrap();
+                    let user = state.users.get(&crate::state::structs::to_irccase(&user_nick)).unwrap();
                     let source = if user.modes.cloacked {
                         format!("{}!~{}@{}", user_nick, user.name, user.cloack)
                     } else {
@@ -653,7 +826,7 @@ impl super::MainState {
                     )
                     .await?;
 
-                    let user_chum = chanobj.users.get(&crate::state::structs::to_unicase(&user_nick)).unwrap();
+                    let user_chum = chanobj.users.get(&crate::state::structs::to_irccase(&user_nick)).unwrap();
                     let mut arg = Vec::new();
                     if user_chum.founder {
                         arg.push("q");
@@ -668,29 +841,77 @@ impl super::MainState {
                     }
                     // send message to other users in channel
                     for nick in chanobj.users.keys() {
-                        if *nick != UniCase::new(user_nick.as_str()) {
-                            state.users.get(&crate::state::structs::to_unicase(&nick.clone())).unwrap().send_msg_display(
+                        if *nick != IrcCase::new(user_nick.as_str()) {
+                            state.users.get(&crate::state::structs::to_irccase(&nick.clone())).unwrap().send_msg_display(
                                 &source,
                                 join_msg.as_str(),
                             )?;
                         }
                         for mode in &arg {
                             let msg = format!("MODE {chname_str} +{mode} {user_nick}");
-                            state.users.get(&crate::state::structs::to_unicase(&nick.clone())).unwrap().send_msg_display(
+                            state.users.get(&crate::state::structs::to_irccase(&nick.clone())).unwrap().send_msg_display(
                                 &self.config.name,
                                 msg.as_str(),
                             )?;
                         }
                     }
+
+                    // saludo del bot de BotServ asignado al canal
+                    if let Some(bot_channel) = state.bot_channels.get(&crate::state::structs::to_irccase(chname_str)) {
+                        if let (Some(greet), Some(bot)) = (&bot_channel.greet, state.users.get(&crate::state::structs::to_irccase(&bot_channel.bot))) {
+                            self.feed_msg_source(&mut conn_state.stream, &bot.source, format!("NOTICE {user_nick} :{greet}"))
+                                .await?;
+                        }
+                    }
                 }
             }
         }
 
-        Ok(())
+        // entradas para +f y +j, una vez enviado el JOIN
+        for ((join, _), chname_str) in joined_created.iter().zip(channels.iter()) {
+            if *join {
+                self.channel_flood_event(state, chname_str, &user_nick, 'j');
+            }
+        }
+
+        Ok(redirects)
+    }
+
+    // Aplica los modos automáticos (+a/+o/+h/+v) que dan los flags de acceso de ChanServ
+    fn apply_access_automodes(chanobj: &mut Channel, nick: &str, flags: &str) {
+        use crate::database::has_access_flag;
+        let user_chum = if let Some(user_chum) = chanobj.users.get_mut(&crate::state::structs::to_irccase(nick)) {
+            user_chum
+        } else {
+            return;
+        };
+        if has_access_flag(flags, 'a') {
+            user_chum.protected = true;
+            let mut protecteds = chanobj.modes.protecteds.take().unwrap_or_default();
+            protecteds.insert(nick.to_string());
+            chanobj.modes.protecteds = Some(protecteds);
+        }
+        if has_access_flag(flags, 'o') {
+            user_chum.operator = true;
+            let mut operators = chanobj.modes.operators.take().unwrap_or_default();
+            operators.insert(nick.to_string());
+            chanobj.modes.operators = Some(operators);
+        }
+        if has_access_flag(flags, 'h') {
+            user_chum.half_oper = true;
+            let mut half_operators = chanobj.modes.half_operators.take().unwrap_or_default();
+            half_operators.insert(nick.to_string());
+            chanobj.modes.half_operators = Some(half_operators);
+        }
+        if has_access_flag(flags, 'v') {
+            user_chum.voice = true;
+            let mut voices = chanobj.modes.voices.take().unwrap_or_default();
+            voices.insert(nick.to_string());
+            chanobj.modes.voices = Some(voices);
+        }
     }
 
     // Función helper para extraer la clave del string de modos
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     fn extract_key_from_modes(&self, modes_str: &str) -> Option<String> {
         let mut chars = modes_str.chars().peekable();
         
@@ -735,9 +956,9 @@ impl super::MainState {
         let mut something_done = false;
 
         for channel in &channels {
-            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(channel.to_owned())) {
+            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel.to_owned())) {
                 // if user in channel
-                let do_it = if chanobj.users.contains_key(&crate::state::structs::to_unicase(&user_nick)) {
+                let do_it = if chanobj.users.contains_key(&crate::state::structs::to_irccase(&user_nick)) {
                     something_done = true;
                     true
                 } else {
@@ -757,7 +978,7 @@ impl super::MainState {
                     } else {
                         format!("PART {channel}")
                     };
-                    let user = state.users.get(&crate::state::structs::to_unicase(&user_nick)).unwrap();
+                    let user = state.users.get(&crate::state::structs::to_irccase(&user_nick)).unwrap();
                     let source = if user.modes.cloacked {
                         format!("{}!~{}@{}", user_nick, user.name, user.cloack)
                     } else {
@@ -766,7 +987,7 @@ impl super::MainState {
                     for nick in chanobj.users.keys() {
                         state
                             .users
-                            .get(&crate::state::structs::to_unicase(&nick.clone()))
+                            .get(&crate::state::structs::to_irccase(&nick.clone()))
                             .unwrap()
                             .send_msg_display(&source, part_msg.as_str())?;
                     }
@@ -788,7 +1009,7 @@ impl super::MainState {
         }
 
         let user_nick = conn_state.user_state.nick.as_ref().unwrap().clone();
-        let user = state.users.get_mut(&crate::state::structs::to_unicase(&user_nick)).unwrap();
+        let user = state.users.get_mut(&crate::state::structs::to_irccase(&user_nick)).unwrap();
 
         // if something done then change last activity time
         if something_done {
@@ -815,13 +1036,13 @@ impl super::MainState {
             let user_nick = conn_state.user_state.nick.as_ref().unwrap();
 
             // if channel exists
-            let do_change_topic = if let Some(chanobj) = state.channels.get(&crate::state::structs::to_unicase(channel)) {
+            let do_change_topic = if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
                 // if user on channel
-                if chanobj.users.contains_key(&crate::state::structs::to_unicase(user_nick)) {
+                if chanobj.users.contains_key(&crate::state::structs::to_irccase(user_nick)) {
                     // if channel topic is not protected otherwise use should be at least
                     // a half-operator.
                     if !chanobj.modes.protected_topic
-                        || chanobj.users.get(&crate::state::structs::to_unicase(user_nick)).unwrap().is_half_operator()
+                        || chanobj.users.get(&crate::state::structs::to_irccase(user_nick)).unwrap().is_half_operator()
                     {
                         true
                     } else {
@@ -849,9 +1070,33 @@ impl super::MainState {
                 false
             };
 
+            // con TOPICLOCK solo el fundador y los usuarios con flag +t pueden cambiar el topic
+            let do_change_topic = if do_change_topic
+                && !state.users.get(&crate::state::structs::to_irccase(user_nick)).is_some_and(|u| u.modes.is_local_oper())
+                && self.chanserv_setting(channel, "topiclock").await
+            {
+                let founder = if let Some(db_arc) = &self.databases.chan_db {
+                    db_arc.read().await.get_channel_info(channel).await.ok().flatten().is_some_and(|info| info.founder == *user_nick)
+                } else {
+                    false
+                };
+                if founder || self.has_chanserv_flag(channel, user_nick, 't').await {
+                    true
+                } else {
+                    self.feed_msg(
+                        &mut conn_state.stream,
+                        ErrChanOpPrivsNeeded482 { client, channel },
+                    )
+                    .await?;
+                    false
+                }
+            } else {
+                do_change_topic
+            };
+
             if do_change_topic {
                 // change topic
-                let chanobj = state.channels.get_mut(&crate::state::structs::to_unicase(channel)).unwrap();
+                let chanobj = state.channels.get_mut(&crate::state::structs::to_irccase(channel)).unwrap();
                 if !topic.is_empty() {
                     chanobj.topic = Some(ChannelTopic::new_with_nick(
                         topic.to_string(),
@@ -859,7 +1104,6 @@ impl super::MainState {
                     ));
                     
                     // Update topic in database if channel is registered
-                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                     {
                         if let Some(db_arc) = &self.databases.chan_db {
                             let mut db = db_arc.write().await;
@@ -874,11 +1118,11 @@ impl super::MainState {
             }
             if do_change_topic {
                 // send message about to all users in channel.
-                let chanobj = state.channels.get(&crate::state::structs::to_unicase(channel)).unwrap();
+                let chanobj = state.channels.get(&crate::state::structs::to_irccase(channel)).unwrap();
                 for cu in chanobj.users.keys() {
                     state
                         .users
-                        .get(&crate::state::structs::to_unicase(cu))
+                        .get(&crate::state::structs::to_irccase(cu))
                         .unwrap()
                         .send_message(msg, &conn_state.user_state.source)?;
                 }
@@ -886,10 +1130,10 @@ impl super::MainState {
         } else {
             // read topic
             let state = self.state.read().await;
-            if let Some(chanobj) = state.channels.get(&crate::state::structs::to_unicase(channel)) {
+            if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
                 let user_nick = conn_state.user_state.nick.as_ref().unwrap();
 
-                if chanobj.users.contains_key(&crate::state::structs::to_unicase(user_nick)) {
+                if chanobj.users.contains_key(&crate::state::structs::to_irccase(user_nick)) {
                     // if user on channel
                     if let Some(ref topic) = chanobj.topic {
                         self.feed_msg(
@@ -939,13 +1183,13 @@ impl super::MainState {
         conn_state: &mut ConnState,
         channel_name: &'a str,
         channel: &'a Channel,
-        users: &HashMap<UniCase<String>, User>,
+        users: &HashMap<IrcCase<String>, User>,
         end: bool,
     ) -> Result<(), Box<dyn StdError + Send + Sync>> {
         let client = conn_state.user_state.client_name();
         let conn_user_nick = conn_state.user_state.nick.as_ref().unwrap();
 
-        let in_channel = channel.users.contains_key(&crate::state::structs::to_unicase(conn_user_nick));
+        let in_channel = channel.users.contains_key(&crate::state::structs::to_irccase(conn_user_nick));
         // if channel is not secret or user on channel.
         if !channel.modes.secret || in_channel {
             const NAMES_COUNT: usize = 20;
@@ -954,7 +1198,7 @@ impl super::MainState {
             let mut name_chunk = Vec::with_capacity(NAMES_COUNT);
 
             for (unick, chum) in &channel.users {
-                let user = users.get(&crate::state::structs::to_unicase(unick.as_str())).unwrap();
+                let user = users.get(&crate::state::structs::to_irccase(unick.as_str())).unwrap();
                 // do not send names of invisible users or user on channel
                 if !user.modes.invisible || in_channel {
                     name_chunk.push(NameReplyStruct {
@@ -1013,7 +1257,7 @@ impl super::MainState {
         if !channels.is_empty() {
             // send names with EndOfNames
             for c in channels {
-                if let Some(channel) = state.channels.get(&crate::state::structs::to_unicase(c)) {
+                if let Some(channel) = state.channels.get(&crate::state::structs::to_irccase(c)) {
                     self.send_names_from_channel(conn_state, c, channel, &state.users, true)
                         .await?;
                 } else {
@@ -1065,51 +1309,81 @@ impl super::MainState {
             )
             .await?;
         } else {
-            let state = self.state.read().await;
+            // canales registrados con la opción PRIVATE de ChanServ
+            let private_channels = self.private_channels().await;
+            let user_nick = crate::state::structs::to_irccase(
+                conn_state.user_state.nick.as_deref().unwrap_or_default(),
+            );
+            let filters = channels
+                .iter()
+                .filter_map(|item| parse_list_filter(item))
+                .filter(|f| !matches!(f, ListFilter::Channel(_)))
+                .collect::<Vec<_>>();
+            // sin canales concretos se listan todos los que pasen los filtros.
+            let names = {
+                let names = channels
+                    .iter()
+                    .filter(|item| matches!(parse_list_filter(item), Some(ListFilter::Channel(_))))
+                    .map(|item| item.to_string())
+                    .collect::<Vec<_>>();
+                if names.is_empty() {
+                    let state = self.state.read().await;
+                    state.channels.keys().map(|chname| chname.to_string()).collect()
+                } else {
+                    names
+                }
+            };
+
             self.feed_msg(&mut conn_state.stream, RplListStart321 { client })
                 .await?;
-            if !channels.is_empty() {
-                // send channels that are public (not secret).
-                for (chname, ch) in channels.iter().filter_map(|chname| {
-                    state
-                        .channels
-                        .get(&crate::state::structs::to_unicase(chname))
-                        .filter(|ch| !ch.modes.secret)
-                        .map(|ch| (chname, ch))
-                }) {
-                    self.feed_msg(
-                        &mut conn_state.stream,
-                        RplList322 {
-                            client,
-                            channel: chname,
-                            client_count: ch.users.len(),
-                            topic: ch
-                                .topic
-                                .as_ref()
-                                .map(|x| &x.topic)
-                                .unwrap_or(&String::new()),
-                        },
-                    )
-                    .await?;
-                }
-            } else {
-                // send channels that are public (not secret).
-                for (chname, ch) in state.channels.iter().filter(|(_, ch)| !ch.modes.secret) {
+            // se envía por partes sin bloquear el estado mientras se escribe (SAFELIST).
+            for chunk in names.chunks(LIST_CHUNK_SIZE) {
+                let entries = {
+                    let state = self.state.read().await;
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                    chunk
+                        .iter()
+                        .filter_map(|chname| {
+                            state.channels.get_key_value(&crate::state::structs::to_irccase(chname))
+                        })
+                        .filter(|(chname, ch)| {
+                            !ch.modes.secret
+                                && (!private_channels.contains(*chname)
+                                    || ch.users.contains_key(&user_nick))
+                        })
+                        .filter(|(chname, ch)| {
+                            filters.iter().all(|f| {
+                                f.matches(
+                                    chname,
+                                    ch.users.len(),
+                                    ch.creation_time,
+                                    ch.topic.as_ref().map(|t| t.set_time),
+                                    now,
+                                )
+                            })
+                        })
+                        .map(|(chname, ch)| {
+                            (
+                                chname.to_string(),
+                                ch.users.len(),
+                                ch.topic.as_ref().map(|t| t.topic.clone()).unwrap_or_default(),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                };
+                for (chname, client_count, topic) in &entries {
                     self.feed_msg(
                         &mut conn_state.stream,
                         RplList322 {
                             client,
                             channel: chname,
-                            client_count: ch.users.len(),
-                            topic: ch
-                                .topic
-                                .as_ref()
-                                .map(|x| &x.topic)
-                                .unwrap_or(&String::new()),
+                            client_count: *client_count,
+                            topic,
                         },
                     )
                     .await?;
                 }
+                conn_state.stream.flush().await?;
             }
             self.feed_msg(&mut conn_state.stream, RplListEnd323 { client })
                 .await?;
@@ -1128,11 +1402,11 @@ impl super::MainState {
         let user_nick = conn_state.user_state.nick.as_ref().unwrap();
         let client = conn_state.user_state.client_name();
 
-        let do_invite = if let Some(chanobj) = state.channels.get(&crate::state::structs::to_unicase(channel)) {
-            if chanobj.users.contains_key(&crate::state::structs::to_unicase(user_nick)) {
+        let do_invite = if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
+            if chanobj.users.contains_key(&crate::state::structs::to_irccase(user_nick)) {
                 let do_invite2 = if chanobj.modes.invite_only {
                     // only operator can invite into channel if channel is invite_only.
-                    if !chanobj.users.get(&crate::state::structs::to_unicase(user_nick)).unwrap().operator {
+                    if !chanobj.users.get(&crate::state::structs::to_irccase(user_nick)).unwrap().is_operator() {
                         self.feed_msg(
                             &mut conn_state.stream,
                             ErrChanOpPrivsNeeded482 { client, channel },
@@ -1146,7 +1420,7 @@ impl super::MainState {
                     true
                 };
                 if do_invite2 {
-                    if chanobj.users.contains_key(&crate::state::structs::to_unicase(nickname)) {
+                    if chanobj.users.contains_key(&crate::state::structs::to_irccase(nickname)) {
                         self.feed_msg(
                             &mut conn_state.stream,
                             ErrUserOnChannel443 {
@@ -1180,9 +1454,43 @@ impl super::MainState {
             false
         };
 
+        // SILENCE, +R y +g del invitado
+        let block = state.users.get(&crate::state::structs::to_irccase(user_nick)).and_then(|inviter| {
+            state
+                .users
+                .get(&crate::state::structs::to_irccase(nickname))
+                .and_then(|invited| invited.private_block(user_nick, inviter))
+        });
+        let do_invite = do_invite && match block {
+            // se descarta sin avisar
+            Some(PrivateBlock::Silenced) => {
+                self.feed_msg(
+                    &mut conn_state.stream,
+                    RplInviting341 {
+                        client,
+                        nick: nickname,
+                        channel,
+                    },
+                )
+                .await?;
+                false
+            }
+            Some(PrivateBlock::NeedRegistered) => {
+                self.feed_msg(&mut conn_state.stream, ErrNoNonReg486 { client, nick: nickname })
+                    .await?;
+                false
+            }
+            Some(PrivateBlock::CallerId) => {
+                self.feed_msg(&mut conn_state.stream, ErrTargUmodeG716 { client, nick: nickname })
+                    .await?;
+                false
+            }
+            None => true,
+        };
+
         if do_invite {
             // check user
-            if let Some(invited) = state.users.get_mut(&crate::state::structs::to_unicase(nickname)) {
+            if let Some(invited) = state.users.get_mut(&crate::state::structs::to_irccase(nickname)) {
                 invited.invited_to.insert(channel.to_string());
                 self.feed_msg(
                     &mut conn_state.stream,
@@ -1208,6 +1516,83 @@ impl super::MainState {
         Ok(())
     }
 
+    pub(super) async fn process_knock<'a>(
+        &self,
+        conn_state: &mut ConnState,
+        channel: &'a str,
+        message: Option<&'a str>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut statem = self.state.write().await;
+        let state = statem.deref_mut();
+        let user_nick = conn_state.user_state.nick.as_ref().unwrap();
+        let client = conn_state.user_state.client_name();
+        let source = &conn_state.user_state.source;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        {
+            let user = state.users.get(&crate::state::structs::to_irccase(user_nick)).unwrap();
+            let ban_target = user.ban_target(source);
+            let inherited = inherited_ban(&state.channels, &ban_target);
+            let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) else {
+                return Ok(self.feed_msg(&mut conn_state.stream, ErrNoSuchChannel403 { client, channel }).await?);
+            };
+            // los canales secretos no se muestran a quien no está en ellos
+            if chanobj.modes.secret && !user.modes.is_local_oper() {
+                return Ok(self.feed_msg(&mut conn_state.stream, ErrNoSuchChannel403 { client, channel }).await?);
+            }
+            if chanobj.users.contains_key(&crate::state::structs::to_irccase(user_nick)) {
+                return Ok(self.feed_msg(&mut conn_state.stream, ErrKnockOnChan714 { client, channel }).await?);
+            }
+            if chanobj.modes.no_knock {
+                return Ok(self.feed_msg(&mut conn_state.stream, ErrCannotKnock480 { client, channel }).await?);
+            }
+            // sólo tiene sentido llamar si no se puede entrar por +i, +k o +l
+            let full = chanobj.modes.client_limit.is_some_and(|limit| chanobj.users.len() >= limit);
+            if !chanobj.modes.invite_only && chanobj.modes.key.is_none() && !full {
+                return Ok(self.feed_msg(&mut conn_state.stream, ErrChanOpen713 { client, channel }).await?);
+            }
+            if chanobj.modes.banned_user(&ban_target, false, &inherited) {
+                return Ok(self.feed_msg(&mut conn_state.stream, ErrCannotSendToChain404 { client, channel }).await?);
+            }
+
+            if !user.modes.is_local_oper() {
+                let reason = if chanobj.flood.count('k', Some(user_nick), KNOCK_USER_DELAY, now) != 0 {
+                    Some("user")
+                } else if chanobj.flood.count('k', None, KNOCK_CHANNEL_DELAY, now) != 0 {
+                    Some("channel")
+                } else {
+                    None
+                };
+                if let Some(reason) = reason {
+                    return Ok(self
+                        .feed_msg(&mut conn_state.stream, ErrTooManyKnock712 { client, channel, reason })
+                        .await?);
+                }
+            }
+        }
+
+        let chname = state.channels.get_key_value(&crate::state::structs::to_irccase(channel)).unwrap().0.to_string();
+        let chanobj = state.channels.get_mut(&crate::state::structs::to_irccase(channel)).unwrap();
+        chanobj.flood.record('k', user_nick, KNOCK_USER_DELAY, now);
+        for (nick, chum) in &chanobj.users {
+            if chum.is_operator() {
+                if let Some(op) = state.users.get(nick) {
+                    op.send_msg_display(
+                        &self.config.name,
+                        RplKnock710 {
+                            client: nick,
+                            channel: &chname,
+                            source,
+                            message,
+                        },
+                    )?;
+                }
+            }
+        }
+        self.feed_msg(&mut conn_state.stream, RplKnockDlvr711 { client, channel: &chname }).await?;
+        Ok(())
+    }
+
     pub(super) async fn process_kick<'a>(
         &self,
         conn_state: &mut ConnState,
@@ -1222,16 +1607,16 @@ impl super::MainState {
 
         let mut kicked = vec![];
 
-        if let Some(chanobj) = state.channels.get(&crate::state::structs::to_unicase(channel)) {
+        if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
             // if user on channel
-            if chanobj.users.contains_key(&crate::state::structs::to_unicase(user_nick)) {
-                let user_chum = chanobj.users.get(&crate::state::structs::to_unicase(user_nick)).unwrap();
+            if chanobj.users.contains_key(&crate::state::structs::to_irccase(user_nick)) {
+                let user_chum = chanobj.users.get(&crate::state::structs::to_irccase(user_nick)).unwrap();
                 // if user is half operator at least.
                 if user_chum.is_half_operator() {
                     let is_only_half_oper = user_chum.is_only_half_operator();
                     for kick_user in &kick_users {
                         let ku = kick_user.to_string();
-                        if let Some(chum) = chanobj.users.get(&crate::state::structs::to_unicase(&ku)) {
+                        if let Some(chum) = chanobj.users.get(&crate::state::structs::to_irccase(&ku)) {
                             if !chum.is_protected()
                                 && (!chum.is_half_operator() || !is_only_half_oper)
                             {
@@ -1282,20 +1667,20 @@ impl super::MainState {
             for ku in &kicked {
                 state.remove_user_from_channel(channel, ku);
             }
-            let chanobj = state.channels.get(&crate::state::structs::to_unicase(channel)).unwrap();
+            let chanobj = state.channels.get(&crate::state::structs::to_irccase(channel)).unwrap();
             for ku in &kicked {
                 let kick_msg = format!("KICK {channel} {ku} :{}", comment.unwrap_or("Kicked"));
                 for nick in chanobj.users.keys() {
                     state
                         .users
-                        .get(&crate::state::structs::to_unicase(nick))
+                        .get(&crate::state::structs::to_irccase(nick))
                         .unwrap()
                         .send_msg_display(&conn_state.user_state.source, kick_msg.clone())?;
                 }
                 // and send to kicked user
                 state
                     .users
-                    .get(&crate::state::structs::to_unicase(ku))
+                    .get(&crate::state::structs::to_irccase(ku))
                     .unwrap()
                     .send_msg_display(&conn_state.user_state.source, kick_msg.clone())?;
             }
@@ -1336,7 +1721,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_join() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -1355,6 +1740,10 @@ mod test {
                 ":irc.irc 366 charlie #fruits :End of /NAMES list".to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
+            assert_eq!(
+                ":irc.irc MODE #fruits +q charlie".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
 
             let mut line_stream2 =
                 login_to_test_and_skip(port, "eddix", "eddie", "Eddie Flower").await;
@@ -1399,22 +1788,27 @@ mod test {
                 topic: None,
                 creation_time: 0,
                 preconfigured: false,
-                modes: ChannelModes::new_for_channel("charlie".to_string()),
+                modes: ChannelModes {
+                    founders: Some(["charlie".to_string()].into()),
+                    ..ChannelModes::default()
+                },
                 default_modes: ChannelDefaultModes::default(),
                 ban_info: HashMap::new(),
+                quiet_info: HashMap::new(),
+                flood: FloodTracker::default(),
                 users: [
                     (
-                        "charlie".to_string(),
+                        IrcCase::new("charlie".to_string()),
                         ChannelUserModes {
                             founder: true,
                             protected: false,
                             voice: false,
-                            operator: true,
+                            operator: false,
                             half_oper: false,
                         },
                     ),
                     (
-                        "eddix".to_string(),
+                        IrcCase::new("eddix".to_string()),
                         ChannelUserModes {
                             founder: false,
                             protected: false,
@@ -1424,7 +1818,7 @@ mod test {
                         },
                     ),
                     (
-                        "logan".to_string(),
+                        IrcCase::new("logan".to_string()),
                         ChannelUserModes {
                             founder: false,
                             protected: false,
@@ -1438,21 +1832,21 @@ mod test {
             };
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#fruits").unwrap();
+                let channel = state.channels.get(&to_irccase("#fruits")).unwrap();
                 exp_channel.creation_time = channel.creation_time;
                 assert_eq!(exp_channel, *channel);
 
                 assert_eq!(
                     HashSet::from(["#fruits".to_string()]),
-                    state.users.get("charlie").unwrap().channels
+                    state.users.get(&to_irccase("charlie")).unwrap().channels
                 );
                 assert_eq!(
                     HashSet::from(["#fruits".to_string()]),
-                    state.users.get("eddix").unwrap().channels
+                    state.users.get(&to_irccase("eddix")).unwrap().channels
                 );
                 assert_eq!(
                     HashSet::from(["#fruits".to_string()]),
-                    state.users.get("logan").unwrap().channels
+                    state.users.get(&to_irccase("logan")).unwrap().channels
                 );
             }
             line_stream3.send("QUIT :Bye".to_string()).await.unwrap();
@@ -1460,7 +1854,7 @@ mod test {
             {
                 let state = main_state.state.read().await;
                 exp_channel.remove_user("logan");
-                let channel = state.channels.get("#fruits").unwrap();
+                let channel = state.channels.get(&to_irccase("#fruits")).unwrap();
                 assert_eq!(exp_channel, *channel);
             }
             line_stream2.send("QUIT :Bye".to_string()).await.unwrap();
@@ -1468,7 +1862,7 @@ mod test {
             {
                 let state = main_state.state.read().await;
                 exp_channel.remove_user("eddix");
-                let channel = state.channels.get("#fruits").unwrap();
+                let channel = state.channels.get(&to_irccase("#fruits")).unwrap();
                 assert_eq!(exp_channel, *channel);
             }
             line_stream.send("QUIT :Bye".to_string()).await.unwrap();
@@ -1476,7 +1870,7 @@ mod test {
             {
                 let state = main_state.state.read().await;
                 exp_channel.remove_user("charlie");
-                assert!(!state.channels.contains_key("#fruits"));
+                assert!(!state.channels.contains_key(&to_irccase("#fruits")));
             }
         }
 
@@ -1505,7 +1899,7 @@ mod test {
                     .write()
                     .await
                     .channels
-                    .get_mut("#fruits")
+                    .get_mut(&to_irccase("#fruits"))
                     .unwrap()
                     .add_voice("charlie");
             }
@@ -1520,10 +1914,10 @@ mod test {
                         .read()
                         .await
                         .channels
-                        .get("#fruits")
+                        .get(&to_irccase("#fruits"))
                         .unwrap()
                         .users
-                        .get("charlie")
+                        .get(&to_irccase("charlie"))
                         .unwrap()
                         .voice
                 );
@@ -1535,21 +1929,19 @@ mod test {
 
     #[tokio::test]
     async fn test_command_join_with_topic() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
                 login_to_test_and_skip(port, "charlie", "charlie2", "Charlie Brown").await;
 
             line_stream.send("JOIN #fruits".to_string()).await.unwrap();
-            for _ in 0..3 {
-                line_stream.next().await.unwrap().unwrap();
-            }
+            skip_until(&mut line_stream, " MODE #fruits +q charlie").await;
 
             time::sleep(Duration::from_millis(50)).await;
             {
                 let mut state = main_state.state.write().await;
-                state.channels.get_mut("#fruits").unwrap().topic = Some(
+                state.channels.get_mut(&to_irccase("#fruits")).unwrap().topic = Some(
                     ChannelTopic::new_with_nick("This topic".to_string(), "charlie".to_string()),
                 );
             }
@@ -1565,6 +1957,12 @@ mod test {
                 ":irc.irc 332 eddix #fruits :This topic".to_string(),
                 line_stream2.next().await.unwrap().unwrap()
             );
+            assert!(line_stream2
+                .next()
+                .await
+                .unwrap()
+                .unwrap()
+                .starts_with(":irc.irc 333 eddix #fruits charlie "));
             assert!(equal_channel_names(
                 ":irc.irc 353 eddix = #fruits :",
                 &["eddix", "~charlie"],
@@ -1595,7 +1993,7 @@ mod test {
                 let mut state = main_state.state.write().await;
                 state
                     .channels
-                    .get_mut("#oranges")
+                    .get_mut(&to_irccase("#oranges"))
                     .unwrap()
                     .modes
                     .client_limit = Some(CLIENT_LIMIT);
@@ -1655,7 +2053,7 @@ mod test {
             time::sleep(Duration::from_millis(70)).await;
             {
                 let mut state = main_state.state.write().await;
-                let chmodes = &mut state.channels.get_mut("#secrets").unwrap().modes;
+                let chmodes = &mut state.channels.get_mut(&to_irccase("#secrets")).unwrap().modes;
                 chmodes.ban = Some(
                     [
                         "roland!*@*".to_string(),
@@ -1724,7 +2122,7 @@ mod test {
             time::sleep(Duration::from_millis(70)).await;
             {
                 let mut state = main_state.state.write().await;
-                let chmodes = &mut state.channels.get_mut("#exclusive").unwrap().modes;
+                let chmodes = &mut state.channels.get_mut(&to_irccase("#exclusive")).unwrap().modes;
                 chmodes.invite_only = true;
                 chmodes.invite_exception = Some(["ex*!*@*".to_string()].into());
             }
@@ -1753,7 +2151,7 @@ mod test {
 
             {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("henry").unwrap().invited_to =
+                state.users.get_mut(&to_irccase("henry")).unwrap().invited_to =
                     ["#exclusive".to_string()].into();
             }
             henry_stream
@@ -1783,7 +2181,7 @@ mod test {
             time::sleep(Duration::from_millis(70)).await;
             {
                 let mut state = main_state.state.write().await;
-                state.channels.get_mut("#protected").unwrap().modes.key =
+                state.channels.get_mut(&to_irccase("#protected")).unwrap().modes.key =
                     Some("longpassword!!".to_string());
             }
 
@@ -1888,7 +2286,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_join_multiple() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -1919,25 +2317,25 @@ mod test {
                 let mut state = main_state.state.write().await;
                 state
                     .channels
-                    .get_mut("#finances")
+                    .get_mut(&to_irccase("#finances"))
                     .unwrap()
                     .modes
                     .client_limit = Some(2);
                 state
                     .channels
-                    .get_mut("#stocks")
+                    .get_mut(&to_irccase("#stocks"))
                     .unwrap()
                     .modes
                     .client_limit = Some(3);
-                state.channels.get_mut("#hardware").unwrap().modes.ban =
+                state.channels.get_mut(&to_irccase("#hardware")).unwrap().modes.ban =
                     Some(["*g*!*@*".to_string()].into());
                 state
                     .channels
-                    .get_mut("#software")
+                    .get_mut(&to_irccase("#software"))
                     .unwrap()
                     .modes
                     .invite_only = true;
-                let modes = &mut state.channels.get_mut("#furnitures").unwrap().modes;
+                let modes = &mut state.channels.get_mut(&to_irccase("#furnitures")).unwrap().modes;
                 modes.invite_only = true;
                 modes.invite_exception = Some(["*g*!*@*".to_string()].into());
             }
@@ -2028,53 +2426,53 @@ mod test {
                 let state = main_state.state.write().await;
                 assert!(!state
                     .channels
-                    .get("#finances")
+                    .get(&to_irccase("#finances"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(!state
                     .channels
-                    .get("#stocks")
+                    .get(&to_irccase("#stocks"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(!state
                     .channels
-                    .get("#hardware")
+                    .get(&to_irccase("#hardware"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(!state
                     .channels
-                    .get("#software")
+                    .get(&to_irccase("#software"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(state
                     .channels
-                    .get("#furnitures")
+                    .get(&to_irccase("#furnitures"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(state
                     .channels
-                    .get("#tools")
+                    .get(&to_irccase("#tools"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(state
                     .channels
-                    .get("#cloaths")
+                    .get(&to_irccase("#cloaths"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert_eq!(
                     HashSet::from([
                         "#tools".to_string(),
                         "#furnitures".to_string(),
                         "#cloaths".to_string()
                     ]),
-                    state.users.get("greg").unwrap().channels
+                    state.users.get(&to_irccase("greg")).unwrap().channels
                 );
             }
         }
@@ -2084,7 +2482,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_join_multiple_with_key() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -2099,10 +2497,10 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let mut state = main_state.state.write().await;
-                state.channels.get_mut("#crypto").unwrap().modes.key = Some("altcoin".to_string());
-                state.channels.get_mut("#servers").unwrap().modes.key =
+                state.channels.get_mut(&to_irccase("#crypto")).unwrap().modes.key = Some("altcoin".to_string());
+                state.channels.get_mut(&to_irccase("#servers")).unwrap().modes.key =
                     Some("amd_epyc".to_string());
-                state.channels.get_mut("#cars").unwrap().modes.key = Some("Buggatti".to_string());
+                state.channels.get_mut(&to_irccase("#cars")).unwrap().modes.key = Some("Buggatti".to_string());
             }
 
             let mut greg_stream =
@@ -2168,41 +2566,41 @@ mod test {
                 let state = main_state.state.write().await;
                 assert!(!state
                     .channels
-                    .get("#crypto")
+                    .get(&to_irccase("#crypto"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(!state
                     .channels
-                    .get("#cars")
+                    .get(&to_irccase("#cars"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(state
                     .channels
-                    .get("#servers")
+                    .get(&to_irccase("#servers"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(state
                     .channels
-                    .get("#drinks")
+                    .get(&to_irccase("#drinks"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert!(state
                     .channels
-                    .get("#job")
+                    .get(&to_irccase("#job"))
                     .unwrap()
                     .users
-                    .contains_key("greg"));
+                    .contains_key(&to_irccase("greg")));
                 assert_eq!(
                     HashSet::from([
                         "#servers".to_string(),
                         "#drinks".to_string(),
                         "#job".to_string()
                     ]),
-                    state.users.get("greg").unwrap().channels
+                    state.users.get(&to_irccase("greg")).unwrap().channels
                 );
             }
         }
@@ -2268,8 +2666,8 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let mut state = main_state.state.write().await;
-                state.channels.get_mut("#roses").unwrap().modes.key = Some("whiterose".to_string());
-                state.channels.get_mut("#fruits").unwrap().modes.key = Some("cocoa".to_string());
+                state.channels.get_mut(&to_irccase("#roses")).unwrap().modes.key = Some("whiterose".to_string());
+                state.channels.get_mut(&to_irccase("#fruits")).unwrap().modes.key = Some("cocoa".to_string());
             }
 
             let mut line_stream = login_to_test_and_skip(port, "rosy", "rosy-f", "Rosy Red").await;
@@ -2277,8 +2675,8 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             let activity = {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("rosy").unwrap().last_activity -= 10;
-                state.users.get("rosy").unwrap().last_activity
+                state.users.get_mut(&to_irccase("rosy")).unwrap().last_activity -= 10;
+                state.users.get(&to_irccase("rosy")).unwrap().last_activity
             };
 
             line_stream.send("JOIN #roses".to_string()).await.unwrap();
@@ -2289,7 +2687,7 @@ mod test {
             );
             {
                 let state = main_state.state.read().await;
-                assert_eq!(activity, state.users.get("rosy").unwrap().last_activity);
+                assert_eq!(activity, state.users.get(&to_irccase("rosy")).unwrap().last_activity);
             }
 
             line_stream
@@ -2303,15 +2701,15 @@ mod test {
             );
             {
                 let state = main_state.state.read().await;
-                assert_ne!(activity, state.users.get("rosy").unwrap().last_activity);
+                assert_ne!(activity, state.users.get(&to_irccase("rosy")).unwrap().last_activity);
                 line_stream.next().await.unwrap().unwrap();
                 line_stream.next().await.unwrap().unwrap();
             }
 
             let activity = {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("rosy").unwrap().last_activity -= 10;
-                state.users.get("rosy").unwrap().last_activity
+                state.users.get_mut(&to_irccase("rosy")).unwrap().last_activity -= 10;
+                state.users.get(&to_irccase("rosy")).unwrap().last_activity
             };
 
             line_stream.send("JOIN #fruits".to_string()).await.unwrap();
@@ -2322,7 +2720,7 @@ mod test {
             );
             {
                 let state = main_state.state.read().await;
-                assert_eq!(activity, state.users.get("rosy").unwrap().last_activity);
+                assert_eq!(activity, state.users.get(&to_irccase("rosy")).unwrap().last_activity);
             }
 
             line_stream.send("JOIN #flowers".to_string()).await.unwrap();
@@ -2333,7 +2731,7 @@ mod test {
             );
             {
                 let state = main_state.state.read().await;
-                assert_ne!(activity, state.users.get("rosy").unwrap().last_activity);
+                assert_ne!(activity, state.users.get(&to_irccase("rosy")).unwrap().last_activity);
             }
         }
 
@@ -2371,13 +2769,13 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let mut state = main_state.state.write().await;
-                state.channels.get_mut("#ebooks").unwrap().modes.key =
+                state.channels.get_mut(&to_irccase("#ebooks")).unwrap().modes.key =
                     Some("Neuromancer".to_string());
-                state.channels.get_mut("#wearables").unwrap().modes.key =
+                state.channels.get_mut(&to_irccase("#wearables")).unwrap().modes.key =
                     Some("Cyberpunk".to_string());
-                state.channels.get_mut("#fitbits").unwrap().modes.key =
+                state.channels.get_mut(&to_irccase("#fitbits")).unwrap().modes.key =
                     Some("training".to_string());
-                state.channels.get_mut("#huawei").unwrap().modes.key =
+                state.channels.get_mut(&to_irccase("#huawei")).unwrap().modes.key =
                     Some("secretpass".to_string());
             }
 
@@ -2386,8 +2784,8 @@ mod test {
 
             let activity = {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("geek").unwrap().last_activity -= 10;
-                state.users.get("geek").unwrap().last_activity
+                state.users.get_mut(&to_irccase("geek")).unwrap().last_activity -= 10;
+                state.users.get(&to_irccase("geek")).unwrap().last_activity
             };
 
             line_stream
@@ -2430,13 +2828,13 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert_ne!(activity, state.users.get("geek").unwrap().last_activity);
+                assert_ne!(activity, state.users.get(&to_irccase("geek")).unwrap().last_activity);
             }
 
             let activity = {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("geek").unwrap().last_activity -= 10;
-                state.users.get("geek").unwrap().last_activity
+                state.users.get_mut(&to_irccase("geek")).unwrap().last_activity -= 10;
+                state.users.get(&to_irccase("geek")).unwrap().last_activity
             };
 
             line_stream
@@ -2454,7 +2852,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert_eq!(activity, state.users.get("geek").unwrap().last_activity);
+                assert_eq!(activity, state.users.get(&to_irccase("geek")).unwrap().last_activity);
             }
         }
 
@@ -2463,7 +2861,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_join_multi_prefix_names() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream = connect_to_test(port).await;
@@ -2479,9 +2877,7 @@ mod test {
                 .unwrap();
             line_stream.send("CAP END".to_string()).await.unwrap();
 
-            for _ in 0..20 {
-                line_stream.next().await.unwrap().unwrap();
-            }
+            skip_until(&mut line_stream, " MODE mati :").await;
 
             line_stream
                 .send("JOIN #oldhardware".to_string())
@@ -2492,7 +2888,7 @@ mod test {
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
-                ":irc.irc 353 mati = #oldhardware :~@mati".to_string(),
+                ":irc.irc 353 mati = #oldhardware :~mati".to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
@@ -2527,7 +2923,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             let mut exp_channel = {
                 let state = main_state.state.read().await;
-                state.channels.get("#math").unwrap().clone()
+                state.channels.get(&to_irccase("#math")).unwrap().clone()
             };
             exp_channel.remove_user("joel");
 
@@ -2544,8 +2940,8 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert_eq!(exp_channel, *state.channels.get("#math").unwrap());
-                assert_eq!(HashSet::new(), state.users.get("joel").unwrap().channels);
+                assert_eq!(exp_channel, *state.channels.get(&to_irccase("#math")).unwrap());
+                assert_eq!(HashSet::new(), state.users.get(&to_irccase("joel")).unwrap().channels);
             }
 
             line_stream2.send("PART #math".to_string()).await.unwrap();
@@ -2557,8 +2953,8 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert!(!state.channels.contains_key("#math"));
-                assert_eq!(HashSet::new(), state.users.get("noah").unwrap().channels);
+                assert!(!state.channels.contains_key(&to_irccase("#math")));
+                assert_eq!(HashSet::new(), state.users.get(&to_irccase("noah")).unwrap().channels);
             }
         }
 
@@ -2588,7 +2984,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             let mut exp_channel = {
                 let state = main_state.state.read().await;
-                state.channels.get("#math").unwrap().clone()
+                state.channels.get(&to_irccase("#math")).unwrap().clone()
             };
             exp_channel.remove_user("joel");
 
@@ -2608,8 +3004,8 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert_eq!(exp_channel, *state.channels.get("#math").unwrap());
-                assert_eq!(HashSet::new(), state.users.get("joel").unwrap().channels);
+                assert_eq!(exp_channel, *state.channels.get(&to_irccase("#math")).unwrap());
+                assert_eq!(HashSet::new(), state.users.get(&to_irccase("joel")).unwrap().channels);
             }
 
             line_stream2
@@ -2624,8 +3020,8 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert!(!state.channels.contains_key("#math"));
-                assert_eq!(HashSet::new(), state.users.get("noah").unwrap().channels);
+                assert!(!state.channels.contains_key(&to_irccase("#math")));
+                assert_eq!(HashSet::new(), state.users.get(&to_irccase("noah")).unwrap().channels);
             }
         }
 
@@ -2656,8 +3052,8 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert!(state.channels.contains_key("#carrots"));
-                assert!(!state.channels.contains_key("#apples"));
+                assert!(state.channels.contains_key(&to_irccase("#carrots")));
+                assert!(!state.channels.contains_key(&to_irccase("#apples")));
             }
         }
 
@@ -2706,9 +3102,9 @@ mod test {
             let (mut exp_math, mut exp_algebra, mut exp_physics) = {
                 let state = main_state.state.read().await;
                 (
-                    state.channels.get("#math").unwrap().clone(),
-                    state.channels.get("#algebra").unwrap().clone(),
-                    state.channels.get("#physics").unwrap().clone(),
+                    state.channels.get(&to_irccase("#math")).unwrap().clone(),
+                    state.channels.get(&to_irccase("#algebra")).unwrap().clone(),
+                    state.channels.get(&to_irccase("#physics")).unwrap().clone(),
                 )
             };
 
@@ -2743,9 +3139,9 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert_eq!(exp_math, *state.channels.get("#math").unwrap());
-                assert_eq!(exp_algebra, *state.channels.get("#algebra").unwrap());
-                assert_eq!(HashSet::new(), state.users.get("marty1").unwrap().channels);
+                assert_eq!(exp_math, *state.channels.get(&to_irccase("#math")).unwrap());
+                assert_eq!(exp_algebra, *state.channels.get(&to_irccase("#algebra")).unwrap());
+                assert_eq!(HashSet::new(), state.users.get(&to_irccase("marty1")).unwrap().channels);
             }
 
             line_stream3
@@ -2774,9 +3170,9 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert_eq!(exp_physics, *state.channels.get("#physics").unwrap());
-                assert_eq!(exp_algebra, *state.channels.get("#algebra").unwrap());
-                assert_eq!(HashSet::new(), state.users.get("lucky1").unwrap().channels);
+                assert_eq!(exp_physics, *state.channels.get(&to_irccase("#physics")).unwrap());
+                assert_eq!(exp_algebra, *state.channels.get(&to_irccase("#algebra")).unwrap());
+                assert_eq!(HashSet::new(), state.users.get(&to_irccase("lucky1")).unwrap().channels);
             }
         }
 
@@ -2811,8 +3207,8 @@ mod test {
 
             let activity = {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("marty1").unwrap().last_activity -= 10;
-                state.users.get("marty1").unwrap().last_activity
+                state.users.get_mut(&to_irccase("marty1")).unwrap().last_activity -= 10;
+                state.users.get(&to_irccase("marty1")).unwrap().last_activity
             };
             line_stream2
                 .send("PART #physics,#algebra :Return".to_string())
@@ -2829,13 +3225,13 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert_eq!(activity, state.users.get("marty1").unwrap().last_activity);
+                assert_eq!(activity, state.users.get(&to_irccase("marty1")).unwrap().last_activity);
             }
 
             let activity = {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("marty1").unwrap().last_activity -= 10;
-                state.users.get("marty1").unwrap().last_activity
+                state.users.get_mut(&to_irccase("marty1")).unwrap().last_activity -= 10;
+                state.users.get(&to_irccase("marty1")).unwrap().last_activity
             };
 
             line_stream2
@@ -2854,7 +3250,7 @@ mod test {
             {
                 // has some activity
                 let state = main_state.state.read().await;
-                assert_ne!(activity, state.users.get("marty1").unwrap().last_activity);
+                assert_ne!(activity, state.users.get(&to_irccase("marty1")).unwrap().last_activity);
             }
         }
 
@@ -2906,13 +3302,13 @@ mod test {
                 let mut state = main_state.state.write().await;
                 state
                     .channels
-                    .get_mut("#trance")
+                    .get_mut(&to_irccase("#trance"))
                     .unwrap()
                     .modes
                     .protected_topic = true;
                 state
                     .channels
-                    .get_mut("#techno")
+                    .get_mut(&to_irccase("#techno"))
                     .unwrap()
                     .modes
                     .protected_topic = true;
@@ -2920,14 +3316,14 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let topic = state.channels.get("#hifi").unwrap().topic.clone().unwrap();
+                let topic = state.channels.get(&to_irccase("#hifi")).unwrap().topic.clone().unwrap();
                 assert_eq!(
                     ("About HiFi".to_string(), "robbie".to_string()),
                     (topic.topic, topic.nick)
                 );
                 let topic = state
                     .channels
-                    .get("#techno")
+                    .get(&to_irccase("#techno"))
                     .unwrap()
                     .topic
                     .clone()
@@ -2938,7 +3334,7 @@ mod test {
                 );
                 let topic = state
                     .channels
-                    .get("#trance")
+                    .get(&to_irccase("#trance"))
                     .unwrap()
                     .topic
                     .clone()
@@ -2964,7 +3360,7 @@ mod test {
             {
                 // old topic
                 let state = main_state.state.read().await;
-                let topic = state.channels.get("#hifi").unwrap().topic.clone().unwrap();
+                let topic = state.channels.get(&to_irccase("#hifi")).unwrap().topic.clone().unwrap();
                 assert_eq!(
                     ("About HiFi".to_string(), "robbie".to_string()),
                     (topic.topic, topic.nick)
@@ -2975,7 +3371,7 @@ mod test {
                 .send("JOIN #hifi,#techno,#trance".to_string())
                 .await
                 .unwrap();
-            for _ in 0..(4 * 3) {
+            for _ in 0..(5 * 3) {
                 line_stream2.next().await.unwrap().unwrap();
             }
             // skip JOIN messages for robbie
@@ -2999,7 +3395,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let topic = state.channels.get("#hifi").unwrap().topic.clone().unwrap();
+                let topic = state.channels.get(&to_irccase("#hifi")).unwrap().topic.clone().unwrap();
                 assert_eq!(
                     ("About HiFi hardware".to_string(), "djtechno".to_string()),
                     (topic.topic, topic.nick)
@@ -3040,7 +3436,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert_eq!(None, state.channels.get("#hifi").unwrap().topic);
+                assert_eq!(None, state.channels.get(&to_irccase("#hifi")).unwrap().topic);
             }
 
             // no channel
@@ -3092,7 +3488,7 @@ mod test {
                 .read()
                 .await
                 .channels
-                .get("#cpus")
+                .get(&to_irccase("#cpus"))
                 .unwrap()
                 .topic
                 .as_ref()
@@ -3111,7 +3507,7 @@ mod test {
                 newbie_stream.next().await.unwrap().unwrap()
             );
             newbie_stream.send("JOIN #cpus".to_string()).await.unwrap();
-            for _ in 0..4 {
+            for _ in 0..5 {
                 newbie_stream.next().await.unwrap().unwrap();
             }
             // after join
@@ -3126,7 +3522,7 @@ mod test {
                 .read()
                 .await
                 .channels
-                .get("#cpus")
+                .get(&to_irccase("#cpus"))
                 .unwrap()
                 .topic
                 .as_ref()
@@ -3283,7 +3679,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_names() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -3292,7 +3688,7 @@ mod test {
                 .send("JOIN #cpus,#gpus,#sdds,#psus,#mobos".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 * 5 {
+            for _ in 0..4 * 5 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -3442,7 +3838,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_names_with_multi_prefix() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream = connect_to_test(port).await;
@@ -3458,17 +3854,22 @@ mod test {
                 .unwrap();
             line_stream.send("CAP END".to_string()).await.unwrap();
 
-            for _ in 0..20 {
-                line_stream.next().await.unwrap().unwrap();
-            }
+            skip_until(&mut line_stream, " MODE forexman :").await;
 
             line_stream
                 .send("JOIN #coins,#forex,#gold".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 * 3 {
+            for _ in 0..4 * 3 {
                 line_stream.next().await.unwrap().unwrap();
             }
+            time::sleep(Duration::from_millis(50)).await;
+            {
+                let mut state = main_state.state.write().await;
+                for chname in ["#coins", "#forex", "#gold"] {
+                    state.channels.get_mut(&to_irccase(chname)).unwrap().add_operator("forexman");
+                }
+            }
 
             let mut gold_stream =
                 login_to_test_and_skip(port, "goldy", "goldie", "Gold Maniac").await;
@@ -3517,7 +3918,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_names_secret() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -3526,15 +3927,15 @@ mod test {
                 .send("JOIN #coins,#forex,#gold".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 * 3 {
+            for _ in 0..4 * 3 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
             time::sleep(Duration::from_millis(50)).await;
             {
                 let mut state = main_state.state.write().await;
-                state.channels.get_mut("#forex").unwrap().modes.secret = true;
-                state.channels.get_mut("#gold").unwrap().modes.secret = true;
+                state.channels.get_mut(&to_irccase("#forex")).unwrap().modes.secret = true;
+                state.channels.get_mut(&to_irccase("#gold")).unwrap().modes.secret = true;
             }
 
             let mut gold_stream =
@@ -3614,7 +4015,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_names_invisible_users() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream = login_to_test_and_skip(port, "zool", "Zool", "Zool Fan").await;
@@ -3622,7 +4023,7 @@ mod test {
                 .send("JOIN #amiga,#arcades,#zool2".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 * 3 {
+            for _ in 0..4 * 3 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -3660,8 +4061,8 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("lolipop").unwrap().modes.invisible = true;
-                state.users.get_mut("chupa").unwrap().modes.invisible = true;
+                state.users.get_mut(&to_irccase("lolipop")).unwrap().modes.invisible = true;
+                state.users.get_mut(&to_irccase("chupa")).unwrap().modes.invisible = true;
             }
 
             let exp_names = HashMap::from([
@@ -3753,9 +4154,13 @@ mod test {
                 .send("JOIN #politics,#economics".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 * 2 + 1 {
-                line_stream2.next().await.unwrap().unwrap();
-            }
+            while !line_stream2
+                .next()
+                .await
+                .unwrap()
+                .unwrap()
+                .starts_with(":irc.irc 366 nick #economics ")
+            {}
 
             for _ in 0..3 * 3 + 3 {
                 line_stream.next().await.unwrap().unwrap();
@@ -3828,6 +4233,63 @@ mod test {
                 line_stream.next().await.unwrap().unwrap()
             );
 
+            // filtros ELIST
+            line_stream.send("LIST >1,#*ics".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 321 edmund Channel :Users  Name".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert!(equal_channel_list(
+                ":irc.irc 322 edmund ",
+                &["#politics 2 :", "#economics 2 :About economics"],
+                &[
+                    &line_stream.next().await.unwrap().unwrap(),
+                    &line_stream.next().await.unwrap().unwrap()
+                ]
+            ));
+            assert_eq!(
+                ":irc.irc 323 edmund :End of /LIST".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
+            line_stream.send("LIST T<5".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 321 edmund Channel :Users  Name".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 322 edmund #economics 2 :About economics".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 323 edmund :End of /LIST".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
+            line_stream.send("LIST C<5,<2,!#POL*".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 321 edmund Channel :Users  Name".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 322 edmund #management 1 :".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 323 edmund :End of /LIST".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
+            line_stream.send("LIST C>5".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 321 edmund Channel :Users  Name".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 323 edmund :End of /LIST".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
             time::sleep(Duration::from_millis(50)).await;
             // secret channel
             main_state
@@ -3835,7 +4297,7 @@ mod test {
                 .write()
                 .await
                 .channels
-                .get_mut("#management")
+                .get_mut(&to_irccase("#management"))
                 .unwrap()
                 .modes
                 .secret = true;
@@ -3864,7 +4326,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_invite() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -3873,9 +4335,7 @@ mod test {
                 .send("JOIN #funky,#punky".to_string())
                 .await
                 .unwrap();
-            for _ in 0..6 {
-                line_stream.next().await.unwrap().unwrap();
-            }
+            skip_until(&mut line_stream, " MODE #punky +q seba").await;
 
             let mut line_stream2 =
                 login_to_test_and_skip(port, "stan", "stan", "Stan Straightforward").await;
@@ -3887,7 +4347,7 @@ mod test {
                     .write()
                     .await
                     .channels
-                    .get_mut("#punky")
+                    .get_mut(&to_irccase("#punky"))
                     .unwrap()
                     .modes
                     .invite_only = true;
@@ -3907,7 +4367,7 @@ mod test {
                     .read()
                     .await
                     .users
-                    .get("stan")
+                    .get(&to_irccase("stan"))
                     .unwrap()
                     .invited_to
                     .contains("#funky"));
@@ -3925,16 +4385,16 @@ mod test {
                 let state = main_state.state.read().await;
                 assert!(!state
                     .users
-                    .get("stan")
+                    .get(&to_irccase("stan"))
                     .unwrap()
                     .invited_to
                     .contains("#funky"));
                 assert!(state
                     .channels
-                    .get("#funky")
+                    .get(&to_irccase("#funky"))
                     .unwrap()
                     .users
-                    .contains_key("stan"));
+                    .contains_key(&to_irccase("stan")));
             }
             line_stream.next().await.unwrap().unwrap(); // skip JOIN
 
@@ -3953,7 +4413,7 @@ mod test {
                     .read()
                     .await
                     .users
-                    .get("stan")
+                    .get(&to_irccase("stan"))
                     .unwrap()
                     .invited_to
                     .contains("#punky"));
@@ -3968,16 +4428,16 @@ mod test {
                 let state = main_state.state.read().await;
                 assert!(!state
                     .users
-                    .get("stan")
+                    .get(&to_irccase("stan"))
                     .unwrap()
                     .invited_to
                     .contains("#punky"));
                 assert!(state
                     .channels
-                    .get("#punky")
+                    .get(&to_irccase("#punky"))
                     .unwrap()
                     .users
-                    .contains_key("stan"));
+                    .contains_key(&to_irccase("stan")));
             }
         }
 
@@ -3986,7 +4446,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_invite_failures() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -3995,9 +4455,7 @@ mod test {
                 .send("JOIN #funky,#punky".to_string())
                 .await
                 .unwrap();
-            for _ in 0..6 {
-                line_stream.next().await.unwrap().unwrap();
-            }
+            skip_until(&mut line_stream, " MODE #punky +q seba").await;
 
             let mut line_stream2 =
                 login_to_test_and_skip(port, "stan", "stan", "Stan Straightforward").await;
@@ -4016,7 +4474,7 @@ mod test {
                     .write()
                     .await
                     .channels
-                    .get_mut("#punky")
+                    .get_mut(&to_irccase("#punky"))
                     .unwrap()
                     .modes
                     .invite_only = true;
@@ -4063,9 +4521,84 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_command_kick() {
+    async fn test_command_knock() {
         let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
 
+        {
+            let mut line_stream =
+                login_to_test_and_skip(port, "seba", "sebastian", "Sebastian Gross").await;
+            line_stream.send("JOIN #punky".to_string()).await.unwrap();
+            for _ in 0..3 {
+                line_stream.next().await.unwrap().unwrap();
+            }
+            {
+                let mut state = main_state.state.write().await;
+                let chanobj = state.channels.get_mut(&to_irccase("#punky")).unwrap();
+                chanobj.modes.invite_only = true;
+                chanobj.users.get_mut(&to_irccase("seba")).unwrap().operator = true;
+            }
+
+            let mut line_stream2 =
+                login_to_test_and_skip(port, "stan", "stan", "Stan Straightforward").await;
+            line_stream2
+                .send("KNOCK #punky :let me in".to_string())
+                .await
+                .unwrap();
+            assert_eq!(
+                ":irc.irc 711 stan #punky :Your KNOCK has been delivered.".to_string(),
+                line_stream2.next().await.unwrap().unwrap()
+            );
+            assert_eq!(
+                ":irc.irc 710 seba #punky stan!~stan@127.0.0.1 :has asked for an invite: let me in".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
+            line_stream2.send("KNOCK #punky".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 712 stan #punky :Too many KNOCKs (user).".to_string(),
+                line_stream2.next().await.unwrap().unwrap()
+            );
+            line_stream2.send("KNOCK #pinky".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 403 stan #pinky :No such channel".to_string(),
+                line_stream2.next().await.unwrap().unwrap()
+            );
+            line_stream.send("KNOCK #punky".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 714 seba #punky :You are already on that channel.".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
+
+            {
+                let mut state = main_state.state.write().await;
+                state.channels.get_mut(&to_irccase("#punky")).unwrap().modes.no_knock = true;
+            }
+            line_stream2.send("KNOCK #punky".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 480 stan :Cannot knock on #punky (+K)".to_string(),
+                line_stream2.next().await.unwrap().unwrap()
+            );
+
+            {
+                let mut state = main_state.state.write().await;
+                let chanobj = state.channels.get_mut(&to_irccase("#punky")).unwrap();
+                chanobj.modes.no_knock = false;
+                chanobj.modes.invite_only = false;
+            }
+            line_stream2.send("KNOCK #punky".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 713 stan #punky :Channel is open.".to_string(),
+                line_stream2.next().await.unwrap().unwrap()
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_command_kick() {
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
+
         {
             let mut line_stream =
                 login_to_test_and_skip(port, "adam", "adam", "Adam Sandwich").await;
@@ -4073,7 +4606,7 @@ mod test {
                 .send("JOIN #impressions".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -4133,7 +4666,7 @@ mod test {
             time::sleep(Duration::from_millis(100)).await;
             {
                 let mut state = main_state.state.write().await;
-                let channel = state.channels.get_mut("#impressions").unwrap();
+                let channel = state.channels.get_mut(&to_irccase("#impressions")).unwrap();
                 channel.add_voice("david");
                 channel.add_half_operator("chris");
                 channel.add_half_operator("charlie");
@@ -4163,10 +4696,10 @@ mod test {
                     .read()
                     .await
                     .channels
-                    .get("#impressions")
+                    .get(&to_irccase("#impressions"))
                     .unwrap()
                     .users
-                    .contains_key("david"));
+                    .contains_key(&to_irccase("david")));
             }
 
             chris_stream
@@ -4184,10 +4717,10 @@ mod test {
                     .read()
                     .await
                     .channels
-                    .get("#impressions")
+                    .get(&to_irccase("#impressions"))
                     .unwrap()
                     .users
-                    .contains_key("charlie"));
+                    .contains_key(&to_irccase("charlie")));
             }
 
             chris_stream
@@ -4205,10 +4738,10 @@ mod test {
                     .read()
                     .await
                     .channels
-                    .get("#impressions")
+                    .get(&to_irccase("#impressions"))
                     .unwrap()
                     .users
-                    .contains_key("ben"));
+                    .contains_key(&to_irccase("ben")));
             }
 
             chris_stream
@@ -4226,10 +4759,10 @@ mod test {
                     .read()
                     .await
                     .channels
-                    .get("#impressions")
+                    .get(&to_irccase("#impressions"))
                     .unwrap()
                     .users
-                    .contains_key("adam"));
+                    .contains_key(&to_irccase("adam")));
             }
 
             ben_stream
@@ -4254,10 +4787,10 @@ mod test {
                     .read()
                     .await
                     .channels
-                    .get("#impressions")
+                    .get(&to_irccase("#impressions"))
                     .unwrap()
                     .users
-                    .contains_key("charlie"));
+                    .contains_key(&to_irccase("charlie")));
             }
 
             chris_stream
@@ -4294,10 +4827,10 @@ mod test {
                     .read()
                     .await
                     .channels
-                    .get("#impressions")
+                    .get(&to_irccase("#impressions"))
                     .unwrap()
                     .users
-                    .contains_key("chris"));
+                    .contains_key(&to_irccase("chris")));
             }
         }
 
@@ -4306,7 +4839,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_kick_self() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -4315,7 +4848,7 @@ mod test {
                 .send("JOIN #impressions".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -4373,7 +4906,7 @@ mod test {
             time::sleep(Duration::from_millis(100)).await;
             {
                 let mut state = main_state.state.write().await;
-                let channel = state.channels.get_mut("#impressions").unwrap();
+                let channel = state.channels.get_mut(&to_irccase("#impressions")).unwrap();
                 channel.add_voice("david");
                 channel.add_half_operator("chris");
                 channel.add_operator("charlie");
@@ -4438,7 +4971,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_kick_reason() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -4447,7 +4980,7 @@ mod test {
                 .send("JOIN #impressions".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -4482,7 +5015,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_kick_multiple_users() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -4491,7 +5024,7 @@ mod test {
                 .send("JOIN #impressions".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -4536,7 +5069,7 @@ mod test {
             time::sleep(Duration::from_millis(100)).await;
             {
                 let mut state = main_state.state.write().await;
-                let channel = state.channels.get_mut("#impressions").unwrap();
+                let channel = state.channels.get_mut(&to_irccase("#impressions")).unwrap();
                 channel.add_half_operator("chris");
                 channel.add_operator("charlie");
                 channel.add_protected("ben");
@@ -18,7 +18,10 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
 use super::*;
+use crate::database::{AccessEntry, ChannelListEntry, ChannelSuspension, ForbiddenEntry, NickRecord};
+use crate::database::{access_level, access_template, access_template_name, apply_access_flags, channel_setting_default, has_access_flag, CHANNEL_SETTINGS};
 use serde::ser::StdError;
+use std::ops::DerefMut;
 use std::time::SystemTime;
 
 impl super::MainState {
@@ -36,6 +39,18 @@ impl super::MainState {
             return Ok(());
         };
 
+        // Un canal suspendido no se puede gestionar salvo por un IRCop
+        if !matches!(subcommand.to_lowercase().as_str(), "help" | "info" | "suspend" | "unsuspend" | "forbid") {
+            if let Some(channel) = params.first() {
+                if let Some(ChannelSuspension { reason, .. }) = self.channel_suspension(channel).await {
+                    if !self.is_ircop(nick).await {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is suspended: {reason}")).await?;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
         match subcommand.to_lowercase().as_str() {
             "register" => {
                 if params.is_empty() {
@@ -50,18 +65,22 @@ impl super::MainState {
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is already registered.")).await?;
                         return Ok(());
                     }
+                    if db.get_forbidden_channel(channel).await?.is_some() {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is forbidden and cannot be registered.")).await?;
+                        return Ok(());
+                    }
 
                     db.add_channel(channel, nick, SystemTime::now()).await?;
                     
                     // Establecer automáticamente el modo +r para canales registrados
                     let mut state = self.state.write().await;
-                    if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(channel)) {
+                    if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
                         chanobj.modes.registered = true;
                         
                         // Notificar a todos los usuarios del canal sobre el cambio de modo
                         let nicks: Vec<String> = chanobj.users.keys().cloned().map(|nick| nick.to_string()).collect();
                         for nick in nicks {
-                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nick)) {
+                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(&nick)) {
                                 let mensaje = format!("MODE {channel} +r");
                                 let _ = user.send_msg_display(&self.config.name, &mensaje);
                             }
@@ -84,7 +103,7 @@ impl super::MainState {
                     let mut db = db_arc.write().await;
                     if let Some(channel_info) = db.get_channel_info(channel).await? {
                         // Check permissions: only the channel creator or an IRCop can drop it
-                        let is_creator = nick == &channel_info.0;
+                        let is_creator = nick == &channel_info.founder;
                         let is_ircop = self.is_ircop(nick).await;
                         
                         if !is_creator && !is_ircop {
@@ -93,17 +112,24 @@ impl super::MainState {
                         }
                         
                         db.delete_channel(channel).await?;
+                        drop(db);
+                        if let Some(bot_db_arc) = &self.databases.bot_db {
+                            if bot_db_arc.write().await.unassign_bot(channel).await? {
+                                self.botserv_unassign(channel).await;
+                            }
+                        }
+                        self.guard_channel(channel, false).await;
                         
                         // Quitar automáticamente el modo +r cuando se elimina el canal
                         let mut state = self.state.write().await;
-                        if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(channel)) {
+                        if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
                             if chanobj.modes.registered {
                                 chanobj.modes.registered = false;
                                 
                                 // Notificar a todos los usuarios del canal sobre el cambio de modo
                                 let nicks: Vec<String> = chanobj.users.keys().cloned().map(|nick| nick.to_string()).collect();
                                 for nick in nicks {
-                                    if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nick)) {
+                                    if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(&nick)) {
                                         let mensaje = format!("MODE {channel} -r");
                                         let _ = user.send_msg_display(&self.config.name, &mensaje);
                                     }
@@ -128,8 +154,11 @@ impl super::MainState {
 
                 if let Some(db_arc) = &self.databases.chan_db {
                     let db = db_arc.read().await;
+                    if let Some(ForbiddenEntry { reason, set_by, set_time, .. }) = db.get_forbidden_channel(channel).await? {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel {channel} is forbidden by {set_by} on {}: {reason}", format_service_time(set_time))).await?;
+                    }
                     if let Some(info) = db.get_channel_info(channel).await? {
-                        let creation_time = info.1.duration_since(SystemTime::UNIX_EPOCH)
+                        let creation_time = info.creation_time.duration_since(SystemTime::UNIX_EPOCH)
                             .unwrap_or_default()
                             .as_secs();
                         let datetime = chrono::DateTime::from_timestamp(creation_time as i64, 0)
@@ -137,18 +166,18 @@ impl super::MainState {
                             .format("%Y-%m-%d %H:%M:%S");
                         
                         // Basic channel information
-                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel {channel} information: Created by {} on {datetime}", info.0)).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel {channel} information: Created by {} on {datetime}", info.founder)).await?;
                         
                         // Show topic if it exists
-                        if let Some(topic) = &info.2 {
+                        if let Some(topic) = &info.topic {
                             self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Topic: {topic}")).await?;
                         } else {
                             self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Topic: Not set")).await?;
                         }
                         
                         // Show topic setter and time if available
-                        if let Some(topic_setter) = &info.4 {
-                            if let Some(topic_time) = &info.5 {
+                        if let Some(topic_setter) = &info.topic_setter {
+                            if let Some(topic_time) = &info.topic_time {
                                 let topic_datetime = chrono::DateTime::from_timestamp(topic_time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs() as i64, 0)
                                     .unwrap_or_default()
                                     .format("%Y-%m-%d %H:%M:%S");
@@ -159,21 +188,40 @@ impl super::MainState {
                         }
                         
                         // Show channel modes from database
-                        let modos_str = match &info.3 {
+                        let modos_str = match &info.modes {
                             Some(modos) if !modos.is_empty() => modos.as_str(),
                             _ => "No special modes",
                         };
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Modes: {modos_str}")).await?;
+
+                        // Show enabled CS SET options
+                        let mut options = Vec::new();
+                        for (setting, default) in CHANNEL_SETTINGS {
+                            if db.get_channel_setting(channel, setting).await.unwrap_or(default) {
+                                options.push(setting.to_uppercase());
+                            }
+                        }
+                        if !options.is_empty() {
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Options: {}", options.join(", "))).await?;
+                        }
+
+                        // Show suspension if any
+                        drop(db);
+                        if let Some(ChannelSuspension { reason, suspended_by, suspended_time, expires }) = self.channel_suspension(channel).await {
+                            let expires = expires.map(format_service_time).unwrap_or_else(|| "never".to_string());
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Suspended by {suspended_by} on {}: {reason} (expires: {expires})", format_service_time(suspended_time))).await?;
+                        }
+                        let db = db_arc.read().await;
                         
                         // Show user access if they have any
-                        if let Ok(Some((access_level, setter, set_time))) = db.get_channel_access(channel, nick).await {
+                        if let Ok(Some(AccessEntry { flags: access_flags, added_by: setter, added_time: set_time, .. })) = db.get_channel_access(channel, nick).await {
                             let access_time = set_time.duration_since(SystemTime::UNIX_EPOCH)
                                 .unwrap_or_default()
                                 .as_secs();
                             let access_datetime = chrono::DateTime::from_timestamp(access_time as i64, 0)
                                 .unwrap_or_default()
                                 .format("%Y-%m-%d %H:%M:%S");
-                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Your access: {access_level} (set by {setter} on {access_datetime})")).await?;
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Your access: +{access_flags}{} (set by {setter} on {access_datetime})", access_template_name(&access_flags).map(|t| format!(" ({})", t.to_uppercase())).unwrap_or_default())).await?;
                         }
                         
                     } else {
@@ -190,6 +238,8 @@ impl super::MainState {
                 }
                 let channel = params[0];
                 let action = params[1].to_lowercase();
+                let template_flags = access_template(subcommand).unwrap_or_default();
+                let level = subcommand.to_lowercase();
                 
                 // Check if channel exists
                 if let Some(db_arc) = &self.databases.chan_db {
@@ -199,14 +249,14 @@ impl super::MainState {
                         return Ok(());
                     }
                     
-                    // Check permissions: only SOP and channel creator can change access
+                    // Check permissions: only users with the +f flag and channel creator can change access
                     let channel_info = db.get_channel_info(channel).await?;
-                    let is_creator = nick == &channel_info.unwrap().0;
-                    let is_sop = db.get_channel_access(channel, nick).await?.map(|access| access.0 == "sop").unwrap_or(false);
+                    let is_creator = nick == &channel_info.unwrap().founder;
+                    let can_modify = db.get_channel_access(channel, nick).await?.map(|access| has_access_flag(&access.flags, 'f')).unwrap_or(false);
                     let is_ircop = self.is_ircop(nick).await;
                     
-                    if !is_creator && !is_sop && !is_ircop {
-                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to modify access in '{channel}'. Only SOP and channel creator can do this.")).await?;
+                    if !is_creator && !can_modify && !is_ircop {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to modify access in '{channel}'. Only users with the +f flag and channel creator can do this.")).await?;
                         return Ok(());
                     }
                     
@@ -226,7 +276,7 @@ impl super::MainState {
                             let mut registrado = false;
                             if let Some(nick_db_arc) = &self.databases.nick_db {
                                 let nick_db = nick_db_arc.read().await;
-                                if let Ok(Some((_user, _registration_date, _email, _url, _vhost, _last_vhost, noaccess, _noop, _showmail))) = nick_db.get_nick_info(target_nick).await {
+                                if let Ok(Some(NickRecord { noaccess, .. })) = nick_db.get_nick_info(target_nick).await {
                                     if noaccess {
                                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Cannot add {target_nick} to access list. User has noaccess mode enabled.")).await?;
                                         return Ok(());
@@ -239,18 +289,25 @@ impl super::MainState {
                                 return Ok(());
                             }
 
+                            // Solo el fundador o un IRCop pueden dar o quitar el flag +f (SOP)
+                            let current = db.get_channel_access(channel, target_nick).await?.map(|access| access.flags).unwrap_or_default();
+                            if has_access_flag(&current, 'f') != has_access_flag(template_flags, 'f') && !is_creator && !is_ircop {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only the channel founder can change the +f flag.")).await?;
+                                return Ok(());
+                            }
+
                             // Check if access already exists
                             if let Some(existing) = db.get_channel_access(channel, target_nick).await? {
-                                if existing.0 == subcommand.to_lowercase() {
+                                if existing.flags == template_flags {
                                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{target_nick} already has {subcommand} access in {channel}.")).await?;
                                 } else {
                                     // Update access level
-                                    db.update_channel_access(channel, target_nick, &subcommand.to_lowercase(), nick, SystemTime::now()).await?;
+                                    db.update_channel_access(channel, target_nick, template_flags, nick, SystemTime::now()).await?;
                                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{target_nick}'s access level in {channel} has been updated to {subcommand}")).await?;
                                 }
                             } else {
                                 // Add new access
-                                db.add_channel_access(channel, target_nick, &subcommand.to_lowercase(), nick, SystemTime::now()).await?;
+                                db.add_channel_access(channel, target_nick, template_flags, nick, SystemTime::now()).await?;
                                 self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{target_nick} has been added to the {subcommand} list of {channel}.")).await?;
                             }
                         }
@@ -263,7 +320,11 @@ impl super::MainState {
                             
                             // Check if access exists
                             if let Some(existing) = db.get_channel_access(channel, target_nick).await? {
-                                if existing.0 == subcommand.to_lowercase() {
+                                if access_level(&existing.flags) == Some(level.as_str()) {
+                                    if has_access_flag(&existing.flags, 'f') && !is_creator && !is_ircop {
+                                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only the channel founder can change the +f flag.")).await?;
+                                        return Ok(());
+                                    }
                                     db.delete_channel_access(channel, target_nick).await?;
                                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{target_nick} has been removed from the {subcommand} list of {channel}.")).await?;
                                 } else {
@@ -275,12 +336,17 @@ impl super::MainState {
                         }
                         "list" => {
                             // List all users with this access level
-                            let access_list = db.get_channel_access_list(channel, Some(&subcommand.to_lowercase())).await?;
+                            let access_list: Vec<AccessEntry> = db
+                                .get_channel_access_list(channel, None)
+                                .await?
+                                .into_iter()
+                                .filter(|access| access_level(&access.flags) == Some(level.as_str()))
+                                .collect();
                             if access_list.is_empty() {
                                 self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :No users with {subcommand} access in {channel}.")).await?;
                             } else {
                                 self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :List {subcommand} of {channel}:")).await?;
-                                for (nick, _level, added_by, added_time) in access_list {
+                                for AccessEntry { nick, added_by, added_time, .. } in access_list {
                                     let datetime = chrono::DateTime::from_timestamp(added_time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs() as i64, 0)
                                         .unwrap_or_default()
                                         .format("%Y-%m-%d %H:%M:%S");
@@ -322,7 +388,7 @@ impl super::MainState {
                     
                     // Obtener información del canal para verificar permisos
                     if let Some(channel_info) = db.get_channel_info(channel).await? {
-                        let current_owner = &channel_info.0; // El primer elemento es el propietario
+                        let current_owner = &channel_info.founder; // El primer elemento es el propietario
                         
                         // Verificar permisos: solo el propietario del canal o un IRCop puede transferir
                         let is_owner = nick == current_owner;
@@ -370,19 +436,19 @@ impl super::MainState {
 
                     // Obtener información del canal para verificar permisos
                     if let Some(channel_info) = db.get_channel_info(channel).await? {
-                        let current_owner = &channel_info.0;
+                        let current_owner = &channel_info.founder;
                         
                         // Verificar permisos: IRCop, propietario del canal, o acceso AOP o superior
                         let is_owner = nick == current_owner;
                         let is_ircop = self.is_ircop(nick).await;
-                        let has_access = if let Ok(Some((access_level, _, _))) = db.get_channel_access(channel, nick).await {
-                            matches!(access_level.as_str(), "aop" | "sop")
+                        let has_access = if let Ok(Some(AccessEntry { flags: access_flags, .. })) = db.get_channel_access(channel, nick).await {
+                            has_access_flag(&access_flags, 't')
                         } else {
                             false
                         };
                         
                         if !is_owner && !is_ircop && !has_access {
-                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to change the topic of the channel '{channel}'. You need to be an IRCop, owner, AOP or superior.")).await?;
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to change the topic of the channel '{channel}'. You need to be an IRCop, owner, or have the +t flag.")).await?;
                             return Ok(());
                         }
                         
@@ -394,7 +460,7 @@ impl super::MainState {
                         
                         // Send topic change to all users in the channel
                         let mut state = self.state.write().await;
-                        if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(channel)) {
+                        if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
                             // Update topic in internal IRCd logic
                             chanobj.topic = Some(ChannelTopic::new_with_nick(new_topic.clone(), nick.to_string()));
                             
@@ -406,7 +472,7 @@ impl super::MainState {
                             // Now access users separately
                             let state = self.state.read().await;
                             for user_nick in user_nicks {
-                                if let Some(user) = state.users.get(&crate::state::structs::to_unicase(&user_nick)) {
+                                if let Some(user) = state.users.get(&crate::state::structs::to_irccase(&user_nick)) {
                                     // Send message using user's sender
                                     let _ = user.send_msg_display("ChanServ", &topic_msg);
                                 }
@@ -466,19 +532,19 @@ impl super::MainState {
                     
                     // Obtener información del canal para verificar permisos
                     if let Some(channel_info) = db.get_channel_info(channel).await? {
-                        let current_owner = &channel_info.0;
+                        let current_owner = &channel_info.founder;
                         
                         // Verificar permisos: IRCop, propietario del canal, o acceso AOP o superior
                         let is_owner = nick == current_owner;
                         let is_ircop = self.is_ircop(nick).await;
-                        let has_access = if let Ok(Some((access_level, _, _))) = db.get_channel_access(channel, nick).await {
-                            matches!(access_level.as_str(), "aop" | "sop")
+                        let has_access = if let Ok(Some(AccessEntry { flags: access_flags, .. })) = db.get_channel_access(channel, nick).await {
+                            has_access_flag(&access_flags, 's')
                         } else {
                             false
                         };
                         
                         if !is_owner && !is_ircop && !has_access {
-                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to modify the mlock of the channel '{channel}'. You need to be an IRCop, owner, AOP or superior.")).await?;
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to modify the mlock of the channel '{channel}'. You need to be an IRCop, owner, or have the +s flag.")).await?;
                             return Ok(());
                         }
                         
@@ -487,7 +553,7 @@ impl super::MainState {
                             db.update_channel_info(channel, None, None, None, Some("")).await?;
                             // Limpiar modos mlock en la lógica interna si el canal existe
                             let mut state = self.state.write().await;
-                            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(channel)) {
+                            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
                                 // Limpiar los modos mlock (solo los modos permitidos por mlock)
                                 self.apply_stored_modes(&mut chanobj.modes, "");
                             }
@@ -499,7 +565,7 @@ impl super::MainState {
                             
                             // Aplicar los modos al canal si existe
                             let mut state = self.state.write().await;
-                            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(channel)) {
+                            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
                                 // Limpiar modos anteriores y aplicar los nuevos
                                 self.apply_stored_modes(&mut chanobj.modes, &args);
                             }
@@ -516,6 +582,417 @@ impl super::MainState {
                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :The database is not configured.")).await?;
                 }
             }
+            "suspend" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS SUSPEND <channel> [+expiry] <reason>")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+
+                if !self.is_ircop(nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only IRCops can suspend channels.")).await?;
+                    return Ok(());
+                }
+
+                // Expiración opcional en la forma +30m, +2h, +7d...
+                let (expires, reason_params) = if let Some(expiry) = params[1].strip_prefix('+') {
+                    if let Some(secs) = parse_duration(expiry) {
+                        (Some(SystemTime::now() + std::time::Duration::from_secs(secs)), &params[2..])
+                    } else {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Invalid expiry '{}'. Use e.g. +30m, +2h, +7d.", params[1])).await?;
+                        return Ok(());
+                    }
+                } else {
+                    (None, &params[1..])
+                };
+                if reason_params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS SUSPEND <channel> [+expiry] <reason>")).await?;
+                    return Ok(());
+                }
+                let reason = reason_params.join(" ");
+
+                if let Some(db_arc) = &self.databases.chan_db {
+                    let mut db = db_arc.write().await;
+                    if db.get_channel_info(channel).await?.is_none() {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is not registered.")).await?;
+                        return Ok(());
+                    }
+                    db.suspend_channel(channel, &reason, nick, SystemTime::now(), expires).await?;
+                    drop(db);
+
+                    let mut state = self.state.write().await;
+                    self.kick_non_opers(&mut state, channel, &format!("Channel suspended: {reason}"));
+                    drop(state);
+
+                    info!("Channel {} suspended by {}: {}", channel, nick, reason);
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' has been suspended.")).await?;
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Database is not configured.")).await?;
+                }
+            }
+            "unsuspend" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS UNSUSPEND <channel>")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+
+                if !self.is_ircop(nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only IRCops can unsuspend channels.")).await?;
+                    return Ok(());
+                }
+
+                if let Some(db_arc) = &self.databases.chan_db {
+                    if db_arc.write().await.unsuspend_channel(channel).await? {
+                        info!("Channel {} unsuspended by {}", channel, nick);
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is no longer suspended.")).await?;
+                    } else {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is not suspended.")).await?;
+                    }
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Database is not configured.")).await?;
+                }
+            }
+            "forbid" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS FORBID <channel> <add|del> [reason]")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                let action = params[1].to_lowercase();
+
+                if !self.is_ircop(nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only IRCops can forbid channels.")).await?;
+                    return Ok(());
+                }
+
+                if let Some(db_arc) = &self.databases.chan_db {
+                    match action.as_str() {
+                        "add" => {
+                            if params.len() < 3 {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS FORBID <channel> add <reason>")).await?;
+                                return Ok(());
+                            }
+                            let reason = params[2..].join(" ");
+                            db_arc.write().await.add_forbidden_channel(channel, &reason, nick, SystemTime::now()).await?;
+
+                            let mut state = self.state.write().await;
+                            self.kick_non_opers(&mut state, channel, &format!("Channel forbidden: {reason}"));
+                            drop(state);
+
+                            info!("Channel {} forbidden by {}: {}", channel, nick, reason);
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is now forbidden.")).await?;
+                        }
+                        "del" => {
+                            if db_arc.write().await.delete_forbidden_channel(channel).await? {
+                                info!("Channel {} unforbidden by {}", channel, nick);
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is no longer forbidden.")).await?;
+                            } else {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is not forbidden.")).await?;
+                            }
+                        }
+                        _ => {
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Invalid action. Use: add or del")).await?;
+                        }
+                    }
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Database is not configured.")).await?;
+                }
+            }
+            "set" => {
+                if params.len() < 3 {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS SET <channel> <option> <on|off>")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                let setting = params[1].to_lowercase();
+                let enabled = match params[2].to_lowercase().as_str() {
+                    "on" => true,
+                    "off" => false,
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS SET <channel> <option> <on|off>")).await?;
+                        return Ok(());
+                    }
+                };
+
+                if !crate::database::is_channel_setting(&setting) {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Unknown option '{}'. Options: KEEPTOPIC, TOPICLOCK, SECUREOPS, RESTRICTED, PRIVATE, GUARD", params[1])).await?;
+                    return Ok(());
+                }
+
+                if let Some(db_arc) = &self.databases.chan_db {
+                    let db = db_arc.read().await;
+                    let channel_info = if let Some(info) = db.get_channel_info(channel).await? {
+                        info
+                    } else {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is not registered.")).await?;
+                        return Ok(());
+                    };
+
+                    // Verificar permisos: IRCop, propietario del canal, o flag +s
+                    let is_owner = nick == &channel_info.founder;
+                    let is_ircop = self.is_ircop(nick).await;
+                    let has_access = if let Ok(Some(AccessEntry { flags: access_flags, .. })) = db.get_channel_access(channel, nick).await {
+                        has_access_flag(&access_flags, 's')
+                    } else {
+                        false
+                    };
+
+                    if !is_owner && !is_ircop && !has_access {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to change the options of '{channel}'. You need to be an IRCop, owner, or have the +s flag.")).await?;
+                        return Ok(());
+                    }
+
+                    drop(db);
+                    db_arc.write().await.set_channel_setting(channel, &setting, enabled).await?;
+
+                    if setting == "guard" {
+                        self.guard_channel(channel, enabled).await;
+                    }
+                    if setting == "secureops" && enabled {
+                        self.secureops_enforce(channel).await;
+                    }
+
+                    let status = if enabled { "on" } else { "off" };
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{} for {channel} is now {status}.", setting.to_uppercase())).await?;
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Database is not configured.")).await?;
+                }
+            }
+            "flags" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS FLAGS <channel> [nick [+flags|-flags|template]]")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+
+                if let Some(db_arc) = &self.databases.chan_db {
+                    let db = db_arc.read().await;
+                    let channel_info = if let Some(info) = db.get_channel_info(channel).await? {
+                        info
+                    } else {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is not registered.")).await?;
+                        return Ok(());
+                    };
+
+                    let is_owner = nick == &channel_info.founder;
+                    let is_ircop = self.is_ircop(nick).await;
+                    let own_flags = db.get_channel_access(channel, nick).await?.map(|access| access.flags).unwrap_or_default();
+
+                    // Sin cambios: mostrar la lista de accesos o los flags de un nick
+                    if params.len() < 3 {
+                        if !is_owner && !is_ircop && own_flags.is_empty() {
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have access to '{channel}'.")).await?;
+                            return Ok(());
+                        }
+                        if let Some(target_nick) = params.get(1) {
+                            if let Some(AccessEntry { flags, added_by, .. }) = db.get_channel_access(channel, target_nick).await? {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Flags for {target_nick} in {channel}: +{flags} (set by {added_by})")).await?;
+                            } else {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{target_nick} doesn't have any access in {channel}.")).await?;
+                            }
+                        } else {
+                            let access_list = db.get_channel_access_list(channel, None).await?;
+                            if access_list.is_empty() {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :The access list of {channel} is empty.")).await?;
+                            } else {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Access list of {channel}:")).await?;
+                                for AccessEntry { nick: target_nick, flags, added_by, .. } in access_list {
+                                    let template = access_template_name(&flags).map(|t| format!(" ({})", t.to_uppercase())).unwrap_or_default();
+                                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  {target_nick} +{flags}{template} (set by {added_by})")).await?;
+                                }
+                            }
+                        }
+                        return Ok(());
+                    }
+
+                    let target_nick = params[1];
+                    let change = params[2];
+
+                    if !is_owner && !is_ircop && !has_access_flag(&own_flags, 'f') {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to modify access in '{channel}'. Only users with the +f flag and channel creator can do this.")).await?;
+                        return Ok(());
+                    }
+
+                    let current = db.get_channel_access(channel, target_nick).await?.map(|access| access.flags).unwrap_or_default();
+                    let new_flags = match apply_access_flags(&current, change) {
+                        Ok(flags) => flags,
+                        Err(flag) => {
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Unknown flag '{flag}'. Valid flags: {}", crate::database::ACCESS_FLAGS)).await?;
+                            return Ok(());
+                        }
+                    };
+
+                    // Solo el fundador o un IRCop pueden dar o quitar el flag +f
+                    if has_access_flag(&current, 'f') != has_access_flag(&new_flags, 'f') && !is_owner && !is_ircop {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only the channel founder can change the +f flag.")).await?;
+                        return Ok(());
+                    }
+
+                    if !new_flags.is_empty() {
+                        // Solo permitir añadir usuarios registrados con NickServ
+                        let mut registrado = false;
+                        if let Some(nick_db_arc) = &self.databases.nick_db {
+                            let nick_db = nick_db_arc.read().await;
+                            if let Ok(Some(NickRecord { noaccess, .. })) = nick_db.get_nick_info(target_nick).await {
+                                if noaccess {
+                                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Cannot add {target_nick} to access list. User has noaccess mode enabled.")).await?;
+                                    return Ok(());
+                                }
+                                registrado = true;
+                            }
+                        }
+                        if !registrado {
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You can only add users registered with NickServ to the access list.")).await?;
+                            return Ok(());
+                        }
+                    }
+
+                    drop(db);
+                    let mut db = db_arc.write().await;
+
+                    if new_flags.is_empty() {
+                        if !current.is_empty() {
+                            db.delete_channel_access(channel, target_nick).await?;
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{target_nick} has been removed from the access list of {channel}.")).await?;
+                    } else {
+                        if current.is_empty() {
+                            db.add_channel_access(channel, target_nick, &new_flags, nick, SystemTime::now()).await?;
+                        } else {
+                            db.update_channel_access(channel, target_nick, &new_flags, nick, SystemTime::now()).await?;
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Flags for {target_nick} in {channel} are now +{new_flags}.")).await?;
+                    }
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Database is not configured.")).await?;
+                }
+            }
+            "akick" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS AKICK <channel> <add|del|list|enforce> [mask|account] [reason]")).await?;
+                    return Ok(());
+                }
+                let channel = params[0];
+                let action = params[1].to_lowercase();
+
+                if let Some(db_arc) = &self.databases.chan_db {
+                    let db = db_arc.read().await;
+                    let channel_info = if let Some(info) = db.get_channel_info(channel).await? {
+                        info
+                    } else {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Channel '{channel}' is not registered.")).await?;
+                        return Ok(());
+                    };
+
+                    // Check permissions: IRCop, channel owner, or the +r flag
+                    let is_owner = nick == &channel_info.founder;
+                    let is_ircop = self.is_ircop(nick).await;
+                    let has_access = if let Ok(Some(AccessEntry { flags: access_flags, .. })) = db.get_channel_access(channel, nick).await {
+                        has_access_flag(&access_flags, 'r')
+                    } else {
+                        false
+                    };
+
+                    if !is_owner && !is_ircop && !has_access {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You don't have permission to modify the AKICK list of '{channel}'. You need to be an IRCop, owner, or have the +r flag.")).await?;
+                        return Ok(());
+                    }
+
+                    drop(db);
+
+                    match action.as_str() {
+                        "add" => {
+                            if params.len() < 3 {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS AKICK <channel> add <mask|account> [reason]")).await?;
+                                return Ok(());
+                            }
+                            let mask = if is_akick_hostmask(params[2]) {
+                                normalize_sourcemask(params[2])
+                            } else {
+                                params[2].to_string()
+                            };
+                            let reason = if params.len() > 3 {
+                                Some(params[3..].join(" "))
+                            } else {
+                                None
+                            };
+
+                            // Las cuentas deben existir en NickServ
+                            if !is_akick_hostmask(&mask) {
+                                if let Some(nick_db_arc) = &self.databases.nick_db {
+                                    if nick_db_arc.read().await.get_nick_info(&mask).await?.is_none() {
+                                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Account '{mask}' is not registered with NickServ.")).await?;
+                                        return Ok(());
+                                    }
+                                }
+                            }
+
+                            db_arc.write().await.add_channel_akick(channel, &mask, reason.as_deref(), nick, SystemTime::now()).await?;
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{mask} has been added to the AKICK list of {channel}.")).await?;
+                        }
+                        "del" => {
+                            if params.len() < 3 {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Usage: /CS AKICK <channel> del <mask|account>")).await?;
+                                return Ok(());
+                            }
+                            let mask = if is_akick_hostmask(params[2]) {
+                                normalize_sourcemask(params[2])
+                            } else {
+                                params[2].to_string()
+                            };
+                            if db_arc.write().await.delete_channel_akick(channel, &mask).await? {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{mask} has been removed from the AKICK list of {channel}.")).await?;
+                            } else {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{mask} is not on the AKICK list of {channel}.")).await?;
+                            }
+                        }
+                        "list" => {
+                            let akick_list = db_arc.read().await.get_channel_akick_list(channel).await?;
+                            if akick_list.is_empty() {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :The AKICK list of {channel} is empty.")).await?;
+                            } else {
+                                self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :AKICK list of {channel}:")).await?;
+                                for (mask, reason, added_by, added_time) in akick_list {
+                                    let datetime = chrono::DateTime::from_timestamp(added_time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs() as i64, 0)
+                                        .unwrap_or_default()
+                                        .format("%Y-%m-%d %H:%M:%S");
+                                    let reason = reason.unwrap_or_else(|| "No reason".to_string());
+                                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  {mask} ({reason}) (added by {added_by} on {datetime})")).await?;
+                                }
+                            }
+                        }
+                        "enforce" => {
+                            let akick_list = db_arc.read().await.get_channel_akick_list(channel).await?;
+                            let mut state = self.state.write().await;
+                            let members: Vec<(String, String, bool)> = if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
+                                chanobj.users.keys().filter_map(|member| {
+                                    state.users.get(member).map(|u| (member.to_string(), u.source.clone(), u.modes.registered))
+                                }).collect()
+                            } else {
+                                Vec::new()
+                            };
+
+                            let mut kicked = 0;
+                            for (member, source, registered) in members {
+                                let account = if registered { Some(member.as_str()) } else { None };
+                                if let Some((mask, reason)) = akick_list.iter().find(|(mask, _, _, _)| akick_matches(mask, &source, account)).map(|(mask, reason, _, _)| (mask.clone(), reason.clone())) {
+                                    let reason = reason.unwrap_or_else(|| AKICK_DEFAULT_REASON.to_string());
+                                    self.apply_akick(&mut state, channel, &member, &akick_ban_mask(&mask, &source), &reason, true);
+                                    kicked += 1;
+                                }
+                            }
+                            drop(state);
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :AKICK enforced on {channel}: {kicked} user(s) kicked.")).await?;
+                        }
+                        _ => {
+                            self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Invalid action. Use: add, del, list or enforce")).await?;
+                        }
+                    }
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Database is not configured.")).await?;
+                }
+            }
             "help" => {
                 if params.is_empty() {
                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :ChanServ - Channel Registration Service")).await?;
@@ -529,6 +1006,14 @@ impl super::MainState {
                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  HOP <channel> <add|del|list> [nick] - Manage half operators")).await?;
                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  AOP <channel> <add|del|list> [nick] - Manage auto operators")).await?;
                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  SOP <channel> <add|del|list> [nick] - Manage super operators")).await?;
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  SET <channel> <option> <on|off> - Set channel options")).await?;
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  FLAGS <channel> [nick [flags]] - Manage access flags")).await?;
+                    self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  AKICK <channel> <add|del|list|enforce> [mask|account] [reason] - Manage auto-kick list")).await?;
+                    if self.is_ircop(nick).await {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  SUSPEND <channel> [+expiry] <reason> - Suspend a channel (IRCop)")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  UNSUSPEND <channel> - Unsuspend a channel (IRCop)")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  FORBID <channel> <add|del> [reason] - Forbid a channel name (IRCop)")).await?;
+                    }
                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  HELP <command> - Get detailed help for a command")).await?;
                     self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  TRANSFER <channel> <nick> - Transfer channel ownership")).await?;
                     return Ok(());
@@ -554,19 +1039,54 @@ impl super::MainState {
                     "topic" => {
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :TOPIC <channel> <topic>")).await?;
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Sets the topic for a registered channel.")).await?;
-                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You must be the channel founder or have AOP+ access.")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You must be the channel founder or have the +t flag.")).await?;
                     }
                     "mlock" => {
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :MLOCK <channel> <modes>")).await?;
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Sets mode lock for a channel. Use 'OFF' to disable.")).await?;
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Allowed modes: n, t, k, l, m, i, O")).await?;
-                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You must be the channel founder or have AOP+ access.")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You must be the channel founder or have the +s flag.")).await?;
                     }
                     "vop" | "hop" | "aop" | "sop" => {
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :{} <channel> <add|del|list> [nick]", command.to_uppercase())).await?;
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Manages {} access for a channel.", command.to_uppercase())).await?;
-                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Access levels: VOP < HOP < AOP < SOP")).await?;
-                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only SOP and channel founder can modify access.")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Access levels are flag templates: VOP=+v HOP=+h AOP=+otsir SOP=+aotsirf")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Users with custom flags are listed under the highest level whose flags they all have.")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only users with the +f flag and channel founder can modify access. Only the founder can add or remove SOP.")).await?;
+                    }
+                    "suspend" | "unsuspend" => {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :SUSPEND <channel> [+expiry] <reason>")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :UNSUSPEND <channel>")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :A suspended channel cannot be joined or managed. Expiry uses s, m, h, d or w (e.g. +7d).")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only IRCops can suspend or unsuspend channels.")).await?;
+                    }
+                    "forbid" => {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :FORBID <channel> <add|del> [reason]")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :A forbidden channel cannot be registered or joined.")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only IRCops can forbid channels.")).await?;
+                    }
+                    "set" => {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :SET <channel> <option> <on|off>")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  KEEPTOPIC  - restore the stored topic when the channel is recreated")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  TOPICLOCK  - only users with the +t flag can change the topic")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  SECUREOPS  - only users with the +o flag can be channel operators")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  RESTRICTED - users without access cannot join")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  PRIVATE    - hide the channel from LIST")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  GUARD      - keep the channel and its modes when it becomes empty")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :You must be the channel founder or have the +s flag.")).await?;
+                    }
+                    "flags" => {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :FLAGS <channel> [nick [+flags|-flags|template]]")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Shows or modifies the access flags of a channel. A template (VOP, HOP, AOP, SOP) replaces all flags.")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  v/h/o/a - auto voice/halfop/op/protect on join")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :  t - TOPIC, s - SET/MLOCK, i - join invite-only channel, r - AKICK, f - modify access")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Only users with the +f flag and channel founder can modify flags.")).await?;
+                    }
+                    "akick" => {
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :AKICK <channel> <add|del|list|enforce> [mask|account] [reason]")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Manages the auto-kick list. Matching users are banned and kicked when they join.")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :Entries can be hostmasks (nick!user@host) or NickServ account names.")).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :ENFORCE kicks users already in the channel. You must be the channel founder or have the +r flag.")).await?;
                     }
                     "transfer" => {
                         self.feed_msg_source(&mut conn_state.stream, "ChanServ", format!("NOTICE {client} :TRANSFER <channel> <nick>")).await?;
@@ -585,6 +1105,465 @@ impl super::MainState {
         Ok(())
     }
 
+    // Suspensión activa del canal; las suspensiones caducadas se eliminan
+    pub(super) async fn channel_suspension(&self, channel: &str) -> Option<ChannelSuspension> {
+        let db_arc = self.databases.chan_db.as_ref()?;
+        let suspension = db_arc.read().await.get_channel_suspension(channel).await.ok()??;
+        if suspension.expires.is_some_and(|expires| expires <= SystemTime::now()) {
+            let _ = db_arc.write().await.unsuspend_channel(channel).await;
+            info!("Suspension of channel {} has expired", channel);
+            return None;
+        }
+        Some(suspension)
+    }
+
+    // Motivo por el que un usuario no puede entrar en el canal (FORBID o SUSPEND)
+    pub(super) async fn channel_join_denied(&self, channel: &str) -> Option<String> {
+        if let Some(db_arc) = &self.databases.chan_db {
+            if let Ok(Some(ForbiddenEntry { reason, .. })) = db_arc.read().await.get_forbidden_channel(channel).await {
+                return Some(format!("Channel {channel} is forbidden: {reason}"));
+            }
+        }
+        self.channel_suspension(channel)
+            .await
+            .map(|suspension| format!("Channel {channel} is suspended: {}", suspension.reason))
+    }
+
+    // Expulsa del canal a todos los usuarios que no son IRCops
+    pub(super) fn kick_non_opers(&self, state: &mut VolatileState, channel: &str, reason: &str) {
+        let members: Vec<String> = if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
+            chanobj.users.keys().map(|n| n.to_string()).collect()
+        } else {
+            return;
+        };
+        let kicked: Vec<&String> = members
+            .iter()
+            .filter(|member| {
+                !state
+                    .users
+                    .get(&crate::state::structs::to_irccase(member))
+                    .is_some_and(|user| user.modes.is_local_oper())
+            })
+            .collect();
+        for kicked_nick in &kicked {
+            for member in &members {
+                if let Some(user) = state.users.get(&crate::state::structs::to_irccase(member)) {
+                    let _ = user.send_msg_display("ChanServ", format!("KICK {channel} {kicked_nick} :{reason}"));
+                }
+            }
+        }
+        for kicked_nick in kicked {
+            state.remove_user_from_channel(channel, kicked_nick);
+        }
+    }
+
+    // Devuelve si una opción de CS SET está activada en el canal
+    pub(super) async fn chanserv_setting(&self, channel: &str, setting: &str) -> bool {
+        if let Some(db_arc) = &self.databases.chan_db {
+            db_arc.read().await.get_channel_setting(channel, setting).await.unwrap_or(channel_setting_default(setting))
+        } else {
+            false
+        }
+    }
+
+    // Comprueba si el nick es el fundador del canal o está en su lista de acceso
+    pub(super) async fn has_chanserv_access(&self, channel: &str, nick: &str) -> bool {
+        if let Some(db_arc) = &self.databases.chan_db {
+            let db = db_arc.read().await;
+            if let Ok(Some(channel_info)) = db.get_channel_info(channel).await {
+                if channel_info.founder == nick {
+                    return true;
+                }
+            }
+            if let Ok(Some(_)) = db.get_channel_access(channel, nick).await {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Con SECUREOPS solo el fundador y los usuarios con flag +o pueden recibir +o
+    pub(super) async fn secureops_denies(&self, channel: &str, nick: &str) -> bool {
+        if !self.chanserv_setting(channel, "secureops").await {
+            return false;
+        }
+        if let Some(db_arc) = &self.databases.chan_db {
+            let db = db_arc.read().await;
+            if let Ok(Some(channel_info)) = db.get_channel_info(channel).await {
+                if channel_info.founder == nick {
+                    return false;
+                }
+            }
+            if let Ok(Some(AccessEntry { flags, .. })) = db.get_channel_access(channel, nick).await {
+                return !has_access_flag(&flags, 'o');
+            }
+        }
+        true
+    }
+
+    // Al activar SECUREOPS quita el +o a los operadores del canal sin acceso de operador.
+    // Los usuarios de servicio (ChanServ, bots de BotServ) lo conservan.
+    pub(super) async fn secureops_enforce(&self, channel: &str) {
+        let operators: Vec<String> = {
+            let state = self.state.read().await;
+            if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
+                chanobj
+                    .users
+                    .iter()
+                    .filter(|(nick, chum)| chum.operator && !state.users.get(*nick).is_some_and(|user| user.service))
+                    .map(|(nick, _)| nick.to_string())
+                    .collect()
+            } else {
+                return;
+            }
+        };
+        let mut deop = vec![];
+        for nick in operators {
+            if self.secureops_denies(channel, &nick).await {
+                deop.push(nick);
+            }
+        }
+        if deop.is_empty() {
+            return;
+        }
+
+        let mut state = self.state.write().await;
+        let key = crate::state::structs::to_irccase(channel);
+        let chname = state.channels.get_key_value(&key).map(|(name, _)| name.to_string()).unwrap_or_else(|| channel.to_string());
+        if let Some(chanobj) = state.channels.get_mut(&key) {
+            for nick in &deop {
+                if chanobj.users.contains_key(&crate::state::structs::to_irccase(nick)) {
+                    chanobj.remove_operator(nick);
+                }
+            }
+        } else {
+            return;
+        }
+        for nick in &deop {
+            super::botserv::broadcast_to_channel(&state, &chname, CHANSERV_NICK, &format!("MODE {chname} -o {nick}"), None);
+        }
+    }
+
+    // Canales registrados con PRIVATE activado, que no se muestran en LIST
+    pub(super) async fn private_channels(&self) -> HashSet<IrcCase<String>> {
+        if let Some(db_arc) = &self.databases.chan_db {
+            if let Ok(channels) = db_arc.read().await.get_channels_with_setting("private").await {
+                return channels.iter().map(|c| crate::state::structs::to_irccase(c)).collect();
+            }
+        }
+        HashSet::new()
+    }
+
+    // Con GUARD ChanServ se queda en el canal, que así no se elimina y conserva sus modos.
+    // Si el canal no existe se crea con el topic y los modos guardados. Al quitar GUARD
+    // ChanServ sale del canal.
+    pub(super) async fn guard_channel(&self, channel: &str, enabled: bool) {
+        let chanserv_key = crate::state::structs::to_irccase(CHANSERV_NICK);
+        if enabled && !self.state.read().await.users.contains_key(&chanserv_key) {
+            self.create_bot_user(CHANSERV_NICK, CHANSERV_NICK, &self.config.name, "Channel Services").await;
+        }
+        let channel_info = if let Some(db_arc) = &self.databases.chan_db {
+            db_arc.read().await.get_channel_info(channel).await.ok().flatten()
+        } else {
+            None
+        };
+        let keeptopic = self.chanserv_setting(channel, "keeptopic").await;
+        let saved = self.saved_channel_modes(channel).await;
+        let configured = self.config.channels.as_ref().is_some_and(|channels| {
+            channels.iter().any(|c| casefold(&c.name) == casefold(channel))
+        });
+
+        let mut statem = self.state.write().await;
+        let state = statem.deref_mut();
+        // un usuario conectado con el nick de ChanServ no es el servicio
+        let is_service = state.users.get(&chanserv_key).is_some_and(|user| user.service);
+        if !enabled && is_service {
+            self.bot_part_channel(state, CHANSERV_NICK, channel, "Guard disabled");
+            if state.users.get(&chanserv_key).is_some_and(|user| user.channels.is_empty()) {
+                state.remove_user(CHANSERV_NICK);
+            }
+        }
+        if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
+            chanobj.preconfigured = enabled || configured;
+            if chanobj.users.is_empty() && !chanobj.preconfigured && !chanobj.modes.permanent {
+                info!("Channel {} has been removed", channel);
+                state.channels.remove(&crate::state::structs::to_irccase(channel));
+            }
+        } else if enabled {
+            let mut modes = ChannelModes::default();
+            let mut topic = None;
+            if let Some(channel_info) = channel_info {
+                if let Some(modes_str) = &channel_info.modes {
+                    self.apply_stored_modes(&mut modes, modes_str);
+                }
+                if keeptopic {
+                    if let Some(topic_text) = channel_info.topic {
+                        topic = Some(ChannelTopic {
+                            topic: topic_text,
+                            nick: channel_info.topic_setter.unwrap_or(channel_info.founder),
+                            set_time: channel_info.topic_time.unwrap_or_else(SystemTime::now)
+                                .duration_since(SystemTime::UNIX_EPOCH)
+                                .unwrap_or_default()
+                                .as_secs(),
+                        });
+                    }
+                }
+            }
+            let mut chanobj = Channel {
+                topic,
+                modes,
+                default_modes: ChannelDefaultModes::default(),
+                ban_info: HashMap::new(),
+                quiet_info: HashMap::new(),
+                flood: FloodTracker::default(),
+                users: HashMap::new(),
+                creation_time: SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                preconfigured: true,
+            };
+            if let Some(saved) = saved {
+                self.apply_saved_channel_modes(channel, &mut chanobj, saved);
+            }
+            state.channels.insert(crate::state::structs::to_irccase(channel), chanobj);
+        }
+        if enabled && is_service {
+            self.bot_join_channel(state, CHANSERV_NICK, channel);
+        }
+    }
+
+    // Crea al arrancar los canales registrados con GUARD activado
+    pub(super) async fn restore_guarded_channels(&self) {
+        let channels = if let Some(db_arc) = &self.databases.chan_db {
+            db_arc.read().await.get_channels_with_setting("guard").await.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        for channel in channels {
+            self.guard_channel(&channel, true).await;
+        }
+    }
+
+    // Guarda los cambios de las listas b/e/I/Z, la clave y el límite si el canal está registrado.
+    // Se llama sin el estado bloqueado, igual que los comandos de ChanServ.
+    pub(super) async fn save_channel_modes(&self, channel: &str, set_by: &str, changes: SavedModeChanges) {
+        if changes.is_empty() {
+            return;
+        }
+        let Some(db_arc) = &self.databases.chan_db else {
+            return;
+        };
+        let mut db = db_arc.write().await;
+        if !matches!(db.get_channel_info(channel).await, Ok(Some(_))) {
+            return;
+        }
+        let now = SystemTime::now();
+        for (list, set, mask, expires_at) in changes.lists {
+            let result = if set {
+                let expires = expires_at.map(|t| SystemTime::UNIX_EPOCH + Duration::from_secs(t));
+                db.add_channel_list_entry(channel, list, &mask, set_by, now, expires).await
+            } else {
+                db.delete_channel_list_entry(channel, list, &mask).await.map(|_| ())
+            };
+            if let Err(e) = result {
+                error!("Can't save mode {}{} {} of channel {}: {}", if set { '+' } else { '-' }, list, mask, channel, e);
+            }
+        }
+        if let Some((key, limit)) = changes.key_limit {
+            if let Err(e) = db.set_channel_key_limit(channel, key.as_deref(), limit).await {
+                error!("Can't save key and limit of channel {}: {}", channel, e);
+            }
+        }
+    }
+
+    // Listas, clave y límite guardados de un canal registrado
+    pub(super) async fn saved_channel_modes(&self, channel: &str) -> Option<SavedChannelModes> {
+        let db_arc = self.databases.chan_db.as_ref()?;
+        let db = db_arc.read().await;
+        db.get_channel_info(channel).await.ok()??;
+        let lists = db.get_channel_list_entries(channel).await.ok()?;
+        let (key, limit) = db.get_channel_key_limit(channel).await.ok()?;
+        Some(SavedChannelModes { lists, key, limit })
+    }
+
+    // Pone en el canal las listas, la clave y el límite guardados. Las entradas con
+    // expiración se quitan a su hora; las que ya han caducado no se aplican.
+    pub(super) fn apply_saved_channel_modes(&self, channel: &str, chanobj: &mut Channel, saved: SavedChannelModes) {
+        let now = SystemTime::now();
+        for entry in saved.lists {
+            if let Some(expires) = entry.expires {
+                let delay = expires.duration_since(now).unwrap_or_default();
+                self.schedule_list_expiry(channel, entry.list, &entry.mask, delay);
+                if delay.is_zero() {
+                    continue;
+                }
+            }
+            let list = match entry.list {
+                'b' => &mut chanobj.modes.ban,
+                'e' => &mut chanobj.modes.exception,
+                'Z' => &mut chanobj.modes.quiet,
+                _ => &mut chanobj.modes.invite_exception,
+            };
+            list.get_or_insert_with(HashSet::new).insert(entry.mask.clone());
+            let info = match entry.list {
+                'b' => Some(&mut chanobj.ban_info),
+                'Z' => Some(&mut chanobj.quiet_info),
+                _ => None,
+            };
+            if let Some(info) = info {
+                let to_secs = |time: SystemTime| time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs();
+                info.insert(
+                    crate::state::structs::to_irccase(&entry.mask),
+                    BanInfo {
+                        set_time: to_secs(entry.set_time),
+                        who: entry.set_by,
+                        expires_at: entry.expires.map(to_secs),
+                    },
+                );
+            }
+        }
+        if saved.key.is_some() {
+            chanobj.modes.key = saved.key;
+        }
+        if saved.limit.is_some() {
+            chanobj.modes.client_limit = saved.limit;
+        }
+    }
+
+    // Quita una entrada de la lista b/e/I/Z del canal cuando caduca, avisando a los
+    // usuarios del canal, y la borra de la base de datos
+    pub(super) fn schedule_list_expiry(&self, channel: &str, list: char, mask: &str, delay: Duration) {
+        let channel_name = channel.to_string();
+        let mask = mask.to_string();
+        let state_clone = self.state.clone();
+        let config_clone = self.config.clone();
+        let chan_db = self.databases.chan_db.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            {
+                let mut state = state_clone.write().await;
+                if let Some(channel) = state.channels.get_mut(&crate::state::structs::to_irccase(&channel_name)) {
+                    let list_set = match list {
+                        'b' => &mut channel.modes.ban,
+                        'e' => &mut channel.modes.exception,
+                        'Z' => &mut channel.modes.quiet,
+                        _ => &mut channel.modes.invite_exception,
+                    };
+                    if list_set.as_mut().is_some_and(|l| l.remove(&mask)) {
+                        match list {
+                            'b' => channel.ban_info.remove(&crate::state::structs::to_irccase(&mask)),
+                            'Z' => channel.quiet_info.remove(&crate::state::structs::to_irccase(&mask)),
+                            _ => None,
+                        };
+                        // Notificar a los usuarios del canal
+                        let nicks: Vec<String> = channel.users.keys().map(|nick| nick.to_string()).collect();
+                        for nick in nicks {
+                            if let Some(user) = state.users.get(&crate::state::structs::to_irccase(&nick)) {
+                                let _ = user.send_msg_display(&config_clone.name, format!("MODE {channel_name} -{list} {mask}"));
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(db_arc) = chan_db {
+                let _ = db_arc.write().await.delete_channel_list_entry(&channel_name, list, &mask).await;
+            }
+        });
+    }
+
+    // Al arrancar, aplica las listas, la clave y el límite guardados a los canales
+    // registrados que ya existen por estar en la configuración
+    pub(super) async fn restore_channel_modes(&self) {
+        let channels: Vec<String> = self.state.read().await.channels.keys().map(|c| c.to_string()).collect();
+        for channel in channels {
+            let Some(saved) = self.saved_channel_modes(&channel).await else {
+                continue;
+            };
+            let mut state = self.state.write().await;
+            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(&channel)) {
+                self.apply_saved_channel_modes(&channel, chanobj, saved);
+            }
+        }
+    }
+
+    // Comprueba si el nick tiene un flag de acceso concreto en el canal
+    pub(super) async fn has_chanserv_flag(&self, channel: &str, nick: &str, flag: char) -> bool {
+        if let Some(db_arc) = &self.databases.chan_db {
+            if let Ok(Some(AccessEntry { flags, .. })) = db_arc.read().await.get_channel_access(channel, nick).await {
+                return has_access_flag(&flags, flag);
+            }
+        }
+        false
+    }
+
+    // Busca la primera entrada AKICK del canal que coincida con el usuario.
+    // Devuelve la máscara y el motivo de la entrada.
+    pub(super) async fn find_akick(
+        &self,
+        channel: &str,
+        source: &str,
+        account: Option<&str>,
+    ) -> Option<(String, Option<String>)> {
+        let db_arc = self.databases.chan_db.as_ref()?;
+        let akick_list = db_arc.read().await.get_channel_akick_list(channel).await.ok()?;
+        akick_list
+            .into_iter()
+            .find(|(mask, _, _, _)| akick_matches(mask, source, account))
+            .map(|(mask, reason, _, _)| (mask, reason))
+    }
+
+    // Pone el ban de una entrada AKICK en el canal y, si se pide, expulsa al usuario.
+    pub(super) fn apply_akick(
+        &self,
+        state: &mut VolatileState,
+        channel: &str,
+        nick: &str,
+        ban_mask: &str,
+        reason: &str,
+        kick: bool,
+    ) {
+        let chanobj = if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(channel)) {
+            chanobj
+        } else {
+            return;
+        };
+
+        let mut ban = chanobj.modes.ban.take().unwrap_or_default();
+        let new_ban = ban.insert(ban_mask.to_string());
+        chanobj.modes.ban = Some(ban);
+        if new_ban {
+            chanobj.ban_info.insert(
+                crate::state::structs::to_irccase(ban_mask),
+                BanInfo {
+                    set_time: SystemTime::now()
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                    who: "ChanServ".to_string(),
+                    expires_at: None,
+                },
+            );
+        }
+
+        let members: Vec<String> = chanobj.users.keys().map(|n| n.to_string()).collect();
+        for member in &members {
+            if let Some(user) = state.users.get(&crate::state::structs::to_irccase(member)) {
+                if new_ban {
+                    let _ = user.send_msg_display("ChanServ", format!("MODE {channel} +b {ban_mask}"));
+                }
+                if kick {
+                    let _ = user.send_msg_display("ChanServ", format!("KICK {channel} {nick} :{reason}"));
+                }
+            }
+        }
+        if kick {
+            state.remove_user_from_channel(channel, nick);
+        }
+    }
+
     fn validate_mlock_modes(&self, modes: &str) -> bool {
         if modes.is_empty() {
             return true; // Permitir mlock vacío
@@ -595,12 +1574,13 @@ impl super::MainState {
             return false;
         }
         
-        // Modos permitidos para mlock: n, t, k, l, m, i, O
+        // Modos permitidos para mlock: n, t, k, l, m, i, O, r y los de FLAG_MODES salvo +P,
+        // que sólo pueden poner los IRCops
         let allowed_modes = ['n', 't', 'k', 'l', 'm', 'i', 'O', 'r'];
         
         // Verificar cada carácter después del signo
         for c in modes[1..].chars() {
-            if !allowed_modes.contains(&c) {
+            if !allowed_modes.contains(&c) && (c == 'P' || !crate::config::FLAG_MODES.contains(c)) {
                 return false;
             }
         }
@@ -676,6 +1656,12 @@ impl super::MainState {
                                 channel_modes.registered = true;
                                 chars.next();
                             }
+                            mode if crate::config::FLAG_MODES.contains(mode) => {
+                                if let Some(flag) = channel_modes.flag_mut(mode) {
+                                    *flag = true;
+                                }
+                                chars.next();
+                            }
                             _ => {
                                 // Modo desconocido, saltarlo
                                 chars.next();
@@ -723,6 +1709,12 @@ impl super::MainState {
                                 channel_modes.registered = false;
                                 chars.next();
                             }
+                            mode if crate::config::FLAG_MODES.contains(mode) => {
+                                if let Some(flag) = channel_modes.flag_mut(mode) {
+                                    *flag = false;
+                                }
+                                chars.next();
+                            }
                             _ => {
                                 // Modo desconocido, saltarlo
                                 chars.next();
@@ -739,4 +1731,425 @@ impl super::MainState {
     }
 }
 
+pub(super) const AKICK_DEFAULT_REASON: &str = "You are not welcome on this channel";
+
+// Nick del usuario de servicio que ChanServ mete en los canales con GUARD
+pub(super) const CHANSERV_NICK: &str = "ChanServ";
+
+// Cambios hechos con MODE que se guardan si el canal está registrado: entradas de
+// las listas b/e/I/Z (lista, añadida, máscara, expiración) y la clave y el límite nuevos
+#[derive(Default)]
+pub(super) struct SavedModeChanges {
+    pub(super) lists: Vec<(char, bool, String, Option<u64>)>,
+    pub(super) key_limit: Option<(Option<String>, Option<usize>)>,
+}
+
+impl SavedModeChanges {
+    fn is_empty(&self) -> bool {
+        self.lists.is_empty() && self.key_limit.is_none()
+    }
+}
+
+pub(super) struct SavedChannelModes {
+    pub(super) lists: Vec<ChannelListEntry>,
+    pub(super) key: Option<String>,
+    pub(super) limit: Option<usize>,
+}
+
+impl SavedChannelModes {
+    // Comprueba los bans y excepciones guardados antes de recrear el canal vacío
+    pub(super) fn banned(&self, target: &BanTarget, inherited: &dyn Fn(&str) -> bool) -> bool {
+        let now = SystemTime::now();
+        let masks = |list: char| -> HashSet<String> {
+            self.lists
+                .iter()
+                .filter(|e| e.list == list && e.expires.is_none_or(|t| t > now))
+                .map(|e| e.mask.clone())
+                .collect()
+        };
+        let modes = ChannelModes {
+            ban: Some(masks('b')),
+            exception: Some(masks('e')),
+            ..ChannelModes::default()
+        };
+        modes.banned_user(target, false, inherited)
+    }
+}
+
+// Las entradas AKICK con '!' o '@' son máscaras; el resto son cuentas de NickServ.
+pub(super) fn is_akick_hostmask(mask: &str) -> bool {
+    mask.contains('!') || mask.contains('@')
+}
+
+pub(super) fn akick_matches(mask: &str, source: &str, account: Option<&str>) -> bool {
+    if is_akick_hostmask(mask) {
+        match_wildcard(mask, source)
+    } else {
+        account.is_some_and(|account| casefold(account) == casefold(mask))
+    }
+}
+
+// Máscara de ban para una entrada AKICK: las máscaras se usan tal cual y para
+// las cuentas se banea el host del usuario.
+pub(super) fn akick_ban_mask(mask: &str, source: &str) -> String {
+    if is_akick_hostmask(mask) {
+        mask.to_string()
+    } else {
+        let host = source.rsplit_once('@').map(|(_, host)| host).unwrap_or(source);
+        format!("*!*@{host}")
+    }
+}
+
+// Fecha en el formato que usan las respuestas de los servicios
+pub(super) fn format_service_time(time: SystemTime) -> String {
+    chrono::DateTime::from_timestamp(time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs() as i64, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_chanserv_guard_join_part() {
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "ben", "ben", "Ben Guard").await;
+            line_stream.send("JOIN #guarded".to_string()).await.unwrap();
+            skip_until(&mut line_stream, " 366 ").await;
+            line_stream.send("CS REGISTER #guarded".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been registered").await;
+
+            line_stream.send("CS SET #guarded GUARD on".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "GUARD for #guarded is now on").await;
+            assert_eq!(
+                ":ChanServ!ChanServ@irc.irc JOIN #guarded".to_string(),
+                skip_until(&mut line_stream, "JOIN").await
+            );
+            assert_eq!(
+                ":ChanServ MODE #guarded +o ChanServ".to_string(),
+                skip_until(&mut line_stream, "MODE").await
+            );
+            {
+                let state = main_state.state.read().await;
+                assert!(state.channels[&to_irccase("#guarded")].users.contains_key(&to_irccase("ChanServ")));
+                assert!(state.users[&to_irccase("ChanServ")].service);
+            }
+
+            line_stream.send("CS SET #guarded GUARD off".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "GUARD for #guarded is now off").await;
+            assert_eq!(
+                ":ChanServ!ChanServ@irc.irc PART #guarded :Guard disabled".to_string(),
+                skip_until(&mut line_stream, "PART").await
+            );
+            {
+                let state = main_state.state.read().await;
+                assert!(!state.channels[&to_irccase("#guarded")].users.contains_key(&to_irccase("ChanServ")));
+                assert!(!state.users.contains_key(&to_irccase("ChanServ")));
+            }
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_chanserv_keeptopic_default() {
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "ben", "ben", "Ben Topic").await;
+            line_stream.send("JOIN #kept".to_string()).await.unwrap();
+            skip_until(&mut line_stream, " 366 ").await;
+            line_stream.send("CS REGISTER #kept".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been registered").await;
+            line_stream.send("TOPIC #kept :Stored topic".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "TOPIC #kept").await;
+            line_stream.send("PART #kept".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "PART #kept").await;
+            assert!(!main_state.state.read().await.channels.contains_key(&to_irccase("#kept")));
+
+            // sin CS SET KEEPTOPIC el topic guardado vuelve al recrear el canal
+            line_stream.send("JOIN #kept".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 332 ben #kept :Stored topic".to_string(),
+                skip_until(&mut line_stream, " 332 ").await
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_chanserv_saved_key_join() {
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "ben", "ben", "Ben Key").await;
+            line_stream.send("JOIN #locked".to_string()).await.unwrap();
+            skip_until(&mut line_stream, " 366 ").await;
+            line_stream.send("CS REGISTER #locked".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been registered").await;
+            // +k en el MLOCK y la clave guardada con MODE
+            line_stream.send("CS MLOCK #locked +k".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "MLock").await;
+            line_stream.send("MODE #locked +k secret".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "MODE #locked +k").await;
+            line_stream.send("PART #locked".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "PART #locked").await;
+
+            let mut line_stream2 = login_to_test_and_skip(port, "jim", "jim", "Jim Key").await;
+            // una sola respuesta por JOIN, sin clave y con una clave incorrecta
+            line_stream2.send("JOIN #locked".to_string()).await.unwrap();
+            line_stream2.send("JOIN #locked wrong".to_string()).await.unwrap();
+            line_stream2.send("PING :done".to_string()).await.unwrap();
+            let mut replies = Vec::new();
+            loop {
+                let line = line_stream2.next().await.unwrap().unwrap();
+                if line.contains("PONG") {
+                    break;
+                }
+                replies.push(line);
+            }
+            assert_eq!(vec![":irc.irc 475 jim #locked :Cannot join channel (+k)".to_string(); 2], replies);
+
+            line_stream2.send("JOIN #locked secret".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, " 366 ").await;
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_chanserv_akick_join() {
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "ben", "ben", "Ben Kicker").await;
+            line_stream.send("JOIN #kicks".to_string()).await.unwrap();
+            skip_until(&mut line_stream, " 366 ").await;
+            line_stream.send("CS REGISTER #kicks".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been registered").await;
+
+            // entrada con máscara
+            line_stream.send("CS AKICK #kicks add bad!*@* Go away".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been added to the AKICK list").await;
+            let mut line_stream2 = login_to_test_and_skip(port, "bad", "bad", "Bad Guy").await;
+            line_stream2.send("JOIN #kicks".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 474 bad #kicks :Cannot join channel (+b)".to_string(),
+                skip_until(&mut line_stream2, " 474 ").await
+            );
+            assert_eq!(
+                ":ChanServ NOTICE bad :You are on the AKICK list of #kicks: Go away".to_string(),
+                skip_until(&mut line_stream2, "NOTICE").await
+            );
+            assert_eq!(
+                ":ChanServ MODE #kicks +b bad!*@*".to_string(),
+                skip_until(&mut line_stream, "MODE #kicks +b").await
+            );
+            assert!(!main_state.state.read().await.channels[&to_irccase("#kicks")].users.contains_key(&to_irccase("bad")));
+
+            // después de AKICK DEL y de quitar el ban puede entrar
+            line_stream.send("CS AKICK #kicks del bad!*@*".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been removed from the AKICK list").await;
+            line_stream.send("MODE #kicks -b bad!*@*".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "MODE #kicks -b").await;
+            line_stream2.send("JOIN #kicks".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, " 366 ").await;
+            assert!(main_state.state.read().await.channels[&to_irccase("#kicks")].users.contains_key(&to_irccase("bad")));
+
+            // entrada con una cuenta de NickServ: se banea el host del usuario
+            {
+                let mut line_stream3 = login_to_test_and_skip(port, "jerry", "jerry", "Jerry Lazy").await;
+                line_stream3.send("NS REGISTER password jerry@example.org".to_string()).await.unwrap();
+                skip_until(&mut line_stream3, "has been registered").await;
+            }
+            line_stream.send("CS AKICK #kicks add jerry".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been added to the AKICK list").await;
+            // sin identificarse no coincide con la cuenta
+            let mut line_stream3 = login_to_test_and_skip(port, "tom", "jerry", "Jerry Lazy").await;
+            line_stream3.send("JOIN #kicks".to_string()).await.unwrap();
+            skip_until(&mut line_stream3, " 366 ").await;
+            line_stream3.send("PART #kicks".to_string()).await.unwrap();
+            skip_until(&mut line_stream3, "PART #kicks").await;
+            line_stream3.send("NS IDENTIFY jerry password".to_string()).await.unwrap();
+            skip_until(&mut line_stream3, "successfully identified").await;
+            line_stream3.send("JOIN #kicks".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 474 jerry #kicks :Cannot join channel (+b)".to_string(),
+                skip_until(&mut line_stream3, " 474 ").await
+            );
+            assert_eq!(
+                format!(":ChanServ NOTICE jerry :You are on the AKICK list of #kicks: {AKICK_DEFAULT_REASON}"),
+                skip_until(&mut line_stream3, "AKICK list").await
+            );
+            assert_eq!(
+                ":ChanServ MODE #kicks +b *!*@127.0.0.1".to_string(),
+                skip_until(&mut line_stream, "MODE #kicks +b").await
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_chanserv_suspend_forbid() {
+        let mut config = memory_database_config();
+        config.operators = Some(vec![OperatorConfig {
+            name: "fanny".to_string(),
+            password: argon2_hash_password("Funny"),
+            mask: None,
+        }]);
+        let (main_state, handle, port) = run_test_server(config).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "fanny", "fanny", "Fanny BumBumBum").await;
+            line_stream.send("OPER fanny Funny".to_string()).await.unwrap();
+            line_stream.next().await.unwrap().unwrap();
+
+            let mut line_stream2 = login_to_test_and_skip(port, "ben", "ben", "Ben Suspended").await;
+            line_stream2.send("JOIN #susp".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, " 366 ").await;
+            line_stream2.send("CS REGISTER #susp".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "has been registered").await;
+
+            // al suspender el canal se expulsa a los que no son IRCops y no pueden volver
+            line_stream.send("CS SUSPEND #susp +1d Abuse".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been suspended").await;
+            assert_eq!(
+                ":ChanServ KICK #susp ben :Channel suspended: Abuse".to_string(),
+                skip_until(&mut line_stream2, "KICK").await
+            );
+            line_stream2.send("JOIN #susp".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 474 ben #susp :Cannot join channel (+b)".to_string(),
+                skip_until(&mut line_stream2, " 474 ").await
+            );
+            assert_eq!(
+                ":ChanServ NOTICE ben :Channel #susp is suspended: Abuse".to_string(),
+                skip_until(&mut line_stream2, "NOTICE").await
+            );
+
+            line_stream.send("CS UNSUSPEND #susp".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "is no longer suspended").await;
+            line_stream2.send("JOIN #susp".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, " 366 ").await;
+
+            // un canal prohibido no se puede registrar ni usar
+            line_stream.send("CS FORBID #evil add Spam".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "is now forbidden").await;
+            line_stream2.send("JOIN #evil".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc 474 ben #evil :Cannot join channel (+b)".to_string(),
+                skip_until(&mut line_stream2, " 474 ").await
+            );
+            assert_eq!(
+                ":ChanServ NOTICE ben :Channel #evil is forbidden: Spam".to_string(),
+                skip_until(&mut line_stream2, "NOTICE").await
+            );
+            assert!(!main_state.state.read().await.channels.contains_key(&to_irccase("#evil")));
+            line_stream.send("JOIN #evil".to_string()).await.unwrap();
+            skip_until(&mut line_stream, " 366 ").await;
+            line_stream.send("CS REGISTER #evil".to_string()).await.unwrap();
+            assert_eq!(
+                ":ChanServ NOTICE fanny :Channel '#evil' is forbidden and cannot be registered.".to_string(),
+                skip_until(&mut line_stream, "NOTICE").await
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_chanserv_secureops_deop() {
+        // jim tiene +o por defecto en el canal preconfigurado
+        let mut config = memory_database_config();
+        config.channels = Some(vec![ChannelConfig {
+            name: "#secure".to_string(),
+            topic: None,
+            modes: ChannelModes {
+                operators: Some(["jim".to_string()].into()),
+                ..ChannelModes::default()
+            },
+        }]);
+        let (main_state, handle, port) = run_test_server(config).await;
+
+        {
+            let mut line_stream2 = login_to_test_and_skip(port, "jim", "jim", "Jim Opped").await;
+            line_stream2.send("JOIN #secure".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, " 366 ").await;
 
+            let mut line_stream = login_to_test_and_skip(port, "ben", "ben", "Ben Secure").await;
+            line_stream.send("JOIN #secure".to_string()).await.unwrap();
+            skip_until(&mut line_stream, " 366 ").await;
+            line_stream.send("CS REGISTER #secure".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been registered").await;
+            assert!(main_state.state.read().await.channels[&to_irccase("#secure")].users[&to_irccase("jim")].operator);
+
+            // al activar SECUREOPS jim pierde el +o
+            line_stream.send("CS SET #secure SECUREOPS on".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "SECUREOPS for #secure is now on").await;
+            assert_eq!(
+                ":ChanServ MODE #secure -o jim".to_string(),
+                skip_until(&mut line_stream2, "MODE #secure -o").await
+            );
+            assert!(!main_state.state.read().await.channels[&to_irccase("#secure")].users[&to_irccase("jim")].operator);
+
+            // y tampoco lo recibe al volver a entrar
+            line_stream2.send("PART #secure".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "PART #secure").await;
+            line_stream2.send("JOIN #secure".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, " 366 ").await;
+            assert!(!main_state.state.read().await.channels[&to_irccase("#secure")].users[&to_irccase("jim")].operator);
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_chanserv_xop_founder_flag() {
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "ben", "ben", "Ben Founder").await;
+            line_stream.send("JOIN #xop".to_string()).await.unwrap();
+            skip_until(&mut line_stream, " 366 ").await;
+            line_stream.send("CS REGISTER #xop".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been registered").await;
+
+            let mut line_stream2 = login_to_test_and_skip(port, "jim", "jim", "Jim Manager").await;
+            line_stream2.send("NS REGISTER password jim@example.org".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "has been registered").await;
+            let mut line_stream3 = login_to_test_and_skip(port, "tom", "tom", "Tom Member").await;
+            line_stream3.send("NS REGISTER password tom@example.org".to_string()).await.unwrap();
+            skip_until(&mut line_stream3, "has been registered").await;
+
+            line_stream.send("CS FLAGS #xop jim +f".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "Flags for jim in #xop are now +f.").await;
+
+            // con +f se gestionan los niveles, pero SOP incluye +f y solo lo da el fundador
+            line_stream2.send("CS SOP #xop ADD tom".to_string()).await.unwrap();
+            assert_eq!(
+                ":ChanServ NOTICE jim :Only the channel founder can change the +f flag.".to_string(),
+                skip_until(&mut line_stream2, "NOTICE").await
+            );
+            line_stream2.send("CS AOP #xop ADD tom".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "tom has been added to the AOP list of #xop.").await;
+
+            // con flags personalizados sigue en la lista del nivel que cubren
+            line_stream.send("CS FLAGS #xop tom +v".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "Flags for tom in #xop are now +votsir.").await;
+            line_stream2.send("CS AOP #xop LIST".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "List AOP of #xop:").await;
+            assert!(skip_until(&mut line_stream2, "NOTICE").await.contains("  tom (added by ben on "));
+            line_stream2.send("CS VOP #xop LIST".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "No users with VOP access in #xop.").await;
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+}
@@ -22,22 +22,13 @@ use serde::ser::StdError;
 use std::ops::DerefMut;
 use std::sync::atomic::Ordering;
 use crate::utils::argon2_verify_password_async;
-#[cfg(any(feature = "sqlite", feature = "mysql"))]
 use base64::Engine;
 struct SupportTokenIntValue {
     name: &'static str,
     value: usize,
 }
 
-static SUPPORT_TOKEN_INT_VALUE: [SupportTokenIntValue; 13] = [
-    SupportTokenIntValue {
-        name: "AWAYLEN",
-        value: 1000,
-    },
-    SupportTokenIntValue {
-        name: "CHANNELLEN",
-        value: 1000,
-    },
+static SUPPORT_TOKEN_INT_VALUE: [SupportTokenIntValue; 5] = [
     SupportTokenIntValue {
         name: "HOSTLEN",
         value: 1000,
@@ -46,42 +37,18 @@ static SUPPORT_TOKEN_INT_VALUE: [SupportTokenIntValue; 13] = [
         name: "KEYLEN",
         value: 1000,
     },
-    SupportTokenIntValue {
-        name: "KICKLEN",
-        value: 1000,
-    },
     SupportTokenIntValue {
         name: "LINELEN",
         value: 2000,
     },
-    SupportTokenIntValue {
-        name: "MAXNICKLEN",
-        value: 200,
-    },
     SupportTokenIntValue {
         name: "MAXPARA",
         value: 500,
     },
-    SupportTokenIntValue {
-        name: "MAXTARGETS",
-        value: 500,
-    },
     SupportTokenIntValue {
         name: "MODES",
         value: 500,
     },
-    SupportTokenIntValue {
-        name: "NICKLEN",
-        value: 200,
-    },
-    SupportTokenIntValue {
-        name: "TOPICLEN",
-        value: 1000,
-    },
-    SupportTokenIntValue {
-        name: "USERLEN",
-        value: 200,
-    },
 ];
 
 impl std::fmt::Display for SupportTokenIntValue {
@@ -95,14 +62,14 @@ struct SupportTokenStringValue {
     value: &'static str,
 }
 
-static SUPPORT_TOKEN_STRING_VALUE: [SupportTokenStringValue; 9] = [
+static SUPPORT_TOKEN_STRING_VALUE: [SupportTokenStringValue; 8] = [
     SupportTokenStringValue {
-        name: "CASEMAPPING",
-        value: "ascii",
+        name: "CALLERID",
+        value: "g",
     },
     SupportTokenStringValue {
         name: "CHANMODES",
-        value: "bBeI,k,l,ahimnopqrstvO",
+        value: "bBeIZ,k,fjlL,achimnopqrstvzCKMOPRST",
     },
     SupportTokenStringValue {
         name: "CHANTYPES",
@@ -116,10 +83,6 @@ static SUPPORT_TOKEN_STRING_VALUE: [SupportTokenStringValue; 9] = [
         name: "INVEX",
         value: "I",
     },
-    SupportTokenStringValue {
-        name: "MAXLIST",
-        value: "beI:1000",
-    },
     SupportTokenStringValue {
         name: "PREFIX",
         value: "(qaohv)~&@%+",
@@ -130,7 +93,7 @@ static SUPPORT_TOKEN_STRING_VALUE: [SupportTokenStringValue; 9] = [
     },
     SupportTokenStringValue {
         name: "USERMODES",
-        value: "OiorwWz",
+        value: "OgiorwRWz",
     },
 ];
 
@@ -140,7 +103,7 @@ impl std::fmt::Display for SupportTokenStringValue {
     }
 }
 
-static SUPPORT_TOKEN_BOOL_VALUE: [&str; 2] = ["FNC", "SAFELIST"];
+static SUPPORT_TOKEN_BOOL_VALUE: [&str; 3] = ["FNC", "KNOCK", "SAFELIST"];
 
 impl super::MainState {
     pub(super) async fn process_cap<'a>(
@@ -151,18 +114,11 @@ impl super::MainState {
         _: Option<u32>,
     ) -> Result<(), Box<dyn StdError + Send + Sync>> {
         match subcommand {
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             CapCommand::LS => {
                 conn_state.caps_negotation = true;
                 self.feed_msg(&mut conn_state.stream, "CAP * LS :multi-prefix sasl message-tags batch labeled-response chathistory read-marker echo-message setname userhost-in-names invite-notify monitor watch")
                     .await
             }
-            #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-            CapCommand::LS => {
-                conn_state.caps_negotation = true;
-                self.feed_msg(&mut conn_state.stream, "CAP * LS :multi-prefix message-tags batch labeled-response chathistory read-marker echo-message setname userhost-in-names invite-notify monitor watch")
-                    .await
-            }
             CapCommand::LIST => {
                 self.feed_msg(
                     &mut conn_state.stream,
@@ -214,7 +170,27 @@ impl super::MainState {
     ) -> Result<(), Box<dyn StdError + Send + Sync>> {
         let client = conn_state.user_state.client_name();
         // support tokens
-        let mut tokens = vec![format!("NETWORK={}", self.config.network)];
+        let mut tokens = vec![
+            format!("NETWORK={}", self.config.network),
+            format!("ELIST={ELIST_TYPES}"),
+            format!("EXTBAN={EXTBAN_PREFIX},{EXTBAN_TYPES}"),
+            format!("CASEMAPPING={}", self.config.casemapping.name()),
+        ];
+        // límites de la sección [limits] de la configuración
+        let limits = &self.config.limits;
+        tokens.extend([
+            format!("AWAYLEN={}", limits.away_len),
+            format!("CHANNELLEN={}", limits.channel_len),
+            format!("KICKLEN={}", limits.kick_len),
+            format!("MAXLIST=beIZ:{}", limits.max_list),
+            format!("MAXNICKLEN={}", limits.nick_len),
+            format!("MAXTARGETS={}", limits.max_targets),
+            format!("NICKLEN={}", limits.nick_len),
+            format!("SILENCE={}", limits.max_silence),
+            format!("ACCEPT={}", limits.max_accept),
+            format!("TOPICLEN={}", limits.topic_len),
+            format!("USERLEN={}", limits.user_len),
+        ]);
         if let Some(max_joins) = self.config.max_joins {
             tokens.push(format!("CHANLIMIT=&#:{}", max_joins));
             tokens.push(format!("MAXCHANNELS={}", max_joins));
@@ -308,12 +284,12 @@ impl super::MainState {
                                         .await
                                         .is_ok()
                                 } else {
-                                    true
+                                    // sin PASS no se puede entrar si hay contraseña configurada
+                                    false
                                 };
 
                                 // Verificar contraseña de NickServ si el nick está registrado
                                 let nickserv_auth = if good && user_state.nick.is_some() {
-                                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                                     {
                                         if let Some(db_arc) = &self.databases.nick_db {
                                             let db = db_arc.read().await;
@@ -341,10 +317,6 @@ impl super::MainState {
                                             true
                                         }
                                     }
-                                    #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-                                    {
-                                        true
-                                    }
                                 } else {
                                     good
                                 };
@@ -354,7 +326,6 @@ impl super::MainState {
                             } else {
                                 // No hay contraseña configurada en el servidor, pero verificar NickServ
                                 let nickserv_auth = if user_state.nick.is_some() {
-                                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                                     {
                                         if let Some(db_arc) = &self.databases.nick_db {
                                             let db = db_arc.read().await;
@@ -382,10 +353,6 @@ impl super::MainState {
                                             true
                                         }
                                     }
-                                    #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-                                    {
-                                        true
-                                    }
                                 } else {
                                     true
                                 };
@@ -406,6 +373,19 @@ impl super::MainState {
         };
         if let Some(good) = auth_opt {
             if good {
+                // Rechazar la conexión si coincide con un AKILL de OperServ
+                {
+                    let name = conn_state.user_state.name.clone().unwrap_or_default();
+                    let ip = conn_state.user_state.ip_addr.to_string();
+                    if let Some(reason) = self.find_akill(&name, &conn_state.user_state.hostname, &ip).await {
+                        info!("AKILL matched for {}: {}", conn_state.user_state.source, reason);
+                        let msg = format!("ERROR :Closing Link: {} (AKILL: {})", conn_state.user_state.hostname, reason);
+                        self.feed_msg(&mut conn_state.stream, msg).await?;
+                        conn_state.quit.store(1, Ordering::SeqCst);
+                        return Ok(());
+                    }
+                }
+
                 let user_nick = conn_state.user_state.nick.clone().unwrap();
 
                 let user_modes = {
@@ -419,6 +399,7 @@ impl super::MainState {
                         conn_state.sender.take().unwrap(),
                         conn_state.quit_sender.take().unwrap(),
                     );
+                    user.cloack_sender = conn_state.cloack_sender.take();
                     if conn_state.is_secure() {
                         user.modes.secure = true;
                     }
@@ -426,12 +407,11 @@ impl super::MainState {
                         user.modes.websocket = true;
                     }
                     // Aplicar vhost si está configurado
-                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                     {
                         if let Some(db_arc) = &self.databases.nick_db {
                             let db = db_arc.read().await;
                             if let Ok(Some(info)) = db.get_nick_info(&user_nick).await {
-                                if let Some(vhost) = info.4 {
+                                if let Some(vhost) = info.vhost {
                                     conn_state.user_state.set_cloack(vhost.clone());
                                     user.cloack = vhost.clone();
                                 }
@@ -440,8 +420,8 @@ impl super::MainState {
                         }
                     }
                     let umode_str = user.modes.to_string();
-                    if !state.users.contains_key(&crate::state::structs::to_unicase(&user_nick)) {
-                        state.add_user(&crate::state::structs::to_unicase(&user_nick), user);
+                    if !state.users.contains_key(&crate::state::structs::to_irccase(&user_nick)) {
+                        state.add_user(&crate::state::structs::to_irccase(&user_nick), user);
                         umode_str
                     } else {
                         // if nick already used
@@ -505,8 +485,8 @@ impl super::MainState {
                                 "-",
                                 env!("CARGO_PKG_VERSION")
                             ),
-                            avail_user_modes: "OiorwWzx",
-                            avail_chmodes: "bBeIklahimnopqrstvO",
+                            avail_user_modes: "OgiorwRWzx",
+                            avail_chmodes: "bBeIZkfjlLachimnopqrstvzCKMOPRST",
                             avail_chmodes_with_params: None,
                         },
                     )
@@ -646,7 +626,6 @@ impl super::MainState {
             Ok(Some(username)) => {
                 // Autenticación SASL exitosa - ahora verificar contra NickServ
                 let nickserv_auth = {
-                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                     {
                         // Verificar si el username es un nick registrado en NickServ
                         if let Some(db_arc) = &self.databases.nick_db {
@@ -679,10 +658,6 @@ impl super::MainState {
                             true
                         }
                     }
-                    #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-                    {
-                        true
-                    }
                 };
 
                 if nickserv_auth {
@@ -754,9 +729,48 @@ impl super::MainState {
         nick: &'a str,
         _msg: &'a Message<'a>,
     ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        // Los nicks prohibidos con NS FORBID solo los pueden usar los IRCops
+        if let Some(db_arc) = &self.databases.nick_db {
+            if let Ok(Some(crate::database::ForbiddenEntry { reason, .. })) = db_arc.read().await.get_forbidden_nick(nick).await {
+                let is_oper = if let Some(current_nick) = &conn_state.user_state.nick {
+                    self.is_ircop(current_nick).await
+                } else {
+                    false
+                };
+                if !is_oper {
+                    let client = conn_state.user_state.client_name();
+                    self.feed_msg(&mut conn_state.stream, ErrErroneusNickname432 { client, nick })
+                        .await?;
+                    self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Nick {nick} is forbidden: {reason}"))
+                        .await?;
+                    return Ok(());
+                }
+            }
+        }
+
+        // Los nicks bloqueados con OS JUPE tampoco se pueden usar salvo por IRCops
+        {
+            let jupe_reason = self.state.read().await.jupes.get(&crate::state::structs::to_irccase(nick)).cloned();
+            if let Some(reason) = jupe_reason {
+                let is_oper = if let Some(current_nick) = &conn_state.user_state.nick {
+                    self.is_ircop(current_nick).await
+                } else {
+                    false
+                };
+                if !is_oper {
+                    let client = conn_state.user_state.client_name();
+                    self.feed_msg(&mut conn_state.stream, ErrErroneusNickname432 { client, nick })
+                        .await?;
+                    self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Nick {nick} is juped: {reason}"))
+                        .await?;
+                    return Ok(());
+                }
+            }
+        }
+
         // Si está en negociación de CAP, simplemente establecer el nick
         if conn_state.caps_negotation {
-            if !self.state.read().await.users.contains_key(&crate::state::structs::to_unicase(nick)) {
+            if !self.state.read().await.users.contains_key(&crate::state::structs::to_irccase(nick)) {
                 conn_state.user_state.set_nick(nick.to_string());
             } else {
                 let client = conn_state.user_state.client_name();
@@ -765,9 +779,8 @@ impl super::MainState {
             }
         } else if !conn_state.user_state.authenticated {
             // No autenticado y no en negociación de CAP
-            if !self.state.read().await.users.contains_key(&crate::state::structs::to_unicase(nick)) {
+            if !self.state.read().await.users.contains_key(&crate::state::structs::to_irccase(nick)) {
                 // Verificar si el nick está registrado en NickServ
-                #[cfg(any(feature = "sqlite", feature = "mysql"))]
                 {
                     if let Some(db_arc) = &self.databases.nick_db {
                         let db = db_arc.read().await;
@@ -795,16 +808,17 @@ impl super::MainState {
             
             // Si no tiene nick establecido, establecerlo
             if conn_state.user_state.nick.is_none() {
-                if !state.users.contains_key(&crate::state::structs::to_unicase(nick)) {
+                if !state.users.contains_key(&crate::state::structs::to_irccase(nick)) {
                     conn_state.user_state.set_nick(nick.to_string());
                     // Crear el usuario en el estado global
-                    let user = User::new(
+                    let mut user = User::new(
                         &self.config,
                         &conn_state.user_state,
                         conn_state.sender.take().unwrap(),
                         conn_state.quit_sender.take().unwrap(),
                     );
-                    state.add_user(&crate::state::structs::to_unicase(nick), user);
+                    user.cloack_sender = conn_state.cloack_sender.take();
+                    state.add_user(&crate::state::structs::to_irccase(nick), user);
                 } else {
                     let client = conn_state.user_state.client_name();
                     self.feed_msg(&mut conn_state.stream, ErrNicknameInUse433 { client, nick })
@@ -816,8 +830,7 @@ impl super::MainState {
                 if nick != old_nick {
                     let nick_str = nick.to_string();
                     // if new nick is not used by other
-                    if !state.users.contains_key(&crate::state::structs::to_unicase(&nick_str)) {
-                        #[cfg(any(feature = "sqlite", feature = "mysql"))]
+                    if !state.users.contains_key(&crate::state::structs::to_irccase(&nick_str)) {
                         {
                             if let Some(db_arc) = &self.databases.nick_db {
                                 let db = db_arc.read().await;
@@ -831,7 +844,7 @@ impl super::MainState {
                             }
                         }
                         let old_source = conn_state.user_state.source.clone();
-                        let mut user = state.users.remove(&crate::state::structs::to_unicase(&old_nick)).unwrap();
+                        let mut user = state.users.remove(&crate::state::structs::to_irccase(&old_nick)).unwrap();
                         conn_state.user_state.set_nick(nick_str.clone());
                         user.update_nick(&conn_state.user_state);
                         conn_state.user_state.cloack = user.get_display_hostname(&self.config.cloack);
@@ -839,10 +852,10 @@ impl super::MainState {
                         conn_state.user_state.update_source();
                         if user.modes.registered {
                             for channel in &user.channels {
-                                if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(&channel.clone())) {
+                                if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(&channel.clone())) {
                                     let nicks: Vec<String> = chanobj.users.keys().cloned().map(|nick| nick.to_string()).collect();
                                     for nicknames in nicks {
-                                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nicknames.to_string())) {
+                                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(&nicknames)) {
                                             let part_msg = format!("PART {} :vHost", channel);
                                             let _ = user.send_msg_display(
                                                 &old_source,
@@ -853,7 +866,7 @@ impl super::MainState {
                                                 &conn_state.user_state.source,
                                                 join_msg.as_str()
                                             );
-                                            if let Some(user_chum) = chanobj.users.get(&crate::state::structs::to_unicase(&old_nick.to_string())) {
+                                            if let Some(user_chum) = chanobj.users.get(&crate::state::structs::to_irccase(&old_nick)) {
                                                 let mut arg = Vec::new();
                                                 if user_chum.founder {
                                                     arg.push("q");
@@ -884,14 +897,14 @@ impl super::MainState {
                         for ch in &user.channels {
                             state
                                 .channels
-                                .get_mut(&crate::state::structs::to_unicase(&ch.clone()))
+                                .get_mut(&crate::state::structs::to_irccase(&ch.clone()))
                                 .unwrap()
                                 .rename_user(&old_nick, nick_str.clone());
                         }
                         // add nick history
                         state.insert_to_nick_history(&old_nick, user.history_entry.clone());
 
-                        state.users.insert(crate::state::structs::to_unicase(&nick_str), user);
+                        state.users.insert(crate::state::structs::to_irccase(&nick_str), user);
                         // wallops users
                         if state.wallops_users.contains(&old_nick) {
                             state.wallops_users.remove(&old_nick);
@@ -901,6 +914,12 @@ impl super::MainState {
                         for u in state.users.values() {
                             let _ = u.send_msg_display(&old_source, nick_change_msg.clone());
                         }
+
+                        // cambios de nick para +f en los canales del usuario
+                        let channels: Vec<String> = state.users[&crate::state::structs::to_irccase(nick)].channels.iter().cloned().collect();
+                        for channel in channels {
+                            self.channel_flood_event(state, &channel, nick, 'n');
+                        }
                     } else {
                         // if nick in use
                         let client = conn_state.user_state.client_name();
@@ -972,7 +991,7 @@ impl super::MainState {
         if let Some(oper_idx) = self.oper_config_idxs.get(nick) {
             // if operator defined in configuration
             let mut state = self.state.write().await;
-            let user = state.users.get_mut(&crate::state::structs::to_unicase(user_nick)).unwrap();
+            let user = state.users.get_mut(&crate::state::structs::to_irccase(user_nick)).unwrap();
             let op_cfg_opt = self.config.operators.as_ref().unwrap().get(*oper_idx);
             let op_config = op_cfg_opt.as_ref().unwrap();
 
@@ -1023,7 +1042,7 @@ impl super::MainState {
         if let Some(nick) = &conn_state.user_state.nick {
             let user_channels = {
                 let state = self.state.read().await;
-                if let Some(user) = state.users.get(&crate::state::structs::to_unicase(nick)) {
+                if let Some(user) = state.users.get(&crate::state::structs::to_irccase(nick)) {
                     user.channels.clone()
                 } else {
                     HashSet::new()
@@ -1035,10 +1054,10 @@ impl super::MainState {
             let quit_msg = format!("QUIT :{}", conn_state.user_state.quit_reason);
             for chname in &user_channels {
                 let state = self.state.read().await;
-                if let Some(channel) = state.channels.get(&crate::state::structs::to_unicase(chname)) {
+                if let Some(channel) = state.channels.get(&crate::state::structs::to_irccase(chname)) {
                     for other_nick in channel.users.keys() {
-                        if **other_nick != **UniCase::new(nick) {
-                            if let Some(other_user) = state.users.get(&crate::state::structs::to_unicase(other_nick)) {
+                        if **other_nick != **IrcCase::new(nick) {
+                            if let Some(other_user) = state.users.get(&crate::state::structs::to_irccase(other_nick)) {
                                 let _ = other_user.send_msg_display(source, quit_msg.clone());
                             }
                         }
@@ -1048,7 +1067,7 @@ impl super::MainState {
             // remove user from state
             {
                 let mut state = self.state.write().await;
-                state.remove_user(&crate::state::structs::to_unicase(nick));
+                state.remove_user(&crate::state::structs::to_irccase(nick));
                 conn_state.quit.store(1, Ordering::SeqCst);
             }
         } else {
@@ -1176,7 +1195,10 @@ mod test {
             line_stream.send("CAP END".to_string()).await.unwrap();
 
             assert_eq!(
-                ":irc.irc CAP * LS :multi-prefix".to_string(),
+                ":irc.irc CAP * LS :multi-prefix sasl message-tags batch labeled-response \
+                    chathistory read-marker echo-message setname userhost-in-names \
+                    invite-notify monitor watch"
+                    .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
@@ -1208,9 +1230,7 @@ mod test {
                 line_stream.next().await.unwrap().unwrap()
             );
 
-            for _ in 3..18 {
-                line_stream.next().await.unwrap().unwrap();
-            }
+            skip_until(&mut line_stream, " MODE mati :").await;
 
             line_stream.send("CAP LIST".to_string()).await.unwrap();
             assert_eq!(
@@ -1236,7 +1256,10 @@ mod test {
             line_stream.send("NICK mati2".to_string()).await.unwrap();
 
             assert_eq!(
-                ":irc.irc CAP * LS :multi-prefix".to_string(),
+                ":irc.irc CAP * LS :multi-prefix sasl message-tags batch labeled-response \
+                    chathistory read-marker echo-message setname userhost-in-names \
+                    invite-notify monitor watch"
+                    .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
@@ -1286,11 +1309,9 @@ mod test {
                     "AuthTrial: {:?}",
                     pass
                 );
-                for _ in 1..17 {
-                    line_stream.next().await.unwrap().unwrap();
-                }
+                skip_until(&mut line_stream, " 376 ").await;
                 assert_eq!(
-                    ":irc.irc 221 mati +".to_string(),
+                    ":irc.irc MODE mati :+".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             } else {
@@ -1365,11 +1386,9 @@ mod test {
                     "AuthTrial: {:?}",
                     pass
                 );
-                for _ in 1..17 {
-                    line_stream.next().await.unwrap().unwrap();
-                }
+                skip_until(&mut line_stream, " 376 ").await;
                 assert_eq!(
-                    ":irc.irc 221 luckboy +r".to_string(),
+                    ":irc.irc MODE luckboy :+r".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             } else {
@@ -1409,11 +1428,9 @@ mod test {
                     "AuthTrial: {:?}",
                     pass
                 );
-                for _ in 1..17 {
-                    line_stream.next().await.unwrap().unwrap();
-                }
+                skip_until(&mut line_stream, " 376 ").await;
                 assert_eq!(
-                    ":irc.irc 221 mat +r".to_string(),
+                    ":irc.irc MODE mat :+r".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             } else {
@@ -1453,11 +1470,9 @@ mod test {
                     "AuthTrial: {:?}",
                     pass
                 );
-                for _ in 1..17 {
-                    line_stream.next().await.unwrap().unwrap();
-                }
+                skip_until(&mut line_stream, " 376 ").await;
                 assert_eq!(
-                    ":irc.irc 221 mat2 +r".to_string(),
+                    ":irc.irc MODE mat2 :+r".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             } else {
@@ -1549,11 +1564,9 @@ mod test {
                     "AuthTrial: {:?}",
                     pass
                 );
-                for _ in 1..17 {
-                    line_stream.next().await.unwrap().unwrap();
-                }
+                skip_until(&mut line_stream, " 376 ").await;
                 assert_eq!(
-                    ":irc.irc 221 luckboy +r".to_string(),
+                    ":irc.irc MODE luckboy :+r".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             } else {
@@ -1593,11 +1606,9 @@ mod test {
                     "AuthTrial: {:?}",
                     pass
                 );
-                for _ in 1..17 {
-                    line_stream.next().await.unwrap().unwrap();
-                }
+                skip_until(&mut line_stream, " 376 ").await;
                 assert_eq!(
-                    ":irc.irc 221 mat +r".to_string(),
+                    ":irc.irc MODE mat :+r".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             } else {
@@ -1625,7 +1636,9 @@ mod test {
             wallops: false,
             websocket: true,
             secure: true,
-            cloacked: false
+            cloacked: false,
+            caller_id: false,
+            registered_only: false
         };
         let (main_state, handle, port) = run_test_server(config).await;
 
@@ -1638,7 +1651,7 @@ mod test {
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
-            for _ in 1..7 {
+            for _ in 1..8 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -1668,7 +1681,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let oliver = state.users.get("oliver").unwrap();
+                let oliver = state.users.get(&to_irccase("oliver")).unwrap();
                 assert!(oliver.modes.invisible);
                 assert!(oliver.modes.registered);
                 assert!(oliver.modes.websocket);
@@ -0,0 +1,154 @@
+// flood.rs - channel flood protection (+f) and join throttle (+j)
+//
+// simple-irc-server - simple IRC server
+// Copyright (C) 2022-2024  Mateusz Szpakowski
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+use super::botserv::broadcast_to_channel;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FLOOD_KICK_REASON: &str = "Flood detected (+f)";
+
+impl super::MainState {
+    // Registra un evento del canal (j, m, n o c) y, si se supera el límite de +f, aplica
+    // su acción. Los operadores del canal (desde +h) y los IRCops no cuentan.
+    pub(super) fn channel_flood_event(&self, state: &mut VolatileState, channel: &str, nick: &str, kind: char) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let user = if let Some(user) = state.users.get(&crate::state::structs::to_irccase(nick)) {
+            user
+        } else {
+            return;
+        };
+        if user.modes.is_local_oper() {
+            return;
+        }
+        let ban_mask = if user.modes.cloacked {
+            format!("{nick}!*@*")
+        } else {
+            format!("*!*@{}", user.hostname)
+        };
+        let chname = if let Some((chname, _)) = state.channels.get_key_value(&crate::state::structs::to_irccase(channel)) {
+            chname.to_string()
+        } else {
+            return;
+        };
+        let chanobj = state.channels.get_mut(&crate::state::structs::to_irccase(&chname)).unwrap();
+        if chanobj.users.get(&crate::state::structs::to_irccase(nick)).is_none_or(|chum| chum.is_half_operator()) {
+            return;
+        }
+
+        let rule = chanobj
+            .modes
+            .flood
+            .as_deref()
+            .and_then(|flood| parse_flood_mode(flood).ok())
+            .and_then(|settings| settings.rule(kind).cloned().map(|rule| (rule, settings.seconds)));
+        let throttle_window = if kind == 'j' {
+            chanobj
+                .modes
+                .join_throttle
+                .as_deref()
+                .and_then(|throttle| parse_join_throttle(throttle).ok())
+                .map(|(_, seconds)| seconds)
+        } else {
+            None
+        };
+        let window = match (rule.as_ref().map(|(_, seconds)| *seconds), throttle_window) {
+            (None, None) => return,
+            (flood_window, throttle_window) => flood_window.unwrap_or(0).max(throttle_window.unwrap_or(0)),
+        };
+        chanobj.flood.record(kind, nick, window, now);
+
+        let Some((rule, seconds)) = rule else {
+            return;
+        };
+        // +i y +m cuentan los eventos de todo el canal; kick y ban, los del usuario
+        let offender = matches!(rule.action, FloodAction::Kick | FloodAction::Ban).then_some(nick);
+        if chanobj.flood.count(kind, offender, seconds, now) <= rule.limit {
+            return;
+        }
+        chanobj.flood.clear(kind, offender);
+        let duration = Duration::from_secs(rule.minutes * 60);
+
+        match rule.action {
+            FloodAction::InviteOnly | FloodAction::Moderated => {
+                let (mode, flag) = if rule.action == FloodAction::InviteOnly {
+                    ('i', &mut chanobj.modes.invite_only)
+                } else {
+                    ('m', &mut chanobj.modes.moderated)
+                };
+                if *flag {
+                    return;
+                }
+                *flag = true;
+                broadcast_to_channel(state, &chname, &self.config.name, &format!("MODE {chname} +{mode}"), None);
+                self.schedule_flood_unset(&chname, mode, duration);
+            }
+            FloodAction::Kick | FloodAction::Ban => {
+                if rule.action == FloodAction::Ban {
+                    let ban_mask = format!("{}|{}", ban_mask, duration.as_secs());
+                    let mut ban = chanobj.modes.ban.take().unwrap_or_default();
+                    let new_ban = ban.insert(ban_mask.clone());
+                    chanobj.modes.ban = Some(ban);
+                    if new_ban {
+                        chanobj.ban_info.insert(
+                            crate::state::structs::to_irccase(&ban_mask),
+                            BanInfo {
+                                set_time: now,
+                                who: self.config.name.clone(),
+                                expires_at: Some(now + duration.as_secs()),
+                            },
+                        );
+                        broadcast_to_channel(state, &chname, &self.config.name, &format!("MODE {chname} +b {ban_mask}"), None);
+                        self.schedule_list_expiry(&chname, 'b', &ban_mask, duration);
+                    }
+                }
+                broadcast_to_channel(
+                    state,
+                    &chname,
+                    &self.config.name,
+                    &format!("KICK {chname} {nick} :{FLOOD_KICK_REASON}"),
+                    None,
+                );
+                state.remove_user_from_channel(&chname, nick);
+            }
+        }
+    }
+
+    // Quita el +i o +m puesto por la protección contra flood cuando pasa su tiempo
+    fn schedule_flood_unset(&self, channel: &str, mode: char, delay: Duration) {
+        let channel_name = channel.to_string();
+        let state_clone = self.state.clone();
+        let config_clone = self.config.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            let state = &mut *state_clone.write().await;
+            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(&channel_name)) {
+                let flag = if mode == 'i' {
+                    &mut chanobj.modes.invite_only
+                } else {
+                    &mut chanobj.modes.moderated
+                };
+                if *flag {
+                    *flag = false;
+                    broadcast_to_channel(state, &channel_name, &config_clone.name, &format!("MODE {channel_name} -{mode}"), None);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,382 @@
+// hostserv.rs - HostServ commands
+//
+// simple-irc-server - simple IRC server
+// Copyright (C) 2022-2024  Mateusz Szpakowski
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+use super::chanserv::format_service_time;
+use crate::database::VhostRequest;
+use crate::utils::{argon2_verify_password_async, match_wildcard};
+use serde::ser::StdError;
+use std::time::SystemTime;
+
+// Longitud máxima de un vhost pedido a HostServ
+const MAX_VHOST_LENGTH: usize = 64;
+
+impl super::MainState {
+    pub(super) async fn process_hostserv<'a>(
+        &self,
+        conn_state: &mut ConnState,
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = conn_state.user_state.client_name().to_string();
+        let nick = if let Some(nick) = &conn_state.user_state.nick {
+            nick.clone()
+        } else {
+            self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :You don't have a nick.")).await?;
+            return Ok(());
+        };
+
+        let subcommand = subcommand.to_lowercase();
+        let is_oper = self.is_ircop(&nick).await;
+        if subcommand == "help" {
+            self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :HostServ - Virtual Host Service")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Available commands:")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :  REQUEST <vhost> - Request a vhost, it must be approved by an IRCop")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :  ON - Activate your vhost")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :  OFF - Deactivate your vhost")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :  GROUP <nick> <password> - Copy your vhost to another of your nicks")).await?;
+            if is_oper {
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :  WAITING - List pending vhost requests (IRCop)")).await?;
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :  ACTIVATE <nick> - Approve a vhost request (IRCop)")).await?;
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :  REJECT <nick> [reason] - Reject a vhost request (IRCop)")).await?;
+            }
+            return Ok(());
+        }
+
+        let db_arc = if let Some(db_arc) = &self.databases.nick_db {
+            db_arc
+        } else {
+            self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Database is not configured.")).await?;
+            return Ok(());
+        };
+
+        match subcommand.as_str() {
+            "request" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Usage: /HS REQUEST <vhost>")).await?;
+                    return Ok(());
+                }
+                let vhost = params[0];
+                if !self.is_identified(&nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :You must be identified with NickServ to request a vhost.")).await?;
+                    return Ok(());
+                }
+                if let Err(reason) = validate_vhost(vhost, self.forbidden_vhosts()) {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Invalid vhost '{vhost}': {reason}")).await?;
+                    return Ok(());
+                }
+
+                let mut db = db_arc.write().await;
+                if let Some(info) = db.get_nick_info(&nick).await? {
+                    // Se mantiene el límite de un cambio de vhost cada 24 horas
+                    if let Some(last_vhost_change) = info.last_vhost {
+                        if let Ok(duration) = SystemTime::now().duration_since(last_vhost_change) {
+                            if duration.as_secs() < 86400 {
+                                let remaining = 86400 - duration.as_secs();
+                                let hours = remaining / 3600;
+                                let minutes = (remaining % 3600) / 60;
+                                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :You must wait {hours}h {minutes}m before changing your vhost again.")).await?;
+                                return Ok(());
+                            }
+                        }
+                    }
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :The nick {nick} is not registered.")).await?;
+                    return Ok(());
+                }
+                db.add_vhost_request(&nick, vhost, SystemTime::now()).await?;
+                drop(db);
+
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Your vhost request for '{vhost}' has been sent to the IRCops for approval.")).await?;
+                let state = self.state.read().await;
+                for (oper_nick, user) in state.users.iter().filter(|(_, u)| u.modes.is_local_oper()) {
+                    let _ = user.send_msg_display(
+                        "HostServ",
+                        format!("NOTICE {oper_nick} :{nick} has requested the vhost '{vhost}'. Use /HS WAITING to review it."),
+                    );
+                }
+            }
+            "activate" | "reject" | "waiting" if !is_oper => {
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Only IRCops can review vhost requests.")).await?;
+            }
+            "waiting" => {
+                let requests = db_arc.read().await.get_vhost_requests().await?;
+                if requests.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :There are no pending vhost requests.")).await?;
+                    return Ok(());
+                }
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Pending vhost requests:")).await?;
+                for VhostRequest { nick: req_nick, vhost, request_time } in requests {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :  {req_nick} - {vhost} (requested on {})", format_service_time(request_time))).await?;
+                }
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :End of vhost requests.")).await?;
+            }
+            "activate" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Usage: /HS ACTIVATE <nick>")).await?;
+                    return Ok(());
+                }
+                let target = params[0];
+                let mut db = db_arc.write().await;
+                let vhost = if let Some(VhostRequest { vhost, .. }) = db.get_vhost_request(target).await? {
+                    vhost
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :There is no pending vhost request for {target}.")).await?;
+                    return Ok(());
+                };
+                if db.get_nick_info(target).await?.is_none() {
+                    db.delete_vhost_request(target).await?;
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :The nick {target} is no longer registered.")).await?;
+                    return Ok(());
+                }
+                db.update_nick_info(target, None, None, None, Some(&vhost), Some(SystemTime::now()), None, None, None).await?;
+                db.delete_vhost_request(target).await?;
+                drop(db);
+
+                if self.is_identified(target).await {
+                    self.set_user_vhost(target, Some(&vhost)).await;
+                }
+                self.notify_user(target, format!("Your vhost '{vhost}' has been approved by {nick}.")).await;
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :vhost '{vhost}' for {target} has been activated.")).await?;
+            }
+            "reject" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Usage: /HS REJECT <nick> [reason]")).await?;
+                    return Ok(());
+                }
+                let target = params[0];
+                let reason = params[1..].join(" ");
+                let request = db_arc.read().await.get_vhost_request(target).await?;
+                if let Some(VhostRequest { vhost, .. }) = request {
+                    db_arc.write().await.delete_vhost_request(target).await?;
+                    let message = if reason.is_empty() {
+                        format!("Your vhost request for '{vhost}' has been rejected.")
+                    } else {
+                        format!("Your vhost request for '{vhost}' has been rejected: {reason}")
+                    };
+                    self.notify_user(target, message).await;
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :vhost request of {target} has been rejected.")).await?;
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :There is no pending vhost request for {target}.")).await?;
+                }
+            }
+            "on" => {
+                if !self.is_identified(&nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :You must be identified with NickServ to use your vhost.")).await?;
+                    return Ok(());
+                }
+                let vhost = db_arc.read().await.get_nick_info(&nick).await?.and_then(|info| info.vhost);
+                if let Some(vhost) = vhost {
+                    self.set_user_vhost(&nick, Some(&vhost)).await;
+                    conn_state.user_state.set_cloack(vhost.clone());
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Your vhost '{vhost}' is now active.")).await?;
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :You don't have a vhost. Use /HS REQUEST to ask for one.")).await?;
+                }
+            }
+            "off" => {
+                if let Some(host) = self.set_user_vhost(&nick, None).await {
+                    conn_state.user_state.set_cloack(host);
+                }
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Your vhost has been deactivated.")).await?;
+            }
+            "group" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Usage: /HS GROUP <nick> <password>")).await?;
+                    return Ok(());
+                }
+                let (target, password) = (params[0], params[1]);
+                if !self.is_identified(&nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :You must be identified with NickServ to group your vhost.")).await?;
+                    return Ok(());
+                }
+                let mut db = db_arc.write().await;
+                let vhost = if let Some(vhost) = db.get_nick_info(&nick).await?.and_then(|info| info.vhost) {
+                    vhost
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :You don't have a vhost to group.")).await?;
+                    return Ok(());
+                };
+                // La contraseña demuestra que el otro nick es del mismo usuario
+                let password_ok = match db.get_nick_password(target).await? {
+                    Some(hash) => argon2_verify_password_async(password.to_string(), hash).await.is_ok(),
+                    None => false,
+                };
+                if !password_ok {
+                    self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Nick {target} is not registered or the password is incorrect.")).await?;
+                    return Ok(());
+                }
+                db.update_nick_info(target, None, None, None, Some(&vhost), Some(SystemTime::now()), None, None, None).await?;
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :vhost '{vhost}' has been assigned to {target}.")).await?;
+            }
+            _ => {
+                self.feed_msg_source(&mut conn_state.stream, "HostServ", format!("NOTICE {client} :Unknown command. Use /HS HELP for help.")).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn forbidden_vhosts(&self) -> &[String] {
+        self.config.database.as_ref().map(|db| db.forbidden_vhosts.as_slice()).unwrap_or(&[])
+    }
+
+    async fn is_identified(&self, nick: &str) -> bool {
+        let state = self.state.read().await;
+        state
+            .users
+            .get(&crate::state::structs::to_irccase(nick))
+            .map(|user| user.modes.registered)
+            .unwrap_or(false)
+    }
+
+    async fn notify_user(&self, nick: &str, message: String) {
+        let state = self.state.read().await;
+        if let Some(user) = state.users.get(&crate::state::structs::to_irccase(nick)) {
+            let _ = user.send_msg_display("HostServ", format!("NOTICE {nick} :{message}"));
+        }
+    }
+
+    // Cambia el host visible de un usuario conectado y avisa con CHGHOST a él y
+    // a los usuarios de sus canales. Sin vhost se vuelve al host normal (con
+    // cloak si tiene +x). Devuelve el host aplicado.
+    pub(super) async fn set_user_vhost(&self, nick: &str, vhost: Option<&str>) -> Option<String> {
+        let mut state = self.state.write().await;
+        let user = state.users.get_mut(&crate::state::structs::to_irccase(nick))?;
+        let new_host = match vhost {
+            Some(vhost) => vhost.to_string(),
+            None if user.modes.cloacked => user.get_display_hostname(&self.config.cloack),
+            None => user.hostname.clone(),
+        };
+        if user.cloack == new_host {
+            return Some(new_host);
+        }
+
+        let old_source = user.source.clone();
+        user.cloack = new_host.clone();
+        user.source = format!("{}!~{}@{}", nick, user.name, new_host);
+        // la conexión del usuario también tiene que ver el nuevo host
+        if let Some(cloack_sender) = &user.cloack_sender {
+            let _ = cloack_sender.send(new_host.clone());
+        }
+        let msg = format!("CHGHOST {} {}", user.name, new_host);
+        let channels = user.channels.clone();
+
+        let mut targets = HashSet::new();
+        targets.insert(crate::state::structs::to_irccase(nick));
+        for channel in &channels {
+            if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
+                targets.extend(chanobj.users.keys().cloned());
+            }
+        }
+        for target in targets {
+            if let Some(user) = state.users.get(&target) {
+                let _ = user.send_msg_display(&old_source, &msg);
+            }
+        }
+        Some(new_host)
+    }
+}
+
+// Comprueba que un vhost pedido sea un nombre de host válido y que no coincida
+// con ninguno de los patrones prohibidos de la configuración.
+pub(super) fn validate_vhost(vhost: &str, forbidden: &[String]) -> Result<(), &'static str> {
+    if vhost.is_empty() || vhost.len() > MAX_VHOST_LENGTH {
+        return Err("length must be between 1 and 64 characters");
+    }
+    if !vhost.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-') {
+        return Err("only letters, digits, '.' and '-' are allowed");
+    }
+    if vhost.starts_with(['.', '-']) || vhost.ends_with(['.', '-']) || vhost.contains("..") {
+        return Err("malformed hostname");
+    }
+    if !vhost.chars().any(|c| c.is_ascii_alphabetic()) {
+        return Err("vhost must contain at least one letter");
+    }
+    let lower = vhost.to_lowercase();
+    if forbidden.iter().any(|pattern| match_wildcard(&pattern.to_lowercase(), &lower)) {
+        return Err("vhost is forbidden");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::*;
+    use super::*;
+
+    #[test]
+    fn test_validate_vhost() {
+        let forbidden = vec!["*ircop*".to_string(), "admin.*".to_string()];
+        assert_eq!(Ok(()), validate_vhost("cool.user.net", &forbidden));
+        assert_eq!(Ok(()), validate_vhost("my-host", &forbidden));
+        assert!(validate_vhost("", &forbidden).is_err());
+        assert!(validate_vhost("bad host", &forbidden).is_err());
+        assert!(validate_vhost("bad..host", &forbidden).is_err());
+        assert!(validate_vhost(".host", &forbidden).is_err());
+        assert!(validate_vhost("127.0.0.1", &forbidden).is_err());
+        assert!(validate_vhost("the.IRCop.net", &forbidden).is_err());
+        assert!(validate_vhost("Admin.net", &forbidden).is_err());
+        assert!(validate_vhost(&"a".repeat(65), &forbidden).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hostserv_activate_other_user() {
+        let mut config = memory_database_config();
+        config.operators = Some(vec![OperatorConfig {
+            name: "fanny".to_string(),
+            password: argon2_hash_password("Funny"),
+            mask: None,
+        }]);
+        let (main_state, handle, port) = run_test_server(config).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "fanny", "fanny", "Fanny BumBumBum").await;
+            line_stream.send("OPER fanny Funny".to_string()).await.unwrap();
+            line_stream.next().await.unwrap().unwrap();
+
+            {
+                let mut line_stream2 = login_to_test_and_skip(port, "jerry", "jerry", "Jerry Lazy").await;
+                line_stream2.send("NS REGISTER password jerry@example.org".to_string()).await.unwrap();
+                skip_until(&mut line_stream2, "has been registered").await;
+            }
+            // IDENTIFY desde otra conexión toma el nick registrado
+            let mut line_stream2 = login_to_test_and_skip(port, "tom", "jerry", "Jerry Lazy").await;
+            line_stream2.send("NS IDENTIFY jerry password".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "successfully identified").await;
+            line_stream2.send("HS REQUEST jerry.vhost".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "has been sent").await;
+
+            line_stream.send("HS ACTIVATE jerry".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "has been activated").await;
+            assert_eq!(
+                ":jerry!~jerry@127.0.0.1 CHGHOST jerry jerry.vhost".to_string(),
+                skip_until(&mut line_stream2, "CHGHOST").await
+            );
+
+            // la conexión de jerry ya usa el vhost en sus mensajes
+            line_stream2.send("PRIVMSG fanny :Hello".to_string()).await.unwrap();
+            assert_eq!(
+                ":jerry!~jerry@jerry.vhost PRIVMSG fanny :Hello".to_string(),
+                skip_until(&mut line_stream, "PRIVMSG").await
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+}
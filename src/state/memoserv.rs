@@ -0,0 +1,500 @@
+// memoserv.rs - MemoServ commands
+//
+// simple-irc-server - simple IRC server
+// Copyright (C) 2022-2024  Mateusz Szpakowski
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+use super::chanserv::format_service_time;
+use crate::database::DEFAULT_MEMO_LIMIT;
+use serde::ser::StdError;
+use std::time::SystemTime;
+
+// Resultado de intentar dejar un memo en el buzón de una cuenta
+enum MemoDelivery {
+    Sent,
+    NotRegistered,
+    Ignored,
+    Full,
+}
+
+impl super::MainState {
+    pub(super) async fn process_memoserv<'a>(
+        &self,
+        conn_state: &mut ConnState,
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = conn_state.user_state.client_name().to_string();
+        let nick = if let Some(nick) = &conn_state.user_state.nick {
+            nick.clone()
+        } else {
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :You don't have a nick.")).await?;
+            return Ok(());
+        };
+
+        let subcommand = subcommand.to_lowercase();
+        if subcommand == "help" {
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :MemoServ - Memo Service")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Available commands:")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :  SEND <nick|#channel> <text> - Send a memo to a nick or to everyone with access to a channel")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :  LIST - List your memos")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :  READ <num|new|last> - Read a memo")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :  DEL <num|all> - Delete a memo")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :  FORWARD <num> <nick> - Forward a memo to another nick")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :  IGNORE <add|del|list> [nick] - Manage the nicks whose memos you ignore")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :  LIMIT [nick] <num|default> - Set the maximum number of memos you can receive")).await?;
+            return Ok(());
+        }
+
+        let db_arc = if let Some(db_arc) = &self.databases.memo_db {
+            db_arc
+        } else {
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Database is not configured.")).await?;
+            return Ok(());
+        };
+
+        // Los memos pertenecen a la cuenta, hay que estar identificado con NickServ
+        let (identified, is_oper) = {
+            let state = self.state.read().await;
+            state
+                .users
+                .get(&crate::state::structs::to_irccase(&nick))
+                .map(|user| (user.modes.registered, user.modes.is_local_oper()))
+                .unwrap_or((false, false))
+        };
+        if !identified {
+            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :You must be identified with NickServ to use MemoServ.")).await?;
+            return Ok(());
+        }
+
+        match subcommand.as_str() {
+            "send" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Usage: /MS SEND <nick|#channel> <text>")).await?;
+                    return Ok(());
+                }
+                let target = params[0];
+                let text = params[1..].join(" ");
+
+                if target.starts_with('#') || target.starts_with('&') {
+                    let recipients = if let Some(recipients) = self.channel_memo_recipients(target).await? {
+                        recipients
+                    } else {
+                        self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Channel '{target}' is not registered.")).await?;
+                        return Ok(());
+                    };
+                    if !is_oper && !recipients.iter().any(|r| casefold(r) == casefold(&nick)) {
+                        self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :You don't have access to channel '{target}'.")).await?;
+                        return Ok(());
+                    }
+
+                    let memo_text = format!("[{target}] {text}");
+                    let mut sent = 0;
+                    for recipient in recipients.iter().filter(|r| casefold(r) != casefold(&nick)) {
+                        if let MemoDelivery::Sent = self.deliver_memo(recipient, &nick, &memo_text).await? {
+                            sent += 1;
+                        }
+                    }
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo sent to {sent} user(s) with access to {target}.")).await?;
+                } else {
+                    match self.deliver_memo(target, &nick, &text).await? {
+                        MemoDelivery::Sent => {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo sent to {target}.")).await?;
+                        }
+                        MemoDelivery::NotRegistered => {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Nick '{target}' is not registered.")).await?;
+                        }
+                        MemoDelivery::Ignored => {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :{target} is ignoring your memos.")).await?;
+                        }
+                        MemoDelivery::Full => {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :{target}'s memo box is full.")).await?;
+                        }
+                    }
+                }
+            }
+            "list" => {
+                let memos = db_arc.read().await.get_memos(&nick).await?;
+                if memos.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :You have no memos.")).await?;
+                    return Ok(());
+                }
+                self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memos for {nick}:")).await?;
+                for (i, (_, sender, _, sent_time, read)) in memos.iter().enumerate() {
+                    let marker = if *read { " " } else { "*" };
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :{marker}{} - From {sender} on {}", i + 1, format_service_time(*sent_time))).await?;
+                }
+                self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :End of memo list. Unread memos are marked with *.")).await?;
+            }
+            "read" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Usage: /MS READ <num|new|last>")).await?;
+                    return Ok(());
+                }
+                let memos = db_arc.read().await.get_memos(&nick).await?;
+                let selected: Vec<usize> = match params[0].to_lowercase().as_str() {
+                    "new" => memos.iter().enumerate().filter(|(_, m)| !m.4).map(|(i, _)| i).collect(),
+                    "last" => memos.len().checked_sub(1).into_iter().collect(),
+                    num => match memo_index(num, memos.len()) {
+                        Some(i) => vec![i],
+                        None => {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo {num} does not exist.")).await?;
+                            return Ok(());
+                        }
+                    },
+                };
+                if selected.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :You have no memos to read.")).await?;
+                    return Ok(());
+                }
+
+                let mut db = db_arc.write().await;
+                for i in selected {
+                    let (id, sender, text, sent_time, read) = &memos[i];
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo {} from {sender} ({}):", i + 1, format_service_time(*sent_time))).await?;
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :{text}")).await?;
+                    if !read {
+                        db.mark_memo_read(*id).await?;
+                    }
+                }
+            }
+            "del" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Usage: /MS DEL <num|all>")).await?;
+                    return Ok(());
+                }
+                let mut db = db_arc.write().await;
+                let memos = db.get_memos(&nick).await?;
+                if params[0].eq_ignore_ascii_case("all") {
+                    for (id, _, _, _, _) in &memos {
+                        db.delete_memo(*id).await?;
+                    }
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :All of your memos have been deleted.")).await?;
+                } else if let Some(i) = memo_index(params[0], memos.len()) {
+                    db.delete_memo(memos[i].0).await?;
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo {} has been deleted.", i + 1)).await?;
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo {} does not exist.", params[0])).await?;
+                }
+            }
+            "forward" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Usage: /MS FORWARD <num> <nick>")).await?;
+                    return Ok(());
+                }
+                let target = params[1];
+                let memos = db_arc.read().await.get_memos(&nick).await?;
+                let (_, sender, text, _, _) = if let Some(i) = memo_index(params[0], memos.len()) {
+                    &memos[i]
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo {} does not exist.", params[0])).await?;
+                    return Ok(());
+                };
+
+                let memo_text = format!("[Forwarded from {sender}] {text}");
+                let reply = match self.deliver_memo(target, &nick, &memo_text).await? {
+                    MemoDelivery::Sent => format!("Memo {} forwarded to {target}.", params[0]),
+                    MemoDelivery::NotRegistered => format!("Nick '{target}' is not registered."),
+                    MemoDelivery::Ignored => format!("{target} is ignoring your memos."),
+                    MemoDelivery::Full => format!("{target}'s memo box is full."),
+                };
+                self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :{reply}")).await?;
+            }
+            "ignore" => {
+                let action = params.first().map(|a| a.to_lowercase()).unwrap_or_else(|| "list".to_string());
+                match action.as_str() {
+                    "add" | "del" if params.len() < 2 => {
+                        self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Usage: /MS IGNORE <add|del> <nick>")).await?;
+                    }
+                    "add" => {
+                        db_arc.write().await.add_memo_ignore(&nick, params[1]).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memos from {} will be ignored.", params[1])).await?;
+                    }
+                    "del" => {
+                        if db_arc.write().await.delete_memo_ignore(&nick, params[1]).await? {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :{} removed from your ignore list.", params[1])).await?;
+                        } else {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :{} is not on your ignore list.", params[1])).await?;
+                        }
+                    }
+                    "list" => {
+                        let ignores = db_arc.read().await.get_memo_ignores(&nick).await?;
+                        if ignores.is_empty() {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Your memo ignore list is empty.")).await?;
+                        } else {
+                            self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo ignore list: {}", ignores.join(", "))).await?;
+                        }
+                    }
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Usage: /MS IGNORE <add|del|list> [nick]")).await?;
+                    }
+                }
+            }
+            "limit" => {
+                // Un usuario solo puede bajar su propio límite; un IRCop puede cambiar el de cualquiera
+                let (target, value) = match params.len() {
+                    0 => {
+                        let limit = self.memo_limit(&nick).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Your memo limit is {limit}.")).await?;
+                        return Ok(());
+                    }
+                    1 => (nick.as_str(), params[0]),
+                    _ => (params[0], params[1]),
+                };
+                if !is_oper && casefold(target) != casefold(&nick) {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Only IRCops can change the memo limit of other users.")).await?;
+                    return Ok(());
+                }
+                let limit = if value.eq_ignore_ascii_case("default") {
+                    None
+                } else if let Ok(limit) = value.parse::<usize>() {
+                    if !is_oper && limit > DEFAULT_MEMO_LIMIT {
+                        self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :The memo limit cannot be higher than {DEFAULT_MEMO_LIMIT}.")).await?;
+                        return Ok(());
+                    }
+                    Some(limit)
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Usage: /MS LIMIT [nick] <num|default>")).await?;
+                    return Ok(());
+                };
+                db_arc.write().await.set_memo_limit(target, limit).await?;
+                let limit = limit.unwrap_or(DEFAULT_MEMO_LIMIT);
+                self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Memo limit for {target} set to {limit}.")).await?;
+            }
+            _ => {
+                self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :Unknown command. Use /MS HELP for help.")).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Avisa al usuario de sus memos sin leer, se llama al identificarse con NickServ
+    pub(super) async fn notify_unread_memos(
+        &self,
+        conn_state: &mut ConnState,
+        nick: &str,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        if let Some(db_arc) = &self.databases.memo_db {
+            let unread = db_arc.read().await.count_unread_memos(nick).await?;
+            if unread > 0 {
+                let client = conn_state.user_state.client_name().to_string();
+                self.feed_msg_source(&mut conn_state.stream, "MemoServ", format!("NOTICE {client} :You have {unread} new memo(s). Type /MS READ NEW to read them.")).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn memo_limit(&self, nick: &str) -> Result<usize, Box<dyn StdError + Send + Sync>> {
+        Ok(match &self.databases.memo_db {
+            Some(db_arc) => db_arc.read().await.get_memo_limit(nick).await?.unwrap_or(DEFAULT_MEMO_LIMIT),
+            None => DEFAULT_MEMO_LIMIT,
+        })
+    }
+
+    // Guarda un memo para una cuenta registrada respetando su lista de ignorados
+    // y su límite, y avisa al destinatario si está conectado e identificado.
+    async fn deliver_memo(
+        &self,
+        recipient: &str,
+        sender: &str,
+        text: &str,
+    ) -> Result<MemoDelivery, Box<dyn StdError + Send + Sync>> {
+        if let Some(nick_db) = &self.databases.nick_db {
+            if nick_db.read().await.get_nick_info(recipient).await?.is_none() {
+                return Ok(MemoDelivery::NotRegistered);
+            }
+        }
+        let limit = self.memo_limit(recipient).await?;
+        let db_arc = if let Some(db_arc) = &self.databases.memo_db {
+            db_arc
+        } else {
+            return Ok(MemoDelivery::Full);
+        };
+        let mut db = db_arc.write().await;
+        if db.get_memo_ignores(recipient).await?.iter().any(|i| casefold(i) == casefold(sender)) {
+            return Ok(MemoDelivery::Ignored);
+        }
+        let memos = db.get_memos(recipient).await?;
+        if memos.len() >= limit {
+            return Ok(MemoDelivery::Full);
+        }
+        db.add_memo(recipient, sender, text, SystemTime::now()).await?;
+        drop(db);
+
+        let state = self.state.read().await;
+        if let Some(user) = state.users.get(&crate::state::structs::to_irccase(recipient)) {
+            if user.modes.registered {
+                let _ = user.send_msg_display(
+                    "MemoServ",
+                    format!("NOTICE {recipient} :You have a new memo from {sender}. Type /MS READ {} to read it.", memos.len() + 1),
+                );
+            }
+        }
+        Ok(MemoDelivery::Sent)
+    }
+
+    // Cuentas que reciben un memo de canal: el fundador y todos los que tienen acceso.
+    // Devuelve None si el canal no está registrado.
+    async fn channel_memo_recipients(
+        &self,
+        channel: &str,
+    ) -> Result<Option<Vec<String>>, Box<dyn StdError + Send + Sync>> {
+        let db_arc = if let Some(db_arc) = &self.databases.chan_db {
+            db_arc
+        } else {
+            return Ok(None);
+        };
+        let db = db_arc.read().await;
+        let founder = if let Some(info) = db.get_channel_info(channel).await? {
+            info.founder
+        } else {
+            return Ok(None);
+        };
+        let mut recipients = vec![founder];
+        for access in db.get_channel_access_list(channel, None).await? {
+            if !recipients.iter().any(|r| casefold(r) == casefold(&access.nick)) {
+                recipients.push(access.nick);
+            }
+        }
+        Ok(Some(recipients))
+    }
+}
+
+// Convierte el número de memo que ve el usuario (desde 1) en un índice de la lista
+fn memo_index(num: &str, count: usize) -> Option<usize> {
+    num.parse::<usize>().ok().filter(|n| *n >= 1 && *n <= count).map(|n| n - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::test::*;
+    use super::*;
+
+    #[test]
+    fn test_memo_index() {
+        assert_eq!(Some(0), memo_index("1", 3));
+        assert_eq!(Some(2), memo_index("3", 3));
+        assert_eq!(None, memo_index("0", 3));
+        assert_eq!(None, memo_index("4", 3));
+        assert_eq!(None, memo_index("abc", 3));
+    }
+
+    // Registra el nick en una conexión que se cierra y devuelve otra identificada con él
+    async fn identified_to_test(port: u16, nick: &str, temp_nick: &str) -> Framed<TcpStream, IRCLinesCodec> {
+        {
+            let mut line_stream = login_to_test_and_skip(port, nick, nick, "Memo User").await;
+            line_stream.send(format!("NS REGISTER password {nick}@example.org")).await.unwrap();
+            skip_until(&mut line_stream, "has been registered").await;
+        }
+        let mut line_stream = login_to_test_and_skip(port, temp_nick, nick, "Memo User").await;
+        line_stream.send(format!("NS IDENTIFY {nick} password")).await.unwrap();
+        skip_until(&mut line_stream, "successfully identified").await;
+        line_stream
+    }
+
+    #[tokio::test]
+    async fn test_memoserv_send_and_notify() {
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
+
+        {
+            let mut line_stream = identified_to_test(port, "bob", "bobby").await;
+            {
+                let mut line_stream2 = login_to_test_and_skip(port, "amy", "amy", "Amy Away").await;
+                line_stream2.send("NS REGISTER password amy@example.org".to_string()).await.unwrap();
+                skip_until(&mut line_stream2, "has been registered").await;
+                line_stream2.send("QUIT :Bye".to_string()).await.unwrap();
+            }
+            time::sleep(Duration::from_millis(50)).await;
+            assert!(!main_state.state.read().await.users.contains_key(&to_irccase("amy")));
+
+            // el memo se guarda aunque amy no esté conectada
+            line_stream.send("MS SEND amy Hello offline".to_string()).await.unwrap();
+            assert_eq!(
+                ":MemoServ NOTICE bob :Memo sent to amy.".to_string(),
+                skip_until(&mut line_stream, "MemoServ").await
+            );
+            line_stream.send("MS SEND nobody Hello".to_string()).await.unwrap();
+            assert_eq!(
+                ":MemoServ NOTICE bob :Nick 'nobody' is not registered.".to_string(),
+                skip_until(&mut line_stream, "MemoServ").await
+            );
+
+            // al identificarse se avisa de los memos sin leer
+            let mut line_stream2 = login_to_test_and_skip(port, "amelia", "amy", "Amy Away").await;
+            line_stream2.send("NS IDENTIFY amy password".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "successfully identified").await;
+            assert_eq!(
+                ":MemoServ NOTICE amy :You have 1 new memo(s). Type /MS READ NEW to read them.".to_string(),
+                skip_until(&mut line_stream2, "MemoServ").await
+            );
+            line_stream2.send("MS READ new".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "Memo 1 from bob").await;
+            assert_eq!(
+                ":MemoServ NOTICE amy :Hello offline".to_string(),
+                skip_until(&mut line_stream2, "MemoServ").await
+            );
+
+            // con amy conectada e identificada el aviso llega al momento
+            line_stream.send("MS SEND amy Hello online".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "Memo sent to amy.").await;
+            assert_eq!(
+                ":MemoServ NOTICE amy :You have a new memo from bob. Type /MS READ 2 to read it.".to_string(),
+                skip_until(&mut line_stream2, "MemoServ").await
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+
+    #[tokio::test]
+    async fn test_memoserv_ignore_and_limit() {
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
+
+        {
+            let mut line_stream = identified_to_test(port, "bob", "bobby").await;
+            let mut line_stream2 = identified_to_test(port, "amy", "amelia").await;
+
+            line_stream2.send("MS IGNORE add bob".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "Memos from bob will be ignored.").await;
+            line_stream.send("MS SEND amy Are you there?".to_string()).await.unwrap();
+            assert_eq!(
+                ":MemoServ NOTICE bob :amy is ignoring your memos.".to_string(),
+                skip_until(&mut line_stream, "MemoServ").await
+            );
+            line_stream2.send("MS IGNORE del bob".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "bob removed from your ignore list.").await;
+
+            // amy baja su límite a un memo
+            line_stream2.send("MS LIMIT 1".to_string()).await.unwrap();
+            skip_until(&mut line_stream2, "Memo limit for amy set to 1.").await;
+            line_stream.send("MS SEND amy First".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "Memo sent to amy.").await;
+            line_stream.send("MS SEND amy Second".to_string()).await.unwrap();
+            assert_eq!(
+                ":MemoServ NOTICE bob :amy's memo box is full.".to_string(),
+                skip_until(&mut line_stream, "MemoServ").await
+            );
+            line_stream2.send("MS LIMIT 50".to_string()).await.unwrap();
+            assert_eq!(
+                format!(":MemoServ NOTICE amy :The memo limit cannot be higher than {DEFAULT_MEMO_LIMIT}."),
+                skip_until(&mut line_stream2, "memo limit cannot").await
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+}
@@ -42,15 +42,11 @@ use tokio_util::codec::LinesCodecError;
 use tracing::*;
 #[cfg(feature = "dns_lookup")]
 use trust_dns_resolver::{TokioAsyncResolver, TokioHandle};
-#[cfg(feature = "sqlite")]
-use crate::database::sqlite::{SQLiteNickDatabase, SQLiteChannelDatabase};
-#[cfg(feature = "mysql")]
-use crate::database::mysql::mysql_impl::{MysqlNickDatabase, MysqlChannelDatabase};
-#[cfg(any(feature = "sqlite", feature = "mysql"))]
-use crate::database::{NickDatabase, ChannelDatabase};
+use crate::database::{NickDatabase, ChannelDatabase, MemoDatabase, OperDatabase, BotDatabase};
+use crate::database::memory::DEFAULT_SNAPSHOT_INTERVAL;
+use crate::database::migrations::ServiceDatabases;
 use serde::ser::StdError;
 use tokio::time::{timeout, Duration};
-use unicase::UniCase;
 
 use crate::command::*;
 use crate::config::*;
@@ -64,10 +60,12 @@ use Reply::*;
 mod structs;
 pub(crate) use structs::*;
 
-#[cfg(any(feature = "sqlite", feature = "mysql"))]
 pub(crate) struct Databases {
     pub(crate) nick_db: Option<Arc<RwLock<Box<dyn NickDatabase>>>>,
     pub(crate) chan_db: Option<Arc<RwLock<Box<dyn ChannelDatabase>>>>,
+    pub(crate) memo_db: Option<Arc<RwLock<Box<dyn MemoDatabase>>>>,
+    pub(crate) oper_db: Option<Arc<RwLock<Box<dyn OperDatabase>>>>,
+    pub(crate) bot_db: Option<Arc<RwLock<Box<dyn BotDatabase>>>>,
 }
 
 pub(crate) struct MainState {
@@ -80,20 +78,18 @@ pub(crate) struct MainState {
     // Track connections per IP
     connections_per_ip: Arc<RwLock<HashMap<IpAddr, usize>>>,
     state: Arc<RwLock<VolatileState>>,
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     databases: Databases,
     #[cfg(feature = "amqp")]
     serv_comm: Arc<RwLock<ServerCommunication>>,
     created: String,
     created_time: DateTime<Local>,
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     command_counts: [AtomicU64; 57],
-    #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-    command_counts: [AtomicU64; 42],
 }
 
 impl MainState {
     pub(crate) async fn new_from_config(config: MainConfig) -> Result<MainState, String> {
+        // las claves de los mapas y las bases de datos usan este casemapping.
+        config.casemapping.set_current()?;
         // create indexes for configured users and operators.
         let mut user_config_idxs = HashMap::new();
         if let Some(ref users) = config.users {
@@ -108,41 +104,32 @@ impl MainState {
             });
         }
         let state = Arc::new(RwLock::new(VolatileState::new_from_config(&config)));
-        #[cfg(any(feature = "sqlite", feature = "mysql"))]
         let databases = if let Some(db_config) = &config.database {
-            let (mut nick_db, mut chan_db): (Box<dyn NickDatabase>, Box<dyn ChannelDatabase>) =
-                match db_config.database.as_str() {
-                    #[cfg(feature = "sqlite")]
-                    "sqlite" => (
-                        Box::new(SQLiteNickDatabase::new(&db_config.url).expect("Failed to open nick database")),
-                        Box::new(SQLiteChannelDatabase::new(&db_config.url).expect("Failed to open channel database")),
-                    ),
-                    #[cfg(feature = "mysql")]
-                    "mysql" => (
-                        Box::new(MysqlNickDatabase::new()),
-                        Box::new(MysqlChannelDatabase::new()),
-                    ),
-                    _ => return Err("Unsupported database type".to_string()),
-                };
-
-            nick_db.connect(&db_config.url).await.map_err(|e| e.to_string())?;
-            nick_db.create_table().await.map_err(|e| e.to_string())?;
-
-            chan_db.connect(&db_config.url).await.map_err(|e| e.to_string())?;
-            chan_db.create_table().await.map_err(|e| e.to_string())?;
-            chan_db.create_access_table().await.map_err(|e| e.to_string())?;
-            
-            // Run migrations on concrete instance
-            chan_db.migrate_topic_fields().await.map_err(|e| e.to_string())?;
+            let mut dbs = ServiceDatabases::open(db_config).await?;
+            // Actualiza el esquema antes de usar las bases de datos
+            for (version, description) in dbs.migrate().await.map_err(|e| e.to_string())? {
+                info!("Applied database migration {version}: {description}");
+            }
+            let ServiceDatabases { nick_db, chan_db, memo_db, oper_db, bot_db, snapshot, .. } = dbs;
+            if let Some(snapshot) = snapshot {
+                let interval = db_config.snapshot_interval.unwrap_or(DEFAULT_SNAPSHOT_INTERVAL);
+                snapshot.spawn(Duration::from_secs(interval));
+            }
 
             Databases {
                 nick_db: Some(Arc::new(RwLock::new(nick_db))),
                 chan_db: Some(Arc::new(RwLock::new(chan_db))),
+                memo_db: Some(Arc::new(RwLock::new(memo_db))),
+                oper_db: Some(Arc::new(RwLock::new(oper_db))),
+                bot_db: Some(Arc::new(RwLock::new(bot_db))),
             }
         } else {
             Databases {
                 nick_db: None,
                 chan_db: None,
+                memo_db: None,
+                oper_db: None,
+                bot_db: None,
             }
         };
         #[cfg(feature = "amqp")]
@@ -161,7 +148,6 @@ impl MainState {
             user_config_idxs,
             oper_config_idxs,
             state,
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             databases,
             #[cfg(feature = "amqp")]
             serv_comm,
@@ -169,7 +155,6 @@ impl MainState {
             connections_per_ip,
             created: now.to_rfc2822(),
             created_time: now,
-            #[cfg(any(feature = "sqlite", feature = "mysql"))]
             command_counts: [
                 AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
                 AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
@@ -184,19 +169,10 @@ impl MainState {
                 AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
                 AtomicU64::new(0), AtomicU64::new(0)
             ],
-            #[cfg(not(any(feature = "sqlite", feature = "mysql")))]
-            command_counts: [
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),  
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
-                AtomicU64::new(0), AtomicU64::new(0)
-            ],
         };
+        state.restore_channel_modes().await;
+        state.restore_guarded_channels().await;
+        state.restore_bots().await;
         Ok(state)
     }
 
@@ -205,10 +181,9 @@ impl MainState {
     }
 
     // Verificar si un usuario es IRCop
-    #[cfg(any(feature = "sqlite", feature = "mysql"))]
     async fn is_ircop(&self, nick: &str) -> bool {
         let state = self.state.read().await;
-        if let Some(user) = state.users.get(&crate::state::structs::to_unicase(nick)) {
+        if let Some(user) = state.users.get(&crate::state::structs::to_irccase(nick)) {
             user.modes.is_local_oper()
         } else {
             false
@@ -223,6 +198,8 @@ impl MainState {
     ) -> Option<ConnState> {
         // Check per-IP connection limit
         if let Some(max_per_ip) = self.config.max_connections_per_ip {
+            // Las excepciones de sesión de OperServ sustituyen al límite general
+            let max_per_ip = self.session_exception_limit(ip_addr).await.unwrap_or(max_per_ip);
             let mut ip_conns = self.connections_per_ip.write().await;
             let current_per_ip = ip_conns.get(&ip_addr).copied().unwrap_or(0);
             if current_per_ip >= max_per_ip {
@@ -320,13 +297,18 @@ impl MainState {
                 conn_state.quit.store(1, Ordering::SeqCst);
                 Ok(())
             },
+            Some(cloack) = conn_state.cloack_receiver.recv() => {
+                // vhost activado o quitado por otro usuario (HS ACTIVATE)
+                conn_state.user_state.set_cloack(cloack);
+                Ok(())
+            },
             Ok(hostname_opt) = &mut conn_state.dns_lookup_receiver => {
                 #[cfg(feature = "dns_lookup")]
                 if let Some(hostname) = hostname_opt {
                     conn_state.user_state.set_hostname(hostname);
                     if let Some(nick) = &conn_state.user_state.nick {
                         let mut state = self.state.write().await;
-                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(nick)) {
+                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(nick)) {
                             user.update_hostname(&conn_state.user_state, &self.config.cloack);
                         }
                     }
@@ -386,7 +368,7 @@ impl MainState {
                     }
                 };
 
-                let cmd = match Command::from_message(&msg) {
+                let cmd = match Command::from_message(&msg, &self.config.limits) {
                     Ok(cmd) => {
                         debug!("Comando recibido: {:?}", cmd);
                         cmd
@@ -521,22 +503,40 @@ impl MainState {
                         self.process_ison(conn_state, nicknames).await,
                     DIE{ message } =>
                         self.process_die(conn_state, message).await,
-                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                     NICKSERV{ subcommand, params } =>
                         self.process_nickserv(conn_state, subcommand, params).await,
-                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                     NS{ subcommand, params } =>
                         self.process_nickserv(conn_state, subcommand, params).await,
-                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                     CHANSERV{ subcommand, params } =>
                         self.process_chanserv(conn_state, subcommand, params).await,
-                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                     CS{ subcommand, params } =>
                         self.process_chanserv(conn_state, subcommand, params).await,
                     SETNAME { realname } =>
                         self.process_setname(conn_state, realname).await,
                     MONITOR { subcommand, targets } =>
                         self.process_monitor(conn_state, subcommand, targets).await,
+                    MEMOSERV{ subcommand, params } =>
+                        self.process_memoserv(conn_state, subcommand, params).await,
+                    MS{ subcommand, params } =>
+                        self.process_memoserv(conn_state, subcommand, params).await,
+                    HOSTSERV{ subcommand, params } =>
+                        self.process_hostserv(conn_state, subcommand, params).await,
+                    HS{ subcommand, params } =>
+                        self.process_hostserv(conn_state, subcommand, params).await,
+                    OPERSERV{ subcommand, params } =>
+                        self.process_operserv(conn_state, subcommand, params).await,
+                    OS{ subcommand, params } =>
+                        self.process_operserv(conn_state, subcommand, params).await,
+                    BOTSERV{ subcommand, params } =>
+                        self.process_botserv(conn_state, subcommand, params).await,
+                    BS{ subcommand, params } =>
+                        self.process_botserv(conn_state, subcommand, params).await,
+                    KNOCK{ channel, message } =>
+                        self.process_knock(conn_state, channel, message).await,
+                    ACCEPT{ nicknames } =>
+                        self.process_accept(conn_state, nicknames).await,
+                    SILENCE{ mask } =>
+                        self.process_silence(conn_state, mask).await,
                 }
             },
         }
@@ -587,8 +587,8 @@ async fn user_state_process(main_state: Arc<MainState>, stream: DualTcpStream, a
                     if conn_state.user_state.authenticated {
                         if let Some(nick) = &conn_state.user_state.nick {
                             let mut state = main_state.state.write().await;
-                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(nick)) {
-                                user.source = format!("{}!{}@{}",
+                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(nick)) {
+                                user.source = format!("{}!~{}@{}",
                                     nick, user.name, user.cloack.clone());
                                 conn_state.user_state.source = user.source.clone();
                             }
@@ -613,7 +613,7 @@ async fn user_state_process(main_state: Arc<MainState>, stream: DualTcpStream, a
             // Primero obtenemos una copia de los canales del usuario
             let user_channels = {
                 let state = main_state.state.read().await;
-                if let Some(user) = state.users.get(&crate::state::structs::to_unicase(nick)) {
+                if let Some(user) = state.users.get(&crate::state::structs::to_irccase(nick)) {
                     user.channels.clone()
                 } else {
                     HashSet::new()
@@ -624,7 +624,7 @@ async fn user_state_process(main_state: Arc<MainState>, stream: DualTcpStream, a
             for channel in &user_channels {
                 let channel_users = {
                     let state = main_state.state.read().await;
-                    if let Some(chanobj) = state.channels.get(&crate::state::structs::to_unicase(channel)) {
+                    if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
                         chanobj.users.keys().cloned().collect::<Vec<_>>()
                     } else {
                         continue;
@@ -632,9 +632,9 @@ async fn user_state_process(main_state: Arc<MainState>, stream: DualTcpStream, a
                 };
 
                 for nickname in channel_users {
-                    if nickname != UniCase::new(nick.as_str()) {
+                    if nickname != IrcCase::new(nick.as_str()) {
                         let state = main_state.state.read().await;
-                        if let Some(user) = state.users.get(&crate::state::structs::to_unicase(&nickname)) {
+                        if let Some(user) = state.users.get(&crate::state::structs::to_irccase(&nickname)) {
                             let _ = user.send_msg_display(
                                 &conn_state.user_state.source,
                                 format!("QUIT :{}", conn_state.user_state.quit_reason),
@@ -871,6 +871,10 @@ pub(crate) async fn run_server(
                             }
                             Ok(msg) = &mut quit_receiver => {
                                 info!("Server quit: {}", msg);
+                                // en los tests no se puede terminar el proceso
+                                #[cfg(test)]
+                                break;
+                                #[cfg(not(test))]
                                 std::process::exit(0);
                             }
                         };
@@ -903,6 +907,10 @@ pub(crate) async fn run_server(
                         }
                         Ok(msg) = &mut quit_receiver => {
                             info!("Server quit: {}", msg);
+                            // en los tests no se puede terminar el proceso
+                            #[cfg(test)]
+                            break;
+                            #[cfg(not(test))]
                             std::process::exit(0);
                         }
                     };
@@ -925,6 +933,10 @@ pub(crate) async fn run_server(
                         }
                         Ok(msg) = &mut quit_receiver => {
                             info!("Server quit: {}", msg);
+                            // en los tests no se puede terminar el proceso
+                            #[cfg(test)]
+                            break;
+                            #[cfg(not(test))]
                             std::process::exit(0);
                         }
                     };
@@ -951,6 +963,7 @@ mod test {
     pub(crate) use std::time::Duration;
     use tokio::net::TcpStream;
     pub(crate) use tokio::time;
+    pub(crate) use tokio_util::codec::Framed;
 
     use std::sync::atomic::AtomicU16;
 
@@ -960,7 +973,7 @@ mod test {
 
     pub(crate) async fn run_test_server(
         config: MainConfig,
-    ) -> (Arc<MainState>, JoinHandle<()>, u16) {
+    ) -> (Arc<MainState>, Vec<JoinHandle<()>>, u16) {
         let mut config = config;
         let port = PORT_COUNTER.fetch_add(1, Ordering::SeqCst);
         config.listeners.iter_mut().for_each(|l| l.port = port);
@@ -968,17 +981,13 @@ mod test {
         (main_state, handle, port)
     }
 
-    pub(crate) async fn quit_test_server(main_state: Arc<MainState>, handle: JoinHandle<()>) {
-        main_state
-            .state
-            .write()
-            .await
-            .quit_sender
-            .take()
-            .unwrap()
-            .send("Test".to_string())
-            .unwrap();
-        handle.await.unwrap();
+    pub(crate) async fn quit_test_server(main_state: Arc<MainState>, handles: Vec<JoinHandle<()>>) {
+        // el quit normal termina el proceso, así que en los tests se paran los listeners
+        assert!(main_state.state.write().await.quit_sender.take().is_some());
+        for handle in handles {
+            handle.abort();
+            let _ = handle.await;
+        }
     }
 
     pub(crate) async fn connect_to_test(port: u16) -> Framed<TcpStream, IRCLinesCodec> {
@@ -1009,12 +1018,40 @@ mod test {
         realname: &'a str,
     ) -> Framed<TcpStream, IRCLinesCodec> {
         let mut line_stream = login_to_test(port, nick, name, realname).await;
-        for _ in 0..18 {
-            line_stream.next().await.unwrap().unwrap();
+        // salta la bienvenida hasta la respuesta MODE que la cierra
+        let mode_reply = format!(" MODE {} :", nick);
+        loop {
+            let line = line_stream.next().await.unwrap().unwrap();
+            if line.contains(&mode_reply) {
+                break;
+            }
         }
         line_stream
     }
 
+    // salta líneas hasta la primera que contiene `text` y la devuelve
+    pub(crate) async fn skip_until(line_stream: &mut Framed<TcpStream, IRCLinesCodec>, text: &str) -> String {
+        loop {
+            let line = line_stream.next().await.unwrap().unwrap();
+            if line.contains(text) {
+                return line;
+            }
+        }
+    }
+
+    // configuración con los servicios en la base de datos en memoria
+    pub(crate) fn memory_database_config() -> MainConfig {
+        MainConfig {
+            database: Some(DatabaseConfig {
+                database: "memory".to_string(),
+                url: String::new(),
+                forbidden_vhosts: vec![],
+                snapshot_interval: None,
+            }),
+            ..MainConfig::default()
+        }
+    }
+
     #[cfg(feature = "tls")]
     fn get_cert_file_path() -> String {
         let mut path = PathBuf::new();
@@ -1138,30 +1175,33 @@ mod test {
                     env!("CARGO_PKG_NAME"),
                     "-",
                     env!("CARGO_PKG_VERSION"),
-                    " OiorwWzx bBeI,k,l,ahimnopqrstvO"
+                    " OgiorwRWzx bBeIZ,k,fjlL,achimnopqrstvzCKMOPRST"
                 ),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
-                ":irc.irc 005 mati AWAYLEN=1000 CASEMAPPING=ascii \
-                    CHANMODES=bBeI,k,l,ahimnopqrstvO CHANNELLEN=1000 CHANTYPES=&# EXCEPTS=e FNC \
-                    HOSTLEN=1000 INVEX=I KEYLEN=1000 :are supported by this server"
+                ":irc.irc 005 mati ACCEPT=30 AWAYLEN=1000 CALLERID=g CASEMAPPING=ascii \
+                    CHANMODES=bBeIZ,k,fjlL,achimnopqrstvzCKMOPRST CHANNELLEN=1000 CHANTYPES=&# ELIST=CMNTU \
+                    EXCEPTS=e EXTBAN=~,acjmrz :are supported by this server"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
-                ":irc.irc 005 mati KICKLEN=1000 LINELEN=2000 MAXLIST=beI:1000 \
-                    MAXNICKLEN=200 MAXPARA=500 MAXTARGETS=500 MODES=500 NETWORK=IRCnetwork \
-                    NICKLEN=200 PREFIX=(qaohv)~&@%+ :are supported by this server"
+                ":irc.irc 005 mati FNC HOSTLEN=1000 INVEX=I KEYLEN=1000 KICKLEN=1000 KNOCK LINELEN=2000 \
+                    MAXLIST=beIZ:1000 MAXNICKLEN=200 MAXPARA=500 :are supported by this server"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
-                ":irc.irc 005 mati SAFELIST STATUSMSG=~&@%+ TOPICLEN=1000 USERLEN=200 \
-                    USERMODES=OiorwWz :are supported by this server"
+                ":irc.irc 005 mati MAXTARGETS=500 MODES=500 NETWORK=IRCnetwork NICKLEN=200 PREFIX=(qaohv)~&@%+ \
+                    SAFELIST SILENCE=15 STATUSMSG=~&@%+ TOPICLEN=1000 USERLEN=200 :are supported by this server"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
+            assert_eq!(
+                ":irc.irc 005 mati USERMODES=OgiorwRWz :are supported by this server".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
             assert_eq!(
                 ":irc.irc 251 mati :There are 1 users and 0 invisible \
                     on 1 servers"
@@ -1256,17 +1296,73 @@ mod test {
 
         quit_test_server(main_state, handle).await;
     }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_server_slow_database_query() {
+        use sqlx::Connection;
+
+        // directorio propio de la prueba, se borra con los ficheros -wal y -shm al terminar
+        let db_dir = tempfile::tempdir().unwrap();
+        let db_path = db_dir.path().join("slow_query.db");
+        let db_url = db_path.to_string_lossy().to_string();
+        let mut config = MainConfig::default();
+        config.database = Some(DatabaseConfig {
+            database: "sqlite".to_string(),
+            url: db_url.clone(),
+            forbidden_vhosts: vec![],
+            snapshot_interval: None,
+        });
+        let (main_state, handle, port) = run_test_server(config).await;
+
+        {
+            // otra conexión bloquea la base de datos, así que el registro tiene que esperar
+            let mut lock_conn = sqlx::SqliteConnection::connect(&db_url).await.unwrap();
+            sqlx::query("BEGIN IMMEDIATE").execute(&mut lock_conn).await.unwrap();
+
+            let mut slow_stream = login_to_test_and_skip(port, "slowpoke", "slow", "SlowPoke").await;
+            let mut line_stream = login_to_test_and_skip(port, "mati", "mat", "MatiSzpaki").await;
+            slow_stream
+                .send("NS REGISTER password slow@example.org".to_string())
+                .await
+                .unwrap();
+            time::sleep(Duration::from_millis(100)).await;
+
+            line_stream.send("PING :bumbum".to_string()).await.unwrap();
+            assert_eq!(
+                ":irc.irc PONG irc.irc :bumbum".to_string(),
+                time::timeout(Duration::from_secs(1), line_stream.next())
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .unwrap()
+            );
+
+            sqlx::query("COMMIT").execute(&mut lock_conn).await.unwrap();
+            assert_eq!(
+                ":NickServ NOTICE slowpoke :Nick 'slowpoke' has been registered.".to_string(),
+                slow_stream.next().await.unwrap().unwrap()
+            );
+        }
+
+        quit_test_server(main_state, handle).await;
+        db_dir.close().unwrap();
+    }
 }
 
+mod callerid;
 mod channel_cmds;
 mod conn_cmds;
+mod flood;
 mod rest_cmds;
 mod srv_query_cmds;
 
-#[cfg(any(feature = "sqlite", feature = "mysql"))]
 pub mod nickserv;
-#[cfg(any(feature = "sqlite", feature = "mysql"))]
 pub mod chanserv;
+pub mod memoserv;
+pub mod hostserv;
+pub mod operserv;
+pub mod botserv;
 
 #[cfg(feature = "amqp")]
 pub mod server_communication;
\ No newline at end of file
@@ -20,6 +20,7 @@
 use super::*;
 use serde::ser::StdError;
 use std::time::SystemTime;
+use crate::database::{ForbiddenEntry, NickRecord};
 use crate::utils::argon2_hash_password;
 use crate::utils::validate_username;
 use std::ops::DerefMut;
@@ -70,6 +71,10 @@ impl super::MainState {
                         self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Nick '{nick}' is already registered.")).await?;
                         return Ok(());
                     }
+                    if db.get_forbidden_nick(nick).await?.is_some() {
+                        self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Nick '{nick}' is forbidden and cannot be registered.")).await?;
+                        return Ok(());
+                    }
 
                     let password_hash = argon2_hash_password(password);
 
@@ -89,7 +94,7 @@ impl super::MainState {
                 
                 // Check if user is operator
                 let state = self.state.read().await;
-                let user = state.users.get(&crate::state::structs::to_unicase(nick)).unwrap();
+                let user = state.users.get(&crate::state::structs::to_irccase(nick)).unwrap();
                 let is_oper = user.modes.is_local_oper();
 
                 if let Some(db_arc) = &self.databases.nick_db {
@@ -135,7 +140,7 @@ impl super::MainState {
                     if let Some(info) = db.get_nick_info(nick).await? {
                         if email.to_lowercase() == "off" {
                             // Disable email
-                            db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), None, info.3.as_deref(), info.4.as_deref(), info.5, Some(info.6), Some(info.7), Some(info.8)).await?;
+                            db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), None, info.url.as_deref(), info.vhost.as_deref(), info.last_vhost, Some(info.noaccess), Some(info.noop), Some(info.showmail)).await?;
                             self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Your email has been disabled.")).await?;
                         } else {
                             // Validate email format
@@ -145,7 +150,7 @@ impl super::MainState {
                             }
                             
                             // Update email
-                            db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), Some(email), info.3.as_deref(), info.4.as_deref(), info.5, Some(info.6), Some(info.7), Some(info.8)).await?;
+                            db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), Some(email), info.url.as_deref(), info.vhost.as_deref(), info.last_vhost, Some(info.noaccess), Some(info.noop), Some(info.showmail)).await?;
                             self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Your email has been updated to: {email}")).await?;
                         }
                     } else {
@@ -170,7 +175,7 @@ impl super::MainState {
                     if let Some(info) = db.get_nick_info(nick).await? {
                         if url.to_lowercase() == "off" {
                             // Disable URL
-                            db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.2.as_deref(), None, info.4.as_deref(), info.5, Some(info.6), Some(info.7), Some(info.8)).await?;
+                            db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.email.as_deref(), None, info.vhost.as_deref(), info.last_vhost, Some(info.noaccess), Some(info.noop), Some(info.showmail)).await?;
                             self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Your URL has been disabled.")).await?;
                         } else {
                             // Validate basic URL format
@@ -180,7 +185,7 @@ impl super::MainState {
                             }
                             
                             // Update URL
-                            db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.2.as_deref(), Some(url), info.4.as_deref(), info.5, Some(info.6), Some(info.7), Some(info.8)).await?;
+                            db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.email.as_deref(), Some(url), info.vhost.as_deref(), info.last_vhost, Some(info.noaccess), Some(info.noop), Some(info.showmail)).await?;
                             self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Your URL has been updated to: {url}")).await?;
                         }
                     } else {
@@ -214,7 +219,7 @@ impl super::MainState {
                     // Check if nick is registered
                     if let Some(info) = db.get_nick_info(nick).await? {
                         // Actualizar el estado de noaccess
-                        db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.2.as_deref(), info.3.as_deref(), info.4.as_deref(), info.5, Some(new_noaccess), Some(info.6), Some(info.7)).await?;
+                        db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.email.as_deref(), info.url.as_deref(), info.vhost.as_deref(), info.last_vhost, Some(new_noaccess), Some(info.noop), Some(info.showmail)).await?;
                         
                         let status = if new_noaccess { "activada" } else { "desactivada" };
                         self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :The noaccess option for {nick} has been {status}.")).await?;
@@ -256,7 +261,7 @@ impl super::MainState {
                     // Verificar si el nick objetivo está registrado
                     if let Some(info) = db.get_nick_info(target_nick).await? {
                         // Actualizar el estado de noop
-                        db.update_nick_info(target_nick, Some(conn_state.user_state.source.as_str()), info.2.as_deref(), info.3.as_deref(), info.4.as_deref(), info.5, Some(info.6), Some(new_noop), Some(info.7)).await?;
+                        db.update_nick_info(target_nick, Some(conn_state.user_state.source.as_str()), info.email.as_deref(), info.url.as_deref(), info.vhost.as_deref(), info.last_vhost, Some(info.noaccess), Some(new_noop), Some(info.showmail)).await?;
                         
                         let status = if new_noop { "activada" } else { "desactivada" };
                         self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :The noop option for {target_nick} has been {status}.")).await?;
@@ -292,7 +297,7 @@ impl super::MainState {
                     // Verificar si el nick objetivo está registrado
                     if let Some(info) = db.get_nick_info(nick).await? {
                         // Actualizar el estado de showmail
-                        db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.2.as_deref(), info.3.as_deref(), info.4.as_deref(), info.5, Some(info.6), Some(info.7), Some(new_showmail)).await?;
+                        db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.email.as_deref(), info.url.as_deref(), info.vhost.as_deref(), info.last_vhost, Some(info.noaccess), Some(info.noop), Some(new_showmail)).await?;
                         
                         let status = if new_showmail { "activada" } else { "desactivada" };
                         self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :The showmail option for {nick} has been {status}.")).await?;
@@ -359,13 +364,19 @@ impl super::MainState {
                     Some(vhost_action.to_string())
                 };
 
+                // Los vhosts nuevos pasan por la aprobación de HostServ salvo para IRCops
+                if new_vhost.is_some() && !self.is_ircop(nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :vhosts must be requested with /HS REQUEST <vhost>.")).await?;
+                    return Ok(());
+                }
+
                 if let Some(db_arc) = &self.databases.nick_db {
                     let mut db = db_arc.write().await;
                     
                     // Verificar si el nick está registrado
                     if let Some(info) = db.get_nick_info(nick).await? {
                         // Verificar si han pasado 24 horas desde el último cambio
-                        if let Some(last_vhost_change) = info.5 {
+                        if let Some(last_vhost_change) = info.last_vhost {
                             let now = SystemTime::now();
                             if let Ok(duration) = now.duration_since(last_vhost_change) {
                                 if duration.as_secs() < 86400 { // 24 horas en segundos
@@ -380,7 +391,7 @@ impl super::MainState {
                         
                         // Actualizar el vhost y el timestamp
                         let now = SystemTime::now();
-                        db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.2.as_deref(), info.3.as_deref(), new_vhost.as_deref(), Some(now), Some(info.6), Some(info.7), Some(info.8)).await?;
+                        db.update_nick_info(nick, Some(conn_state.user_state.source.as_str()), info.email.as_deref(), info.url.as_deref(), new_vhost.as_deref(), Some(now), Some(info.noaccess), Some(info.noop), Some(info.showmail)).await?;
                         
                         // Actualizar el vhost en el estado de la conexión y en el estado global
                         if new_vhost.is_some() {
@@ -440,7 +451,7 @@ impl super::MainState {
                             // Contraseña correcta, obtener vhost y proceder.
                             let nick_info = db.get_nick_info(target_nick).await?;
                             let vhost = if let Some(info) = nick_info {
-                                info.4.clone()
+                                info.vhost.clone()
                             } else {
                                 None
                             };
@@ -450,7 +461,7 @@ impl super::MainState {
                             // Verificar si el nick está en uso y desconectar si es necesario
                             let user_to_disconnect = {
                                 let state = self.state.read().await;
-                                if state.users.contains_key(&crate::state::structs::to_unicase(target_nick)) {
+                                if state.users.contains_key(&crate::state::structs::to_irccase(target_nick)) {
                                     // El nick está en uso, preparar para desconectar
                                     Some(target_nick.to_string())
                                 } else {
@@ -461,7 +472,7 @@ impl super::MainState {
                             // Desconectar al usuario existente si es necesario
                             if let Some(existing_nick) = user_to_disconnect {
                                 let mut state = self.state.write().await;
-                                if let Some(user) = state.users.remove(&crate::state::structs::to_unicase(&existing_nick)) {
+                                if let Some(user) = state.users.remove(&crate::state::structs::to_irccase(&existing_nick)) {
                                     // Enviar mensaje de desconexión al usuario existente
                                     if let Some(sender) = user.quit_sender {
                                         let _ = sender.send((existing_nick.clone(), "NickServ: Nick claimed".to_string()));
@@ -479,7 +490,7 @@ impl super::MainState {
                             // Actualizar en el estado global
                             let mut statem = self.state.write().await;
                             let state = statem.deref_mut();
-                            if let Some(mut user) = state.users.remove(&crate::state::structs::to_unicase(&old_nick)) {
+                            if let Some(mut user) = state.users.remove(&crate::state::structs::to_irccase(&old_nick)) {
                                 if vhost.is_some() {
                                     conn_state.user_state.cloack = vhost.clone().expect("ERROR.in.vHost");
                                     user.cloack = vhost.clone().expect("ERROR.in.vHost");
@@ -488,11 +499,11 @@ impl super::MainState {
                                 user.update_nick(&conn_state.user_state);
                                 if !user.modes.registered {
                                     for channel in &user.channels {
-                                        if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(&channel.clone())) {
+                                        if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(&channel.clone())) {
                                             let nicks: Vec<String> = chanobj.users.keys().cloned().map(|nick| nick.to_string()).collect();
                                             for nicknames in nicks {
                                                 if nicknames != target_nick && nicknames != old_nick {
-                                                    if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nicknames)) {
+                                                    if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(&nicknames)) {
                                                         let part_msg = format!("PART {channel} :vHost");
                                                         let _ = user.send_msg_display(
                                                             &old_source,
@@ -503,7 +514,7 @@ impl super::MainState {
                                                             &conn_state.user_state.source,
                                                             join_msg.as_str()
                                                         );
-                                                        if let Some(user_chum) = chanobj.users.get(&crate::state::structs::to_unicase(&old_nick)) {
+                                                        if let Some(user_chum) = chanobj.users.get(&crate::state::structs::to_irccase(&old_nick)) {
                                                             let mut arg = Vec::new();
                                                             if user_chum.founder {
                                                                 arg.push("q");
@@ -533,7 +544,7 @@ impl super::MainState {
                                 user.modes.registered = true;
                                 // Actualizar canales
                                 for ch in &user.channels {
-                                    if let Some(channel) = state.channels.get_mut(&crate::state::structs::to_unicase(&ch.clone())) {
+                                    if let Some(channel) = state.channels.get_mut(&crate::state::structs::to_irccase(&ch.clone())) {
                                         channel.rename_user(&old_nick, target_nick.to_string());
                                     }
                                 }
@@ -548,7 +559,7 @@ impl super::MainState {
                                 state.insert_to_nick_history(&old_nick, user.history_entry.clone());
                                 
                                 // Insertar con el nuevo nick
-                                state.users.insert(crate::state::structs::to_unicase(target_nick), user.clone());
+                                state.users.insert(crate::state::structs::to_irccase(target_nick), user.clone());
                             }
                             
                             // Obtener el nuevo client_name después de las modificaciones
@@ -560,6 +571,9 @@ impl super::MainState {
                             for u in state.users.values() {
                                 let _ = u.send_msg_display(&old_source, nick_change_msg.clone());
                             }
+                            drop(statem);
+
+                            self.notify_unread_memos(conn_state, target_nick).await?;
                             
                         } else {
                             self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Incorrect password.")).await?;
@@ -572,9 +586,13 @@ impl super::MainState {
                 }
             }
             "info" => {
+                let nick = params.first().copied().unwrap_or(nick.as_str());
                 if let Some(db_arc) = &self.databases.nick_db {
                     let db = db_arc.read().await;
-                    if let Some((user, registration_date, email, url, vhost, last_vhost, noaccess, noop, showmail)) = db.get_nick_info(nick).await? {
+                    if let Some(ForbiddenEntry { reason, set_by, set_time, .. }) = db.get_forbidden_nick(nick).await? {
+                        self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Nick {nick} is forbidden by {set_by} on {}: {reason}", super::chanserv::format_service_time(set_time))).await?;
+                    }
+                    if let Some(NickRecord { user, registration_time: registration_date, email, url, vhost, last_vhost, noaccess, noop, showmail }) = db.get_nick_info(nick).await? {
                         self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Nick information: {nick}")).await?;
                         self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :User: {user}")).await?;
                         if let Some(vhost) = &vhost {
@@ -628,6 +646,59 @@ impl super::MainState {
                     self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Database is not configured.")).await?;
                 }
             }
+            "forbid" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Usage: /NS FORBID <nick> <add|del> [reason]")).await?;
+                    return Ok(());
+                }
+                let target = params[0];
+                let action = params[1].to_lowercase();
+
+                if !self.is_ircop(nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Only IRCops can forbid nicks.")).await?;
+                    return Ok(());
+                }
+
+                if let Some(db_arc) = &self.databases.nick_db {
+                    match action.as_str() {
+                        "add" => {
+                            if params.len() < 3 {
+                                self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Usage: /NS FORBID <nick> add <reason>")).await?;
+                                return Ok(());
+                            }
+                            let reason = params[2..].join(" ");
+                            db_arc.write().await.add_forbidden_nick(target, &reason, nick, SystemTime::now()).await?;
+
+                            // Desconectar al usuario que está usando el nick, salvo que sea IRCop
+                            let mut state = self.state.write().await;
+                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(target)) {
+                                if !user.modes.is_local_oper() {
+                                    if let Some(sender) = user.quit_sender.take() {
+                                        let _ = sender.send(("NickServ".to_string(), format!("Nick forbidden: {reason}")));
+                                    }
+                                }
+                            }
+                            drop(state);
+
+                            info!("Nick {} forbidden by {}: {}", target, nick, reason);
+                            self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Nick '{target}' is now forbidden.")).await?;
+                        }
+                        "del" => {
+                            if db_arc.write().await.delete_forbidden_nick(target).await? {
+                                info!("Nick {} unforbidden by {}", target, nick);
+                                self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Nick '{target}' is no longer forbidden.")).await?;
+                            } else {
+                                self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Nick '{target}' is not forbidden.")).await?;
+                            }
+                        }
+                        _ => {
+                            self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Invalid action. Use: add or del")).await?;
+                        }
+                    }
+                } else {
+                    self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :Database is not configured.")).await?;
+                }
+            }
             "help" => {
                 self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :NickServ commands:")).await?;
                 self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :  REGISTER <password> - Register your nick")).await?;
@@ -641,6 +712,9 @@ impl super::MainState {
                 self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :  PASSWORD <password> - Change your password")).await?;
                 self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :  IDENTIFY <nickname> <password> - Identify yourself to the server")).await?;
                 self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :  INFO [nick] - Show nick information")).await?;
+                if self.is_ircop(nick).await {
+                    self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :  FORBID <nick> <add|del> [reason] - Forbid a nick (IRCop)")).await?;
+                }
                 self.feed_msg_source(&mut conn_state.stream, "NickServ", format!("NOTICE {client} :  HELP - Show available commands")).await?;
             }
             _ => {
@@ -650,3 +724,54 @@ impl super::MainState {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::super::test::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_nickserv_forbid() {
+        let mut config = memory_database_config();
+        config.operators = Some(vec![OperatorConfig {
+            name: "fanny".to_string(),
+            password: argon2_hash_password("Funny"),
+            mask: None,
+        }]);
+        let (main_state, handle, port) = run_test_server(config).await;
+
+        {
+            let mut line_stream = login_to_test_and_skip(port, "fanny", "fanny", "Fanny BumBumBum").await;
+            line_stream.send("OPER fanny Funny".to_string()).await.unwrap();
+            line_stream.next().await.unwrap().unwrap();
+
+            // el usuario que tiene el nick se desconecta
+            let mut line_stream2 = login_to_test_and_skip(port, "baddie", "baddie", "Bad Die").await;
+            line_stream.send("NS FORBID baddie add Impersonation".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "is now forbidden").await;
+            assert_eq!(
+                ":irc.irc User killed by NickServ: Nick forbidden: Impersonation".to_string(),
+                skip_until(&mut line_stream2, "killed").await
+            );
+            time::sleep(Duration::from_millis(50)).await;
+            assert!(!main_state.state.read().await.users.contains_key(&to_irccase("baddie")));
+
+            // no se puede volver a coger el nick
+            let mut line_stream2 = login_to_test(port, "baddie", "baddie", "Bad Die").await;
+            assert!(skip_until(&mut line_stream2, " 432 ").await.contains(" baddie "));
+            assert!(skip_until(&mut line_stream2, "NOTICE").await.ends_with(" :Nick baddie is forbidden: Impersonation"));
+
+            // los IRCops pueden usar un nick prohibido, pero no registrarlo
+            line_stream.send("NS FORBID fanny add Reserved".to_string()).await.unwrap();
+            skip_until(&mut line_stream, "is now forbidden").await;
+            line_stream.send("NS REGISTER password fanny@example.org".to_string()).await.unwrap();
+            assert_eq!(
+                ":NickServ NOTICE fanny :Nick 'fanny' is forbidden and cannot be registered.".to_string(),
+                skip_until(&mut line_stream, "NOTICE").await
+            );
+            assert!(main_state.state.read().await.users.contains_key(&to_irccase("fanny")));
+        }
+
+        quit_test_server(main_state, handle).await;
+    }
+}
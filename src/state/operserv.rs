@@ -0,0 +1,494 @@
+// operserv.rs - OperServ commands
+//
+// simple-irc-server - simple IRC server
+// Copyright (C) 2022-2024  Mateusz Szpakowski
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+use super::chanserv::format_service_time;
+use crate::database::AkillEntry;
+use crate::utils::{match_wildcard, parse_duration};
+use serde::ser::StdError;
+use std::ops::DerefMut;
+use std::time::SystemTime;
+
+// Número de entradas que se guardan en el registro de comandos de OperServ
+const OPER_LOG_SIZE: usize = 100;
+
+impl super::MainState {
+    pub(super) async fn process_operserv<'a>(
+        &self,
+        conn_state: &mut ConnState,
+        subcommand: &'a str,
+        params: Vec<&'a str>,
+    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let client = conn_state.user_state.client_name().to_string();
+        let nick = if let Some(nick) = &conn_state.user_state.nick {
+            nick.clone()
+        } else {
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :You don't have a nick.")).await?;
+            return Ok(());
+        };
+
+        if !self.is_ircop(&nick).await {
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Access denied. OperServ is only available to IRCops.")).await?;
+            return Ok(());
+        }
+
+        let subcommand = subcommand.to_lowercase();
+        if subcommand == "help" {
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :OperServ - Network Administration Service")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Available commands:")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  AKILL ADD <user@host> [+expiry] <reason> - Ban a mask from the network")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  AKILL DEL <user@host> - Remove an AKILL")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  AKILL LIST - List AKILLs")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  GLOBAL <message> - Send a notice to all users")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  EXCEPTION ADD <ip> <limit> <reason> - Allow more sessions from an IP (0 = unlimited)")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  EXCEPTION DEL <ip> - Remove a session exception")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  EXCEPTION LIST - List session exceptions")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  MODE <channel> <modes> [args] - Change channel modes overriding access")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  JUPE ADD <nick|#channel> [reason] - Block a nick or channel")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  JUPE DEL <nick|#channel> - Remove a jupe")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  JUPE LIST - List jupes")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  STATS - Show network statistics")).await?;
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  LOG [count] - Show the last OperServ commands")).await?;
+            return Ok(());
+        }
+
+        // Todos los comandos salvo HELP y LOG quedan registrados
+        if subcommand != "log" {
+            self.log_oper_command(&nick, &subcommand, &params).await;
+        }
+
+        let db_arc = if let Some(db_arc) = &self.databases.oper_db {
+            db_arc
+        } else {
+            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Database is not configured.")).await?;
+            return Ok(());
+        };
+
+        match subcommand.as_str() {
+            "akill" => {
+                let action = params.first().map(|s| s.to_lowercase()).unwrap_or_default();
+                match action.as_str() {
+                    "add" => {
+                        if params.len() < 3 {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS AKILL ADD <user@host> [+expiry] <reason>")).await?;
+                            return Ok(());
+                        }
+                        // se guarda en minúsculas, igual que se compara
+                        let mask = params[1].to_lowercase();
+                        if !valid_akill_mask(&mask) {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Invalid mask '{mask}'. Use user@host and avoid masks matching everyone.")).await?;
+                            return Ok(());
+                        }
+                        // Expiración opcional en la forma +30m, +2h, +7d...
+                        let (expires, reason_params) = if let Some(expiry) = params[2].strip_prefix('+') {
+                            if let Some(secs) = parse_duration(expiry) {
+                                (Some(SystemTime::now() + std::time::Duration::from_secs(secs)), &params[3..])
+                            } else {
+                                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Invalid expiry '{}'. Use e.g. +30m, +2h, +7d.", params[2])).await?;
+                                return Ok(());
+                            }
+                        } else {
+                            (None, &params[2..])
+                        };
+                        if reason_params.is_empty() {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS AKILL ADD <user@host> [+expiry] <reason>")).await?;
+                            return Ok(());
+                        }
+                        let reason = reason_params.join(" ");
+                        db_arc.write().await.add_akill(&mask, &reason, &nick, SystemTime::now(), expires).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :AKILL added for {mask}.")).await?;
+
+                        // Desconectar a los usuarios que ya coinciden con la máscara
+                        let mut state = self.state.write().await;
+                        for user in state.users.values_mut() {
+                            if !user.modes.is_local_oper() && akill_matches(&mask, &format!("{}@{}", user.name, user.hostname)) {
+                                if let Some(sender) = user.quit_sender.take() {
+                                    let _ = sender.send(("OperServ".to_string(), format!("AKILL: {reason}")));
+                                }
+                            }
+                        }
+                    }
+                    "del" => {
+                        if params.len() < 2 {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS AKILL DEL <user@host>")).await?;
+                            return Ok(());
+                        }
+                        let mask = params[1];
+                        if db_arc.write().await.delete_akill(mask).await? {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :AKILL for {mask} removed.")).await?;
+                        } else {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :There is no AKILL for {mask}.")).await?;
+                        }
+                    }
+                    "list" => {
+                        let akills = db_arc.read().await.get_akills().await?;
+                        if akills.is_empty() {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :The AKILL list is empty.")).await?;
+                            return Ok(());
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :AKILL list:")).await?;
+                        for (i, AkillEntry { mask, reason, set_by, set_time, expires }) in akills.iter().enumerate() {
+                            let expiry = match expires {
+                                Some(expires) => format!("expires {}", format_service_time(*expires)),
+                                None => "permanent".to_string(),
+                            };
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  {}. {mask} - {reason} (by {set_by} on {}, {expiry})", i + 1, format_service_time(*set_time))).await?;
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :End of AKILL list.")).await?;
+                    }
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS AKILL <ADD|DEL|LIST> [args]")).await?;
+                    }
+                }
+            }
+            "global" => {
+                if params.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS GLOBAL <message>")).await?;
+                    return Ok(());
+                }
+                let message = params.join(" ");
+                let state = self.state.read().await;
+                for (unick, user) in state.users.iter() {
+                    let _ = user.send_msg_display("Global", format!("NOTICE {unick} :[Global Notice] {message}"));
+                }
+                drop(state);
+                info!("Global notice from {}: {}", nick, message);
+            }
+            "exception" => {
+                let action = params.first().map(|s| s.to_lowercase()).unwrap_or_default();
+                match action.as_str() {
+                    "add" => {
+                        if params.len() < 4 {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS EXCEPTION ADD <ip> <limit> <reason>")).await?;
+                            return Ok(());
+                        }
+                        let ip = match params[1].parse::<IpAddr>() {
+                            Ok(ip) => ip.to_string(),
+                            Err(_) => {
+                                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Invalid IP address '{}'.", params[1])).await?;
+                                return Ok(());
+                            }
+                        };
+                        let limit = match params[2].parse::<usize>() {
+                            Ok(limit) => limit,
+                            Err(_) => {
+                                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Invalid limit '{}'.", params[2])).await?;
+                                return Ok(());
+                            }
+                        };
+                        let reason = params[3..].join(" ");
+                        db_arc.write().await.add_session_exception(&ip, limit, &reason, &nick, SystemTime::now()).await?;
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Session limit for {ip} set to {}.", format_session_limit(limit))).await?;
+                    }
+                    "del" => {
+                        if params.len() < 2 {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS EXCEPTION DEL <ip>")).await?;
+                            return Ok(());
+                        }
+                        let ip = params[1].parse::<IpAddr>().map(|ip| ip.to_string()).unwrap_or_else(|_| params[1].to_string());
+                        if db_arc.write().await.delete_session_exception(&ip).await? {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Session exception for {ip} removed.")).await?;
+                        } else {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :There is no session exception for {ip}.")).await?;
+                        }
+                    }
+                    "list" => {
+                        let exceptions = db_arc.read().await.get_session_exceptions().await?;
+                        if exceptions.is_empty() {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :The session exception list is empty.")).await?;
+                            return Ok(());
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Session exceptions:")).await?;
+                        for (ip, limit, reason, set_by, set_time) in exceptions {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  {ip} - limit {} - {reason} (by {set_by} on {})", format_session_limit(limit), format_service_time(set_time))).await?;
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :End of session exceptions.")).await?;
+                    }
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS EXCEPTION <ADD|DEL|LIST> [args]")).await?;
+                    }
+                }
+            }
+            "mode" => {
+                if params.len() < 2 {
+                    self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS MODE <channel> <modes> [args]")).await?;
+                    return Ok(());
+                }
+                let target = params[0];
+                if validate_channel(target).is_err() {
+                    self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :{target} is not a valid channel.")).await?;
+                    return Ok(());
+                }
+                // Se reutiliza el parser de MODE para agrupar los modos con sus argumentos
+                let line = format!("MODE {}", params.join(" "));
+                let message = Message::from_shared_str(&line).map_err(|e| e.to_string())?;
+                let modes = match Command::from_message(&message, &self.config.limits) {
+                    Ok(Command::MODE { modes, .. }) => modes,
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Invalid mode string.")).await?;
+                        return Ok(());
+                    }
+                };
+
+                let mut statem = self.state.write().await;
+                let state = statem.deref_mut();
+                if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(target)) {
+                    // Se actúa como fundador del canal, sin necesidad de estar dentro
+                    let chum = ChannelUserModes {
+                        founder: true,
+                        protected: true,
+                        voice: false,
+                        operator: true,
+                        half_oper: true,
+                    };
+                    let saved_changes = self.process_mode_channel(conn_state, &state.users, chanobj, target, modes, &chum).await?;
+                    drop(statem);
+                    let set_by = conn_state.user_state.nick.clone().unwrap_or_default();
+                    self.save_channel_modes(target, &set_by, saved_changes).await;
+                } else {
+                    drop(statem);
+                    self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Channel {target} does not exist.")).await?;
+                }
+            }
+            "jupe" => {
+                let action = params.first().map(|s| s.to_lowercase()).unwrap_or_default();
+                match action.as_str() {
+                    "add" => {
+                        if params.len() < 2 {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS JUPE ADD <nick|#channel> [reason]")).await?;
+                            return Ok(());
+                        }
+                        let target = params[1];
+                        let is_channel = validate_channel(target).is_ok();
+                        if !is_channel && validate_username(target).is_err() {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :{target} is not a valid nick or channel.")).await?;
+                            return Ok(());
+                        }
+                        let reason = if params.len() > 2 {
+                            params[2..].join(" ")
+                        } else {
+                            format!("Juped by {nick}")
+                        };
+
+                        let mut state = self.state.write().await;
+                        state.jupes.insert(crate::state::structs::to_irccase(target), reason.clone());
+                        // Un nick bloqueado no puede seguir en uso
+                        if !is_channel {
+                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(target)) {
+                                if let Some(sender) = user.quit_sender.take() {
+                                    let _ = sender.send(("OperServ".to_string(), format!("Juped: {reason}")));
+                                }
+                            }
+                        }
+                        drop(state);
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :{target} has been juped.")).await?;
+                    }
+                    "del" => {
+                        if params.len() < 2 {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS JUPE DEL <nick|#channel>")).await?;
+                            return Ok(());
+                        }
+                        let target = params[1];
+                        let removed = self.state.write().await.jupes.remove(&crate::state::structs::to_irccase(target)).is_some();
+                        if removed {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Jupe on {target} removed.")).await?;
+                        } else {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :{target} is not juped.")).await?;
+                        }
+                    }
+                    "list" => {
+                        let jupes = self.state.read().await.jupes.iter()
+                            .map(|(target, reason)| (target.to_string(), reason.clone()))
+                            .collect::<Vec<_>>();
+                        if jupes.is_empty() {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :The jupe list is empty.")).await?;
+                            return Ok(());
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Juped nicks and channels:")).await?;
+                        for (target, reason) in jupes {
+                            self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  {target} - {reason}")).await?;
+                        }
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :End of jupe list.")).await?;
+                    }
+                    _ => {
+                        self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Usage: /OS JUPE <ADD|DEL|LIST> [args]")).await?;
+                    }
+                }
+            }
+            "stats" => {
+                let uptime = (Local::now() - self.created_time).num_seconds().max(0) as u64;
+                let (users, invisible, opers, max_users, channels) = {
+                    let state = self.state.read().await;
+                    (
+                        state.users.len(),
+                        state.invisible_users_count,
+                        state.operators_count,
+                        state.max_users_count,
+                        state.channels.len(),
+                    )
+                };
+                let akills = db_arc.read().await.get_akills().await?.len();
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Network statistics:")).await?;
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  Uptime: {}", format_uptime(uptime))).await?;
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  Users: {users} ({invisible} invisible, max {max_users})")).await?;
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  IRCops: {opers}")).await?;
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  Channels: {channels}")).await?;
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  Connections: {}", self.conns_count.load(Ordering::SeqCst))).await?;
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  AKILLs: {akills}")).await?;
+            }
+            "log" => {
+                let count = params.first().and_then(|s| s.parse::<usize>().ok()).unwrap_or(20);
+                let entries = {
+                    let state = self.state.read().await;
+                    let skip = state.oper_log.len().saturating_sub(count);
+                    state.oper_log.iter().skip(skip).cloned().collect::<Vec<_>>()
+                };
+                if entries.is_empty() {
+                    self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :The command log is empty.")).await?;
+                    return Ok(());
+                }
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Last {} OperServ commands:", entries.len())).await?;
+                for entry in entries {
+                    self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :  {entry}")).await?;
+                }
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :End of command log.")).await?;
+            }
+            _ => {
+                self.feed_msg_source(&mut conn_state.stream, "OperServ", format!("NOTICE {client} :Unknown command. Use /OS HELP for a list of commands.")).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Guarda un comando de OperServ en el registro en memoria
+    async fn log_oper_command(&self, nick: &str, subcommand: &str, params: &[&str]) {
+        let entry = format!(
+            "[{}] {}: {} {}",
+            format_service_time(SystemTime::now()),
+            nick,
+            subcommand.to_uppercase(),
+            params.join(" ")
+        );
+        info!("OperServ: {}", entry);
+        let mut state = self.state.write().await;
+        if state.oper_log.len() >= OPER_LOG_SIZE {
+            state.oper_log.pop_front();
+        }
+        state.oper_log.push_back(entry.trim_end().to_string());
+    }
+
+    // Devuelve el motivo del AKILL que afecta a user@host o user@ip, si lo hay.
+    // Los AKILL caducados se eliminan al encontrarlos.
+    pub(super) async fn find_akill(&self, name: &str, hostname: &str, ip: &str) -> Option<String> {
+        let db_arc = self.databases.oper_db.as_ref()?;
+        let akills = db_arc.read().await.get_akills().await.ok()?;
+        let now = SystemTime::now();
+        let by_host = format!("{}@{}", name, hostname);
+        let by_ip = format!("{}@{}", name, ip);
+        for AkillEntry { mask, reason, expires, .. } in akills {
+            if expires.is_some_and(|expires| expires <= now) {
+                let _ = db_arc.write().await.delete_akill(&mask).await;
+                continue;
+            }
+            if akill_matches(&mask, &by_host) || akill_matches(&mask, &by_ip) {
+                return Some(reason);
+            }
+        }
+        None
+    }
+
+    // Límite de conexiones para una IP con excepción de sesión (0 = sin límite)
+    pub(super) async fn session_exception_limit(&self, ip_addr: IpAddr) -> Option<usize> {
+        let db_arc = self.databases.oper_db.as_ref()?;
+        match db_arc.read().await.get_session_exception(&ip_addr.to_string()).await {
+            Ok(Some(0)) => Some(usize::MAX),
+            Ok(limit) => limit,
+            Err(_) => None,
+        }
+    }
+}
+
+// Una máscara de AKILL debe tener la forma user@host y no puede cubrir a todos los usuarios
+fn valid_akill_mask(mask: &str) -> bool {
+    match mask.split_once('@') {
+        Some((user, host)) => {
+            !user.is_empty()
+                && !host.is_empty()
+                && !host.contains('@')
+                && !mask.contains(' ')
+                && host.chars().any(|c| c != '*' && c != '?' && c != '.')
+        }
+        None => false,
+    }
+}
+
+// Las máscaras de AKILL no distinguen mayúsculas de minúsculas
+fn akill_matches(mask: &str, user_host: &str) -> bool {
+    match_wildcard(&mask.to_lowercase(), &user_host.to_lowercase())
+}
+
+fn format_session_limit(limit: usize) -> String {
+    if limit == 0 {
+        "unlimited".to_string()
+    } else {
+        limit.to_string()
+    }
+}
+
+fn format_uptime(secs: u64) -> String {
+    format!(
+        "{} days, {:02}:{:02}:{:02}",
+        secs / 86400,
+        (secs % 86400) / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_valid_akill_mask() {
+        assert!(valid_akill_mask("*@192.168.1.*"));
+        assert!(valid_akill_mask("baduser@*.example.com"));
+        assert!(!valid_akill_mask("*@*"));
+        assert!(!valid_akill_mask("*@*.*"));
+        assert!(!valid_akill_mask("nohost"));
+        assert!(!valid_akill_mask("@host.com"));
+        assert!(!valid_akill_mask("user@"));
+        assert!(!valid_akill_mask("a@b@c"));
+    }
+
+    #[test]
+    fn test_akill_matches() {
+        assert!(akill_matches("BadUser@*.Example.COM", "baduser@host.example.com"));
+        assert!(akill_matches("*@192.168.1.*", "Someone@192.168.1.20"));
+        assert!(akill_matches("baduser@*.example.com", "BADUSER@Host.Example.Com"));
+        assert!(!akill_matches("BadUser@*.Example.COM", "gooduser@host.example.com"));
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!("0 days, 00:00:59", format_uptime(59));
+        assert_eq!("2 days, 03:04:05", format_uptime(2 * 86400 + 3 * 3600 + 4 * 60 + 5));
+    }
+}
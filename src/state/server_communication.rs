@@ -385,11 +385,11 @@ impl ServerCommunication {
                 let server_message = format!("{command} {channel} {text}");
                 let state = self.state.read().await;
                 // Verificar si el canal existe
-                if let Some(chanobj) = state.channels.get(&crate::state::structs::to_unicase(channel)) {
+                if let Some(chanobj) = state.channels.get(&crate::state::structs::to_irccase(channel)) {
                     let nicks: Vec<String> = chanobj.users.keys().map(|k| k.to_string()).collect();
                     for nick in nicks {
                         if *nick != snick {
-                            if let Some(user) = state.users.get(&crate::state::structs::to_unicase(&nick)) {
+                            if let Some(user) = state.users.get(&crate::state::structs::to_irccase(&nick)) {
                                 if self.uuid.to_string() != result.get_uuid() {
                                     let _ = user.send_msg_display(
                                         result.get_user(),
@@ -418,7 +418,7 @@ impl ServerCommunication {
                     let mut state = self.state.write().await;
                     let source = self.parse_user(result.get_user().to_string());
                     let snick = source.unwrap().nick.clone();
-                    let chanobj: &mut Channel = state.channels.get_mut(&crate::state::structs::to_unicase(channel)).ok_or("Canal no encontrado")?;
+                    let chanobj: &mut Channel = state.channels.get_mut(&crate::state::structs::to_irccase(channel)).ok_or("Canal no encontrado")?;
                     let mut gban = chanobj.modes.global_ban.take().unwrap_or_default();
                     let norm_bmask = normalize_sourcemask(mask);
                     gban.insert(norm_bmask.clone());
@@ -430,7 +430,7 @@ impl ServerCommunication {
                         (mask.to_string(), None)
                     };
                     chanobj.ban_info.insert(
-                        crate::state::structs::to_unicase(&norm_bmask),
+                        crate::state::structs::to_irccase(&norm_bmask),
                         BanInfo {
                             who: snick.to_string(),
                             set_time: SystemTime::now()
@@ -443,7 +443,7 @@ impl ServerCommunication {
                     chanobj.modes.global_ban = Some(gban);
                     let nicks: Vec<String> = chanobj.users.keys().map(|k| k.to_string()).collect();
                     for nick in nicks {
-                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nick.to_string())) {
+                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(&nick)) {
                             if self.uuid.to_string() != result.get_uuid() {
                                 let _ = user.send_msg_display(
                                     result.get_user(),
@@ -468,15 +468,15 @@ impl ServerCommunication {
 
                             // Remover el ban global expirado
                             let mut state = state_clone.write().await;
-                            if let Some(channel) = state.channels.get_mut(&crate::state::structs::to_unicase(&channel_name)) {
+                            if let Some(channel) = state.channels.get_mut(&crate::state::structs::to_irccase(&channel_name)) {
                                 if let Some(ban_set) = &mut channel.modes.global_ban {
                                     ban_set.remove(&ban_mask_for_timeout);
-                                    channel.ban_info.remove(&crate::state::structs::to_unicase(&ban_mask_for_timeout));
+                                    channel.ban_info.remove(&crate::state::structs::to_irccase(&ban_mask_for_timeout));
 
                                     // Notificar a los usuarios del canal
                                     let nicks: Vec<String> = channel.users.keys().map(|k| k.to_string()).collect();
                                     for nick in nicks {
-                                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nick)) {
+                                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(&nick)) {
                                             // Solo notificar a usuarios conectados a este servidor
                                             if server_uuid != result.get_uuid() {
                                                 let _ = user.send_msg_display(
@@ -492,14 +492,14 @@ impl ServerCommunication {
                     }
                 } else if mode == "-B" {
                     let mut state = self.state.write().await;
-                    let chanobj: &mut Channel = state.channels.get_mut(&crate::state::structs::to_unicase(channel)).ok_or("Canal no encontrado")?;
+                    let chanobj: &mut Channel = state.channels.get_mut(&crate::state::structs::to_irccase(channel)).ok_or("Canal no encontrado")?;
                     let mut gban = chanobj.modes.global_ban.take().unwrap_or_default();
                     let norm_bmask = normalize_sourcemask(mask);
                     gban.remove(&norm_bmask);
-                    chanobj.ban_info.remove(&crate::state::structs::to_unicase(&norm_bmask));
+                    chanobj.ban_info.remove(&crate::state::structs::to_irccase(&norm_bmask));
                     let nicks: Vec<String> = chanobj.users.keys().map(|k| k.to_string()).collect();
                     for nick in nicks {
-                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nick)) {
+                        if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(&nick)) {
                             if self.uuid.to_string() != result.get_uuid() {
                                 let _ = user.send_msg_display(
                                     result.get_user(),
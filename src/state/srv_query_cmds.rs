@@ -18,13 +18,13 @@
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
 use super::*;
+use super::chanserv::SavedModeChanges;
 use crate::help::*;
 use serde::ser::StdError;
 use std::ops::DerefMut;
 use std::sync::atomic::Ordering;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::time::Duration;
-use unicase::UniCase;
 
 impl super::MainState {
     pub(super) async fn process_motd<'a>(
@@ -304,7 +304,7 @@ impl super::MainState {
         } else {
             let state = self.state.read().await;
             let user_nick = conn_state.user_state.nick.as_ref().unwrap();
-            let user = state.users.get(&crate::state::structs::to_unicase(user_nick)).unwrap();
+            let user = state.users.get(&crate::state::structs::to_irccase(user_nick)).unwrap();
 
             if user.modes.is_local_oper() {
                 match stat {
@@ -366,6 +366,19 @@ impl super::MainState {
             )
             .await?;
         } else {
+            // sin AMQP la red es sólo este servidor
+            #[cfg(not(feature = "amqp"))]
+            self.feed_msg(
+                &mut conn_state.stream,
+                RplLinks364 {
+                    client,
+                    server: &self.config.name,
+                    mask: &self.config.name,
+                    hop_count: 0,
+                    server_info: &self.config.info,
+                },
+            )
+            .await?;
             #[cfg(feature = "amqp")]
             {
                 let servers = self.serv_comm.read().await.servers.read().await.clone();
@@ -464,22 +477,24 @@ impl super::MainState {
         Ok(())
     }
 
-    async fn process_mode_channel<'a>(
+    pub(super) async fn process_mode_channel<'a>(
         &self,
         conn_state: &mut ConnState,
-        users: &HashMap<UniCase<String>, User>,
+        users: &HashMap<IrcCase<String>, User>,
         chanobj: &mut Channel,
         target: &'a str,
         modes: Vec<(&'a str, Vec<&'a str>)>,
         chum: &ChannelUserModes,
-    ) -> Result<(), Box<dyn StdError + Send + Sync>> {
+    ) -> Result<SavedModeChanges, Box<dyn StdError + Send + Sync>> {
         let client = conn_state.user_state.client_name();
         let if_op = chum.is_operator();
         let if_half_op = chum.is_half_operator();
-        let user = users.get(&crate::state::structs::to_unicase(client));
+        let user = users.get(&crate::state::structs::to_irccase(client));
         let if_oper = user.as_ref().unwrap().modes.is_local_oper();
         let mut set_mode_args: Vec<String> = Vec::new();
         let mut unset_mode_args: Vec<String> = Vec::new();
+        let mut saved_changes = SavedModeChanges::default();
+        let mut key_limit_changed = false;
         if modes.is_empty() {
             // Los modos de canal son hasta el primer espacio
             let modes_full = chanobj.modes.to_string();
@@ -559,7 +574,7 @@ impl super::MainState {
                                 .await?;
                             }
                         }
-                        'i' | 'm' | 't' | 'n' | 's' | 'l' | 'k' | 'v' => {
+                        'i' | 'm' | 't' | 'n' | 's' | 'l' | 'k' | 'f' | 'j' | 'L' | 'v' | 'c' | 'S' | 'C' | 'T' | 'R' | 'M' | 'z' | 'K' => {
                             if !if_half_op && !if_oper {
                                 self.feed_msg(
                                     &mut conn_state.stream,
@@ -571,8 +586,8 @@ impl super::MainState {
                                 .await?;
                             }
                         }
-                        'r' => {
-                            // El modo +r solo puede ser manipulado por el IRCd
+                        'r' | 'P' => {
+                            // Los modos +r y +P solo pueden ser manipulados por el IRCd
                             if !if_oper {
                                 self.feed_msg(
                                     &mut conn_state.stream,
@@ -594,7 +609,7 @@ impl super::MainState {
                             if let Some(bmask) = margs_it.next() {
                                 if if_half_op {
                                     let mut ban = chanobj.modes.ban.take().unwrap_or_default();
-                                    let norm_bmask = normalize_sourcemask(bmask);
+                                    let norm_bmask = normalize_banmask(bmask);
                                     
                                     // Extraer el tiempo de expiración si existe
                                     let (_, duration) = if let Some(idx) = bmask.find('|') {
@@ -605,7 +620,17 @@ impl super::MainState {
                                         (bmask.to_string(), None)
                                     };
 
-                                    if mode_set {
+                                    if mode_set && ban.len() >= self.config.limits.max_list && !ban.contains(&norm_bmask) {
+                                        self.feed_msg(
+                                            &mut conn_state.stream,
+                                            ErrBanListFull478 {
+                                                client,
+                                                channel: target,
+                                                mode: 'b',
+                                            },
+                                        )
+                                        .await?;
+                                    } else if mode_set {
                                         // put to applied modes
                                         set_modes_string.push('b');
                                         set_mode_args.push(norm_bmask.clone());
@@ -620,7 +645,7 @@ impl super::MainState {
                                         let expires_at = duration.map(|d| current_time + d);
                                         
                                         chanobj.ban_info.insert(
-                                            crate::state::structs::to_unicase(&norm_bmask.clone()),
+                                            crate::state::structs::to_irccase(&norm_bmask.clone()),
                                             BanInfo {
                                                 who: conn_state
                                                     .user_state
@@ -633,34 +658,9 @@ impl super::MainState {
                                             },
                                         );
 
+                                        saved_changes.lists.push(('b', true, norm_bmask.clone(), expires_at));
                                         if let Some(duration) = duration {
-                                            let channel_name = target.to_string();
-                                            let ban_mask_for_timeout = norm_bmask.clone(); // Usa la variable String, se moverá a la closure
-                                            let state_clone = self.state.clone();
-                                            let config_clone = self.config.clone();
-                    
-                                            tokio::spawn(async move {
-                                                tokio::time::sleep(Duration::from_secs(duration)).await;
-                    
-                                                // Remover el ban expirado
-                                                let mut state = state_clone.write().await;
-                                                if let Some(channel) = state.channels.get_mut(&crate::state::structs::to_unicase(&channel_name)) {
-                                                    if let Some(ban_set) = &mut channel.modes.ban {
-                                                        // Ahora ban_mask_for_timeout es un String, así que &ban_mask_for_timeout es &String
-                                                        ban_set.remove(&ban_mask_for_timeout);
-                                                        channel.ban_info.remove(&crate::state::structs::to_unicase(&ban_mask_for_timeout));
-                    
-                                                        // Notificar a los usuarios del canal
-                                                        let nicks: Vec<String> = channel.users.keys().cloned().map(|nick| nick.to_string()).collect();
-                                                        for nick in nicks {
-                                                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nick)) {
-                                                                let mensaje = format!("MODE {channel_name} -b {ban_mask_for_timeout}"); // Aquí también usa la String
-                                                                let _ = user.send_msg_display(&config_clone.name, &mensaje);
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            });
+                                            self.schedule_list_expiry(target, 'b', &norm_bmask, Duration::from_secs(duration));
                                         }
                                     } else {
                                         // put to applied modes
@@ -668,7 +668,8 @@ impl super::MainState {
                                         unset_mode_args.push(norm_bmask.clone());
 
                                         ban.remove(&norm_bmask);
-                                        chanobj.ban_info.remove(&crate::state::structs::to_unicase(&norm_bmask));
+                                        chanobj.ban_info.remove(&crate::state::structs::to_irccase(&norm_bmask));
+                                        saved_changes.lists.push(('b', false, norm_bmask.clone(), None));
                                     }
                                     chanobj.modes.ban = Some(ban);
                                 } else {
@@ -685,7 +686,7 @@ impl super::MainState {
                                 // print
                                 if let Some(ban) = &chanobj.modes.ban {
                                     for b in ban {
-                                        if let Some(ban_info) = chanobj.ban_info.get(&crate::state::structs::to_unicase(&b.clone())) {
+                                        if let Some(ban_info) = chanobj.ban_info.get(&crate::state::structs::to_irccase(&b.clone())) {
                                             self.feed_msg(
                                                 &mut conn_state.stream,
                                                 RplBanList367 {
@@ -745,7 +746,7 @@ impl super::MainState {
                                         gban.insert(norm_bmask.clone());
                                         // add to ban_info
                                         chanobj.ban_info.insert(
-                                            crate::state::structs::to_unicase(&norm_bmask.clone()),
+                                            crate::state::structs::to_irccase(&norm_bmask.clone()),
                                             BanInfo {
                                                 who: conn_state
                                                     .user_state
@@ -772,15 +773,15 @@ impl super::MainState {
                     
                                                 // Remover el ban global expirado
                                                 let mut state = state_clone.write().await;
-                                                if let Some(channel) = state.channels.get_mut(&crate::state::structs::to_unicase(&channel_name)) {
+                                                if let Some(channel) = state.channels.get_mut(&crate::state::structs::to_irccase(&channel_name)) {
                                                     if let Some(ban_set) = &mut channel.modes.global_ban {
                                                         ban_set.remove(&ban_mask_for_timeout);
-                                                        channel.ban_info.remove(&crate::state::structs::to_unicase(&ban_mask_for_timeout));
+                                                        channel.ban_info.remove(&crate::state::structs::to_irccase(&ban_mask_for_timeout));
                     
                                                         // Notificar a los usuarios del canal
                                                         let nicks: Vec<String> = channel.users.keys().cloned().map(|nick| nick.to_string()).collect();
                                                         for nick in nicks {
-                                                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_unicase(&nick)) {
+                                                            if let Some(user) = state.users.get_mut(&crate::state::structs::to_irccase(&nick)) {
                                                                 let mensaje = format!("MODE {channel_name} -B {ban_mask_for_timeout}");
                                                                 let _ = user.send_msg_display(&config_clone.name, &mensaje);
                                                             }
@@ -802,7 +803,7 @@ impl super::MainState {
                                         unset_mode_args.push(norm_bmask.clone());
 
                                         gban.remove(&norm_bmask);
-                                        chanobj.ban_info.remove(&crate::state::structs::to_unicase(&norm_bmask));
+                                        chanobj.ban_info.remove(&crate::state::structs::to_irccase(&norm_bmask));
                                         #[cfg(feature = "amqp")]
                                         if !target.starts_with('&') {
                                             let serv_comm = self.serv_comm.read().await;
@@ -826,7 +827,7 @@ impl super::MainState {
                                 // print
                                 if let Some(ban) = &chanobj.modes.global_ban {
                                     for b in ban {
-                                        if let Some(ban_info) = chanobj.ban_info.get(&crate::state::structs::to_unicase(&b.clone())) {
+                                        if let Some(ban_info) = chanobj.ban_info.get(&crate::state::structs::to_irccase(&b.clone())) {
                                             self.feed_msg(
                                                 &mut conn_state.stream,
                                                 RplBanList367 {
@@ -869,19 +870,31 @@ impl super::MainState {
                                 if if_half_op {
                                     let mut exp =
                                         chanobj.modes.exception.take().unwrap_or_default();
-                                    let norm_emask = normalize_sourcemask(emask);
-                                    if mode_set {
+                                    let norm_emask = normalize_banmask(emask);
+                                    if mode_set && exp.len() >= self.config.limits.max_list && !exp.contains(&norm_emask) {
+                                        self.feed_msg(
+                                            &mut conn_state.stream,
+                                            ErrBanListFull478 {
+                                                client,
+                                                channel: target,
+                                                mode: 'e',
+                                            },
+                                        )
+                                        .await?;
+                                    } else if mode_set {
                                         // put to applied modes
                                         set_modes_string.push('e');
                                         set_mode_args.push(norm_emask.clone());
 
                                         exp.insert(norm_emask.clone());
+                                        saved_changes.lists.push(('e', true, norm_emask.clone(), None));
                                     } else {
                                         // put to applied modes
                                         unset_modes_string.push('e');
                                         unset_mode_args.push(norm_emask.clone());
 
                                         exp.remove(&norm_emask);
+                                        saved_changes.lists.push(('e', false, norm_emask.clone(), None));
                                     }
                                     chanobj.modes.exception = Some(exp);
                                 } else {
@@ -924,18 +937,30 @@ impl super::MainState {
                                 if if_half_op {
                                     let mut exp =
                                         chanobj.modes.invite_exception.take().unwrap_or_default();
-                                    let norm_imask = normalize_sourcemask(imask);
-                                    if mode_set {
+                                    let norm_imask = normalize_banmask(imask);
+                                    if mode_set && exp.len() >= self.config.limits.max_list && !exp.contains(&norm_imask) {
+                                        self.feed_msg(
+                                            &mut conn_state.stream,
+                                            ErrBanListFull478 {
+                                                client,
+                                                channel: target,
+                                                mode: 'I',
+                                            },
+                                        )
+                                        .await?;
+                                    } else if mode_set {
                                         // put to applied modes
                                         set_modes_string.push('I');
                                         set_mode_args.push(norm_imask.clone());
                                         exp.insert(norm_imask.clone());
+                                        saved_changes.lists.push(('I', true, norm_imask.clone(), None));
                                     } else {
                                         // put to applied modes
                                         unset_modes_string.push('I');
                                         unset_mode_args.push(norm_imask.clone());
 
                                         exp.remove(&norm_imask);
+                                        saved_changes.lists.push(('I', false, norm_imask.clone(), None));
                                     }
                                     chanobj.modes.invite_exception = Some(exp);
                                 } else {
@@ -973,12 +998,118 @@ impl super::MainState {
                                 .await?;
                             }
                         }
+                        'Z' => {
+                            if let Some(zmask) = margs_it.next() {
+                                if if_half_op {
+                                    let mut quiet = chanobj.modes.quiet.take().unwrap_or_default();
+                                    let norm_zmask = normalize_banmask(zmask);
+                                    // tiempo de expiración opcional, como en los bans: máscara|segundos
+                                    let duration = norm_zmask
+                                        .split_once('|')
+                                        .and_then(|(_, secs)| secs.parse::<u64>().ok());
+
+                                    if mode_set && quiet.len() >= self.config.limits.max_list && !quiet.contains(&norm_zmask) {
+                                        self.feed_msg(
+                                            &mut conn_state.stream,
+                                            ErrBanListFull478 {
+                                                client,
+                                                channel: target,
+                                                mode: 'Z',
+                                            },
+                                        )
+                                        .await?;
+                                    } else if mode_set {
+                                        // put to applied modes
+                                        set_modes_string.push('Z');
+                                        set_mode_args.push(norm_zmask.clone());
+
+                                        quiet.insert(norm_zmask.clone());
+                                        let current_time = SystemTime::now()
+                                            .duration_since(UNIX_EPOCH)
+                                            .unwrap()
+                                            .as_secs();
+                                        let expires_at = duration.map(|d| current_time + d);
+                                        chanobj.quiet_info.insert(
+                                            crate::state::structs::to_irccase(&norm_zmask),
+                                            BanInfo {
+                                                who: conn_state
+                                                    .user_state
+                                                    .nick
+                                                    .as_ref()
+                                                    .unwrap()
+                                                    .to_string(),
+                                                set_time: current_time,
+                                                expires_at,
+                                            },
+                                        );
+
+                                        saved_changes.lists.push(('Z', true, norm_zmask.clone(), expires_at));
+                                        if let Some(duration) = duration {
+                                            self.schedule_list_expiry(target, 'Z', &norm_zmask, Duration::from_secs(duration));
+                                        }
+                                    } else {
+                                        // put to applied modes
+                                        unset_modes_string.push('Z');
+                                        unset_mode_args.push(norm_zmask.clone());
+
+                                        quiet.remove(&norm_zmask);
+                                        chanobj.quiet_info.remove(&crate::state::structs::to_irccase(&norm_zmask));
+                                        saved_changes.lists.push(('Z', false, norm_zmask.clone(), None));
+                                    }
+                                    chanobj.modes.quiet = Some(quiet);
+                                } else {
+                                    self.feed_msg(
+                                        &mut conn_state.stream,
+                                        ErrChanOpPrivsNeeded482 {
+                                            client,
+                                            channel: target,
+                                        },
+                                    )
+                                    .await?;
+                                }
+                            } else {
+                                // print
+                                if let Some(quiet) = &chanobj.modes.quiet {
+                                    for z in quiet {
+                                        let info = chanobj.quiet_info.get(&crate::state::structs::to_irccase(z));
+                                        self.feed_msg(
+                                            &mut conn_state.stream,
+                                            RplQuietList728 {
+                                                client,
+                                                channel: target,
+                                                mask: z,
+                                                who: info.map_or("", |i| i.who.as_str()),
+                                                set_ts: info.map_or(0, |i| i.set_time),
+                                            },
+                                        )
+                                        .await?;
+                                    }
+                                }
+                                self.feed_msg(
+                                    &mut conn_state.stream,
+                                    RplEndOfQuietList729 {
+                                        client,
+                                        channel: target,
+                                    },
+                                )
+                                .await?;
+                            }
+                        }
                         'o' | 'v' | 'h' | 'q' | 'a' => {
                             let arg = margs_it.next().unwrap();
-                            if chanobj.users.contains_key(&crate::state::structs::to_unicase(arg)) {
+                            if chanobj.users.contains_key(&crate::state::structs::to_irccase(arg)) {
                                 match mchar {
                                     'o' => {
-                                        if if_op || if_oper {
+                                        // con SECUREOPS solo los usuarios con flag +o pueden ser operadores
+                                        let secureops_denied = mode_set && self.secureops_denies(target, arg).await;
+                                        if secureops_denied && (if_op || if_oper) {
+                                            self.feed_msg_source(
+                                                &mut conn_state.stream,
+                                                "ChanServ",
+                                                format!("NOTICE {client} :{arg} is not allowed to be an operator in {target} (SECUREOPS)."),
+                                            )
+                                            .await?;
+                                        } else if if_op || if_oper {
                                             if mode_set {
                                                 set_modes_string.push('o');
                                                 set_mode_args.push(arg.to_string());
@@ -1058,6 +1189,7 @@ impl super::MainState {
                         }
                         'l' => {
                             if if_half_op || if_oper {
+                                key_limit_changed = true;
                                 chanobj.modes.client_limit = if mode_set {
                                     let arg = margs_it.next().unwrap();
                                     set_modes_string.push('l');
@@ -1070,8 +1202,27 @@ impl super::MainState {
                                 };
                             }
                         }
+                        'f' | 'j' | 'L' => {
+                            if if_half_op || if_oper {
+                                let param = if mode_set {
+                                    let arg = margs_it.next().unwrap();
+                                    set_modes_string.push(mchar);
+                                    set_mode_args.push(arg.to_string());
+                                    Some(arg.to_string())
+                                } else {
+                                    unset_modes_string.push(mchar);
+                                    None
+                                };
+                                match mchar {
+                                    'f' => chanobj.modes.flood = param,
+                                    'j' => chanobj.modes.join_throttle = param,
+                                    _ => chanobj.modes.redirect = param,
+                                }
+                            }
+                        }
                         'k' => {
                             if if_half_op || if_oper {
+                                key_limit_changed = true;
                                 chanobj.modes.key = if mode_set {
                                     let arg = margs_it.next().unwrap();
                                     set_modes_string.push('k');
@@ -1133,6 +1284,18 @@ impl super::MainState {
                                 }
                             }
                         }
+                        'c' | 'S' | 'C' | 'T' | 'R' | 'M' | 'z' | 'P' | 'K' => {
+                            if if_oper || (if_half_op && mchar != 'P') {
+                                if let Some(flag) = chanobj.modes.flag_mut(mchar) {
+                                    *flag = mode_set;
+                                }
+                                if mode_set {
+                                    set_modes_string.push(mchar);
+                                } else {
+                                    unset_modes_string.push(mchar);
+                                }
+                            }
+                        }
                         'r' => {
                             // El modo +r solo puede ser manipulado por IRCops
                             if if_oper {
@@ -1148,43 +1311,40 @@ impl super::MainState {
                     }
                 }
             }
+            if key_limit_changed {
+                saved_changes.key_limit = Some((chanobj.modes.key.clone(), chanobj.modes.client_limit));
+            }
 
             // send applied modes to user
             if !set_modes_string.is_empty()
                 || !unset_modes_string.is_empty()
             {
+                // primero todos los modos y después sus argumentos: "+oh-k ariel danny xxx"
                 let mut mode_string = String::new();
                 if !set_modes_string.is_empty() {
                     mode_string.push('+');
                     mode_string += &set_modes_string;
-                    if !set_mode_args.is_empty() {
-                        mode_string.push(' ');
-                        mode_string += &set_mode_args.join(" ");
-                    }
                 }
                 if !unset_modes_string.is_empty() {
-                    if !mode_string.is_empty() {
-                        mode_string.push(' ');
-                    }
                     mode_string.push('-');
                     mode_string += &unset_modes_string;
-                    if !unset_mode_args.is_empty() {
-                        mode_string.push(' ');
-                        mode_string += &unset_mode_args.join(" ");
-                    }
+                }
+                for arg in set_mode_args.iter().chain(unset_mode_args.iter()) {
+                    mode_string.push(' ');
+                    mode_string += arg;
                 }
                 let mode_string = format!("MODE {target} {mode_string}");
 
                 for unick in chanobj.users.keys() {
                     // to all users of channel
                     users
-                        .get(&crate::state::structs::to_unicase(unick))
+                        .get(&crate::state::structs::to_irccase(unick))
                         .unwrap()
                         .send_msg_display(&conn_state.user_state.source, mode_string.as_str())?;
                 }
             }
         } // if modes.len() == 0
-        Ok(())
+        Ok(saved_changes)
     }
 
     async fn process_mode_user<'a>(
@@ -1195,7 +1355,7 @@ impl super::MainState {
         modes: Vec<(&'a str, Vec<&'a str>)>,
     ) -> Result<(), Box<dyn StdError + Send + Sync>> {
         let client = conn_state.user_state.client_name();
-        let user = state.users.get_mut(&crate::state::structs::to_unicase(target)).unwrap();
+        let user = state.users.get_mut(&crate::state::structs::to_irccase(target)).unwrap();
         let user_nick = target;
         if modes.is_empty() {
             self.feed_msg(
@@ -1316,7 +1476,7 @@ impl super::MainState {
                             if mode_set {
                                 if !user.modes.local_oper {
                                     if self.oper_config_idxs.contains_key(user_nick) {
-                                        user.modes.oper = true;
+                                        user.modes.local_oper = true;
                                         if !user.modes.oper {
                                             state.operators_count += 1;
                                             // put to applied modes
@@ -1330,8 +1490,8 @@ impl super::MainState {
                                         .await?;
                                     }
                                 }
-                            } else if user.modes.oper {
-                                user.modes.oper = false;
+                            } else if user.modes.local_oper {
+                                user.modes.local_oper = false;
                                 if !user.modes.oper {
                                     state.operators_count -= 1;
                                     // put to applied modes
@@ -1339,18 +1499,33 @@ impl super::MainState {
                                 }
                             }
                         }
+                        'g' | 'R' => {
+                            let flag = if mchar == 'g' {
+                                &mut user.modes.caller_id
+                            } else {
+                                &mut user.modes.registered_only
+                            };
+                            if *flag != mode_set {
+                                *flag = mode_set;
+                                // put to applied modes
+                                if mode_set {
+                                    set_modes_string.push(mchar);
+                                } else {
+                                    unset_modes_string.push(mchar);
+                                }
+                            }
+                        }
                         'x' => {
                             if mode_set {
                                 if !user.modes.cloacked {
                                     set_modes_string.push('x');
                                     user.modes.cloacked = true;
                                     user.cloack = user.get_display_hostname(&self.config.cloack);
-                                    #[cfg(any(feature = "sqlite", feature = "mysql"))]
                                     {
                                         if let Some(db_arc) = &self.databases.nick_db {
                                             let db = db_arc.read().await;
                                             if let Ok(Some(info)) = db.get_nick_info(user_nick).await {
-                                                if let Some(vhost) = info.4 {
+                                                if let Some(vhost) = info.vhost {
                                                     user.cloack = vhost.clone();
                                                 } else {
                                                     user.cloack = user.get_display_hostname(&self.config.cloack);
@@ -1406,8 +1581,8 @@ impl super::MainState {
 
         if validate_channel(target).is_ok() {
             // channel
-            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_unicase(target)) {
-                let (chum, error) = if let Some(chum) = chanobj.users.get(&crate::state::structs::to_unicase(user_nick)) {
+            if let Some(chanobj) = state.channels.get_mut(&crate::state::structs::to_irccase(target)) {
+                let (chum, error) = if let Some(chum) = chanobj.users.get(&crate::state::structs::to_irccase(user_nick)) {
                     (*chum, false)
                 } else {
                     self.feed_msg(
@@ -1421,7 +1596,7 @@ impl super::MainState {
                     (ChannelUserModes::default(), true)
                 };
                 if !error {
-                    self.process_mode_channel(
+                    let saved_changes = self.process_mode_channel(
                         conn_state,
                         &state.users,
                         chanobj,
@@ -1430,6 +1605,9 @@ impl super::MainState {
                         &chum,
                     )
                     .await?;
+                    drop(statem);
+                    let set_by = conn_state.user_state.nick.clone().unwrap_or_default();
+                    self.save_channel_modes(target, &set_by, saved_changes).await;
                 }
             } else {
                 self.feed_msg(
@@ -1443,6 +1621,17 @@ impl super::MainState {
             }
         } else {
             // user mode
+            if !state.users.contains_key(&crate::state::structs::to_irccase(target)) {
+                self.feed_msg(
+                    &mut conn_state.stream,
+                    ErrNoSuchNick401 {
+                        client,
+                        nick: target,
+                    },
+                )
+                .await?;
+                return Ok(());
+            }
             if target != user_nick {
                 self.feed_msg(
                     &mut conn_state.stream,
@@ -1474,10 +1663,17 @@ mod test {
                 ":irc.irc 375 tommy :- irc.irc Message of the day - ".to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
-            assert_eq!(
-                ":irc.irc 372 tommy :Hello, world!".to_string(),
-                line_stream.next().await.unwrap().unwrap()
-            );
+            // el MOTD sale de motd.txt si existe, si no de la configuración
+            let motd_lines = match std::fs::read_to_string("motd.txt") {
+                Ok(content) => content.lines().map(|l| l.to_string()).collect::<Vec<_>>(),
+                Err(_) => vec!["Hello, world!".to_string()],
+            };
+            for motd_line in motd_lines {
+                assert_eq!(
+                    format!(":irc.irc 372 tommy :{}", motd_line),
+                    line_stream.next().await.unwrap().unwrap()
+                );
+            }
             assert_eq!(
                 ":irc.irc 376 tommy :End of /MOTD command.".to_string(),
                 line_stream.next().await.unwrap().unwrap()
@@ -1505,25 +1701,28 @@ mod test {
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
-                ":irc.irc 005 tommy AWAYLEN=1000 CASEMAPPING=ascii \
-                    CHANMODES=bBeI,k,l,ahimnopqrstvO CHANNELLEN=1000 CHANTYPES=&# EXCEPTS=e FNC \
-                    HOSTLEN=1000 INVEX=I KEYLEN=1000 :are supported by this server"
+                ":irc.irc 005 tommy ACCEPT=30 AWAYLEN=1000 CALLERID=g CASEMAPPING=ascii \
+                    CHANMODES=bBeIZ,k,fjlL,achimnopqrstvzCKMOPRST CHANNELLEN=1000 CHANTYPES=&# ELIST=CMNTU \
+                    EXCEPTS=e EXTBAN=~,acjmrz :are supported by this server"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
-                ":irc.irc 005 tommy KICKLEN=1000 LINELEN=2000 MAXLIST=beI:1000 \
-                    MAXNICKLEN=200 MAXPARA=500 MAXTARGETS=500 MODES=500 NETWORK=IRCnetwork \
-                    NICKLEN=200 PREFIX=(qaohv)~&@%+ :are supported by this server"
+                ":irc.irc 005 tommy FNC HOSTLEN=1000 INVEX=I KEYLEN=1000 KICKLEN=1000 KNOCK LINELEN=2000 \
+                    MAXLIST=beIZ:1000 MAXNICKLEN=200 MAXPARA=500 :are supported by this server"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
-                ":irc.irc 005 tommy SAFELIST STATUSMSG=~&@%+ TOPICLEN=1000 USERLEN=200 \
-                    USERMODES=Oiorw :are supported by this server"
+                ":irc.irc 005 tommy MAXTARGETS=500 MODES=500 NETWORK=IRCnetwork NICKLEN=200 PREFIX=(qaohv)~&@%+ \
+                    SAFELIST SILENCE=15 STATUSMSG=~&@%+ TOPICLEN=1000 USERLEN=200 :are supported by this server"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
+            assert_eq!(
+                ":irc.irc 005 tommy USERMODES=OgiorwRWz :are supported by this server".to_string(),
+                line_stream.next().await.unwrap().unwrap()
+            );
         }
 
         quit_test_server(main_state, handle).await;
@@ -1624,7 +1823,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let mut state = main_state.state.write().await;
-                state.users.get_mut("leon").unwrap().modes.invisible = true;
+                state.users.get_mut(&to_irccase("leon")).unwrap().modes.invisible = true;
                 state.invisible_users_count += 1;
             }
 
@@ -1759,7 +1958,7 @@ mod test {
                 login_to_test_and_skip(port, "timmy", "tim", "Timmy Greater").await;
             line_stream.send("LINKS".to_string()).await.unwrap();
             assert_eq!(
-                ":irc.irc 364 timmy irc.irc irc.irc :0 This is IRC server".to_string(),
+                ":irc.irc 364 timmy irc.irc irc.irc :0 ZeusIRCd Server".to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             assert_eq!(
@@ -1863,7 +2062,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert!(state.users.get("sonny").unwrap().modes.wallops);
+                assert!(state.users.get(&to_irccase("sonny")).unwrap().modes.wallops);
                 assert!(state.wallops_users.contains("sonny"));
             }
 
@@ -1876,7 +2075,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert!(!state.users.get("sonny").unwrap().modes.wallops);
+                assert!(!state.users.get(&to_irccase("sonny")).unwrap().modes.wallops);
                 assert!(!state.wallops_users.contains("sonny"));
             }
 
@@ -1952,7 +2151,7 @@ mod test {
                         .read()
                         .await
                         .users
-                        .get("roland")
+                        .get(&to_irccase("roland"))
                         .unwrap()
                         .modes
                         .registered
@@ -1976,7 +2175,7 @@ mod test {
                         .read()
                         .await
                         .users
-                        .get("roland")
+                        .get(&to_irccase("roland"))
                         .unwrap()
                         .modes
                         .registered
@@ -1999,14 +2198,12 @@ mod test {
         {
             let mut line_stream =
                 login_to_test_and_skip(port, "roland", "roland", "Roland TechnoMusic").await;
-            line_stream
-                .send("OPER expert NoWay".to_string())
-                .await
-                .unwrap();
-            assert_eq!(
-                ":irc.irc 381 roland :You are now an IRC operator".to_string(),
-                line_stream.next().await.unwrap().unwrap()
-            );
+            // OPER sólo da +O, el +o global se pone a mano
+            {
+                let mut state = main_state.state.write().await;
+                state.users.get_mut(&to_irccase("roland")).unwrap().modes.oper = true;
+                state.operators_count += 1;
+            }
             line_stream.send("MODE roland".to_string()).await.unwrap();
             assert_eq!(
                 ":irc.irc 221 roland +o".to_string(),
@@ -2024,7 +2221,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert!(!state.users.get("roland").unwrap().modes.wallops);
+                assert!(!state.users.get(&to_irccase("roland")).unwrap().modes.wallops);
                 assert_eq!(0, state.operators_count);
             }
 
@@ -2063,7 +2260,7 @@ mod test {
             );
             line_stream.send("MODE roland".to_string()).await.unwrap();
             assert_eq!(
-                ":irc.irc 221 roland +o".to_string(),
+                ":irc.irc 221 roland +O".to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             line_stream
@@ -2078,7 +2275,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                assert!(!state.users.get("roland").unwrap().modes.wallops);
+                assert!(!state.users.get(&to_irccase("roland")).unwrap().modes.wallops);
                 assert_eq!(0, state.operators_count);
             }
 
@@ -2125,7 +2322,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let roland = state.users.get("roland").unwrap();
+                let roland = state.users.get(&to_irccase("roland")).unwrap();
                 assert!(roland.modes.wallops);
                 assert!(roland.modes.invisible);
                 assert!(!roland.modes.registered);
@@ -2145,7 +2342,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let roland = state.users.get("roland").unwrap();
+                let roland = state.users.get(&to_irccase("roland")).unwrap();
                 assert!(!roland.modes.wallops);
                 assert!(!roland.modes.invisible);
                 assert!(roland.modes.registered);
@@ -2159,7 +2356,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_mode_channel() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -2168,7 +2365,7 @@ mod test {
                 .send("JOIN #mychannel".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -2206,7 +2403,7 @@ mod test {
                 .unwrap();
             for line_stream in [&mut line_stream, &mut ariel_stream, &mut danny_stream] {
                 assert_eq!(
-                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +mts +k xxxz +l 10".to_string(),
+                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +mtskl xxxz 10".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             }
@@ -2223,7 +2420,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(channel.modes.moderated);
                 assert!(channel.modes.secret);
                 assert!(channel.modes.protected_topic);
@@ -2237,9 +2434,7 @@ mod test {
                 .unwrap();
             for line_stream in [&mut line_stream, &mut ariel_stream, &mut danny_stream] {
                 assert_eq!(
-                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel -mtskl \
-                        +o ariel +h danny"
-                        .to_string(),
+                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +oh-mtskl ariel danny".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             }
@@ -2247,7 +2442,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(!channel.modes.moderated);
                 assert!(!channel.modes.secret);
                 assert!(!channel.modes.protected_topic);
@@ -2268,7 +2463,7 @@ mod test {
                 .unwrap();
             for line_stream in [&mut line_stream, &mut ariel_stream, &mut danny_stream] {
                 assert_eq!(
-                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel -o ariel -h danny".to_string(),
+                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel -oh ariel danny".to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
             }
@@ -2276,7 +2471,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(!channel.modes.operators.as_ref().unwrap().contains("ariel"));
                 assert!(!channel
                     .modes
@@ -2301,7 +2496,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_mode_channel_modes() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -2310,7 +2505,7 @@ mod test {
                 .send("JOIN #mychannel".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -2325,7 +2520,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(channel.modes.no_external_messages);
                 assert!(channel.modes.invite_only);
             }
@@ -2341,7 +2536,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(!channel.modes.no_external_messages);
                 assert!(!channel.modes.invite_only);
             }
@@ -2352,7 +2547,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_mode_channel_lists() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -2361,7 +2556,7 @@ mod test {
                 .send("JOIN #mychannel".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -2370,15 +2565,15 @@ mod test {
                 .await
                 .unwrap();
             assert_eq!(
-                ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +b nick*!*@* +b \
-                    *digger.com!*@* +e nicki*!*@* +I guru*!*@*"
+                ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +bbeI nick*!*@* \
+                    *digger.com!*@* nicki*!*@* guru*!*@*"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             time::sleep(Duration::from_millis(100)).await;
             let set_time = {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert_eq!(
                     Some(HashSet::from([
                         "nick*!*@*".to_string(),
@@ -2386,11 +2581,11 @@ mod test {
                     ])),
                     channel.modes.ban
                 );
-                let set_time = channel.ban_info.get(&crate::state::structs::to_unicase("nick*!*@*")).unwrap().set_time;
+                let set_time = channel.ban_info.get(&crate::state::structs::to_irccase("nick*!*@*")).unwrap().set_time;
                 assert_eq!(
                     HashMap::from([
                         (
-                            "nick*!*@*".to_string(),
+                            IrcCase::new("nick*!*@*".to_string()),
                             BanInfo {
                                 who: "sonny".to_string(),
                                 set_time,
@@ -2398,7 +2593,7 @@ mod test {
                             }
                         ),
                         (
-                            "*digger.com!*@*".to_string(),
+                            IrcCase::new("*digger.com!*@*".to_string()),
                             BanInfo {
                                 who: "sonny".to_string(),
                                 set_time,
@@ -2512,8 +2707,7 @@ mod test {
                 .await
                 .unwrap();
             assert_eq!(
-                ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +b nick*!*@* \
-                    +e nicki*!*@* +I guru*!*@*"
+                ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +beI nick*!*@* nicki*!*@* guru*!*@*"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
@@ -2521,7 +2715,7 @@ mod test {
             time::sleep(Duration::from_millis(100)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert_eq!(
                     Some(HashSet::from([
                         "nick*!*@*".to_string(),
@@ -2544,22 +2738,22 @@ mod test {
                 .await
                 .unwrap();
             assert_eq!(
-                ":sonny!~sonnyx@127.0.0.1 MODE #mychannel -b nick*!*@* \
-                    -b *dxx!*@* -e nicki*!*@* -I xxguru*!*@*"
+                ":sonny!~sonnyx@127.0.0.1 MODE #mychannel -bbeI nick*!*@* \
+                    *dxx!*@* nicki*!*@* xxguru*!*@*"
                     .to_string(),
                 line_stream.next().await.unwrap().unwrap()
             );
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert_eq!(
                     Some(HashSet::from(["*digger.com!*@*".to_string()])),
                     channel.modes.ban
                 );
                 assert_eq!(
                     HashMap::from([(
-                        "*digger.com!*@*".to_string(),
+                        IrcCase::new("*digger.com!*@*".to_string()),
                         BanInfo {
                             who: "sonny".to_string(),
                             set_time,
@@ -2581,7 +2775,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_mode_channel_half_op() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -2590,7 +2784,7 @@ mod test {
                 .send("JOIN #mychannel".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -2631,7 +2825,7 @@ mod test {
                 let state = main_state.state.read().await;
                 assert_eq!(
                     Some(20),
-                    state.channels.get("#mychannel").unwrap().modes.client_limit
+                    state.channels.get(&to_irccase("#mychannel")).unwrap().modes.client_limit
                 );
             }
         }
@@ -2641,7 +2835,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_mode_channel_user_modes() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -2650,7 +2844,7 @@ mod test {
                 .send("JOIN #mychannel".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -2712,8 +2906,8 @@ mod test {
                 &mut jonathan_stream,
             ] {
                 assert_eq!(
-                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +q ariel +a danny \
-                        +o cimon +h harry +v jonathan"
+                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +qaohv ariel danny \
+                        cimon harry jonathan"
                         .to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
@@ -2722,7 +2916,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(channel.modes.founders.as_ref().unwrap().contains("ariel"));
                 assert!(channel.modes.protecteds.as_ref().unwrap().contains("danny"));
                 assert!(channel.modes.operators.as_ref().unwrap().contains("cimon"));
@@ -2752,8 +2946,8 @@ mod test {
                 &mut jonathan_stream,
             ] {
                 assert_eq!(
-                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel -q ariel -a danny \
-                        -o cimon -h harry -v jonathan"
+                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel -qaohv ariel danny \
+                        cimon harry jonathan"
                         .to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
@@ -2762,7 +2956,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(!channel.modes.founders.as_ref().unwrap().contains("ariel"));
                 assert!(!channel.modes.protecteds.as_ref().unwrap().contains("danny"));
                 assert!(!channel.modes.operators.as_ref().unwrap().contains("cimon"));
@@ -2781,7 +2975,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_mode_channel_user_modes_fail_1() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -2790,7 +2984,7 @@ mod test {
                 .send("JOIN #mychannel".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -2838,7 +3032,7 @@ mod test {
 
     #[tokio::test]
     async fn test_command_mode_channel_user_modes_privs() {
-        let (main_state, handle, port) = run_test_server(MainConfig::default()).await;
+        let (main_state, handle, port) = run_test_server(memory_database_config()).await;
 
         {
             let mut line_stream =
@@ -2847,7 +3041,7 @@ mod test {
                 .send("JOIN #mychannel".to_string())
                 .await
                 .unwrap();
-            for _ in 0..3 {
+            for _ in 0..4 {
                 line_stream.next().await.unwrap().unwrap();
             }
 
@@ -2909,8 +3103,8 @@ mod test {
                 &mut jonathan_stream,
             ] {
                 assert_eq!(
-                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +q ariel +a danny \
-                        +o cimon +h harry +v jonathan"
+                    ":sonny!~sonnyx@127.0.0.1 MODE #mychannel +qaohv ariel danny \
+                        cimon harry jonathan"
                         .to_string(),
                     line_stream.next().await.unwrap().unwrap()
                 );
@@ -2970,7 +3164,7 @@ mod test {
             time::sleep(Duration::from_millis(50)).await;
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(!channel
                     .modes
                     .half_operators
@@ -3001,7 +3195,7 @@ mod test {
             );
             {
                 let state = main_state.state.read().await;
-                let channel = state.channels.get("#mychannel").unwrap();
+                let channel = state.channels.get(&to_irccase("#mychannel")).unwrap();
                 assert!(channel
                     .modes
                     .operators
@@ -20,6 +20,7 @@
 use flagset::{flags, FlagSet};
 use futures::{future::Fuse, future::FutureExt};
 use std::collections::{HashMap, HashSet};
+use std::collections::VecDeque;
 use std::fmt;
 use std::net::IpAddr;
 use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
@@ -32,23 +33,82 @@ use tokio::time;
 use tracing::*;
 use sha2::{Sha256, Digest};
 use tokio::sync::RwLock;
-use unicase::UniCase;
+use std::cmp::Ordering as CmpOrdering;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
 
 use crate::command::*;
-
-// Helper function to convert string to UniCase for case-insensitive lookups
-pub(crate) fn to_unicase(s: &str) -> UniCase<String> {
-    UniCase::new(s.to_string())
-}
 use crate::config::*;
 use crate::utils::*;
 
+// Nick o nombre de canal que se compara según el casemapping configurado.
+// Es la clave de todos los mapas de VolatileState. Guarda la forma canónica
+// para no tener que recalcularla en cada hash o comparación.
+#[derive(Clone, Debug)]
+pub(crate) struct IrcCase<S> {
+    name: S,
+    folded: String,
+}
+
+impl<S: AsRef<str>> IrcCase<S> {
+    pub(crate) fn new(name: S) -> IrcCase<S> {
+        let folded = casefold(name.as_ref());
+        IrcCase { name, folded }
+    }
+}
+
+impl<S> Deref for IrcCase<S> {
+    type Target = S;
+    fn deref(&self) -> &S {
+        &self.name
+    }
+}
+
+impl<S1: AsRef<str>, S2: AsRef<str>> PartialEq<IrcCase<S2>> for IrcCase<S1> {
+    fn eq(&self, other: &IrcCase<S2>) -> bool {
+        self.folded == other.folded
+    }
+}
+
+impl<S: AsRef<str>> Eq for IrcCase<S> {}
+
+impl<S: AsRef<str>> Hash for IrcCase<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.folded.hash(state);
+    }
+}
+
+impl<S: AsRef<str>> PartialOrd for IrcCase<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: AsRef<str>> Ord for IrcCase<S> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.folded.cmp(&other.folded)
+    }
+}
+
+impl<S: fmt::Display> fmt::Display for IrcCase<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+// clave para buscar un nick o canal en los mapas de VolatileState.
+pub(crate) fn to_irccase(s: &str) -> IrcCase<String> {
+    IrcCase::new(s.to_string())
+}
+
 #[derive(Debug)]
 pub(super) struct User {
     pub(super) hostname: String,
     pub(super) cloack: String,
     pub(super) sender: UnboundedSender<String>,
     pub(super) quit_sender: Option<oneshot::Sender<(String, String)>>,
+    // envía a la tarea de la conexión el nuevo host visible (vhost puesto por otro usuario)
+    pub(super) cloack_sender: Option<UnboundedSender<String>>,
     pub(super) name: String,
     pub(super) realname: String,
     pub(super) source: String, // IRC source for mask matching
@@ -59,12 +119,57 @@ pub(super) struct User {
     pub(super) last_activity: u64,
     pub(super) signon: u64,
     pub(super) identified: bool,
+    // usuario de servicio sin conexión (bots de BotServ, ChanServ)
+    pub(super) service: bool,
+    // nicks que pueden mandar privados con +g (ACCEPT) y a cuáles ya se les avisó
+    pub(super) accept: HashSet<IrcCase<String>>,
+    pub(super) callerid_notified: HashSet<IrcCase<String>>,
+    // máscaras nick!user@host cuyos privados e invitaciones se descartan (SILENCE)
+    pub(super) silence: Vec<String>,
     pub(super) history_entry: NickHistoryEntry,
     #[cfg(feature = "amqp")]
     pub(super) server: String,
 }
 
+// Por qué un privado o una invitación no llega a su destinatario
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PrivateBlock {
+    // el remitente está en la lista de SILENCE: se descarta sin avisar
+    Silenced,
+    // +R y el remitente no está identificado
+    NeedRegistered,
+    // +g y el remitente no está en la lista de ACCEPT
+    CallerId,
+}
+
 impl User {
+    // Comprueba SILENCE, +R y +g para un privado de otro usuario. Los IRCops sólo
+    // se pueden silenciar.
+    pub(super) fn private_block(&self, sender_nick: &str, sender: &User) -> Option<PrivateBlock> {
+        if self.silence.iter().any(|mask| match_wildcard(mask, &sender.source)) {
+            Some(PrivateBlock::Silenced)
+        } else if sender.modes.is_local_oper() {
+            None
+        } else if self.modes.registered_only && !sender.modes.registered {
+            Some(PrivateBlock::NeedRegistered)
+        } else if self.modes.caller_id && !self.accept.contains(&to_irccase(sender_nick)) {
+            Some(PrivateBlock::CallerId)
+        } else {
+            None
+        }
+    }
+
+    // Datos del usuario para comprobar las listas b/e/I/Z con extbans
+    pub(super) fn ban_target<'a>(&'a self, source: &'a str) -> BanTarget<'a> {
+        BanTarget {
+            source,
+            account: if self.modes.registered { source.split('!').next() } else { None },
+            realname: &self.realname,
+            channels: &self.channels,
+            secure: self.modes.secure,
+        }
+    }
+
     pub(super) fn new(
         config: &MainConfig,
         user_state: &ConnUserState,
@@ -82,6 +187,7 @@ impl User {
             cloack: user_state.hostname.clone(),
             sender,
             quit_sender: Some(quit_sender),
+            cloack_sender: None,
             name: user_state.name.as_ref().cloned().unwrap_or_default(),
             realname: user_state.realname.as_ref().cloned().unwrap_or_default(),
             source: user_state.source.clone(),
@@ -92,6 +198,10 @@ impl User {
             last_activity: now_ts,
             signon: now_ts,
             identified: false,
+            service: false,
+            accept: HashSet::new(),
+            callerid_notified: HashSet::new(),
+            silence: vec![],
             history_entry: NickHistoryEntry {
                 username: user_state.name.as_ref().cloned().unwrap_or_default(),
                 hostname: user_state.hostname.clone(),
@@ -112,6 +222,53 @@ impl User {
         user
     }
 
+    // Usuario sin conexión que representa a un bot de BotServ.
+    // Los mensajes que recibe se descartan en `sender`.
+    #[cfg_attr(not(feature = "amqp"), allow(unused_variables))]
+    pub(super) fn new_bot(
+        config: &MainConfig,
+        nick: &str,
+        name: &str,
+        host: &str,
+        realname: &str,
+        sender: UnboundedSender<String>,
+    ) -> User {
+        let now_ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        User {
+            hostname: host.to_string(),
+            cloack: host.to_string(),
+            sender,
+            quit_sender: None,
+            cloack_sender: None,
+            name: name.to_string(),
+            realname: realname.to_string(),
+            source: format!("{nick}!{name}@{host}"),
+            modes: UserModes::default(),
+            away: None,
+            channels: HashSet::new(),
+            invited_to: HashSet::new(),
+            last_activity: now_ts,
+            signon: now_ts,
+            identified: false,
+            service: true,
+            accept: HashSet::new(),
+            callerid_notified: HashSet::new(),
+            silence: vec![],
+            history_entry: NickHistoryEntry {
+                username: name.to_string(),
+                hostname: host.to_string(),
+                cloack: host.to_string(),
+                realname: realname.to_string(),
+                signon: now_ts,
+            },
+            #[cfg(feature = "amqp")]
+            server: config.name.clone(),
+        }
+    }
+
     // update nick - mainly source
     pub(super) fn update_nick(&mut self, user_state: &ConnUserState) {
         self.source = user_state.source.clone();
@@ -328,6 +485,7 @@ impl Clone for User {
             cloack: self.cloack.clone(),
             sender: self.sender.clone(), // Esto funciona si UnboundedSender implementa Clone
             quit_sender: None, // No se puede clonar oneshot::Sender
+            cloack_sender: self.cloack_sender.clone(),
             name: self.name.clone(),
             realname: self.realname.clone(),
             source: self.source.clone(),
@@ -338,6 +496,10 @@ impl Clone for User {
             last_activity: self.last_activity,
             signon: self.signon,
             identified: self.identified,
+            service: self.service,
+            accept: self.accept.clone(),
+            callerid_notified: self.callerid_notified.clone(),
+            silence: self.silence.clone(),
             history_entry: self.history_entry.clone(),
             #[cfg(feature = "amqp")]
             server: self.server.clone(),
@@ -510,6 +672,37 @@ pub(super) struct BanInfo {
     pub(super) expires_at: Option<u64>,
 }
 
+// Eventos recientes de un canal: (tipo, nick, segundo). Los tipos son los de FLOOD_TYPES
+// y 'k' para los KNOCK.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub(super) struct FloodTracker {
+    events: Vec<(char, IrcCase<String>, u64)>,
+}
+
+impl FloodTracker {
+    // Guarda un evento, olvidando los del mismo tipo que ya están fuera de la ventana
+    pub(super) fn record(&mut self, kind: char, nick: &str, window: u64, now: u64) {
+        self.events.retain(|(k, _, time)| *k != kind || time + window > now);
+        self.events.push((kind, to_irccase(nick), now));
+    }
+
+    // Eventos de un tipo dentro de la ventana, de todo el canal o sólo de un nick
+    pub(super) fn count(&self, kind: char, nick: Option<&str>, window: u64, now: u64) -> usize {
+        self.events
+            .iter()
+            .filter(|(k, n, time)| {
+                *k == kind && time + window > now && nick.is_none_or(|nick| *n == to_irccase(nick))
+            })
+            .count()
+    }
+
+    // Olvida los eventos de un tipo una vez aplicada la acción, del canal o de un nick
+    pub(super) fn clear(&mut self, kind: char, nick: Option<&str>) {
+        self.events
+            .retain(|(k, n, _)| *k != kind || nick.is_some_and(|nick| *n != to_irccase(nick)));
+    }
+}
+
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub(super) struct ChannelDefaultModes {
     pub(super) operators: HashSet<String>,
@@ -537,10 +730,15 @@ pub(super) struct Channel {
     pub(super) topic: Option<ChannelTopic>,
     pub(super) modes: ChannelModes,
     pub(super) default_modes: ChannelDefaultModes,
-    pub(super) ban_info: HashMap<UniCase<String>, BanInfo>,
-    pub(super) users: HashMap<UniCase<String>, ChannelUserModes>,
+    pub(super) ban_info: HashMap<IrcCase<String>, BanInfo>,
+    // quién y cuándo puso cada máscara de la lista de silenciados (+Z)
+    pub(super) quiet_info: HashMap<IrcCase<String>, BanInfo>,
+    // entradas, mensajes, cambios de nick y CTCPs recientes para +f y +j
+    pub(super) flood: FloodTracker,
+    pub(super) users: HashMap<IrcCase<String>, ChannelUserModes>,
     pub(super) creation_time: u64,
-    // if channel is preconfigured - it comes from configuration
+    // if channel is preconfigured - it comes from configuration or is guarded
+    // by ChanServ (CS SET GUARD). Such channel is not removed when it is empty.
     pub(super) preconfigured: bool,
 }
 
@@ -548,12 +746,14 @@ impl Channel {
     pub(super) fn new_on_user_join(user_nick: String) -> Channel {
         let mut users = HashMap::new();
         users.insert(
-            crate::state::structs::to_unicase(&user_nick),
+            crate::state::structs::to_irccase(&user_nick),
             ChannelUserModes::new_for_created_channel(),
         );
         Channel {
             topic: None,
             ban_info: HashMap::new(),
+            quiet_info: HashMap::new(),
+            flood: FloodTracker::default(),
             default_modes: ChannelDefaultModes::default(),
             modes: ChannelModes::new_for_channel(user_nick),
             users,
@@ -598,12 +798,21 @@ impl Channel {
             protecteds.insert(user_nick.clone());
             self.modes.protecteds = Some(protecteds);
         }
-        self.users.insert(crate::state::structs::to_unicase(user_nick), chum);
+        self.users.insert(crate::state::structs::to_irccase(user_nick), chum);
+    }
+
+    // Con +j, si ya se ha llegado al número de entradas permitidas en la ventana
+    pub(super) fn join_throttled(&self, now: u64) -> bool {
+        self.modes
+            .join_throttle
+            .as_deref()
+            .and_then(|throttle| parse_join_throttle(throttle).ok())
+            .is_some_and(|(joins, seconds)| self.flood.count('j', None, seconds, now) >= joins)
     }
 
     pub(super) fn rename_user(&mut self, old_nick: &String, nick: String) {
-        let oldchumode = self.users.remove(&crate::state::structs::to_unicase(old_nick)).unwrap();
-        self.users.insert(crate::state::structs::to_unicase(&nick), oldchumode);
+        let oldchumode = self.users.remove(&crate::state::structs::to_irccase(old_nick)).unwrap();
+        self.users.insert(crate::state::structs::to_irccase(&nick), oldchumode);
         self.modes.rename_user(old_nick, nick);
     }
 
@@ -614,7 +823,7 @@ impl Channel {
         self.remove_founder(nick);
         self.remove_voice(nick);
         self.remove_protected(nick);
-        self.users.remove(&crate::state::structs::to_unicase(nick));
+        self.users.remove(&crate::state::structs::to_irccase(nick));
     }
 
     // add/remove user from list
@@ -622,64 +831,75 @@ impl Channel {
         let mut ops = self.modes.operators.take().unwrap_or_default();
         ops.insert(nick.to_string());
         self.modes.operators = Some(ops);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().operator = true;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().operator = true;
     }
     pub(super) fn remove_operator(&mut self, nick: &str) {
         let mut ops = self.modes.operators.take().unwrap_or_default();
         ops.remove(nick);
         self.modes.operators = Some(ops);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().operator = false;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().operator = false;
     }
     pub(super) fn add_half_operator(&mut self, nick: &str) {
         let mut half_ops = self.modes.half_operators.take().unwrap_or_default();
         half_ops.insert(nick.to_string());
         self.modes.half_operators = Some(half_ops);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().half_oper = true;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().half_oper = true;
     }
     pub(super) fn remove_half_operator(&mut self, nick: &str) {
         let mut half_ops = self.modes.half_operators.take().unwrap_or_default();
         half_ops.remove(nick);
         self.modes.half_operators = Some(half_ops);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().half_oper = false;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().half_oper = false;
     }
     pub(super) fn add_voice(&mut self, nick: &str) {
         let mut voices = self.modes.voices.take().unwrap_or_default();
         voices.insert(nick.to_string());
         self.modes.voices = Some(voices);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().voice = true;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().voice = true;
     }
     pub(super) fn remove_voice(&mut self, nick: &str) {
         let mut voices = self.modes.voices.take().unwrap_or_default();
         voices.remove(nick);
         self.modes.voices = Some(voices);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().voice = false;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().voice = false;
     }
     pub(super) fn add_founder(&mut self, nick: &str) {
         let mut founders = self.modes.founders.take().unwrap_or_default();
         founders.insert(nick.to_string());
         self.modes.founders = Some(founders);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().founder = true;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().founder = true;
     }
     pub(super) fn remove_founder(&mut self, nick: &str) {
         let mut founders = self.modes.founders.take().unwrap_or_default();
         founders.remove(nick);
         self.modes.founders = Some(founders);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().founder = false;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().founder = false;
     }
     pub(super) fn add_protected(&mut self, nick: &str) {
         let mut protecteds = self.modes.protecteds.take().unwrap_or_default();
         protecteds.insert(nick.to_string());
         self.modes.protecteds = Some(protecteds);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().protected = true;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().protected = true;
     }
     pub(super) fn remove_protected(&mut self, nick: &str) {
         let mut protecteds = self.modes.protecteds.take().unwrap_or_default();
         protecteds.remove(nick);
         self.modes.protecteds = Some(protecteds);
-        self.users.get_mut(&crate::state::structs::to_unicase(nick)).unwrap().protected = false;
+        self.users.get_mut(&crate::state::structs::to_irccase(nick)).unwrap().protected = false;
     }
 }
 
+// Bot de BotServ asignado a un canal y su configuración
+#[derive(Debug, Clone, Default)]
+pub(super) struct BotChannel {
+    pub(super) bot: String,
+    pub(super) greet: Option<String>,
+    pub(super) kickers: HashSet<String>,
+    pub(super) badwords: Vec<String>,
+    // último mensaje de cada usuario y cuántas veces seguidas lo ha repetido
+    pub(super) last_messages: HashMap<IrcCase<String>, (String, usize)>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct NickHistoryEntry {
     pub(super) username: String,
@@ -938,6 +1158,18 @@ impl ConnUserState {
     }
 }
 
+// Bans heredados con ~j:#canal. Sólo se sigue un nivel, así que no puede haber ciclos.
+pub(super) fn inherited_ban<'a>(
+    channels: &'a HashMap<IrcCase<String>, Channel>,
+    target: &'a BanTarget<'a>,
+) -> impl Fn(&str) -> bool + 'a {
+    move |channel| {
+        channels
+            .get(&to_irccase(channel))
+            .is_some_and(|c| c.modes.banned_user(target, false, &|_| false))
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct ConnState {
     // use BufferedLineStream to avoid deadlocks when sending is not still finished.
@@ -962,6 +1194,9 @@ pub(crate) struct ConnState {
     pub(super) dns_lookup_receiver: Fuse<oneshot::Receiver<Option<String>>>,
     #[cfg(feature = "dns_lookup")]
     pub(super) dns_lookup_sender: Option<oneshot::Sender<Option<String>>>,
+    // cloack_sender - se guarda en User para cambiar el host visible desde otra conexión.
+    pub(super) cloack_sender: Option<UnboundedSender<String>>,
+    pub(super) cloack_receiver: UnboundedReceiver<String>,
 
     pub(super) user_state: ConnUserState,
 
@@ -981,6 +1216,7 @@ impl ConnState {
         let (ping_sender, ping_receiver) = unbounded_channel();
         let (timeout_sender, timeout_receiver) = unbounded_channel();
         let (quit_sender, quit_receiver) = oneshot::channel();
+        let (cloack_sender, cloack_receiver) = unbounded_channel();
         #[cfg(feature = "dns_lookup")]
         let (dns_lookup_sender, dns_lookup_receiver) = oneshot::channel();
         #[cfg(not(feature = "dns_lookup"))]
@@ -1001,6 +1237,8 @@ impl ConnState {
             #[cfg(feature = "dns_lookup")]
             dns_lookup_sender: Some(dns_lookup_sender),
             dns_lookup_receiver: dns_lookup_receiver.fuse(),
+            cloack_sender: Some(cloack_sender),
+            cloack_receiver,
             caps_negotation: false,
             caps: CapState::default(),
             quit: Arc::new(AtomicI32::new(0)),
@@ -1094,14 +1332,20 @@ async fn pong_client_timeout(
 }
 
 pub struct VolatileState {
-    pub(super) users: HashMap<UniCase<String>, User>,
-    pub(super) channels: HashMap<UniCase<String>, Channel>,
+    pub(super) users: HashMap<IrcCase<String>, User>,
+    pub(super) channels: HashMap<IrcCase<String>, Channel>,
     pub(super) wallops_users: HashSet<String>,
     pub(super) invisible_users_count: usize,
     pub(super) operators_count: usize,
     pub(super) max_users_count: usize,
     pub(super) nick_histories: HashMap<String, Vec<NickHistoryEntry>>,
     pub(super) quit_sender: Option<oneshot::Sender<String>>,
+    // nicks y canales bloqueados con OS JUPE (clave -> motivo)
+    pub(super) jupes: HashMap<IrcCase<String>, String>,
+    // últimos comandos de OperServ ejecutados por IRCops
+    pub(super) oper_log: VecDeque<String>,
+    // canales con un bot de BotServ asignado
+    pub(super) bot_channels: HashMap<IrcCase<String>, BotChannel>,
 }
 
 impl VolatileState {
@@ -1114,10 +1358,12 @@ impl VolatileState {
                 let def_ch_modes = ChannelDefaultModes::new_from_modes_and_cleanup(&mut ch_modes);
 
                 channels.insert(
-                    UniCase::new(c.name.clone()),
+                    IrcCase::new(c.name.clone()),
                     Channel {
                         topic: c.topic.as_ref().map(|x| ChannelTopic::new(x.clone())),
                         ban_info: HashMap::new(),
+                        quiet_info: HashMap::new(),
+                        flood: FloodTracker::default(),
                         default_modes: def_ch_modes,
                         modes: ch_modes,
                         users: HashMap::new(),
@@ -1142,6 +1388,9 @@ impl VolatileState {
             max_users_count: 0,
             nick_histories: HashMap::new(),
             quit_sender: Some(quit_sender),
+            jupes: HashMap::new(),
+            oper_log: VecDeque::new(),
+            bot_channels: HashMap::new(),
         }
     }
 
@@ -1156,7 +1405,7 @@ impl VolatileState {
         if user.modes.is_local_oper() {
             self.operators_count += 1;
         }
-        self.users.insert(UniCase::new(unick.to_string()), user);
+        self.users.insert(IrcCase::new(unick.to_string()), user);
         if self.users.len() > self.max_users_count {
             self.max_users_count = self.users.len();
         }
@@ -1165,21 +1414,21 @@ impl VolatileState {
     // remove user from channel and remove channel from user.
     // remove same channel if no more users at channel.
     pub(super) fn remove_user_from_channel<'a>(&mut self, channel: &'a str, nick: &'a str) {
-        if let Some(chanobj) = self.channels.get_mut(&UniCase::new(channel.to_string())) {
+        if let Some(chanobj) = self.channels.get_mut(&IrcCase::new(channel.to_string())) {
             chanobj.remove_user(nick);
-            if chanobj.users.is_empty() && !chanobj.preconfigured {
+            if chanobj.users.is_empty() && !chanobj.preconfigured && !chanobj.modes.permanent {
                 info!("Channel {} has been removed", channel);
-                self.channels.remove(&UniCase::new(channel.to_string()));
+                self.channels.remove(&IrcCase::new(channel.to_string()));
             }
         }
-        if let Some(user) = self.users.get_mut(&UniCase::new(nick.to_string())) {
+        if let Some(user) = self.users.get_mut(&IrcCase::new(nick.to_string())) {
             user.channels.remove(channel);
         }
     }
 
     // remove user - including stats like invisible users.
     pub(super) fn remove_user(&mut self, nick: &str) {
-        if let Some(user) = self.users.remove(&UniCase::new(nick.to_string())) {
+        if let Some(user) = self.users.remove(&IrcCase::new(nick.to_string())) {
             if user.modes.is_local_oper() {
                 self.operators_count -= 1;
             }
@@ -1215,6 +1464,9 @@ impl Clone for VolatileState {
             max_users_count: self.max_users_count,
             nick_histories: self.nick_histories.clone(),
             quit_sender: None,
+            jupes: self.jupes.clone(),
+            oper_log: self.oper_log.clone(),
+            bot_channels: self.bot_channels.clone(),
         }
     }
 }
@@ -1235,7 +1487,9 @@ mod test {
             wallops: false,
             websocket: false,
             secure: false,
-            cloacked: false
+            cloacked: false,
+            caller_id: false,
+            registered_only: false
         };
         let user_state = ConnUserState {
             ip_addr: "127.0.0.1".parse().unwrap(),
@@ -1383,10 +1637,11 @@ mod test {
         assert_eq!(
             "~",
             chum.to_string(&CapState {
-                multi_prefix: false
+                multi_prefix: false,
+                ..CapState::default()
             })
         );
-        assert_eq!("~&@", chum.to_string(&CapState { multi_prefix: true }));
+        assert_eq!("~&@", chum.to_string(&CapState { multi_prefix: true, ..CapState::default() }));
 
         let chum = ChannelUserModes {
             founder: false,
@@ -1398,10 +1653,11 @@ mod test {
         assert_eq!(
             "%",
             chum.to_string(&CapState {
-                multi_prefix: false
+                multi_prefix: false,
+                ..CapState::default()
             })
         );
-        assert_eq!("%+", chum.to_string(&CapState { multi_prefix: true }));
+        assert_eq!("%+", chum.to_string(&CapState { multi_prefix: true, ..CapState::default() }));
     }
 
     #[test]
@@ -1517,8 +1773,10 @@ mod test {
                 modes: ChannelModes::new_for_channel("dizzy".to_string()),
                 default_modes: ChannelDefaultModes::default(),
                 ban_info: HashMap::new(),
+                quiet_info: HashMap::new(),
+                flood: FloodTracker::default(),
                 users: [(
-                    "dizzy".to_string(),
+                    IrcCase::new("dizzy".to_string()),
                     ChannelUserModes::new_for_created_channel()
                 )]
                 .into(),
@@ -1553,7 +1811,7 @@ mod test {
         let mut exp_channel = Channel::new_on_user_join("runner".to_string());
         exp_channel.default_modes = channel.default_modes.clone();
         exp_channel.users.insert(
-            "fasty".to_string(),
+            IrcCase::new("fasty".to_string()),
             ChannelUserModes {
                 founder: true,
                 protected: false,
@@ -1563,7 +1821,7 @@ mod test {
             },
         );
         exp_channel.users.insert(
-            "quicker".to_string(),
+            IrcCase::new("quicker".to_string()),
             ChannelUserModes {
                 founder: false,
                 protected: true,
@@ -1573,7 +1831,7 @@ mod test {
             },
         );
         exp_channel.users.insert(
-            "leader".to_string(),
+            IrcCase::new("leader".to_string()),
             ChannelUserModes {
                 founder: false,
                 protected: false,
@@ -1583,7 +1841,7 @@ mod test {
             },
         );
         exp_channel.users.insert(
-            "rover".to_string(),
+            IrcCase::new("rover".to_string()),
             ChannelUserModes {
                 founder: false,
                 protected: false,
@@ -1593,7 +1851,7 @@ mod test {
             },
         );
         exp_channel.users.insert(
-            "cyclist".to_string(),
+            IrcCase::new("cyclist".to_string()),
             ChannelUserModes {
                 founder: false,
                 protected: false,
@@ -1604,42 +1862,42 @@ mod test {
         );
         exp_channel
             .users
-            .insert("doer".to_string(), ChannelUserModes::default());
-        exp_channel.modes.founders = Some(["fasty".to_string(), "runner".to_string()].into());
+            .insert(IrcCase::new("doer".to_string()), ChannelUserModes::default());
+        exp_channel.modes.founders = Some(["fasty".to_string()].into());
         exp_channel.modes.protecteds = Some(["quicker".to_string()].into());
-        exp_channel.modes.operators = Some(["leader".to_string(), "runner".to_string()].into());
+        exp_channel.modes.operators = Some(["leader".to_string()].into());
         exp_channel.modes.half_operators = Some(["rover".to_string()].into());
         exp_channel.modes.voices = Some(["cyclist".to_string()].into());
 
         assert_eq!(exp_channel, channel);
 
         channel.remove_user(&"doer".to_string());
-        exp_channel.users.remove(&"doer".to_string());
+        exp_channel.users.remove(&to_irccase("doer"));
         assert_eq!(exp_channel, channel);
 
         channel.remove_user(&"cyclist".to_string());
-        exp_channel.users.remove(&"cyclist".to_string());
+        exp_channel.users.remove(&to_irccase("cyclist"));
         exp_channel.modes.voices = Some(HashSet::new());
         assert_eq!(exp_channel, channel);
 
         channel.remove_user(&"rover".to_string());
-        exp_channel.users.remove(&"rover".to_string());
+        exp_channel.users.remove(&to_irccase("rover"));
         exp_channel.modes.half_operators = Some(HashSet::new());
         assert_eq!(exp_channel, channel);
 
         channel.remove_user(&"leader".to_string());
-        exp_channel.users.remove(&"leader".to_string());
-        exp_channel.modes.operators = Some(["runner".to_string()].into());
+        exp_channel.users.remove(&to_irccase("leader"));
+        exp_channel.modes.operators = Some(HashSet::new());
         assert_eq!(exp_channel, channel);
 
         channel.remove_user(&"quicker".to_string());
-        exp_channel.users.remove(&"quicker".to_string());
+        exp_channel.users.remove(&to_irccase("quicker"));
         exp_channel.modes.protecteds = Some(HashSet::new());
         assert_eq!(exp_channel, channel);
 
         channel.remove_user(&"fasty".to_string());
-        exp_channel.users.remove(&"fasty".to_string());
-        exp_channel.modes.founders = Some(["runner".to_string()].into());
+        exp_channel.users.remove(&to_irccase("fasty"));
+        exp_channel.modes.founders = Some(HashSet::new());
         assert_eq!(exp_channel, channel);
     }
 
@@ -1653,8 +1911,10 @@ mod test {
                 modes: ChannelModes::new_for_channel("diggy".to_string()),
                 default_modes: ChannelDefaultModes::default(),
                 ban_info: HashMap::new(),
+                quiet_info: HashMap::new(),
+                flood: FloodTracker::default(),
                 users: [(
-                    "diggy".to_string(),
+                    IrcCase::new("diggy".to_string()),
                     ChannelUserModes::new_for_created_channel()
                 )]
                 .into(),
@@ -1665,6 +1925,27 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_channel_flood_tracker() {
+        let mut channel = Channel::new_on_user_join("dizzy".to_string());
+        channel.modes.join_throttle = Some("2:10".to_string());
+        channel.flood.record('j', "guru", 10, 100);
+        assert!(!channel.join_throttled(101));
+        channel.flood.record('m', "guru", 10, 101);
+        channel.flood.record('j', "vip", 10, 102);
+        assert!(channel.join_throttled(105));
+        assert!(!channel.join_throttled(111));
+        assert_eq!(1, channel.flood.count('j', Some("VIP"), 10, 105));
+        assert_eq!(1, channel.flood.count('m', None, 10, 105));
+
+        channel.flood.clear('j', Some("guru"));
+        assert_eq!(0, channel.flood.count('j', Some("guru"), 10, 105));
+        assert_eq!(1, channel.flood.count('j', None, 10, 105));
+        channel.flood.clear('j', None);
+        assert_eq!(0, channel.flood.count('j', None, 10, 105));
+        assert_eq!(1, channel.flood.count('m', None, 10, 105));
+    }
+
     #[test]
     fn test_channel_add_remove_mode() {
         let mut channel = Channel::new_on_user_join("dizzy".to_string());
@@ -1674,16 +1955,18 @@ mod test {
             modes: ChannelModes::new_for_channel("dizzy".to_string()),
             default_modes: ChannelDefaultModes::default(),
             ban_info: HashMap::new(),
+            quiet_info: HashMap::new(),
+            flood: FloodTracker::default(),
             users: [
                 (
-                    "dizzy".to_string(),
+                    IrcCase::new("dizzy".to_string()),
                     ChannelUserModes::new_for_created_channel(),
                 ),
-                ("inventor".to_string(), ChannelUserModes::default()),
-                ("guru".to_string(), ChannelUserModes::default()),
-                ("halfguru".to_string(), ChannelUserModes::default()),
-                ("vip".to_string(), ChannelUserModes::default()),
-                ("talker".to_string(), ChannelUserModes::default()),
+                (IrcCase::new("inventor".to_string()), ChannelUserModes::default()),
+                (IrcCase::new("guru".to_string()), ChannelUserModes::default()),
+                (IrcCase::new("halfguru".to_string()), ChannelUserModes::default()),
+                (IrcCase::new("vip".to_string()), ChannelUserModes::default()),
+                (IrcCase::new("talker".to_string()), ChannelUserModes::default()),
             ]
             .into(),
             creation_time: channel.creation_time,
@@ -1692,24 +1975,24 @@ mod test {
 
         channel
             .users
-            .insert("inventor".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("inventor".to_string()), ChannelUserModes::default());
         channel
             .users
-            .insert("guru".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("guru".to_string()), ChannelUserModes::default());
         channel
             .users
-            .insert("halfguru".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("halfguru".to_string()), ChannelUserModes::default());
         channel
             .users
-            .insert("vip".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("vip".to_string()), ChannelUserModes::default());
         channel
             .users
-            .insert("talker".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("talker".to_string()), ChannelUserModes::default());
 
         channel.add_founder("inventor");
-        exp_channel.modes.founders = Some(["dizzy".to_string(), "inventor".to_string()].into());
+        exp_channel.modes.founders = Some(["inventor".to_string()].into());
         exp_channel.users.insert(
-            "inventor".to_string(),
+            IrcCase::new("inventor".to_string()),
             ChannelUserModes {
                 founder: true,
                 protected: false,
@@ -1721,16 +2004,16 @@ mod test {
         assert_eq!(exp_channel, channel);
 
         channel.remove_founder("inventor");
-        exp_channel.modes.founders = Some(["dizzy".to_string()].into());
+        exp_channel.modes.founders = Some(HashSet::new());
         exp_channel
             .users
-            .insert("inventor".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("inventor".to_string()), ChannelUserModes::default());
         assert_eq!(exp_channel, channel);
 
         channel.add_operator("guru");
-        exp_channel.modes.operators = Some(["dizzy".to_string(), "guru".to_string()].into());
+        exp_channel.modes.operators = Some(["guru".to_string()].into());
         exp_channel.users.insert(
-            "guru".to_string(),
+            IrcCase::new("guru".to_string()),
             ChannelUserModes {
                 founder: false,
                 protected: false,
@@ -1742,16 +2025,16 @@ mod test {
         assert_eq!(exp_channel, channel);
 
         channel.remove_operator("guru");
-        exp_channel.modes.operators = Some(["dizzy".to_string()].into());
+        exp_channel.modes.operators = Some(HashSet::new());
         exp_channel
             .users
-            .insert("guru".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("guru".to_string()), ChannelUserModes::default());
         assert_eq!(exp_channel, channel);
 
         channel.add_half_operator("halfguru");
         exp_channel.modes.half_operators = Some(["halfguru".to_string()].into());
         exp_channel.users.insert(
-            "halfguru".to_string(),
+            IrcCase::new("halfguru".to_string()),
             ChannelUserModes {
                 founder: false,
                 protected: false,
@@ -1766,13 +2049,13 @@ mod test {
         exp_channel.modes.half_operators = Some(HashSet::new());
         exp_channel
             .users
-            .insert("halfguru".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("halfguru".to_string()), ChannelUserModes::default());
         assert_eq!(exp_channel, channel);
 
         channel.add_protected("vip");
         exp_channel.modes.protecteds = Some(["vip".to_string()].into());
         exp_channel.users.insert(
-            "vip".to_string(),
+            IrcCase::new("vip".to_string()),
             ChannelUserModes {
                 founder: false,
                 protected: true,
@@ -1787,13 +2070,13 @@ mod test {
         exp_channel.modes.protecteds = Some(HashSet::new());
         exp_channel
             .users
-            .insert("vip".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("vip".to_string()), ChannelUserModes::default());
         assert_eq!(exp_channel, channel);
 
         channel.add_voice("talker");
         exp_channel.modes.voices = Some(["talker".to_string()].into());
         exp_channel.users.insert(
-            "talker".to_string(),
+            IrcCase::new("talker".to_string()),
             ChannelUserModes {
                 founder: false,
                 protected: false,
@@ -1808,7 +2091,7 @@ mod test {
         exp_channel.modes.voices = Some(HashSet::new());
         exp_channel
             .users
-            .insert("talker".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("talker".to_string()), ChannelUserModes::default());
         assert_eq!(exp_channel, channel);
     }
 
@@ -1838,38 +2121,44 @@ mod test {
         assert_eq!(
             HashMap::from([
                 (
-                    "#gooddays".to_string(),
+                    IrcCase::new("#gooddays".to_string()),
                     Channel {
                         topic: Some(ChannelTopic::new("About good days".to_string())),
                         modes: ChannelModes::default(),
                         default_modes: ChannelDefaultModes::default(),
                         ban_info: HashMap::new(),
+                        quiet_info: HashMap::new(),
+                        flood: FloodTracker::default(),
                         users: HashMap::new(),
-                        creation_time: state.channels.get("#gooddays").unwrap().creation_time,
+                        creation_time: state.channels.get(&to_irccase("#gooddays")).unwrap().creation_time,
                         preconfigured: true
                     }
                 ),
                 (
-                    "#pets".to_string(),
+                    IrcCase::new("#pets".to_string()),
                     Channel {
                         topic: Some(ChannelTopic::new("About pets".to_string())),
                         modes: ChannelModes::default(),
                         default_modes: ChannelDefaultModes::default(),
                         ban_info: HashMap::new(),
+                        quiet_info: HashMap::new(),
+                        flood: FloodTracker::default(),
                         users: HashMap::new(),
-                        creation_time: state.channels.get("#pets").unwrap().creation_time,
+                        creation_time: state.channels.get(&to_irccase("#pets")).unwrap().creation_time,
                         preconfigured: true
                     }
                 ),
                 (
-                    "&cactuses".to_string(),
+                    IrcCase::new("&cactuses".to_string()),
                     Channel {
                         topic: None,
                         modes: ChannelModes::default(),
                         default_modes: ChannelDefaultModes::default(),
                         ban_info: HashMap::new(),
+                        quiet_info: HashMap::new(),
+                        flood: FloodTracker::default(),
                         users: HashMap::new(),
-                        creation_time: state.channels.get("&cactuses").unwrap().creation_time,
+                        creation_time: state.channels.get(&to_irccase("&cactuses")).unwrap().creation_time,
                         preconfigured: true
                     }
                 )
@@ -1913,39 +2202,39 @@ mod test {
             .iter()
             .for_each(|(chname, nick)| {
                 state.channels.insert(
-                    UniCase::new(chname.to_string()),
+                    IrcCase::new(chname.to_string()),
                     Channel::new_on_user_join(nick.to_string()),
                 );
                 state
                     .users
-                    .get_mut(&nick.to_string())
+                    .get_mut(&to_irccase(nick))
                     .unwrap()
                     .channels
                     .insert(chname.to_string());
             });
         state
             .channels
-            .get_mut(&UniCase::new("#something".to_string()))
+            .get_mut(&IrcCase::new("#something".to_string()))
             .unwrap()
             .users
-            .insert("matixi".to_string(), ChannelUserModes::default());
+            .insert(IrcCase::new("matixi".to_string()), ChannelUserModes::default());
         state
             .users
-            .get_mut("matixi")
+            .get_mut(&to_irccase("matixi"))
             .unwrap()
             .channels
             .insert("#something".to_string());
 
         state.remove_user_from_channel("#something", "matixi");
-        assert!(state.channels.contains_key(&UniCase::new("#something".to_string())));
+        assert!(state.channels.contains_key(&IrcCase::new("#something".to_string())));
         assert_eq!(
             HashMap::new(),
-            state.channels.get(&UniCase::new("#something".to_string())).unwrap().users
+            state.channels.get(&IrcCase::new("#something".to_string())).unwrap().users
         );
         state.remove_user_from_channel("#matixichan", "matixi");
-        assert!(!state.channels.contains_key(&UniCase::new("#matixichan".to_string())));
+        assert!(!state.channels.contains_key(&IrcCase::new("#matixichan".to_string())));
         state.remove_user_from_channel("#tulipan", "matixi");
-        assert!(!state.channels.contains_key(&UniCase::new("#tulipan".to_string())));
+        assert!(!state.channels.contains_key(&IrcCase::new("#tulipan".to_string())));
     }
 
     #[test]
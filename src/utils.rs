@@ -24,10 +24,14 @@ use bytes::{BufMut, BytesMut};
 use futures::task::{Context, Poll};
 use futures::{Sink, SinkExt, Stream};
 use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::error::Error;
 use std::io;
 use std::pin::Pin;
+#[cfg(not(test))]
+use std::sync::OnceLock;
 use tokio::io::ReadBuf;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
@@ -44,6 +48,7 @@ use base64::{Engine as _, engine::general_purpose};
 use crate::command::CommandError;
 use crate::command::CommandError::*;
 use crate::command::CommandId::*;
+use crate::config::FLAG_MODES;
 
 #[derive(Debug)]
 pub(crate) enum DualTcpStream {
@@ -336,6 +341,18 @@ impl Decoder for IRCLinesCodec {
     }
 }
 
+pub(crate) fn validate_source(s: &str) -> bool {
+    if s.contains(':') {
+        false
+    } else {
+        // in nick!user@host '!' must be before '@'
+        match (s.find('!'), s.find('@')) {
+            (Some(excl_pos), Some(atchar_pos)) => excl_pos < atchar_pos,
+            _ => true,
+        }
+    }
+}
+
 pub(crate) fn validate_username(username: &str) -> Result<(), ValidationError> {
     if !username.is_empty() && (username.as_bytes()[0] == b'#' || username.as_bytes()[0] == b'&') {
         Err(ValidationError::new(
@@ -419,7 +436,7 @@ pub(crate) fn validate_usermodes<'a>(
         if !ms.is_empty() {
             if ms
                 .find(|c| {
-                    c != '+' && c != '-' && c != 'i' && c != 'o' && c != 'O' && c != 'r' && c != 'w' && c != 'W' && c != 'z' && c != 'x'
+                    c != '+' && c != '-' && c != 'i' && c != 'o' && c != 'O' && c != 'r' && c != 'w' && c != 'W' && c != 'z' && c != 'x' && c != 'g' && c != 'R'
                 })
                 .is_some()
             {
@@ -457,8 +474,18 @@ pub(crate) fn validate_channelmodes<'a>(
                     '-' => {
                         mode_set = false;
                     }
-                    'b' | 'B' | 'e' | 'I' => {
-                        margs_it.next(); // consume argument
+                    'b' | 'B' | 'e' | 'I' | 'Z' => {
+                        // consume argument
+                        if let Some(arg) = margs_it.next() {
+                            if c != 'B' {
+                                validate_extban(c, arg).map_err(|description| InvalidModeParam {
+                                    target: target.to_string(),
+                                    modechar: c,
+                                    param: arg.to_string(),
+                                    description,
+                                })?;
+                            }
+                        }
                         arg_param_idx += 1;
                     }
                     'o' | 'v' | 'h' | 'q' | 'a' => {
@@ -509,6 +536,38 @@ pub(crate) fn validate_channelmodes<'a>(
                             });
                         }
                     }
+                    'f' | 'j' | 'L' => {
+                        if mode_set {
+                            if let Some(arg) = margs_it.next() {
+                                let result = match c {
+                                    'f' => parse_flood_mode(arg).map(|_| ()),
+                                    'j' => parse_join_throttle(arg).map(|_| ()),
+                                    _ => validate_channel(arg).map_err(|e| e.to_string()),
+                                };
+                                result.map_err(|description| InvalidModeParam {
+                                    target: target.to_string(),
+                                    modechar: c,
+                                    param: arg.to_string(),
+                                    description,
+                                })?;
+                                arg_param_idx += 1;
+                            } else {
+                                return Err(InvalidModeParam {
+                                    target: target.to_string(),
+                                    modechar: c,
+                                    param: "".to_string(),
+                                    description: "No argument".to_string(),
+                                });
+                            }
+                        } else if let Some(arg) = margs_it.next() {
+                            return Err(InvalidModeParam {
+                                target: target.to_string(),
+                                modechar: c,
+                                param: arg.to_string(),
+                                description: "Unexpected argument".to_string(),
+                            });
+                        }
+                    }
                     'k' => {
                         if mode_set {
                             if margs_it.next().is_some() {
@@ -532,6 +591,7 @@ pub(crate) fn validate_channelmodes<'a>(
                         }
                     }
                     'i' | 'm' | 't' | 'n' | 's' | 'r' => {}
+                    c if FLAG_MODES.contains(c) => {}
                     c => {
                         return Err(UnknownMode(param_idx, c, target.to_string()));
                     }
@@ -583,10 +643,10 @@ pub(crate) fn match_wildcard<'a>(pattern: &'a str, text: &'a str) -> bool {
                 // if last current character is asterisk
                 let mut i = 0;
                 // find first single wildcards occurrence.
-                while i <= t.len() - m.len() && !starts_single_wilcards(m, &t[i..]) {
+                while i + m.len() <= t.len() && !starts_single_wilcards(m, &t[i..]) {
                     i += 1;
                 }
-                if i <= t.len() - m.len() {
+                if i + m.len() <= t.len() {
                     // if found
                     t = &t[i + m.len()..];
                 } else {
@@ -594,7 +654,7 @@ pub(crate) fn match_wildcard<'a>(pattern: &'a str, text: &'a str) -> bool {
                 }
             } else {
                 // if last pattern is not asterisk
-                if !starts_single_wilcards(m, &t[t.len() - m.len()..]) {
+                if t.len() < m.len() || !starts_single_wilcards(m, &t[t.len() - m.len()..]) {
                     return false;
                 }
                 t = &t[t.len()..t.len()];
@@ -627,6 +687,422 @@ pub(crate) fn normalize_sourcemask(mask: &str) -> String {
     out
 }
 
+// Extbans: máscaras de las listas b/e/I/Z que comprueban algo distinto de nick!user@host.
+//   ~a:cuenta    cuenta de NickServ identificada    ~r:realname  nombre real
+//   ~c:#canal    usuario dentro de otro canal       ~z           conexión TLS
+//   ~m:máscara   (sólo +b) puede entrar pero no hablar
+//   ~j:#canal    hereda los bans de otro canal
+pub(crate) const EXTBAN_PREFIX: char = '~';
+pub(crate) const EXTBAN_TYPES: &str = "acjmrz";
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ExtBan<'a> {
+    Account(&'a str),
+    Realname(&'a str),
+    Channel(&'a str),
+    Secure,
+    Mute(&'a str),
+    Join(&'a str),
+}
+
+// Devuelve None si la máscara no es una extban
+pub(crate) fn parse_extban(mask: &str) -> Option<Result<ExtBan<'_>, String>> {
+    let rest = mask.strip_prefix(EXTBAN_PREFIX)?;
+    let mut chars = rest.chars();
+    let Some(kind) = chars.next() else {
+        return Some(Err("Missing extban type".to_string()));
+    };
+    let arg = match chars.as_str() {
+        "" => None,
+        arg => match arg.strip_prefix(':') {
+            Some(arg) if !arg.is_empty() => Some(arg),
+            _ => return Some(Err(format!("Expected ~{kind}:<argument>"))),
+        },
+    };
+    Some(match (kind, arg) {
+        ('z', None) => Ok(ExtBan::Secure),
+        ('z', Some(_)) => Err("~z takes no argument".to_string()),
+        ('a' | 'r' | 'c' | 'm' | 'j', None) => Err(format!("~{kind} needs an argument")),
+        ('a', Some(account)) => Ok(ExtBan::Account(account)),
+        ('r', Some(realname)) => Ok(ExtBan::Realname(realname)),
+        ('c', Some(channel)) => {
+            if channel.starts_with(['#', '&']) {
+                Ok(ExtBan::Channel(channel))
+            } else {
+                Err(format!("Invalid channel '{channel}'"))
+            }
+        }
+        ('j', Some(channel)) => {
+            if validate_channel(channel).is_ok() {
+                Ok(ExtBan::Join(channel))
+            } else {
+                Err(format!("Invalid channel '{channel}'"))
+            }
+        }
+        ('m', Some(inner)) => match parse_extban(inner) {
+            Some(Ok(ExtBan::Mute(_))) => Err("~m can't be nested".to_string()),
+            Some(Err(e)) => Err(e),
+            _ => Ok(ExtBan::Mute(inner)),
+        },
+        (kind, _) => Err(format!("Unknown extban type '{kind}'")),
+    })
+}
+
+// Comprueba una extban de la lista b, e, I o Z. En las listas b y Z se quita antes la expiración.
+pub(crate) fn validate_extban(list: char, mask: &str) -> Result<(), String> {
+    let mask = if matches!(list, 'b' | 'Z') { mask.split('|').next().unwrap_or(mask) } else { mask };
+    match parse_extban(mask) {
+        Some(Ok(ExtBan::Mute(_))) if list != 'b' => Err("~m is only valid in the ban list".to_string()),
+        Some(Err(e)) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+// Normaliza una máscara de las listas b/e/I/Z. Las extbans se dejan como están, salvo la
+// máscara nick!user@host de ~m. La expiración "|segundos" se conserva al final.
+pub(crate) fn normalize_banmask(mask: &str) -> String {
+    if let Some((mask, expiry)) = mask.split_once('|') {
+        return format!("{}|{}", normalize_banmask(mask), expiry);
+    }
+    if let Some(inner) = mask.strip_prefix("~m:") {
+        if inner.starts_with(EXTBAN_PREFIX) {
+            mask.to_string()
+        } else {
+            format!("~m:{}", normalize_sourcemask(inner))
+        }
+    } else if mask.starts_with(EXTBAN_PREFIX) {
+        mask.to_string()
+    } else {
+        normalize_sourcemask(mask)
+    }
+}
+
+// Casemapping de nicks y canales, anunciado en el token CASEMAPPING.
+//   ascii           sólo A-Z ↔ a-z
+//   rfc1459         además []\^ ↔ {}|~
+//   rfc1459-strict  además []\ ↔ {}| (sin ^ ↔ ~)
+//   rfc7613         minúsculas Unicode, para nicks UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CaseMapping {
+    #[default]
+    Ascii,
+    Rfc1459,
+    Rfc1459Strict,
+    Rfc7613,
+}
+
+// casemapping en uso - se fija una sola vez al arrancar el servidor desde la configuración,
+// porque cambiarlo en caliente desordenaría las claves ya guardadas.
+#[cfg(not(test))]
+static CURRENT_CASEMAPPING: OnceLock<CaseMapping> = OnceLock::new();
+
+// en las pruebas cada test (cada hilo) fija su propio casemapping
+#[cfg(test)]
+thread_local! {
+    static CURRENT_CASEMAPPING: std::cell::OnceCell<CaseMapping> = const { std::cell::OnceCell::new() };
+}
+
+impl CaseMapping {
+    #[cfg(not(test))]
+    pub(crate) fn current() -> CaseMapping {
+        CURRENT_CASEMAPPING.get().copied().unwrap_or_default()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn current() -> CaseMapping {
+        CURRENT_CASEMAPPING.with(|current| current.get().copied().unwrap_or_default())
+    }
+
+    pub(crate) fn set_current(self) -> Result<(), String> {
+        #[cfg(not(test))]
+        let current = *CURRENT_CASEMAPPING.get_or_init(|| self);
+        #[cfg(test)]
+        let current = CURRENT_CASEMAPPING.with(|current| *current.get_or_init(|| self));
+        if current == self {
+            Ok(())
+        } else {
+            Err(format!(
+                "Casemapping already set to {}, can't change it to {}",
+                current.name(),
+                self.name()
+            ))
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            CaseMapping::Ascii => "ascii",
+            CaseMapping::Rfc1459 => "rfc1459",
+            CaseMapping::Rfc1459Strict => "rfc1459-strict",
+            CaseMapping::Rfc7613 => "rfc7613",
+        }
+    }
+
+    fn fold_char(self, c: char) -> char {
+        match (self, c) {
+            (_, 'A'..='Z') => c.to_ascii_lowercase(),
+            (CaseMapping::Rfc1459 | CaseMapping::Rfc1459Strict, '[') => '{',
+            (CaseMapping::Rfc1459 | CaseMapping::Rfc1459Strict, ']') => '}',
+            (CaseMapping::Rfc1459 | CaseMapping::Rfc1459Strict, '\\') => '|',
+            (CaseMapping::Rfc1459, '^') => '~',
+            _ => c,
+        }
+    }
+
+    // forma canónica del nombre: dos nombres chocan si su forma canónica es la misma.
+    pub(crate) fn fold(self, name: &str) -> Cow<'_, str> {
+        if self == CaseMapping::Rfc7613 {
+            Cow::Owned(name.to_lowercase())
+        } else if name.chars().all(|c| self.fold_char(c) == c) {
+            Cow::Borrowed(name)
+        } else {
+            Cow::Owned(name.chars().map(|c| self.fold_char(c)).collect())
+        }
+    }
+}
+
+// forma canónica de un nick o canal según el casemapping en uso (para las bases de datos).
+pub(crate) fn casefold(name: &str) -> String {
+    CaseMapping::current().fold(name).into_owned()
+}
+
+// Parámetros de LIST con ELIST: cada elemento es un canal o un filtro.
+//   >n, <n      más / menos de n usuarios (U)
+//   C>n, C<n    canal creado hace más / menos de n minutos
+//   T>n, T<n    topic puesto hace más / menos de n minutos
+//   #*máscara*  nombre que encaja con la máscara (M), "!#*máscara*" que no encaja (N)
+// Un canal se lista si cumple todos los filtros.
+pub(crate) const ELIST_TYPES: &str = "CMNTU";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListFilter<'a> {
+    Channel(&'a str),
+    UsersOver(usize),
+    UsersUnder(usize),
+    CreatedOver(u64),
+    CreatedUnder(u64),
+    TopicOver(u64),
+    TopicUnder(u64),
+    Mask(&'a str),
+    NotMask(&'a str),
+}
+
+pub(crate) fn parse_list_filter(item: &str) -> Option<ListFilter<'_>> {
+    if let Some(n) = item.strip_prefix('>') {
+        n.parse().ok().map(ListFilter::UsersOver)
+    } else if let Some(n) = item.strip_prefix('<') {
+        n.parse().ok().map(ListFilter::UsersUnder)
+    } else if let Some(n) = item.strip_prefix("C>") {
+        n.parse().ok().map(ListFilter::CreatedOver)
+    } else if let Some(n) = item.strip_prefix("C<") {
+        n.parse().ok().map(ListFilter::CreatedUnder)
+    } else if let Some(n) = item.strip_prefix("T>") {
+        n.parse().ok().map(ListFilter::TopicOver)
+    } else if let Some(n) = item.strip_prefix("T<") {
+        n.parse().ok().map(ListFilter::TopicUnder)
+    } else if let Some(mask) = item.strip_prefix('!') {
+        validate_channel(mask).ok().map(|_| ListFilter::NotMask(mask))
+    } else if validate_channel(item).is_ok() {
+        if item.contains(['*', '?']) {
+            Some(ListFilter::Mask(item))
+        } else {
+            Some(ListFilter::Channel(item))
+        }
+    } else {
+        None
+    }
+}
+
+impl ListFilter<'_> {
+    // created y topic_time en segundos desde UNIX_EPOCH; sin topic no cumple T>/T<.
+    pub(crate) fn matches(
+        &self,
+        channel: &str,
+        users: usize,
+        created: u64,
+        topic_time: Option<u64>,
+        now: u64,
+    ) -> bool {
+        let age = |t: u64| now.saturating_sub(t) / 60;
+        match *self {
+            ListFilter::Channel(name) => casefold(name) == casefold(channel),
+            ListFilter::UsersOver(n) => users > n,
+            ListFilter::UsersUnder(n) => users < n,
+            ListFilter::CreatedOver(m) => age(created) > m,
+            ListFilter::CreatedUnder(m) => age(created) < m,
+            ListFilter::TopicOver(m) => topic_time.is_some_and(|t| age(t) > m),
+            ListFilter::TopicUnder(m) => topic_time.is_some_and(|t| age(t) < m),
+            ListFilter::Mask(mask) => match_wildcard(&casefold(mask), &casefold(channel)),
+            ListFilter::NotMask(mask) => !match_wildcard(&casefold(mask), &casefold(channel)),
+        }
+    }
+}
+
+// Los CTCP van entre \x01; ACTION (/me) se trata como un mensaje normal
+pub(crate) fn is_ctcp(text: &str) -> bool {
+    text.starts_with('\x01') && !text.starts_with("\x01ACTION")
+}
+
+// Códigos mIRC de color y formato: negrita, color, color hex, monoespaciado, inverso,
+// cursiva, tachado, subrayado y reset
+const FORMAT_CODES: [char; 9] = ['\x02', '\x03', '\x04', '\x11', '\x16', '\x1d', '\x1e', '\x1f', '\x0f'];
+
+pub(crate) fn has_formatting(text: &str) -> bool {
+    text.contains(FORMAT_CODES)
+}
+
+// Quita los códigos de formato, con los números de color que siguen a \x03 ("4", "04,12")
+// y a \x04 ("ff0000", "ff0000,000000")
+pub(crate) fn strip_formatting(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !FORMAT_CODES.contains(&c) {
+            out.push(c);
+            continue;
+        }
+        let (digits, is_digit): (usize, fn(&char) -> bool) = match c {
+            '\x03' => (2, |c| c.is_ascii_digit()),
+            '\x04' => (6, |c| c.is_ascii_hexdigit()),
+            _ => continue,
+        };
+        let skip_color = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+            let mut n = 0;
+            while n < digits && chars.peek().is_some_and(is_digit) {
+                chars.next();
+                n += 1;
+            }
+            n > 0
+        };
+        if skip_color(&mut chars) && chars.peek() == Some(&',') {
+            let mut lookahead = chars.clone();
+            lookahead.next();
+            if lookahead.peek().is_some_and(is_digit) {
+                chars.next();
+                skip_color(&mut chars);
+            }
+        }
+    }
+    out
+}
+
+// Protección contra flood del canal (+f): "[5j,10m,3n,4c#b10]:15".
+//   j entradas, m mensajes, n cambios de nick, c CTCPs; ":15" es la ventana en segundos.
+//   Tras '#' va la acción: i (+i) o m (+m) cuentan los eventos de todo el canal,
+//   k (kick) o b (ban y kick) los del usuario. Los minutos que dura la acción son opcionales.
+//   Sin acción: j→i, m→m, n→k, c→k.
+pub(crate) const FLOOD_TYPES: &str = "jmnc";
+pub(crate) const FLOOD_ACTION_MINUTES: u64 = 5;
+const FLOOD_MAX_SECONDS: u64 = 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FloodAction {
+    InviteOnly,
+    Moderated,
+    Kick,
+    Ban,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FloodRule {
+    pub(crate) kind: char,
+    pub(crate) limit: usize,
+    pub(crate) action: FloodAction,
+    pub(crate) minutes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FloodSettings {
+    pub(crate) rules: Vec<FloodRule>,
+    pub(crate) seconds: u64,
+}
+
+impl FloodSettings {
+    pub(crate) fn rule(&self, kind: char) -> Option<&FloodRule> {
+        self.rules.iter().find(|r| r.kind == kind)
+    }
+}
+
+fn parse_flood_seconds(seconds: &str) -> Result<u64, String> {
+    seconds
+        .parse::<u64>()
+        .ok()
+        .filter(|s| (1..=FLOOD_MAX_SECONDS).contains(s))
+        .ok_or_else(|| format!("Invalid number of seconds '{seconds}'"))
+}
+
+fn parse_flood_rule(rule: &str) -> Result<FloodRule, String> {
+    let (count, action) = match rule.split_once('#') {
+        Some((count, action)) => (count, Some(action)),
+        None => (rule, None),
+    };
+    let Some(kind) = count.chars().last().filter(|k| FLOOD_TYPES.contains(*k)) else {
+        return Err(format!("Unknown flood type in '{rule}'"));
+    };
+    let limit = count[..count.len() - 1]
+        .parse::<usize>()
+        .ok()
+        .filter(|l| *l > 0)
+        .ok_or_else(|| format!("Invalid count in '{rule}'"))?;
+    let (action, minutes) = if let Some(action) = action {
+        let mut chars = action.chars();
+        let action = match chars.next() {
+            Some('i') => FloodAction::InviteOnly,
+            Some('m') => FloodAction::Moderated,
+            Some('k') => FloodAction::Kick,
+            Some('b') => FloodAction::Ban,
+            _ => return Err(format!("Unknown flood action in '{rule}'")),
+        };
+        let minutes = match chars.as_str() {
+            "" => FLOOD_ACTION_MINUTES,
+            minutes => minutes
+                .parse::<u64>()
+                .ok()
+                .filter(|m| *m > 0)
+                .ok_or_else(|| format!("Invalid number of minutes in '{rule}'"))?,
+        };
+        (action, minutes)
+    } else {
+        let action = match kind {
+            'j' => FloodAction::InviteOnly,
+            'm' => FloodAction::Moderated,
+            _ => FloodAction::Kick,
+        };
+        (action, FLOOD_ACTION_MINUTES)
+    };
+    Ok(FloodRule { kind, limit, action, minutes })
+}
+
+pub(crate) fn parse_flood_mode(param: &str) -> Result<FloodSettings, String> {
+    let Some((rules, seconds)) = param.strip_prefix('[').and_then(|p| p.split_once("]:")) else {
+        return Err("Expected [rules]:seconds".to_string());
+    };
+    let seconds = parse_flood_seconds(seconds)?;
+    let mut parsed: Vec<FloodRule> = Vec::new();
+    for rule in rules.split(',') {
+        let rule = parse_flood_rule(rule)?;
+        if parsed.iter().any(|r| r.kind == rule.kind) {
+            return Err(format!("Duplicate flood type '{}'", rule.kind));
+        }
+        parsed.push(rule);
+    }
+    Ok(FloodSettings { rules: parsed, seconds })
+}
+
+// Límite de entradas al canal (+j): "entradas:segundos"
+pub(crate) fn parse_join_throttle(param: &str) -> Result<(usize, u64), String> {
+    let Some((joins, seconds)) = param.split_once(':') else {
+        return Err("Expected joins:seconds".to_string());
+    };
+    let joins = joins
+        .parse::<usize>()
+        .ok()
+        .filter(|j| *j > 0)
+        .ok_or_else(|| format!("Invalid number of joins '{joins}'"))?;
+    Ok((joins, parse_flood_seconds(seconds)?))
+}
+
 //  argon2
 
 static ARGON2_M_COST: u32 = 2048;
@@ -654,6 +1130,26 @@ lazy_static! {
     );
 }
 
+// parse duration in form: 30s, 15m, 2h, 7d, 1w or plain number of seconds.
+// Returns duration in seconds.
+pub(crate) fn parse_duration(s: &str) -> Option<u64> {
+    let (num, unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&s[..i], c.to_ascii_lowercase()),
+        Some(_) => (s, 's'),
+        None => return None,
+    };
+    let num = num.parse::<u64>().ok()?;
+    let mult = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 604800,
+        _ => return None,
+    };
+    num.checked_mul(mult)
+}
+
 pub(crate) fn argon2_hash_password(password: &str) -> String {
     ARGON2
         .hash_password(password.as_bytes(), ARGON2_SALT.as_str())
@@ -849,11 +1345,12 @@ mod test {
 
     #[test]
     fn test_validate_source() {
-        assert_eq!(true, validate_source("bob!bobby@host.com"));
-        assert_eq!(true, validate_source("bobby@host.com"));
-        assert_eq!(true, validate_source("bob!bobby"));
-        assert_eq!(true, validate_source("host.com"));
-        assert_eq!(false, validate_source("bob@bobby!host.com"));
+        assert!(validate_source("bob!bobby@host.com"));
+        assert!(validate_source("bobby@host.com"));
+        assert!(validate_source("bob!bobby"));
+        assert!(validate_source("host.com"));
+        assert!(!validate_source("bob@bobby!host.com"));
+        assert!(!validate_source("bob:bobby@host.com"));
     }
 
     #[test]
@@ -1005,7 +1502,7 @@ mod test {
         );
         assert_eq!(
             Err("Unknown umode flag in parameter 2".to_string()),
-            validate_usermodes(&vec![(" +io-rw", vec![]), ("-x", vec![])])
+            validate_usermodes(&vec![("+io-rw", vec![]), ("-y", vec![])])
                 .map_err(|e| e.to_string())
         );
     }
@@ -1194,6 +1691,8 @@ mod test {
         assert!(!match_wildcard("*?and *", "Aliceund Others"));
         assert!(!match_wildcard("* and?*", "Alice undOthers"));
         assert!(match_wildcard("lu*na*Xna*Y", "lulu and nanaXnaY"));
+        assert!(!match_wildcard("*admin*", "ab"));
+        assert!(!match_wildcard("*admin", "ab"));
         assert!(match_wildcard(
             "lu*Xlu*Wlu*Zlu*B",
             "lulululuYlululuXlululuWluluZluluAluluB"
@@ -1229,6 +1728,145 @@ mod test {
         assert_eq!("bob.com!*@*", &normalize_sourcemask("bob.com"));
     }
 
+    #[test]
+    fn test_parse_list_filter() {
+        assert_eq!(Some(ListFilter::UsersOver(5)), parse_list_filter(">5"));
+        assert_eq!(Some(ListFilter::UsersUnder(10)), parse_list_filter("<10"));
+        assert_eq!(Some(ListFilter::CreatedOver(60)), parse_list_filter("C>60"));
+        assert_eq!(Some(ListFilter::CreatedUnder(5)), parse_list_filter("C<5"));
+        assert_eq!(Some(ListFilter::TopicOver(1)), parse_list_filter("T>1"));
+        assert_eq!(Some(ListFilter::TopicUnder(2)), parse_list_filter("T<2"));
+        assert_eq!(Some(ListFilter::Channel("#rust")), parse_list_filter("#rust"));
+        assert_eq!(Some(ListFilter::Mask("#*ru?t*")), parse_list_filter("#*ru?t*"));
+        assert_eq!(Some(ListFilter::NotMask("#*rust*")), parse_list_filter("!#*rust*"));
+        assert_eq!(None, parse_list_filter(">x"));
+        assert_eq!(None, parse_list_filter("C=5"));
+        assert_eq!(None, parse_list_filter("rust"));
+        assert_eq!(None, parse_list_filter("!rust"));
+
+        let now = 100000;
+        assert!(ListFilter::UsersOver(2).matches("#a", 3, now, None, now));
+        assert!(!ListFilter::UsersUnder(3).matches("#a", 3, now, None, now));
+        assert!(ListFilter::CreatedOver(10).matches("#a", 1, now - 700, None, now));
+        assert!(!ListFilter::CreatedUnder(10).matches("#a", 1, now - 700, None, now));
+        assert!(ListFilter::TopicUnder(10).matches("#a", 1, now, Some(now - 60), now));
+        assert!(!ListFilter::TopicOver(0).matches("#a", 1, now, None, now));
+        assert!(ListFilter::Channel("#RUST").matches("#rust", 1, now, None, now));
+        assert!(ListFilter::Mask("#*UST").matches("#rust", 1, now, None, now));
+        assert!(!ListFilter::NotMask("#r*").matches("#rust", 1, now, None, now));
+    }
+
+    #[test]
+    fn test_casemapping_fold() {
+        assert_eq!("nick[a]\\^", CaseMapping::Ascii.fold("NiCK[a]\\^"));
+        assert_eq!("żółw", CaseMapping::Ascii.fold("żółw"));
+        assert_eq!("ŻÓŁw", CaseMapping::Ascii.fold("ŻÓŁW"));
+        assert_eq!("nick{a}|~", CaseMapping::Rfc1459.fold("NiCK[a]\\^"));
+        assert_eq!("nick{a}|^", CaseMapping::Rfc1459Strict.fold("NiCK[a]\\^"));
+        assert_eq!("#chan{}", CaseMapping::Rfc1459Strict.fold("#chan{}"));
+        assert_eq!("żółw[]", CaseMapping::Rfc7613.fold("ŻÓŁW[]"));
+        assert!(matches!(CaseMapping::Rfc1459.fold("abc{}"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_parse_extban() {
+        assert_eq!(None, parse_extban("bob!*@*"));
+        assert_eq!(Some(Ok(ExtBan::Account("bob*"))), parse_extban("~a:bob*"));
+        assert_eq!(Some(Ok(ExtBan::Secure)), parse_extban("~z"));
+        assert_eq!(Some(Ok(ExtBan::Mute("*!*@spam.com"))), parse_extban("~m:*!*@spam.com"));
+        assert_eq!(Some(Ok(ExtBan::Join("#other"))), parse_extban("~j:#other"));
+        assert!(parse_extban("~a").unwrap().is_err());
+        assert!(parse_extban("~a:").unwrap().is_err());
+        assert!(parse_extban("~z:x").unwrap().is_err());
+        assert!(parse_extban("~c:other").unwrap().is_err());
+        assert!(parse_extban("~m:~m:bob").unwrap().is_err());
+        assert!(parse_extban("~x:bob").unwrap().is_err());
+
+        assert_eq!(Ok(()), validate_extban('b', "~m:bob|60"));
+        assert!(validate_extban('e', "~m:bob").is_err());
+        assert_eq!(Ok(()), validate_extban('Z', "~a:bob|60"));
+        assert!(validate_extban('Z', "~m:bob").is_err());
+        assert_eq!("~m:bob!*@*", &normalize_banmask("~m:bob"));
+        assert_eq!("~a:bob", &normalize_banmask("~a:bob"));
+        assert_eq!("bob!*@*|60", &normalize_banmask("bob|60"));
+        assert_eq!(
+            Err("Invalid mode parameter: #xchan b ~q:x Unknown extban type 'q'".to_string()),
+            validate_channelmodes("#xchan", &vec![("+b", vec!["~q:x"])]).map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn test_strip_formatting() {
+        assert!(!has_formatting("\x01ACTION waves\x01"));
+        assert!(has_formatting("\x02bold\x02"));
+        assert_eq!("bold text", strip_formatting("\x02bold\x0f text"));
+        assert_eq!("red on blue", strip_formatting("\x0304,12red on blue\x03"));
+        assert_eq!(" apples", strip_formatting("\x031,5 apples"));
+        assert_eq!(", comma", strip_formatting("\x03, comma"));
+        assert_eq!("hex", strip_formatting("\x04ff0000,00ff00hex"));
+        assert_eq!("9 lives", strip_formatting("\x03049 lives"));
+        assert!(is_ctcp("\x01VERSION\x01"));
+        assert!(!is_ctcp("\x01ACTION waves\x01"));
+    }
+
+    #[test]
+    fn test_parse_flood_mode() {
+        assert_eq!(
+            Ok(FloodSettings {
+                rules: vec![
+                    FloodRule { kind: 'j', limit: 5, action: FloodAction::InviteOnly, minutes: FLOOD_ACTION_MINUTES },
+                    FloodRule { kind: 'm', limit: 10, action: FloodAction::Moderated, minutes: FLOOD_ACTION_MINUTES },
+                    FloodRule { kind: 'c', limit: 3, action: FloodAction::Ban, minutes: 10 },
+                ],
+                seconds: 15,
+            }),
+            parse_flood_mode("[5j,10m,3c#b10]:15")
+        );
+        assert_eq!(FloodAction::Kick, parse_flood_mode("[2n]:60").unwrap().rule('n').unwrap().action);
+        assert!(parse_flood_mode("5j:15").is_err());
+        assert!(parse_flood_mode("[5x]:15").is_err());
+        assert!(parse_flood_mode("[0j]:15").is_err());
+        assert!(parse_flood_mode("[5j#q]:15").is_err());
+        assert!(parse_flood_mode("[5j,3j]:15").is_err());
+        assert!(parse_flood_mode("[5j]:0").is_err());
+
+        assert_eq!(Ok((3, 10)), parse_join_throttle("3:10"));
+        assert!(parse_join_throttle("3").is_err());
+        assert!(parse_join_throttle("0:10").is_err());
+
+        assert_eq!(
+            Ok(()),
+            validate_channelmodes("#xchan", &vec![("+fj", vec!["[5j,10m]:15", "3:10"])]).map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(()),
+            validate_channelmodes("#xchan", &vec![("-fj", vec![])]).map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            Ok(()),
+            validate_channelmodes("#xchan", &vec![("+Lz", vec!["#overflow"])]).map_err(|e| e.to_string())
+        );
+        assert!(validate_channelmodes("#xchan", &vec![("+L", vec!["overflow"])]).is_err());
+        assert_eq!(
+            Err("Invalid mode parameter: #xchan j 3 Expected joins:seconds".to_string()),
+            validate_channelmodes("#xchan", &vec![("+j", vec!["3"])]).map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(Some(45), parse_duration("45"));
+        assert_eq!(Some(45), parse_duration("45s"));
+        assert_eq!(Some(900), parse_duration("15m"));
+        assert_eq!(Some(7200), parse_duration("2H"));
+        assert_eq!(Some(604800), parse_duration("7d"));
+        assert_eq!(Some(1209600), parse_duration("2w"));
+        assert_eq!(None, parse_duration(""));
+        assert_eq!(None, parse_duration("d"));
+        assert_eq!(None, parse_duration("5y"));
+        assert_eq!(None, parse_duration("x5m"));
+    }
+
     #[test]
     fn test_test_argon2_verify_password() {
         let phash = argon2_hash_password("lalalaXX");